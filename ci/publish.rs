@@ -22,6 +22,7 @@ const CRATES_TO_PUBLISH: &[&str] = &[
     "wasm-encoder",
     "wast",
     "wat",
+    "wasm-interp",
     "wasm-smith",
     "wasm-mutate",
     "wasm-shrink",
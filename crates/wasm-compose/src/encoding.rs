@@ -70,6 +70,16 @@ impl Encodable {
         }
     }
 
+    /// Defines a type, reusing a previous structurally identical definition
+    /// in the same scope if there is one.
+    fn interned_ty(&mut self, encode: impl FnOnce(ComponentTypeEncoder<'_>)) -> u32 {
+        match self {
+            Encodable::Component(t) => t.interned_ty(encode),
+            Encodable::Instance(t) => t.interned_ty(encode),
+            Encodable::Builder(t) => t.interned_ty(encode),
+        }
+    }
+
     fn core_type(&mut self) -> ComponentCoreTypeEncoder<'_> {
         match self {
             Encodable::Component(t) => t.core_type(),
@@ -450,9 +460,14 @@ impl<'a> TypeEncoder<'a> {
     ) -> u32 {
         let ty = &self.0.types[id];
         let instance = self.instance(state, ty.exports.iter().map(|(n, t)| (n.as_str(), *t)));
-        let index = state.cur.encodable.type_count();
-        state.cur.encodable.ty().instance(&instance);
-        index
+        if ty.exports.values().any(|t| self.defines_types(*t)) {
+            let index = state.cur.encodable.type_count();
+            state.cur.encodable.ty().instance(&instance);
+            return index;
+        }
+        state.cur.encodable.interned_ty(|t| {
+            t.instance(&instance);
+        })
     }
 
     fn component_type(&self, state: &mut TypeState<'a>, id: ComponentTypeId) -> u32 {
@@ -464,9 +479,46 @@ impl<'a> TypeEncoder<'a> {
             ty.exports.iter().map(|(n, t)| (n.as_str(), *t)),
         );
 
-        let index = state.cur.encodable.type_count();
-        state.cur.encodable.ty().component(&component);
-        index
+        if ty
+            .imports
+            .values()
+            .chain(ty.exports.values())
+            .any(|t| self.defines_types(*t))
+        {
+            let index = state.cur.encodable.type_count();
+            state.cur.encodable.ty().component(&component);
+            return index;
+        }
+        state.cur.encodable.interned_ty(|t| {
+            t.component(&component);
+        })
+    }
+
+    /// Returns whether `ty` defines any types, either itself or through the
+    /// exports of an instance or the imports and exports of a component.
+    ///
+    /// Instance and component types which define types aren't deduplicated.
+    /// Each import of a shared instance type would share the identities of
+    /// the types it exports, and WIT decoding expects each exported type to
+    /// belong to only one interface.
+    fn defines_types(&self, ty: ComponentEntityType) -> bool {
+        match ty {
+            ComponentEntityType::Type { .. } => true,
+            ComponentEntityType::Instance(id) => self.0.types[id]
+                .exports
+                .values()
+                .any(|t| self.defines_types(*t)),
+            ComponentEntityType::Component(id) => {
+                let ty = &self.0.types[id];
+                ty.imports
+                    .values()
+                    .chain(ty.exports.values())
+                    .any(|t| self.defines_types(*t))
+            }
+            ComponentEntityType::Module(_)
+            | ComponentEntityType::Func(_)
+            | ComponentEntityType::Value(_) => false,
+        }
     }
 
     fn component_func_type(&self, state: &mut TypeState<'a>, id: ComponentFuncTypeId) -> u32 {
@@ -479,12 +531,9 @@ impl<'a> TypeEncoder<'a> {
 
         let result = ty.result.map(|ty| self.component_val_type(state, ty));
 
-        let index = state.cur.encodable.type_count();
-        let mut f = state.cur.encodable.ty().function();
-
-        f.params(params).result(result);
-
-        index
+        state.cur.encodable.interned_ty(|t| {
+            t.function().params(params).result(result);
+        })
     }
 
     /// Translates a type `id` provided, returning the index that it is defined
@@ -637,16 +686,10 @@ impl<'a> TypeEncoder<'a> {
         let ty = &self.0.types[id];
 
         match ty {
-            ComponentDefinedType::Primitive(ty) => {
-                let index = state.cur.encodable.type_count();
-                state
-                    .cur
-                    .encodable
-                    .ty()
-                    .defined_type()
-                    .primitive((*ty).into());
-                index
-            }
+            ComponentDefinedType::Primitive(ty) => state
+                .cur
+                .encodable
+                .interned_ty(|t| t.defined_type().primitive((*ty).into())),
             ComponentDefinedType::Record(r) => self.record(state, r),
             ComponentDefinedType::Variant(v) => self.variant(state, v),
             ComponentDefinedType::List(ty) => self.list(state, *ty),
@@ -660,15 +703,17 @@ impl<'a> TypeEncoder<'a> {
             ComponentDefinedType::Result { ok, err } => self.result(state, *ok, *err),
             ComponentDefinedType::Own(r) => {
                 let ty = self.ty(state, (*r).into());
-                let index = state.cur.encodable.type_count();
-                state.cur.encodable.ty().defined_type().own(ty);
-                index
+                state
+                    .cur
+                    .encodable
+                    .interned_ty(|t| t.defined_type().own(ty))
             }
             ComponentDefinedType::Borrow(r) => {
                 let ty = self.ty(state, (*r).into());
-                let index = state.cur.encodable.type_count();
-                state.cur.encodable.ty().defined_type().borrow(ty);
-                index
+                state
+                    .cur
+                    .encodable
+                    .interned_ty(|t| t.defined_type().borrow(ty))
             }
             ComponentDefinedType::Future(ty) => self.future(state, *ty),
             ComponentDefinedType::Stream(ty) => self.stream(state, *ty),
@@ -682,9 +727,10 @@ impl<'a> TypeEncoder<'a> {
             .map(|(n, ty)| (n.as_str(), self.component_val_type(state, *ty)))
             .collect::<Vec<_>>();
 
-        let index = state.cur.encodable.type_count();
-        state.cur.encodable.ty().defined_type().record(fields);
-        index
+        state
+            .cur
+            .encodable
+            .interned_ty(|t| t.defined_type().record(fields))
     }
 
     fn variant(&self, state: &mut TypeState<'a>, variant: &VariantType) -> u32 {
@@ -701,16 +747,18 @@ impl<'a> TypeEncoder<'a> {
                 )
             })
             .collect::<Vec<_>>();
-        let index = state.cur.encodable.type_count();
-        state.cur.encodable.ty().defined_type().variant(cases);
-        index
+        state
+            .cur
+            .encodable
+            .interned_ty(|t| t.defined_type().variant(cases))
     }
 
     fn list(&self, state: &mut TypeState<'a>, ty: ct::ComponentValType) -> u32 {
         let ty = self.component_val_type(state, ty);
-        let index = state.cur.encodable.type_count();
-        state.cur.encodable.ty().defined_type().list(ty);
-        index
+        state
+            .cur
+            .encodable
+            .interned_ty(|t| t.defined_type().list(ty))
     }
 
     fn fixed_size_list(
@@ -720,14 +768,10 @@ impl<'a> TypeEncoder<'a> {
         elements: u32,
     ) -> u32 {
         let ty = self.component_val_type(state, ty);
-        let index = state.cur.encodable.type_count();
         state
             .cur
             .encodable
-            .ty()
-            .defined_type()
-            .fixed_size_list(ty, elements);
-        index
+            .interned_ty(|t| t.defined_type().fixed_size_list(ty, elements))
     }
 
     fn tuple(&self, state: &mut TypeState<'a>, tuple: &TupleType) -> u32 {
@@ -736,41 +780,33 @@ impl<'a> TypeEncoder<'a> {
             .iter()
             .map(|ty| self.component_val_type(state, *ty))
             .collect::<Vec<_>>();
-        let index = state.cur.encodable.type_count();
-        state.cur.encodable.ty().defined_type().tuple(types);
-        index
+        state
+            .cur
+            .encodable
+            .interned_ty(|t| t.defined_type().tuple(types))
     }
 
     fn flags(
         encodable: &mut Encodable,
         names: &wasmparser::collections::IndexSet<KebabString>,
     ) -> u32 {
-        let index = encodable.type_count();
-        encodable
-            .ty()
-            .defined_type()
-            .flags(names.iter().map(|n| n.as_str()));
-        index
+        encodable.interned_ty(|t| t.defined_type().flags(names.iter().map(|n| n.as_str())))
     }
 
     fn enum_type(
         encodable: &mut Encodable,
         cases: &wasmparser::collections::IndexSet<KebabString>,
     ) -> u32 {
-        let index = encodable.type_count();
-        encodable
-            .ty()
-            .defined_type()
-            .enum_type(cases.iter().map(|c| c.as_str()));
-        index
+        encodable.interned_ty(|t| t.defined_type().enum_type(cases.iter().map(|c| c.as_str())))
     }
 
     fn option(&self, state: &mut TypeState<'a>, ty: ct::ComponentValType) -> u32 {
         let ty = self.component_val_type(state, ty);
 
-        let index = state.cur.encodable.type_count();
-        state.cur.encodable.ty().defined_type().option(ty);
-        index
+        state
+            .cur
+            .encodable
+            .interned_ty(|t| t.defined_type().option(ty))
     }
 
     fn result(
@@ -782,9 +818,10 @@ impl<'a> TypeEncoder<'a> {
         let ok = ok.map(|ty| self.component_val_type(state, ty));
         let err = err.map(|ty| self.component_val_type(state, ty));
 
-        let index = state.cur.encodable.type_count();
-        state.cur.encodable.ty().defined_type().result(ok, err);
-        index
+        state
+            .cur
+            .encodable
+            .interned_ty(|t| t.defined_type().result(ok, err))
     }
 
     fn export(
@@ -814,17 +851,19 @@ impl<'a> TypeEncoder<'a> {
     fn future(&self, state: &mut TypeState<'a>, ty: Option<ct::ComponentValType>) -> u32 {
         let ty = ty.map(|ty| self.component_val_type(state, ty));
 
-        let index = state.cur.encodable.type_count();
-        state.cur.encodable.ty().defined_type().future(ty);
-        index
+        state
+            .cur
+            .encodable
+            .interned_ty(|t| t.defined_type().future(ty))
     }
 
     fn stream(&self, state: &mut TypeState<'a>, ty: Option<ct::ComponentValType>) -> u32 {
         let ty = ty.map(|ty| self.component_val_type(state, ty));
 
-        let index = state.cur.encodable.type_count();
-        state.cur.encodable.ty().defined_type().stream(ty);
-        index
+        state
+            .cur
+            .encodable
+            .interned_ty(|t| t.defined_type().stream(ty))
     }
 }
 
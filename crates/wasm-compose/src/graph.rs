@@ -1564,8 +1564,7 @@ mod test {
       (export (;0;) "e1" (func (type 0)))
       (type (;1;) (func (param "a" u32)))
       (export (;1;) "e3" (func (type 1)))
-      (type (;2;) (func))
-      (export (;2;) "e2" (func (type 2)))
+      (export (;2;) "e2" (func (type 0)))
     )
   )
   (import "i1" (instance (;0;) (type 0)))
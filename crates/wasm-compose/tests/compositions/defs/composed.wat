@@ -16,10 +16,7 @@
       (instance)
     )
     (import "other1" (instance (;1;) (type 1)))
-    (type (;2;)
-      (instance)
-    )
-    (import "other2" (instance (;2;) (type 2)))
+    (import "other2" (instance (;2;) (type 1)))
     (core module (;0;)
       (type (;0;) (func (param i32)))
       (type (;1;) (func (param i32 i32 i32 i32) (result i32)))
@@ -0,0 +1,59 @@
+(component
+  (type (;0;)
+    (instance
+      (type (;0;) (func (param "x" u32)))
+      (export (;0;) "f" (func (type 0)))
+    )
+  )
+  (import "a" (instance (;0;) (type 0)))
+  (import "b" (instance (;1;) (type 0)))
+  (type (;1;)
+    (instance
+      (type (;0;) (func (param "x" string)))
+      (export (;0;) "f" (func (type 0)))
+    )
+  )
+  (import "c" (instance (;2;) (type 1)))
+  (import "e" (instance (;3;) (type 0)))
+  (component (;0;)
+    (type (;0;)
+      (instance
+        (type (;0;) (func (param "x" u32)))
+        (export (;0;) "f" (func (type 0)))
+      )
+    )
+    (import "a" (instance (;0;) (type 0)))
+    (import "b" (instance (;1;) (type 0)))
+    (type (;1;)
+      (instance
+        (type (;0;) (func (param "x" string)))
+        (export (;0;) "f" (func (type 0)))
+      )
+    )
+    (import "c" (instance (;2;) (type 1)))
+    (type (;2;)
+      (instance)
+    )
+    (import "d" (instance (;3;) (type 2)))
+  )
+  (component (;1;)
+    (type (;0;)
+      (instance
+        (type (;0;) (func (param "x" u32)))
+        (export (;0;) "f" (func (type 0)))
+      )
+    )
+    (import "e" (instance (;0;) (type 0)))
+  )
+  (instance (;4;) (instantiate 1
+      (with "e" (instance 3))
+    )
+  )
+  (instance (;5;) (instantiate 0
+      (with "d" (instance 4))
+      (with "a" (instance 0))
+      (with "b" (instance 1))
+      (with "c" (instance 2))
+    )
+  )
+)
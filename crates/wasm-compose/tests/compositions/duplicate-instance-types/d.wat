@@ -0,0 +1,3 @@
+(component
+  (import "e" (instance (export "f" (func (param "x" u32)))))
+)
@@ -0,0 +1,6 @@
+(component
+  (import "a" (instance (export "f" (func (param "x" u32)))))
+  (import "b" (instance (export "f" (func (param "x" u32)))))
+  (import "c" (instance (export "f" (func (param "x" string)))))
+  (import "d" (instance))
+)
@@ -31,10 +31,7 @@
       (instance)
     )
     (import "b" (instance (;0;) (type 0)))
-    (type (;1;)
-      (instance)
-    )
-    (import "a" (instance (;1;) (type 1)))
+    (import "a" (instance (;1;) (type 0)))
   )
   (component (;1;)
     (type (;0;)
@@ -5,8 +5,7 @@
       (export (;1;) "t" (type (eq 0)))
       (type (;2;) (func (result 1)))
       (export (;0;) "f2" (func (type 2)))
-      (type (;3;) (func (result 1)))
-      (export (;1;) "f1" (func (type 3)))
+      (export (;1;) "f1" (func (type 2)))
     )
   )
   (import "c" (instance (;0;) (type 0)))
@@ -15,10 +14,7 @@
       (instance)
     )
     (import "b" (instance (;0;) (type 0)))
-    (type (;1;)
-      (instance)
-    )
-    (import "a" (instance (;1;) (type 1)))
+    (import "a" (instance (;1;) (type 0)))
   )
   (component (;1;)
     (type (;0;)
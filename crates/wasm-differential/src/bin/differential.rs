@@ -0,0 +1,83 @@
+//! Compares `wasm-interp` against Wasmtime on random modules.
+//!
+//! Usage: `differential [iterations]`
+//!
+//! Runs forever unless a number of iterations is given. Each mismatch is
+//! printed and the original and shrunk modules are written to
+//! `mismatch-N.wasm` and `mismatch-N-shrunk.wasm` in the current directory.
+
+use arbitrary::{Error, Unstructured};
+use rand::RngCore;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicIsize, AtomicUsize, Ordering::SeqCst};
+use wasm_differential::{Differential, Interp, Wasmtime};
+
+struct State {
+    remaining: AtomicIsize,
+    total: AtomicUsize,
+    mismatches: AtomicUsize,
+}
+
+fn main() {
+    let remaining = match std::env::args().nth(1) {
+        Some(n) => n.parse().expect("iterations must be a number"),
+        None => isize::MAX,
+    };
+    let state = Arc::new(State {
+        remaining: AtomicIsize::new(remaining),
+        total: AtomicUsize::new(0),
+        mismatches: AtomicUsize::new(0),
+    });
+    let threads = (0..num_cpus::get())
+        .map(|_| {
+            let state = state.clone();
+            std::thread::spawn(move || state.run_worker())
+        })
+        .collect::<Vec<_>>();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    let total = state.total.load(SeqCst);
+    let mismatches = state.mismatches.load(SeqCst);
+    println!("{mismatches} mismatches in {total} modules");
+    if mismatches > 0 {
+        std::process::exit(1);
+    }
+}
+
+impl State {
+    fn run_worker(&self) {
+        let mut differential = Differential::new(Interp::new(), Wasmtime::new());
+        let mut rng = rand::rng();
+        let mut data = Vec::new();
+
+        while self.remaining.fetch_sub(1, SeqCst) > 0 {
+            data.truncate(0);
+            data.resize(1024, 0);
+            rng.fill_bytes(&mut data);
+            let mismatch = loop {
+                match differential.run(&mut Unstructured::new(&data)) {
+                    Ok(mismatch) => break mismatch,
+                    Err(Error::NotEnoughData) => {
+                        let cur = data.len();
+                        let extra = 1024;
+                        data.resize(cur + extra, 0);
+                        rng.fill_bytes(&mut data[cur..]);
+                    }
+                    Err(e) => panic!("failed to generate module: {e}"),
+                }
+            };
+            self.total.fetch_add(1, SeqCst);
+
+            if let Some(mismatch) = mismatch {
+                let i = self.mismatches.fetch_add(1, SeqCst);
+                let wasm_file = format!("mismatch-{i}.wasm");
+                let shrunk_file = format!("mismatch-{i}-shrunk.wasm");
+                std::fs::write(&wasm_file, &mismatch.wasm).unwrap();
+                std::fs::write(&shrunk_file, &mismatch.shrunk).unwrap();
+                println!("{mismatch}\n  written to `{wasm_file}` and `{shrunk_file}`");
+            }
+        }
+    }
+}
@@ -0,0 +1,133 @@
+//! An [`Engine`] for the `wasm-interp` reference interpreter.
+
+use crate::{Engine, Execution, Val};
+use wasm_interp::{Instance, Module, Store};
+use wasmparser::WasmFeatures;
+
+/// The `wasm-interp` reference interpreter.
+pub struct Interp {
+    fuel: u64,
+}
+
+impl Interp {
+    /// Creates a new interpreter engine.
+    pub fn new() -> Interp {
+        Interp { fuel: 10_000_000 }
+    }
+
+    /// Sets the number of operators each instance may execute, after which
+    /// execution stops with [`Execution::Unsupported`].
+    ///
+    /// This is only a safety net for modules which don't terminate on their
+    /// own. Defaults to ten million.
+    pub fn fuel(mut self, fuel: u64) -> Interp {
+        self.fuel = fuel;
+        self
+    }
+}
+
+impl Default for Interp {
+    fn default() -> Interp {
+        Interp::new()
+    }
+}
+
+impl Engine for Interp {
+    type Instance = (Store, Instance);
+
+    fn name(&self) -> &str {
+        "wasm-interp"
+    }
+
+    fn features(&self) -> WasmFeatures {
+        WasmFeatures::WASM2
+            | WasmFeatures::MULTI_MEMORY
+            | WasmFeatures::MEMORY64
+            | WasmFeatures::EXTENDED_CONST
+            | WasmFeatures::TAIL_CALL
+    }
+
+    fn instantiate(&mut self, wasm: &[u8]) -> Execution<Self::Instance> {
+        let module = match Module::new(self.features(), wasm) {
+            Ok(module) => module,
+            Err(e) => return Execution::Unsupported(format!("{e:#}")),
+        };
+        let mut store = Store::new();
+        store.set_fuel(self.fuel);
+        match store.instantiate(&module, &[]) {
+            Ok(instance) => Execution::Ok((store, instance)),
+            Err(e) => classify(e),
+        }
+    }
+
+    fn invoke(
+        &mut self,
+        (store, instance): &mut Self::Instance,
+        name: &str,
+        args: &[Val],
+    ) -> Execution<Vec<Val>> {
+        let Some(func) = store
+            .get_export(*instance, name)
+            .and_then(|e| e.into_func())
+        else {
+            return Execution::Unsupported(format!("no exported function `{name}`"));
+        };
+        let Some(args) = args
+            .iter()
+            .map(|v| to_interp(*v))
+            .collect::<Option<Vec<_>>>()
+        else {
+            return Execution::Unsupported("unsupported argument".to_string());
+        };
+        match store.invoke(func, &args) {
+            Ok(results) => Execution::Ok(results.into_iter().map(from_interp).collect()),
+            Err(e) => classify(e),
+        }
+    }
+
+    fn global(&mut self, (store, instance): &mut Self::Instance, name: &str) -> Option<Val> {
+        let global = store.get_export(*instance, name)?.into_global()?;
+        Some(from_interp(store.global_get(global)))
+    }
+
+    fn memory(&mut self, (store, instance): &mut Self::Instance, name: &str) -> Option<Vec<u8>> {
+        let memory = store.get_export(*instance, name)?.into_memory()?;
+        Some(store.memory_data(memory).to_vec())
+    }
+}
+
+fn classify<T>(e: anyhow::Error) -> Execution<T> {
+    match e.downcast_ref::<wasm_interp::Trap>() {
+        // Running out of stack or fuel depends on the engine.
+        Some(trap) if matches!(trap.message(), "call stack exhausted" | "all fuel consumed") => {
+            Execution::Unsupported(trap.message().to_string())
+        }
+        Some(trap) => Execution::Trap(trap.message().to_string()),
+        None => Execution::Unsupported(format!("{e:#}")),
+    }
+}
+
+fn to_interp(val: Val) -> Option<wasm_interp::Val> {
+    Some(match val {
+        Val::I32(x) => wasm_interp::Val::I32(x),
+        Val::I64(x) => wasm_interp::Val::I64(x),
+        Val::F32(x) => wasm_interp::Val::F32(x),
+        Val::F64(x) => wasm_interp::Val::F64(x),
+        Val::V128(x) => wasm_interp::Val::V128(x),
+        Val::FuncRef { null: true } => wasm_interp::Val::FuncRef(None),
+        Val::ExternRef { null: true } => wasm_interp::Val::ExternRef(None),
+        Val::FuncRef { null: false } | Val::ExternRef { null: false } => return None,
+    })
+}
+
+fn from_interp(val: wasm_interp::Val) -> Val {
+    match val {
+        wasm_interp::Val::I32(x) => Val::I32(x),
+        wasm_interp::Val::I64(x) => Val::I64(x),
+        wasm_interp::Val::F32(x) => Val::F32(x),
+        wasm_interp::Val::F64(x) => Val::F64(x),
+        wasm_interp::Val::V128(x) => Val::V128(x),
+        wasm_interp::Val::FuncRef(f) => Val::FuncRef { null: f.is_none() },
+        wasm_interp::Val::ExternRef(e) => Val::ExternRef { null: e.is_none() },
+    }
+}
@@ -0,0 +1,453 @@
+//! A differential execution harness for WebAssembly engines.
+//!
+//! This crate generates modules with [`wasm_smith`] whose execution is
+//! deterministic: NaNs are canonicalized, trapping instructions are guarded,
+//! and all loops and calls consume fuel so that execution always terminates.
+//! Each module is then run on two [`Engine`]s, which instantiate it, invoke
+//! each of its exported functions in order, and snapshot its exported globals
+//! and memories after every step. The first difference between the two
+//! engines is reported as a [`Mismatch`], along with a smaller test case found
+//! with [`wasm_shrink`].
+//!
+//! The [`Interp`] engine, built on `wasm-interp`, is always available, and the
+//! [`Wasmtime`] engine is available with the `wasmtime` feature, which is
+//! enabled by default.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use wasm_differential::{Differential, Interp, Wasmtime};
+//!
+//! let mut differential = Differential::new(Interp::new(), Wasmtime::new());
+//! if let Some(mismatch) = differential.run(&mut u)? {
+//!     std::fs::write("mismatch.wasm", &mismatch.shrunk)?;
+//!     panic!("{}", mismatch.description);
+//! }
+//! ```
+
+use arbitrary::Unstructured;
+use std::fmt;
+use wasm_smith::{Config, Module};
+use wasmparser::{HeapType, Parser, Payload, ValType, Validator, WasmFeatures};
+
+mod interp;
+#[cfg(feature = "wasmtime")]
+mod wasmtime;
+
+pub use crate::interp::Interp;
+#[cfg(feature = "wasmtime")]
+pub use crate::wasmtime::Wasmtime;
+
+/// A WebAssembly value, as observed from outside of an engine.
+///
+/// Floating-point values are stored as their raw bits. References can't be
+/// compared across engines, so only whether they're null is recorded.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Val {
+    /// An `i32` value.
+    I32(i32),
+    /// An `i64` value.
+    I64(i64),
+    /// The bits of an `f32` value.
+    F32(u32),
+    /// The bits of an `f64` value.
+    F64(u64),
+    /// A `v128` value.
+    V128(u128),
+    /// A function reference.
+    FuncRef {
+        /// Whether this is the null reference.
+        null: bool,
+    },
+    /// An external reference.
+    ExternRef {
+        /// Whether this is the null reference.
+        null: bool,
+    },
+}
+
+/// The outcome of running code in an [`Engine`].
+#[derive(Clone, Debug)]
+pub enum Execution<T> {
+    /// Execution finished normally.
+    Ok(T),
+    /// Execution trapped.
+    ///
+    /// The message is specific to each engine, so it isn't compared.
+    Trap(String),
+    /// Execution couldn't finish for reasons other than the semantics of the
+    /// module, for example exhausting the native stack, running out of fuel,
+    /// or reaching an instruction that the engine doesn't support.
+    ///
+    /// No comparisons are made after an engine reports this.
+    Unsupported(String),
+}
+
+/// A WebAssembly engine which can be compared against others.
+///
+/// Modules given to engines have no imports, and only use the features
+/// returned by [`Engine::features`].
+pub trait Engine {
+    /// An instance of a module, along with any state it needs.
+    type Instance;
+
+    /// The name of this engine, used when describing mismatches.
+    fn name(&self) -> &str;
+
+    /// The WebAssembly features which this engine supports.
+    fn features(&self) -> WasmFeatures;
+
+    /// Compiles and instantiates `wasm`, running its start function if any.
+    fn instantiate(&mut self, wasm: &[u8]) -> Execution<Self::Instance>;
+
+    /// Invokes the exported function `name` of `instance` with `args`.
+    fn invoke(
+        &mut self,
+        instance: &mut Self::Instance,
+        name: &str,
+        args: &[Val],
+    ) -> Execution<Vec<Val>>;
+
+    /// Returns the current value of the exported global `name`, or `None` if
+    /// it can't be represented as a [`Val`].
+    fn global(&mut self, instance: &mut Self::Instance, name: &str) -> Option<Val>;
+
+    /// Returns the current contents of the exported memory `name`.
+    fn memory(&mut self, instance: &mut Self::Instance, name: &str) -> Option<Vec<u8>>;
+}
+
+/// A difference in behavior between two engines.
+#[derive(Clone, Debug)]
+pub struct Mismatch {
+    /// The module which the engines disagree on.
+    pub wasm: Vec<u8>,
+    /// A description of the first difference found.
+    pub description: String,
+    /// A smaller module which the engines still disagree on, or a copy of
+    /// `wasm` if it couldn't be shrunk.
+    pub shrunk: Vec<u8>,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.description.fmt(f)
+    }
+}
+
+/// Compares the execution of generated modules between the engines `A` and
+/// `B`.
+pub struct Differential<A, B> {
+    a: A,
+    b: B,
+    fuel: u32,
+    shrink_attempts: u32,
+}
+
+impl<A: Engine, B: Engine> Differential<A, B> {
+    /// Creates a new harness comparing the engines `a` and `b`.
+    pub fn new(a: A, b: B) -> Differential<A, B> {
+        Differential {
+            a,
+            b,
+            fuel: 100,
+            shrink_attempts: 1000,
+        }
+    }
+
+    /// Sets the amount of fuel given to generated modules, which is roughly
+    /// the number of loop iterations and calls they may execute in total.
+    ///
+    /// Defaults to 100.
+    pub fn fuel(mut self, fuel: u32) -> Self {
+        self.fuel = fuel;
+        self
+    }
+
+    /// Sets the number of attempts made to shrink a module which the engines
+    /// disagree on, where 0 disables shrinking.
+    ///
+    /// Defaults to 1000.
+    pub fn shrink_attempts(mut self, attempts: u32) -> Self {
+        self.shrink_attempts = attempts;
+        self
+    }
+
+    /// Generates an arbitrary module whose execution is deterministic.
+    pub fn generate(&self, u: &mut Unstructured<'_>) -> arbitrary::Result<Vec<u8>> {
+        let mut module = Module::new(config(u)?, u)?;
+        module
+            .ensure_termination(self.fuel)
+            .expect("generated functions can always be made to terminate");
+        Ok(module.to_bytes())
+    }
+
+    /// Generates an arbitrary module and checks that both engines agree on
+    /// its behavior.
+    pub fn run(&mut self, u: &mut Unstructured<'_>) -> arbitrary::Result<Option<Mismatch>> {
+        let wasm = self.generate(u)?;
+        Ok(self.check(&wasm))
+    }
+
+    /// Checks that both engines agree on the behavior of `wasm`, shrinking it
+    /// if they don't.
+    pub fn check(&mut self, wasm: &[u8]) -> Option<Mismatch> {
+        let description = self.compare(wasm)?;
+        log::debug!("found mismatch: {description}");
+        let shrunk = self.shrink(wasm);
+        Some(Mismatch {
+            wasm: wasm.to_vec(),
+            description,
+            shrunk,
+        })
+    }
+
+    /// Runs `wasm` on both engines, returning a description of the first
+    /// difference between them, if any.
+    ///
+    /// Modules which use features that either engine doesn't support, or for
+    /// which either engine reports [`Execution::Unsupported`], are only
+    /// compared up to that point.
+    pub fn compare(&mut self, wasm: &[u8]) -> Option<String> {
+        let exports = Exports::new(wasm, self.a.features() & self.b.features())?;
+
+        let a = self.a.instantiate(wasm);
+        let b = self.b.instantiate(wasm);
+        let (mut a, mut b) = match (a, b) {
+            (Execution::Ok(a), Execution::Ok(b)) => (a, b),
+            (a, b) => {
+                let a = map(a, |_| ());
+                let b = map(b, |_| ());
+                return self.compare_executions("instantiation", &a, &b).err()?;
+            }
+        };
+        if let Err(e) = self.compare_state("instantiation", &exports, &mut a, &mut b) {
+            return e;
+        }
+
+        for (name, args) in exports.funcs.iter() {
+            let step = format!("calling `{name}`");
+            let ra = self.a.invoke(&mut a, name, args);
+            let rb = self.b.invoke(&mut b, name, args);
+            if let Err(e) = self.compare_executions(&step, &ra, &rb) {
+                return e;
+            }
+            if let Err(e) = self.compare_state(&step, &exports, &mut a, &mut b) {
+                return e;
+            }
+        }
+        None
+    }
+
+    /// Compares the outcome of one step, returning `Err` if comparison should
+    /// stop, along with a description of the mismatch, if any.
+    fn compare_executions<T: PartialEq + fmt::Debug>(
+        &self,
+        step: &str,
+        a: &Execution<T>,
+        b: &Execution<T>,
+    ) -> Result<(), Option<String>> {
+        match (a, b) {
+            (Execution::Unsupported(msg), _) | (_, Execution::Unsupported(msg)) => {
+                log::debug!("stopping comparison at {step}: {msg}");
+                Err(None)
+            }
+            (Execution::Ok(x), Execution::Ok(y)) if x == y => Ok(()),
+            (Execution::Trap(_), Execution::Trap(_)) => Ok(()),
+            (a, b) => Err(Some(format!(
+                "{step}: {} {} but {} {}",
+                self.a.name(),
+                describe(a),
+                self.b.name(),
+                describe(b),
+            ))),
+        }
+    }
+
+    /// Compares the exported globals and memories of both instances.
+    fn compare_state(
+        &mut self,
+        step: &str,
+        exports: &Exports,
+        a: &mut A::Instance,
+        b: &mut B::Instance,
+    ) -> Result<(), Option<String>> {
+        for name in exports.globals.iter() {
+            let x = self.a.global(a, name);
+            let y = self.b.global(b, name);
+            if x != y {
+                return Err(Some(format!(
+                    "after {step}: global `{name}` is {x:?} in {} but {y:?} in {}",
+                    self.a.name(),
+                    self.b.name(),
+                )));
+            }
+        }
+        for name in exports.memories.iter() {
+            let x = self.a.memory(a, name).unwrap_or_default();
+            let y = self.b.memory(b, name).unwrap_or_default();
+            if x.len() != y.len() {
+                return Err(Some(format!(
+                    "after {step}: memory `{name}` is {} bytes in {} but {} bytes in {}",
+                    x.len(),
+                    self.a.name(),
+                    y.len(),
+                    self.b.name(),
+                )));
+            }
+            if let Some(i) = x.iter().zip(&y).position(|(x, y)| x != y) {
+                return Err(Some(format!(
+                    "after {step}: byte {i} of memory `{name}` is {:#x} in {} but {:#x} in {}",
+                    x[i],
+                    self.a.name(),
+                    y[i],
+                    self.b.name(),
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn shrink(&mut self, wasm: &[u8]) -> Vec<u8> {
+        if self.shrink_attempts == 0 {
+            return wasm.to_vec();
+        }
+        let result = wasm_shrink::WasmShrink::default()
+            .attempts(self.shrink_attempts)
+            .run(wasm.to_vec(), |candidate| {
+                Ok(self.compare(candidate).is_some())
+            });
+        match result {
+            Ok(info) => info.output,
+            Err(e) => {
+                log::warn!("failed to shrink mismatched module: {e:#}");
+                wasm.to_vec()
+            }
+        }
+    }
+}
+
+/// Returns a `wasm-smith` configuration for modules whose execution is
+/// deterministic and which only use widely supported proposals.
+///
+/// Imports are disabled, everything is exported so that all state of an
+/// instance can be observed, and memories and tables are kept small so that
+/// growing them behaves the same in all engines.
+pub fn config(u: &mut Unstructured<'_>) -> arbitrary::Result<Config> {
+    let mut config: Config = u.arbitrary()?;
+    config.canonicalize_nans = true;
+    config.disallow_traps = true;
+    config.allow_invalid_funcs = false;
+    config.export_everything = true;
+    config.available_imports = None;
+    config.min_imports = 0;
+    config.max_imports = 0;
+    config.memory_max_size_required = true;
+    config.max_memory32_bytes = config.max_memory32_bytes.min(1 << 20);
+    config.max_memory64_bytes = config.max_memory64_bytes.min(1 << 20);
+    config.table_max_size_required = true;
+    config.max_table_elements = config.max_table_elements.min(1_000);
+
+    // Threads and relaxed SIMD are nondeterministic, and the remaining
+    // proposals aren't supported by all engines.
+    config.threads_enabled = false;
+    config.relaxed_simd_enabled = false;
+    config.gc_enabled = false;
+    config.exceptions_enabled = false;
+    config.custom_page_sizes_enabled = false;
+    config.wide_arithmetic_enabled = false;
+    config.shared_everything_threads_enabled = false;
+    config.stack_switching_enabled = false;
+    Ok(config)
+}
+
+/// The exports of a module which are compared between engines.
+struct Exports {
+    /// Exported functions along with the arguments they're called with.
+    funcs: Vec<(String, Vec<Val>)>,
+    globals: Vec<String>,
+    memories: Vec<String>,
+}
+
+impl Exports {
+    /// Collects the exports of `wasm`, or returns `None` if it isn't valid
+    /// with `features`.
+    fn new(wasm: &[u8], features: WasmFeatures) -> Option<Exports> {
+        let types = match Validator::new_with_features(features).validate_all(wasm) {
+            Ok(types) => types,
+            Err(e) => {
+                log::debug!("not comparing module: {e}");
+                return None;
+            }
+        };
+        let mut exports = Exports {
+            funcs: Vec::new(),
+            globals: Vec::new(),
+            memories: Vec::new(),
+        };
+        for payload in Parser::new(0).parse_all(wasm) {
+            let Ok(Payload::ExportSection(reader)) = payload else {
+                continue;
+            };
+            for export in reader {
+                let export = export.ok()?;
+                let name = export.name.to_string();
+                match export.kind {
+                    wasmparser::ExternalKind::Func => {
+                        let types = types.as_ref();
+                        let ty = types[types.core_function_at(export.index)].unwrap_func();
+                        // Functions which take arguments that can't be created
+                        // from outside the engine are skipped.
+                        let args = ty.params().iter().map(|ty| dummy_value(*ty)).collect();
+                        if let Some(args) = args {
+                            exports.funcs.push((name, args));
+                        }
+                    }
+                    wasmparser::ExternalKind::Global => exports.globals.push(name),
+                    wasmparser::ExternalKind::Memory => exports.memories.push(name),
+                    _ => {}
+                }
+            }
+        }
+        Some(exports)
+    }
+}
+
+fn dummy_value(ty: ValType) -> Option<Val> {
+    Some(match ty {
+        ValType::I32 => Val::I32(0),
+        ValType::I64 => Val::I64(0),
+        ValType::F32 => Val::F32(0),
+        ValType::F64 => Val::F64(0),
+        ValType::V128 => Val::V128(0),
+        ValType::Ref(r) if r.is_nullable() => match r.heap_type() {
+            HeapType::Abstract { shared: false, ty } => match ty {
+                wasmparser::AbstractHeapType::Func | wasmparser::AbstractHeapType::NoFunc => {
+                    Val::FuncRef { null: true }
+                }
+                wasmparser::AbstractHeapType::Extern | wasmparser::AbstractHeapType::NoExtern => {
+                    Val::ExternRef { null: true }
+                }
+                _ => return None,
+            },
+            HeapType::Concrete(_) => Val::FuncRef { null: true },
+            _ => return None,
+        },
+        ValType::Ref(_) => return None,
+    })
+}
+
+fn map<T, U>(execution: Execution<T>, f: impl FnOnce(T) -> U) -> Execution<U> {
+    match execution {
+        Execution::Ok(t) => Execution::Ok(f(t)),
+        Execution::Trap(msg) => Execution::Trap(msg),
+        Execution::Unsupported(msg) => Execution::Unsupported(msg),
+    }
+}
+
+fn describe<T: fmt::Debug>(execution: &Execution<T>) -> String {
+    match execution {
+        Execution::Ok(t) => format!("returned {t:?}"),
+        Execution::Trap(msg) => format!("trapped ({msg})"),
+        Execution::Unsupported(msg) => format!("failed ({msg})"),
+    }
+}
@@ -0,0 +1,149 @@
+//! An [`Engine`] for Wasmtime.
+
+use crate::{Engine, Execution, Val};
+use wasmparser::WasmFeatures;
+use wasmtime::{Instance, Module, Store, Trap};
+
+/// The Wasmtime runtime, compiling modules with Cranelift.
+pub struct Wasmtime {
+    engine: wasmtime::Engine,
+    fuel: u64,
+}
+
+impl Wasmtime {
+    /// Creates a new Wasmtime engine with the features of
+    /// [`Engine::features`] enabled.
+    pub fn new() -> Wasmtime {
+        let mut config = wasmtime::Config::new();
+        config
+            .consume_fuel(true)
+            .wasm_multi_memory(true)
+            .wasm_memory64(true)
+            .wasm_extended_const(true)
+            .wasm_tail_call(true)
+            .wasm_relaxed_simd(false)
+            .wasm_gc(false)
+            .wasm_function_references(false)
+            .wasm_exceptions(false);
+        Wasmtime {
+            engine: wasmtime::Engine::new(&config).expect("valid Wasmtime configuration"),
+            fuel: 10_000_000,
+        }
+    }
+
+    /// Sets the amount of fuel each instance may consume, after which
+    /// execution stops with [`Execution::Unsupported`].
+    ///
+    /// This is only a safety net for modules which don't terminate on their
+    /// own. Defaults to ten million.
+    pub fn fuel(mut self, fuel: u64) -> Wasmtime {
+        self.fuel = fuel;
+        self
+    }
+}
+
+impl Default for Wasmtime {
+    fn default() -> Wasmtime {
+        Wasmtime::new()
+    }
+}
+
+impl Engine for Wasmtime {
+    type Instance = (Store<()>, Instance);
+
+    fn name(&self) -> &str {
+        "wasmtime"
+    }
+
+    fn features(&self) -> WasmFeatures {
+        WasmFeatures::WASM2
+            | WasmFeatures::MULTI_MEMORY
+            | WasmFeatures::MEMORY64
+            | WasmFeatures::EXTENDED_CONST
+            | WasmFeatures::TAIL_CALL
+    }
+
+    fn instantiate(&mut self, wasm: &[u8]) -> Execution<Self::Instance> {
+        let module = match Module::new(&self.engine, wasm) {
+            Ok(module) => module,
+            Err(e) => return Execution::Unsupported(format!("{e:#}")),
+        };
+        let mut store = Store::new(&self.engine, ());
+        store.set_fuel(self.fuel).unwrap();
+        match Instance::new(&mut store, &module, &[]) {
+            Ok(instance) => Execution::Ok((store, instance)),
+            Err(e) => classify(e),
+        }
+    }
+
+    fn invoke(
+        &mut self,
+        (store, instance): &mut Self::Instance,
+        name: &str,
+        args: &[Val],
+    ) -> Execution<Vec<Val>> {
+        let Some(func) = instance.get_func(&mut *store, name) else {
+            return Execution::Unsupported(format!("no exported function `{name}`"));
+        };
+        let Some(args) = args
+            .iter()
+            .map(|v| to_wasmtime(*v))
+            .collect::<Option<Vec<_>>>()
+        else {
+            return Execution::Unsupported("unsupported argument".to_string());
+        };
+        let mut results = vec![wasmtime::Val::I32(0); func.ty(&*store).results().len()];
+        if let Err(e) = func.call(&mut *store, &args, &mut results) {
+            return classify(e);
+        }
+        match results.into_iter().map(from_wasmtime).collect() {
+            Some(results) => Execution::Ok(results),
+            None => Execution::Unsupported("unsupported result".to_string()),
+        }
+    }
+
+    fn global(&mut self, (store, instance): &mut Self::Instance, name: &str) -> Option<Val> {
+        let global = instance.get_global(&mut *store, name)?;
+        from_wasmtime(global.get(&mut *store))
+    }
+
+    fn memory(&mut self, (store, instance): &mut Self::Instance, name: &str) -> Option<Vec<u8>> {
+        let memory = instance.get_memory(&mut *store, name)?;
+        Some(memory.data(&*store).to_vec())
+    }
+}
+
+fn classify<T>(e: anyhow::Error) -> Execution<T> {
+    match e.downcast_ref::<Trap>() {
+        // Running out of stack or fuel depends on the engine.
+        Some(Trap::StackOverflow | Trap::OutOfFuel) => Execution::Unsupported(e.to_string()),
+        Some(trap) => Execution::Trap(trap.to_string()),
+        None => Execution::Unsupported(format!("{e:#}")),
+    }
+}
+
+fn to_wasmtime(val: Val) -> Option<wasmtime::Val> {
+    Some(match val {
+        Val::I32(x) => wasmtime::Val::I32(x),
+        Val::I64(x) => wasmtime::Val::I64(x),
+        Val::F32(x) => wasmtime::Val::F32(x),
+        Val::F64(x) => wasmtime::Val::F64(x),
+        Val::V128(x) => wasmtime::Val::V128(x.into()),
+        Val::FuncRef { null: true } => wasmtime::Val::FuncRef(None),
+        Val::ExternRef { null: true } => wasmtime::Val::ExternRef(None),
+        Val::FuncRef { null: false } | Val::ExternRef { null: false } => return None,
+    })
+}
+
+fn from_wasmtime(val: wasmtime::Val) -> Option<Val> {
+    Some(match val {
+        wasmtime::Val::I32(x) => Val::I32(x),
+        wasmtime::Val::I64(x) => Val::I64(x),
+        wasmtime::Val::F32(x) => Val::F32(x),
+        wasmtime::Val::F64(x) => Val::F64(x),
+        wasmtime::Val::V128(x) => Val::V128(x.as_u128()),
+        wasmtime::Val::FuncRef(f) => Val::FuncRef { null: f.is_none() },
+        wasmtime::Val::ExternRef(e) => Val::ExternRef { null: e.is_none() },
+        wasmtime::Val::AnyRef(_) => return None,
+    })
+}
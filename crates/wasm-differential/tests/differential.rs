@@ -0,0 +1,123 @@
+use arbitrary::Unstructured;
+use rand::{RngCore, SeedableRng, rngs::SmallRng};
+use wasm_differential::{Differential, Engine, Execution, Interp, Val};
+use wasmparser::WasmFeatures;
+
+/// An engine which gets `i32` results wrong by one.
+struct OffByOne(Interp);
+
+impl Engine for OffByOne {
+    type Instance = <Interp as Engine>::Instance;
+
+    fn name(&self) -> &str {
+        "off-by-one"
+    }
+
+    fn features(&self) -> WasmFeatures {
+        self.0.features()
+    }
+
+    fn instantiate(&mut self, wasm: &[u8]) -> Execution<Self::Instance> {
+        self.0.instantiate(wasm)
+    }
+
+    fn invoke(
+        &mut self,
+        instance: &mut Self::Instance,
+        name: &str,
+        args: &[Val],
+    ) -> Execution<Vec<Val>> {
+        match self.0.invoke(instance, name, args) {
+            Execution::Ok(results) => Execution::Ok(
+                results
+                    .into_iter()
+                    .map(|v| match v {
+                        Val::I32(x) => Val::I32(x.wrapping_add(1)),
+                        v => v,
+                    })
+                    .collect(),
+            ),
+            other => other,
+        }
+    }
+
+    fn global(&mut self, instance: &mut Self::Instance, name: &str) -> Option<Val> {
+        self.0.global(instance, name)
+    }
+
+    fn memory(&mut self, instance: &mut Self::Instance, name: &str) -> Option<Vec<u8>> {
+        self.0.memory(instance, name)
+    }
+}
+
+fn for_each_module(n: usize, mut f: impl FnMut(&mut Unstructured<'_>)) {
+    let mut rng = SmallRng::seed_from_u64(0);
+    let mut buf = vec![0; 2048];
+    for _ in 0..n {
+        rng.fill_bytes(&mut buf);
+        f(&mut Unstructured::new(&buf));
+    }
+}
+
+#[test]
+fn interp_agrees_with_itself() {
+    let mut differential = Differential::new(Interp::new(), Interp::new());
+    for_each_module(100, |u| {
+        if let Ok(Some(mismatch)) = differential.run(u) {
+            panic!("{mismatch}");
+        }
+    });
+}
+
+#[cfg(feature = "wasmtime")]
+#[test]
+fn interp_agrees_with_wasmtime() {
+    use wasm_differential::Wasmtime;
+
+    let mut differential = Differential::new(Interp::new(), Wasmtime::new());
+    for_each_module(100, |u| {
+        if let Ok(Some(mismatch)) = differential.run(u) {
+            panic!("{mismatch}");
+        }
+    });
+}
+
+#[test]
+fn mismatches_are_shrunk() {
+    let wasm = wat::parse_str(
+        r#"
+            (module
+                (global (export "g") (mut i32) (i32.const 0))
+                (memory (export "m") 1 1)
+                (func (export "a") (result i64)
+                    global.get 0
+                    i32.const 1
+                    i32.add
+                    global.set 0
+                    i64.const 7)
+                (func (export "b") (param i32) (result i32)
+                    local.get 0
+                    i32.const 2
+                    i32.mul)
+                (func (export "c") (result f32)
+                    f32.const 1.5)
+            )
+        "#,
+    )
+    .unwrap();
+
+    let mut differential = Differential::new(Interp::new(), OffByOne(Interp::new()));
+    let mismatch = differential.check(&wasm).expect("engines should disagree");
+    assert!(
+        mismatch.description.contains("calling `b`"),
+        "{}",
+        mismatch.description
+    );
+    assert_eq!(mismatch.wasm, wasm);
+    assert!(mismatch.shrunk.len() < wasm.len());
+    assert!(differential.compare(&mismatch.shrunk).is_some());
+
+    // Both engines agree when no `i32` results are involved.
+    let wasm = wat::parse_str(r#"(module (func (export "a") (result i64) i64.const 1))"#).unwrap();
+    assert!(differential.compare(&wasm).is_none());
+}
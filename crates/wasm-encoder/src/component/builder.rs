@@ -39,6 +39,9 @@ pub struct ComponentBuilder {
     types: u32,
     components: u32,
     values: u32,
+
+    /// Types defined with `interned_ty`, used to deduplicate them.
+    interner: TypeInterner,
 }
 
 impl ComponentBuilder {
@@ -295,6 +298,21 @@ impl ComponentBuilder {
         (inc(&mut self.types), self.types().ty())
     }
 
+    /// Defines a type in this component, reusing a previous definition if
+    /// it's structurally identical.
+    ///
+    /// See [`ComponentTypeSection::interned_ty`] for more information.
+    pub fn interned_ty(&mut self, encode: impl FnOnce(ComponentTypeEncoder<'_>)) -> u32 {
+        let mut interner = mem::take(&mut self.interner);
+        let index = interner.intern(encode, |bytes| {
+            let (index, encoder) = self.ty();
+            encoder.0.extend_from_slice(bytes);
+            index
+        });
+        self.interner = interner;
+        index
+    }
+
     /// Creates a new instance type within this component.
     pub fn type_instance(&mut self, ty: &InstanceType) -> u32 {
         self.types().instance(ty);
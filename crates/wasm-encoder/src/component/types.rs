@@ -3,6 +3,7 @@ use crate::{
     Alias, ComponentExportKind, ComponentOuterAliasKind, ComponentSection, ComponentSectionId,
     ComponentTypeRef, CoreTypeEncoder, Encode, EntityType, ValType, encode_section,
 };
+use alloc::collections::BTreeMap;
 use alloc::vec::Vec;
 
 /// Represents the type of a core module.
@@ -166,6 +167,7 @@ pub struct ComponentType {
     core_types_added: u32,
     types_added: u32,
     instances_added: u32,
+    interner: TypeInterner,
 }
 
 impl ComponentType {
@@ -196,6 +198,21 @@ impl ComponentType {
         ComponentTypeEncoder(&mut self.bytes)
     }
 
+    /// Define a type in this component type, reusing a previous definition
+    /// if it's structurally identical.
+    ///
+    /// See [`ComponentTypeSection::interned_ty`] for more information.
+    pub fn interned_ty(&mut self, encode: impl FnOnce(ComponentTypeEncoder<'_>)) -> u32 {
+        let mut interner = core::mem::take(&mut self.interner);
+        let index = interner.intern(encode, |bytes| {
+            let index = self.types_added;
+            self.ty().0.extend_from_slice(bytes);
+            index
+        });
+        self.interner = interner;
+        index
+    }
+
     /// Defines an alias for an exported item of a prior instance or an
     /// outer type.
     pub fn alias(&mut self, alias: Alias<'_>) -> &mut Self {
@@ -303,6 +320,14 @@ impl InstanceType {
         self.0.ty()
     }
 
+    /// Define a type in this instance type, reusing a previous definition
+    /// if it's structurally identical.
+    ///
+    /// See [`ComponentTypeSection::interned_ty`] for more information.
+    pub fn interned_ty(&mut self, encode: impl FnOnce(ComponentTypeEncoder<'_>)) -> u32 {
+        self.0.interned_ty(encode)
+    }
+
     /// Defines an outer core type alias in this component type.
     pub fn alias(&mut self, alias: Alias<'_>) -> &mut Self {
         self.0.alias(alias);
@@ -425,7 +450,7 @@ pub(crate) fn encode_resultlist(sink: &mut Vec<u8>, ty: Option<ComponentValType>
 
 /// Used to encode component and instance types.
 #[derive(Debug)]
-pub struct ComponentTypeEncoder<'a>(&'a mut Vec<u8>);
+pub struct ComponentTypeEncoder<'a>(pub(crate) &'a mut Vec<u8>);
 
 impl<'a> ComponentTypeEncoder<'a> {
     /// Define a component type.
@@ -465,6 +490,43 @@ impl<'a> ComponentTypeEncoder<'a> {
     }
 }
 
+/// Hash-conses type definitions within a single type index space.
+///
+/// Types are keyed by their encoding. Any type indices referenced by a type
+/// refer to the same index space the type is defined in, so two types with
+/// the same encoding are structurally identical and one can be used in place
+/// of the other.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TypeInterner {
+    types: BTreeMap<Vec<u8>, u32>,
+}
+
+impl TypeInterner {
+    /// Encodes a type with `encode` and returns the index of a previous
+    /// definition of the same type, if any, or otherwise defines it with
+    /// `define` which returns the new index.
+    pub(crate) fn intern(
+        &mut self,
+        encode: impl FnOnce(ComponentTypeEncoder<'_>),
+        define: impl FnOnce(&[u8]) -> u32,
+    ) -> u32 {
+        let mut bytes = Vec::new();
+        encode(ComponentTypeEncoder(&mut bytes));
+
+        // Resource types are generative: each definition is a new type
+        // regardless of its structure, so these are never deduplicated.
+        if bytes.first() == Some(&0x3f) {
+            return define(&bytes);
+        }
+        if let Some(index) = self.types.get(&bytes) {
+            return *index;
+        }
+        let index = define(&bytes);
+        self.types.insert(bytes, index);
+        index
+    }
+}
+
 /// Represents a primitive component value type.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum PrimitiveValType {
@@ -710,6 +772,7 @@ impl ComponentDefinedTypeEncoder<'_> {
 pub struct ComponentTypeSection {
     bytes: Vec<u8>,
     num_added: u32,
+    interner: TypeInterner,
 }
 
 impl ComponentTypeSection {
@@ -737,6 +800,43 @@ impl ComponentTypeSection {
         ComponentTypeEncoder(&mut self.bytes)
     }
 
+    /// Encode a type into this section, reusing a previous definition if
+    /// it's structurally identical.
+    ///
+    /// The type is encoded with `encode` and if a type with the same encoding
+    /// was previously defined with this method then that type's index is
+    /// returned and nothing is added to this section. Otherwise the type is
+    /// appended to this section. Resource types are never deduplicated as
+    /// each definition of a resource is a distinct type.
+    ///
+    /// The index returned is the index of the type within this section.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use wasm_encoder::{ComponentTypeSection, ComponentValType, PrimitiveValType};
+    ///
+    /// let u8 = ComponentValType::Primitive(PrimitiveValType::U8);
+    /// let u32 = ComponentValType::Primitive(PrimitiveValType::U32);
+    ///
+    /// let mut types = ComponentTypeSection::new();
+    /// let a = types.interned_ty(|t| t.defined_type().list(u8));
+    /// let b = types.interned_ty(|t| t.defined_type().list(u8));
+    /// let c = types.interned_ty(|t| t.defined_type().list(u32));
+    /// assert_eq!((a, b, c), (0, 0, 1));
+    /// assert_eq!(types.len(), 2);
+    /// ```
+    pub fn interned_ty(&mut self, encode: impl FnOnce(ComponentTypeEncoder<'_>)) -> u32 {
+        let mut interner = core::mem::take(&mut self.interner);
+        let index = interner.intern(encode, |bytes| {
+            let index = self.num_added;
+            self.ty().0.extend_from_slice(bytes);
+            index
+        });
+        self.interner = interner;
+        index
+    }
+
     /// Define a component type in this type section.
     pub fn component(&mut self, ty: &ComponentType) -> &mut Self {
         self.ty().component(ty);
@@ -780,3 +880,101 @@ impl ComponentSection for ComponentTypeSection {
         ComponentSectionId::Type.into()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Component, ComponentBuilder};
+
+    fn list(ty: PrimitiveValType) -> impl FnOnce(ComponentTypeEncoder<'_>) {
+        move |t| t.defined_type().list(ComponentValType::Primitive(ty))
+    }
+
+    #[test]
+    fn identical_types_are_interned() {
+        let mut types = ComponentTypeSection::new();
+        let a = types.interned_ty(list(PrimitiveValType::U8));
+        let b = types.interned_ty(list(PrimitiveValType::U32));
+        let c = types.interned_ty(list(PrimitiveValType::U8));
+        let d = types.interned_ty(|t| {
+            t.function().params([("a", ComponentValType::Type(a))]);
+        });
+        let e = types.interned_ty(|t| {
+            t.function().params([("a", ComponentValType::Type(c))]);
+        });
+        assert_eq!((a, b, c, d, e), (0, 1, 0, 2, 2));
+        assert_eq!(types.len(), 3);
+    }
+
+    #[test]
+    fn resources_are_not_interned() {
+        let mut builder = ComponentBuilder::default();
+        let a = builder.interned_ty(|t| t.resource(ValType::I32, None));
+        let b = builder.interned_ty(|t| t.resource(ValType::I32, None));
+        assert_eq!((a, b), (0, 1));
+        assert_eq!(builder.type_count(), 2);
+
+        let bytes = builder.finish();
+        wasmparser::Validator::new().validate_all(&bytes).unwrap();
+    }
+
+    #[test]
+    fn section_indices_are_local() {
+        let mut builder = ComponentBuilder::default();
+        builder
+            .ty()
+            .1
+            .defined_type()
+            .list(ComponentValType::Primitive(PrimitiveValType::U8));
+        assert_eq!(builder.interned_ty(list(PrimitiveValType::U8)), 1);
+
+        // A second section's indices start from zero regardless of the types
+        // defined before it.
+        let mut component = Component::new();
+        let mut first = ComponentTypeSection::new();
+        first
+            .defined_type()
+            .list(ComponentValType::Primitive(PrimitiveValType::U8));
+        component.section(&first);
+        let mut second = ComponentTypeSection::new();
+        let a = second.interned_ty(list(PrimitiveValType::U8));
+        let b = second.interned_ty(list(PrimitiveValType::U8));
+        assert_eq!((a, b), (0, 0));
+        assert_eq!(second.len(), 1);
+        component.section(&second);
+        wasmparser::Validator::new()
+            .validate_all(&component.finish())
+            .unwrap();
+    }
+
+    #[test]
+    fn interning_is_scoped() {
+        let mut outer = ComponentType::new();
+        let a = outer.interned_ty(list(PrimitiveValType::U8));
+        let b = outer.interned_ty(list(PrimitiveValType::U8));
+        assert_eq!((a, b), (0, 0));
+
+        // Types in nested scopes are in a separate index space, so they're
+        // never folded into the outer scope's types.
+        let mut instance = InstanceType::new();
+        instance
+            .ty()
+            .defined_type()
+            .primitive(PrimitiveValType::U32);
+        let c = instance.interned_ty(list(PrimitiveValType::U8));
+        let d = instance.interned_ty(list(PrimitiveValType::U8));
+        assert_eq!((c, d), (1, 1));
+        assert_eq!(instance.type_count(), 2);
+
+        let mut component = ComponentType::new();
+        let e = component.interned_ty(list(PrimitiveValType::U8));
+        assert_eq!(e, 0);
+        assert_eq!(component.type_count(), 1);
+
+        let f = outer.interned_ty(|t| t.instance(&instance));
+        let g = outer.interned_ty(|t| t.component(&component));
+        let h = outer.interned_ty(|t| t.instance(&instance));
+        assert_eq!((f, g, h), (1, 2, 1));
+        assert_eq!(outer.type_count(), 3);
+    }
+}
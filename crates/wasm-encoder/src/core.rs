@@ -1,5 +1,6 @@
 mod branch_hints;
 mod code;
+mod code_metadata;
 mod custom;
 mod data;
 mod dump;
@@ -20,6 +21,7 @@ mod types;
 
 pub use branch_hints::*;
 pub use code::*;
+pub use code_metadata::*;
 pub use custom::*;
 pub use data::*;
 pub use dump::*;
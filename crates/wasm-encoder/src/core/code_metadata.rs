@@ -0,0 +1,131 @@
+use crate::{CustomSection, Encode, Section, SectionId};
+use alloc::borrow::Cow;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Helper structure to encode a generic `metadata.code.*` custom section.
+///
+/// Code metadata sections attach bytes to instructions, identified by their
+/// offset within a function body. This format was defined as part of the
+/// branch-hinting proposal for WebAssembly, and [`BranchHints`] is a
+/// specialization of it for the `metadata.code.branch_hint` section.
+///
+/// [`BranchHints`]: crate::BranchHints
+///
+/// # Example
+///
+/// ```
+/// use wasm_encoder::*;
+///
+/// let mut module = Module::new();
+///
+/// let mut types = TypeSection::new();
+/// types.ty().function([], []);
+/// module.section(&types);
+///
+/// let mut funcs = FunctionSection::new();
+/// funcs.function(0);
+/// module.section(&funcs);
+///
+/// let mut code = CodeSection::new();
+/// let mut body = Function::new([]);
+///
+/// let nop_offset = body.byte_len();
+/// body.instructions().nop().end();
+/// code.function(&body);
+///
+/// let mut metadata = CodeMetadataSection::new("my_hint");
+/// metadata.function_metadata(0, [CodeMetadataEntry {
+///     func_offset: nop_offset as u32,
+///     data: b"hi",
+/// }]);
+/// module.section(&metadata);
+/// module.section(&code);
+///
+/// let wasm = module.finish();
+/// let wat = wasmprinter::print_bytes(&wasm).unwrap();
+/// assert_eq!(wat, r#"(module
+///   (type (;0;) (func))
+///   (func (;0;) (type 0)
+///     (@metadata.code.my_hint "hi")
+///     nop
+///   )
+/// )
+/// "#);
+/// ```
+#[derive(Debug)]
+pub struct CodeMetadataSection {
+    name: String,
+    bytes: Vec<u8>,
+    num_funcs: u32,
+}
+
+/// A single piece of metadata for an instruction within a function.
+#[derive(Debug, Clone, Copy)]
+pub struct CodeMetadataEntry<'a> {
+    /// The offset, in bytes from the beginning of the function, to the
+    /// instruction that this metadata applies to.
+    pub func_offset: u32,
+    /// The payload of this metadata.
+    pub data: &'a [u8],
+}
+
+impl CodeMetadataSection {
+    /// Construct an empty encoder for the `metadata.code.{name}` custom
+    /// section.
+    pub fn new(name: &str) -> Self {
+        CodeMetadataSection {
+            name: format!("metadata.code.{name}"),
+            bytes: Vec::new(),
+            num_funcs: 0,
+        }
+    }
+
+    /// Adds the metadata for the instructions of the `func` specified.
+    pub fn function_metadata<'a, I>(&mut self, func: u32, entries: I)
+    where
+        I: IntoIterator<Item = CodeMetadataEntry<'a>>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        self.num_funcs += 1;
+        func.encode(&mut self.bytes);
+        let entries = entries.into_iter();
+        entries.len().encode(&mut self.bytes);
+        for entry in entries {
+            entry.func_offset.encode(&mut self.bytes);
+            entry.data.encode(&mut self.bytes);
+        }
+    }
+
+    /// Returns if this is an empty section.
+    pub fn is_empty(&self) -> bool {
+        self.num_funcs == 0
+    }
+
+    /// Returns the number of functions that have metadata registered in this
+    /// section.
+    pub fn len(&self) -> u32 {
+        self.num_funcs
+    }
+}
+
+impl Encode for CodeMetadataSection {
+    fn encode(&self, sink: &mut Vec<u8>) {
+        let mut data = Vec::new();
+        self.num_funcs.encode(&mut data);
+        data.extend(&self.bytes);
+
+        CustomSection {
+            name: Cow::Borrowed(&self.name),
+            data: Cow::Borrowed(&data),
+        }
+        .encode(sink);
+    }
+}
+
+impl Section for CodeMetadataSection {
+    fn id(&self) -> u8 {
+        SectionId::Custom.into()
+    }
+}
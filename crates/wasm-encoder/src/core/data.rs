@@ -66,6 +66,32 @@ pub enum DataSegmentMode<'a> {
     Passive,
 }
 
+impl DataSegmentMode<'_> {
+    /// Encodes the prefix of a data segment which precedes its data.
+    pub(crate) fn encode(&self, sink: &mut Vec<u8>) {
+        match *self {
+            DataSegmentMode::Passive => {
+                sink.push(0x01);
+            }
+            DataSegmentMode::Active {
+                memory_index: 0,
+                offset,
+            } => {
+                sink.push(0x00);
+                offset.encode(sink);
+            }
+            DataSegmentMode::Active {
+                memory_index,
+                offset,
+            } => {
+                sink.push(0x02);
+                memory_index.encode(sink);
+                offset.encode(sink);
+            }
+        }
+    }
+}
+
 impl DataSection {
     /// Create a new data section encoder.
     pub fn new() -> Self {
@@ -88,26 +114,7 @@ impl DataSection {
         D: IntoIterator<Item = u8>,
         D::IntoIter: ExactSizeIterator,
     {
-        match segment.mode {
-            DataSegmentMode::Passive => {
-                self.bytes.push(0x01);
-            }
-            DataSegmentMode::Active {
-                memory_index: 0,
-                offset,
-            } => {
-                self.bytes.push(0x00);
-                offset.encode(&mut self.bytes);
-            }
-            DataSegmentMode::Active {
-                memory_index,
-                offset,
-            } => {
-                self.bytes.push(0x02);
-                memory_index.encode(&mut self.bytes);
-                offset.encode(&mut self.bytes);
-            }
-        }
+        segment.mode.encode(&mut self.bytes);
 
         let data = segment.data.into_iter();
         data.len().encode(&mut self.bytes);
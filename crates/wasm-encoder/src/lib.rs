@@ -84,11 +84,15 @@ mod core;
 mod raw;
 #[cfg(feature = "wasmparser")]
 pub mod reencode;
+#[cfg(feature = "std")]
+mod writer;
 
 #[cfg(feature = "component-model")]
 pub use self::component::*;
 pub use self::core::*;
 pub use self::raw::*;
+#[cfg(feature = "std")]
+pub use self::writer::*;
 
 use alloc::vec::Vec;
 
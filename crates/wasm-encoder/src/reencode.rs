@@ -1622,6 +1622,22 @@ pub mod utils {
         reencoder: &mut T,
         arg: wasmparser::Operator<'a>,
     ) -> Result<crate::Instruction<'a>, Error<T::Error>> {
+        translate_instruction(reencoder, arg)
+    }
+
+    /// Translates a single operator into an instruction which owns all of its
+    /// data and doesn't borrow from `arg`.
+    pub fn owned_instruction<T: ?Sized + Reencode>(
+        reencoder: &mut T,
+        arg: wasmparser::Operator<'_>,
+    ) -> Result<crate::Instruction<'static>, Error<T::Error>> {
+        translate_instruction(reencoder, arg)
+    }
+
+    fn translate_instruction<'b, T: ?Sized + Reencode>(
+        reencoder: &mut T,
+        arg: wasmparser::Operator<'_>,
+    ) -> Result<crate::Instruction<'b>, Error<T::Error>> {
         use crate::Instruction;
         use alloc::borrow::Cow;
 
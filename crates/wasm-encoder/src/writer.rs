@@ -0,0 +1,476 @@
+//! Streaming encoders which write directly to an [`io::Write`] and
+//! [`io::Seek`] destination.
+//!
+//! The [`Module`](crate::Module) and [`Component`](crate::Component) builders
+//! buffer an entire binary in memory, and sections such as the code section
+//! buffer all of their contents before being appended. For very large binaries
+//! the writers in this module can be used instead. Sections are written as
+//! they're added, and sections whose contents are added piecemeal, such as
+//! [`CodeSectionWriter`] and [`DataSectionWriter`], write each function body
+//! or data segment immediately. Section sizes and item counts are patched in
+//! afterwards by seeking back to a fixed-width placeholder, so memory usage is
+//! bounded by the largest single item rather than the whole binary.
+//!
+//! Note that placeholders are encoded as padded 5-byte LEB128 integers, so the
+//! output is not byte-for-byte identical to what the in-memory builders
+//! produce, although it is equivalent.
+
+use crate::{DataSegment, Encode, Function, Section, SectionId};
+use alloc::vec::Vec;
+use std::io::{self, Seek, SeekFrom, Write};
+
+/// The width, in bytes, of a placeholder LEB128 integer which is patched
+/// later.
+const PLACEHOLDER_LEN: u64 = 5;
+
+/// Writes a fixed-width placeholder for a `u32` to be patched later with
+/// [`patch`], returning the position of the placeholder.
+fn placeholder(dst: &mut (impl Write + Seek)) -> io::Result<u64> {
+    let pos = dst.stream_position()?;
+    dst.write_all(&[0; PLACEHOLDER_LEN as usize])?;
+    Ok(pos)
+}
+
+/// Overwrites the placeholder at `pos` with `value`, encoded as a padded
+/// LEB128 integer, and then restores the position of `dst`.
+fn patch(dst: &mut (impl Write + Seek), pos: u64, value: u32) -> io::Result<()> {
+    let end = dst.stream_position()?;
+    dst.seek(SeekFrom::Start(pos))?;
+    dst.write_all(&[
+        (value as u8 & 0x7f) | 0x80,
+        ((value >> 7) as u8 & 0x7f) | 0x80,
+        ((value >> 14) as u8 & 0x7f) | 0x80,
+        ((value >> 21) as u8 & 0x7f) | 0x80,
+        (value >> 28) as u8,
+    ])?;
+    dst.seek(SeekFrom::Start(end))?;
+    Ok(())
+}
+
+/// Patches the size placeholder at `pos` with the number of bytes written
+/// since the placeholder.
+fn patch_size(dst: &mut (impl Write + Seek), pos: u64) -> io::Result<()> {
+    let end = dst.stream_position()?;
+    let size = u32::try_from(end - pos - PLACEHOLDER_LEN)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "section too large"))?;
+    patch(dst, pos, size)
+}
+
+/// A streaming encoder for a WebAssembly module.
+///
+/// This is the streaming equivalent of [`Module`](crate::Module). As with
+/// `Module` it's the caller's responsibility to write sections in the proper
+/// order.
+///
+/// # Example
+///
+/// ```
+/// use std::io::Cursor;
+/// use wasm_encoder::{
+///     Function, FunctionSection, Instruction, ModuleWriter, TypeSection, ValType,
+/// };
+///
+/// let mut types = TypeSection::new();
+/// types.ty().function([], [ValType::I32]);
+/// let mut functions = FunctionSection::new();
+/// functions.function(0);
+///
+/// let mut module = ModuleWriter::new(Cursor::new(Vec::new()))?;
+/// module.section(&types)?;
+/// module.section(&functions)?;
+///
+/// let mut code = module.code_section()?;
+/// let mut f = Function::new([]);
+/// f.instructions().i32_const(42).end();
+/// code.function(&f)?;
+/// code.finish()?;
+///
+/// let wasm = module.finish()?.into_inner();
+/// assert!(wasmparser::validate(&wasm).is_ok());
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct ModuleWriter<W: Write + Seek> {
+    dst: W,
+    /// Position of the size placeholder if this module is nested within a
+    /// component.
+    size_at: Option<u64>,
+}
+
+impl<W: Write + Seek> ModuleWriter<W> {
+    /// Begins writing a new module to `dst`, writing the module header
+    /// immediately.
+    pub fn new(dst: W) -> io::Result<Self> {
+        Self::new_nested(dst, None)
+    }
+
+    fn new_nested(mut dst: W, size_at: Option<u64>) -> io::Result<Self> {
+        dst.write_all(&crate::Module::HEADER)?;
+        Ok(ModuleWriter { dst, size_at })
+    }
+
+    /// Writes a section into this module.
+    ///
+    /// The section is encoded in memory first, so this is best suited for
+    /// sections which are small. Use [`ModuleWriter::code_section`] and
+    /// [`ModuleWriter::data_section`] to write those sections incrementally.
+    pub fn section(&mut self, section: &impl Section) -> io::Result<&mut Self> {
+        let mut bytes = Vec::new();
+        section.append_to(&mut bytes);
+        self.dst.write_all(&bytes)?;
+        Ok(self)
+    }
+
+    /// Begins a code section which writes each function body as it's added.
+    ///
+    /// The returned writer must be [finished](CodeSectionWriter::finish)
+    /// before any other sections are written.
+    pub fn code_section(&mut self) -> io::Result<CodeSectionWriter<'_, W>> {
+        Ok(CodeSectionWriter {
+            section: VecSectionWriter::new(&mut self.dst, SectionId::Code)?,
+        })
+    }
+
+    /// Begins a data section which writes each data segment as it's added.
+    ///
+    /// The returned writer must be [finished](DataSectionWriter::finish)
+    /// before any other sections are written.
+    pub fn data_section(&mut self) -> io::Result<DataSectionWriter<'_, W>> {
+        Ok(DataSectionWriter {
+            section: VecSectionWriter::new(&mut self.dst, SectionId::Data)?,
+        })
+    }
+
+    /// Finishes writing this module, returning the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        if let Some(pos) = self.size_at {
+            patch_size(&mut self.dst, pos)?;
+        }
+        Ok(self.dst)
+    }
+}
+
+/// A section of a vector of items whose size and count are patched in when
+/// the section is finished.
+#[derive(Debug)]
+struct VecSectionWriter<'a, W: Write + Seek> {
+    dst: &'a mut W,
+    size_at: u64,
+    count_at: u64,
+    count: u32,
+    scratch: Vec<u8>,
+}
+
+impl<'a, W: Write + Seek> VecSectionWriter<'a, W> {
+    fn new(dst: &'a mut W, id: SectionId) -> io::Result<Self> {
+        dst.write_all(&[id.into()])?;
+        let size_at = placeholder(dst)?;
+        let count_at = placeholder(dst)?;
+        Ok(VecSectionWriter {
+            dst,
+            size_at,
+            count_at,
+            count: 0,
+            scratch: Vec::new(),
+        })
+    }
+
+    /// Writes the contents of `scratch` as a new item.
+    fn flush_item(&mut self) -> io::Result<()> {
+        self.dst.write_all(&self.scratch)?;
+        self.scratch.clear();
+        self.count += 1;
+        Ok(())
+    }
+
+    fn finish(self) -> io::Result<()> {
+        patch(self.dst, self.count_at, self.count)?;
+        patch_size(self.dst, self.size_at)
+    }
+}
+
+/// A streaming encoder for the code section of a module, created with
+/// [`ModuleWriter::code_section`].
+#[derive(Debug)]
+pub struct CodeSectionWriter<'a, W: Write + Seek> {
+    section: VecSectionWriter<'a, W>,
+}
+
+impl<W: Write + Seek> CodeSectionWriter<'_, W> {
+    /// The number of functions written so far.
+    pub fn len(&self) -> u32 {
+        self.section.count
+    }
+
+    /// Determines if no functions have been written yet.
+    pub fn is_empty(&self) -> bool {
+        self.section.count == 0
+    }
+
+    /// Writes a function body into this code section.
+    pub fn function(&mut self, func: &Function) -> io::Result<&mut Self> {
+        func.encode(&mut self.section.scratch);
+        self.section.flush_item()?;
+        Ok(self)
+    }
+
+    /// Writes a raw, already-encoded, function body into this code section.
+    ///
+    /// As with [`CodeSection::raw`](crate::CodeSection::raw) the length prefix
+    /// of the function body is automatically prepended and should not be
+    /// included in `data`.
+    pub fn raw(&mut self, data: &[u8]) -> io::Result<&mut Self> {
+        data.len().encode(&mut self.section.scratch);
+        self.section.flush_item()?;
+        self.section.dst.write_all(data)?;
+        Ok(self)
+    }
+
+    /// Finishes this section by writing its size and number of functions.
+    pub fn finish(self) -> io::Result<()> {
+        self.section.finish()
+    }
+}
+
+/// A streaming encoder for the data section of a module, created with
+/// [`ModuleWriter::data_section`].
+#[derive(Debug)]
+pub struct DataSectionWriter<'a, W: Write + Seek> {
+    section: VecSectionWriter<'a, W>,
+}
+
+impl<W: Write + Seek> DataSectionWriter<'_, W> {
+    /// The number of data segments written so far.
+    pub fn len(&self) -> u32 {
+        self.section.count
+    }
+
+    /// Determines if no data segments have been written yet.
+    pub fn is_empty(&self) -> bool {
+        self.section.count == 0
+    }
+
+    /// Writes a data segment into this data section.
+    ///
+    /// The data of the segment is copied to the destination without being
+    /// buffered in its entirety.
+    pub fn segment<D>(&mut self, segment: DataSegment<D>) -> io::Result<&mut Self>
+    where
+        D: IntoIterator<Item = u8>,
+        D::IntoIter: ExactSizeIterator,
+    {
+        segment.mode.encode(&mut self.section.scratch);
+        let data = segment.data.into_iter();
+        data.len().encode(&mut self.section.scratch);
+        self.section.flush_item()?;
+
+        let mut buf = [0; 4096];
+        let mut len = 0;
+        for byte in data {
+            buf[len] = byte;
+            len += 1;
+            if len == buf.len() {
+                self.section.dst.write_all(&buf)?;
+                len = 0;
+            }
+        }
+        self.section.dst.write_all(&buf[..len])?;
+        Ok(self)
+    }
+
+    /// Copies an already-encoded data segment into this data section.
+    pub fn raw(&mut self, already_encoded_data_segment: &[u8]) -> io::Result<&mut Self> {
+        self.section.flush_item()?;
+        self.section.dst.write_all(already_encoded_data_segment)?;
+        Ok(self)
+    }
+
+    /// Finishes this section by writing its size and number of segments.
+    pub fn finish(self) -> io::Result<()> {
+        self.section.finish()
+    }
+}
+
+#[cfg(feature = "component-model")]
+pub use self::component::*;
+
+#[cfg(feature = "component-model")]
+mod component {
+    use super::*;
+    use crate::{ComponentSection, ComponentSectionId};
+
+    /// A streaming encoder for a WebAssembly component.
+    ///
+    /// This is the streaming equivalent of [`Component`](crate::Component).
+    /// Nested modules and components are also written in a streaming fashion
+    /// with [`ComponentWriter::core_module`] and
+    /// [`ComponentWriter::component`].
+    #[derive(Debug)]
+    pub struct ComponentWriter<W: Write + Seek> {
+        dst: W,
+        /// Position of the size placeholder if this component is nested
+        /// within another component.
+        size_at: Option<u64>,
+    }
+
+    impl<W: Write + Seek> ComponentWriter<W> {
+        /// Begins writing a new component to `dst`, writing the component
+        /// header immediately.
+        pub fn new(dst: W) -> io::Result<Self> {
+            Self::new_nested(dst, None)
+        }
+
+        fn new_nested(mut dst: W, size_at: Option<u64>) -> io::Result<Self> {
+            dst.write_all(&crate::Component::HEADER)?;
+            Ok(ComponentWriter { dst, size_at })
+        }
+
+        /// Writes a section into this component.
+        pub fn section(&mut self, section: &impl ComponentSection) -> io::Result<&mut Self> {
+            let mut bytes = Vec::new();
+            section.append_to_component(&mut bytes);
+            self.dst.write_all(&bytes)?;
+            Ok(self)
+        }
+
+        /// Begins a nested core module section within this component.
+        ///
+        /// The returned writer must be [finished](ModuleWriter::finish)
+        /// before any other sections are written to this component.
+        pub fn core_module(&mut self) -> io::Result<ModuleWriter<&mut W>> {
+            self.dst
+                .write_all(&[ComponentSectionId::CoreModule.into()])?;
+            let size_at = placeholder(&mut self.dst)?;
+            ModuleWriter::new_nested(&mut self.dst, Some(size_at))
+        }
+
+        /// Begins a nested component section within this component.
+        ///
+        /// The returned writer must be [finished](ComponentWriter::finish)
+        /// before any other sections are written to this component.
+        pub fn component(&mut self) -> io::Result<ComponentWriter<&mut W>> {
+            self.dst
+                .write_all(&[ComponentSectionId::Component.into()])?;
+            let size_at = placeholder(&mut self.dst)?;
+            ComponentWriter::new_nested(&mut self.dst, Some(size_at))
+        }
+
+        /// Finishes writing this component, returning the underlying writer.
+        pub fn finish(mut self) -> io::Result<W> {
+            if let Some(pos) = self.size_at {
+                patch_size(&mut self.dst, pos)?;
+            }
+            Ok(self.dst)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+    use std::io::Cursor;
+
+    fn module_sections() -> (TypeSection, FunctionSection, MemorySection) {
+        let mut types = TypeSection::new();
+        types.ty().function([ValType::I32], [ValType::I32]);
+        let mut functions = FunctionSection::new();
+        functions.function(0);
+        functions.function(0);
+        let mut memories = MemorySection::new();
+        memories.memory(MemoryType {
+            minimum: 1,
+            maximum: None,
+            memory64: false,
+            shared: false,
+            page_size_log2: None,
+        });
+        (types, functions, memories)
+    }
+
+    fn function() -> Function {
+        let mut f = Function::new([(1, ValType::I64)]);
+        f.instructions().local_get(0).end();
+        f
+    }
+
+    #[test]
+    fn module_matches_in_memory_encoding() {
+        let (types, functions, memories) = module_sections();
+        let big = vec![0xab; 10_000];
+
+        let mut expected = Module::new();
+        let mut code = CodeSection::new();
+        code.function(&function());
+        code.raw(&[0, 0x20, 0, 0x0b]);
+        let mut data = DataSection::new();
+        data.active(0, &ConstExpr::i32_const(0), big.iter().copied());
+        data.passive([1, 2, 3]);
+        expected
+            .section(&types)
+            .section(&functions)
+            .section(&memories)
+            .section(&code)
+            .section(&data);
+        let expected = expected.finish();
+
+        let mut module = ModuleWriter::new(Cursor::new(Vec::new())).unwrap();
+        module.section(&types).unwrap();
+        module.section(&functions).unwrap();
+        module.section(&memories).unwrap();
+        let mut code = module.code_section().unwrap();
+        code.function(&function()).unwrap();
+        code.raw(&[0, 0x20, 0, 0x0b]).unwrap();
+        assert_eq!(code.len(), 2);
+        code.finish().unwrap();
+        let mut data = module.data_section().unwrap();
+        data.segment(DataSegment {
+            mode: DataSegmentMode::Active {
+                memory_index: 0,
+                offset: &ConstExpr::i32_const(0),
+            },
+            data: big.iter().copied(),
+        })
+        .unwrap();
+        data.segment(DataSegment {
+            mode: DataSegmentMode::Passive,
+            data: [1, 2, 3],
+        })
+        .unwrap();
+        data.finish().unwrap();
+        let actual = module.finish().unwrap().into_inner();
+
+        wasmparser::validate(&actual).unwrap();
+        assert_eq!(
+            wasmprinter::print_bytes(&expected).unwrap(),
+            wasmprinter::print_bytes(&actual).unwrap(),
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "component-model")]
+    fn nested_in_component() {
+        let (types, functions, _) = module_sections();
+
+        let mut component = ComponentWriter::new(Cursor::new(Vec::new())).unwrap();
+        let mut module = component.core_module().unwrap();
+        module.section(&types).unwrap();
+        module.section(&functions).unwrap();
+        let mut code = module.code_section().unwrap();
+        code.function(&function()).unwrap();
+        code.function(&function()).unwrap();
+        code.finish().unwrap();
+        module.finish().unwrap();
+
+        let mut nested = component.component().unwrap();
+        nested.core_module().unwrap().finish().unwrap();
+        nested.finish().unwrap();
+
+        let mut instances = InstanceSection::new();
+        instances.instantiate(0, Vec::<(&str, ModuleArg)>::new());
+        component.section(&instances).unwrap();
+        let actual = component.finish().unwrap().into_inner();
+
+        wasmparser::Validator::new().validate_all(&actual).unwrap();
+    }
+}
@@ -0,0 +1,996 @@
+//! The interpreter loop, which executes function bodies one operator at a
+//! time.
+//!
+//! Operators are decoded from the original module bytes each time they're
+//! executed. Structured control flow is implemented with a stack of labels
+//! per frame along with the side table of block targets computed in
+//! `Module::new`, and all calls, including tail calls, push and pop frames on
+//! an explicit stack rather than recursing.
+
+use crate::module::{Code, ModuleInner};
+use crate::num;
+use crate::store::{FuncInst, Store};
+use crate::{Func, Module, Val, trap};
+use anyhow::{Result, bail};
+use wasmparser::{
+    AbstractHeapType, BinaryReader, BlockType, FrameKind, FrameStack, HeapType, MemArg, Operator,
+    ValType, VisitOperator, VisitSimdOperator,
+};
+
+/// The maximum number of frames on the call stack before execution traps.
+const MAX_FRAMES: usize = 10_000;
+
+pub(crate) struct Exec<'s, 'm> {
+    pub store: &'s mut Store,
+    modules: &'m [Module],
+    pub stack: Vec<Val>,
+    frames: Vec<Frame<'m>>,
+}
+
+struct Frame<'m> {
+    module: &'m ModuleInner,
+    instance: usize,
+    code: &'m Code,
+    reader: BinaryReader<'m>,
+    locals: Vec<Val>,
+    /// The labels of all blocks entered in this frame, where the first label
+    /// is the function body itself.
+    labels: Vec<Label>,
+}
+
+#[derive(Copy, Clone)]
+struct Label {
+    kind: FrameKind,
+    /// The number of values a branch to this label carries.
+    arity: usize,
+    /// The height of the value stack below this label's parameters.
+    height: usize,
+    /// The offset that a branch to this label continues at.
+    cont: usize,
+}
+
+/// Returns the null reference of the type `heap`.
+pub(crate) fn null_ref(heap: HeapType) -> Result<Val> {
+    match heap {
+        HeapType::Abstract {
+            shared: false,
+            ty: AbstractHeapType::Extern | AbstractHeapType::NoExtern,
+        } => Ok(Val::ExternRef(None)),
+        HeapType::Abstract {
+            shared: false,
+            ty: AbstractHeapType::Func | AbstractHeapType::NoFunc,
+        }
+        | HeapType::Concrete(_) => Ok(Val::FuncRef(None)),
+        _ => bail!("unsupported reference type: {heap:?}"),
+    }
+}
+
+fn default_val(ty: ValType) -> Result<Val> {
+    Ok(match ty {
+        ValType::I32 => Val::I32(0),
+        ValType::I64 => Val::I64(0),
+        ValType::F32 => Val::F32(0),
+        ValType::F64 => Val::F64(0),
+        ValType::V128 => Val::V128(0),
+        ValType::Ref(r) => null_ref(r.heap_type())?,
+    })
+}
+
+macro_rules! unop {
+    ($self:ident, $pop:ident, $ctor:ident, |$a:ident| $e:expr) => {{
+        let $a = $self.$pop();
+        $self.push(Val::$ctor($e));
+    }};
+}
+
+macro_rules! binop {
+    ($self:ident, $pop:ident, $ctor:ident, |$a:ident, $b:ident| $e:expr) => {{
+        let $b = $self.$pop();
+        let $a = $self.$pop();
+        $self.push(Val::$ctor($e));
+    }};
+}
+
+macro_rules! cmpop {
+    ($self:ident, $pop:ident, |$a:ident, $b:ident| $e:expr) => {{
+        let $b = $self.$pop();
+        let $a = $self.$pop();
+        $self.push(Val::I32(i32::from($e)));
+    }};
+}
+
+impl<'s, 'm> Exec<'s, 'm> {
+    pub fn new(store: &'s mut Store, modules: &'m [Module]) -> Exec<'s, 'm> {
+        Exec {
+            store,
+            modules,
+            stack: Vec::new(),
+            frames: Vec::new(),
+        }
+    }
+
+    pub fn invoke(mut self, func: Func, args: &[Val]) -> Result<Vec<Val>> {
+        self.stack.extend_from_slice(args);
+        self.call(func)?;
+        while let Some(frame) = self.frames.last_mut() {
+            if let Some(fuel) = &mut self.store.fuel {
+                if *fuel == 0 {
+                    return Err(trap!("all fuel consumed"));
+                }
+                *fuel -= 1;
+            }
+            let offset = frame.reader.original_position();
+            let kind = frame.labels.last().unwrap().kind;
+            let op = frame.reader.visit_operator(&mut ReadOperator(kind))?;
+            self.step(op, offset)?;
+        }
+        Ok(self.stack)
+    }
+
+    fn call(&mut self, func: Func) -> Result<()> {
+        match &self.store.funcs[func.0] {
+            FuncInst::Host { ty, func } => {
+                let func = func.clone();
+                let args = self.stack.split_off(self.stack.len() - ty.params().len());
+                let results = func(&args)?;
+                self.stack.extend(results);
+            }
+            FuncInst::Wasm {
+                ty,
+                instance,
+                index,
+            } => {
+                if self.frames.len() >= MAX_FRAMES {
+                    return Err(trap!("call stack exhausted"));
+                }
+                let module = &*self.modules[*instance].inner;
+                let code = &module.code[*index];
+                let mut locals = self.stack.split_off(self.stack.len() - ty.params().len());
+                for ty in code.locals.iter() {
+                    locals.push(default_val(*ty)?);
+                }
+                let label = Label {
+                    kind: FrameKind::Block,
+                    arity: ty.results().len(),
+                    height: self.stack.len(),
+                    cont: code.body.end,
+                };
+                self.frames.push(Frame {
+                    module,
+                    instance: *instance,
+                    code,
+                    reader: reader_at(module, code, code.body.start),
+                    locals,
+                    labels: vec![label],
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Replaces the current frame with a call to `func`.
+    fn return_call(&mut self, func: Func) -> Result<()> {
+        let params = self.store.func_ty(func).params().len();
+        let frame = self.frames.pop().unwrap();
+        let height = frame.labels[0].height;
+        let len = self.stack.len();
+        self.stack.drain(height..len - params);
+        self.call(func)
+    }
+
+    fn frame(&mut self) -> &mut Frame<'m> {
+        self.frames.last_mut().unwrap()
+    }
+
+    /// Branches to the label `depth` labels up from the innermost one.
+    fn branch(&mut self, depth: u32) {
+        let frame = self.frames.last_mut().unwrap();
+        let index = frame.labels.len() - 1 - depth as usize;
+        let label = frame.labels[index];
+        let len = self.stack.len();
+        self.stack.drain(label.height..len - label.arity);
+        if index == 0 {
+            self.frames.pop();
+        } else if label.kind == FrameKind::Loop {
+            frame.labels.truncate(index + 1);
+            frame.jump(label.cont);
+        } else {
+            frame.labels.truncate(index);
+            frame.jump(label.cont);
+        }
+    }
+
+    fn block_arity(&self, ty: BlockType) -> (usize, usize) {
+        match ty {
+            BlockType::Empty => (0, 0),
+            BlockType::Type(_) => (0, 1),
+            BlockType::FuncType(i) => {
+                let ty = &self.frames.last().unwrap().module.types[i as usize];
+                (ty.params().len(), ty.results().len())
+            }
+        }
+    }
+
+    pub(crate) fn push(&mut self, val: Val) {
+        self.stack.push(val);
+    }
+
+    pub(crate) fn pop(&mut self) -> Val {
+        self.stack.pop().unwrap()
+    }
+
+    pub(crate) fn pop_i32(&mut self) -> i32 {
+        match self.pop() {
+            Val::I32(x) => x,
+            val => unreachable!("expected i32, found {val:?}"),
+        }
+    }
+
+    pub(crate) fn pop_i64(&mut self) -> i64 {
+        match self.pop() {
+            Val::I64(x) => x,
+            val => unreachable!("expected i64, found {val:?}"),
+        }
+    }
+
+    pub(crate) fn pop_f32_bits(&mut self) -> u32 {
+        match self.pop() {
+            Val::F32(x) => x,
+            val => unreachable!("expected f32, found {val:?}"),
+        }
+    }
+
+    pub(crate) fn pop_f64_bits(&mut self) -> u64 {
+        match self.pop() {
+            Val::F64(x) => x,
+            val => unreachable!("expected f64, found {val:?}"),
+        }
+    }
+
+    fn pop_f32(&mut self) -> f32 {
+        f32::from_bits(self.pop_f32_bits())
+    }
+
+    fn pop_f64(&mut self) -> f64 {
+        f64::from_bits(self.pop_f64_bits())
+    }
+
+    pub(crate) fn pop_v128(&mut self) -> u128 {
+        match self.pop() {
+            Val::V128(x) => x,
+            val => unreachable!("expected v128, found {val:?}"),
+        }
+    }
+
+    /// Pops an address or length, which is an `i64` if `is64` or an `i32`
+    /// otherwise.
+    fn pop_index(&mut self, is64: bool) -> u64 {
+        if is64 {
+            self.pop_i64() as u64
+        } else {
+            u64::from(self.pop_i32() as u32)
+        }
+    }
+
+    /// Pushes an address or length, which is an `i64` if `is64` or an `i32`
+    /// otherwise.
+    fn push_index(&mut self, is64: bool, val: u64) {
+        if is64 {
+            self.push(Val::I64(val as i64));
+        } else {
+            self.push(Val::I32(val as i32));
+        }
+    }
+
+    fn func(&self, index: u32) -> Func {
+        let instance = self.frames.last().unwrap().instance;
+        self.store.instances[instance].funcs[index as usize]
+    }
+
+    fn table(&self, index: u32) -> usize {
+        let instance = self.frames.last().unwrap().instance;
+        self.store.instances[instance].tables[index as usize].0
+    }
+
+    fn memory(&self, index: u32) -> usize {
+        let instance = self.frames.last().unwrap().instance;
+        self.store.instances[instance].memories[index as usize].0
+    }
+
+    fn global(&self, index: u32) -> usize {
+        let instance = self.frames.last().unwrap().instance;
+        self.store.instances[instance].globals[index as usize].0
+    }
+
+    /// Pops an address for `memarg` and returns the memory and the effective
+    /// address for an access of `len` bytes, trapping if it's out of bounds.
+    fn address(&mut self, memarg: &MemArg, len: usize) -> Result<(usize, usize)> {
+        let memory = self.memory(memarg.memory);
+        let base = self.pop_index(self.store.memories[memory].ty.memory64);
+        let addr = u128::from(base) + u128::from(memarg.offset);
+        let size = self.store.memories[memory].data.len() as u128;
+        if addr + len as u128 > size {
+            return Err(trap!("out of bounds memory access"));
+        }
+        Ok((memory, addr as usize))
+    }
+
+    pub(crate) fn load<const N: usize>(&mut self, memarg: &MemArg) -> Result<[u8; N]> {
+        let (memory, addr) = self.address(memarg, N)?;
+        let data = &self.store.memories[memory].data;
+        Ok(data[addr..][..N].try_into().unwrap())
+    }
+
+    pub(crate) fn store<const N: usize>(&mut self, memarg: &MemArg, bytes: [u8; N]) -> Result<()> {
+        let (memory, addr) = self.address(memarg, N)?;
+        self.store.memories[memory].data[addr..][..N].copy_from_slice(&bytes);
+        Ok(())
+    }
+
+    /// Pops the operand of `call_indirect` and returns the function it refers
+    /// to, trapping if it doesn't exist or isn't of the type `type_index`.
+    fn indirect_callee(&mut self, type_index: u32, table_index: u32) -> Result<Func> {
+        let table = self.table(table_index);
+        let index = self.pop_index(self.store.tables[table].ty.table64);
+        let func = match usize::try_from(index)
+            .ok()
+            .and_then(|i| self.store.tables[table].elems.get(i))
+        {
+            Some(Val::FuncRef(Some(func))) => *func,
+            Some(_) => return Err(trap!("uninitialized element {index}")),
+            None => return Err(trap!("undefined element")),
+        };
+        let expected = &self.frames.last().unwrap().module.types[type_index as usize];
+        if self.store.func_ty(func) != expected {
+            return Err(trap!("indirect call type mismatch"));
+        }
+        Ok(func)
+    }
+
+    /// Checks that `offset..offset + len` is within `size`, returning the
+    /// range's start.
+    fn bounds(offset: u64, len: u64, size: usize) -> Option<usize> {
+        let end = offset.checked_add(len)?;
+        if end > size as u64 {
+            return None;
+        }
+        Some(offset as usize)
+    }
+
+    fn step(&mut self, op: Operator<'m>, offset: usize) -> Result<()> {
+        use Operator::*;
+
+        match op {
+            Unreachable => return Err(trap!("unreachable")),
+            Nop => {}
+            Block { blockty } => {
+                let (params, results) = self.block_arity(blockty);
+                let height = self.stack.len() - params;
+                let frame = self.frame();
+                let cont = frame.code.blocks[&offset].end;
+                frame.labels.push(Label {
+                    kind: FrameKind::Block,
+                    arity: results,
+                    height,
+                    cont,
+                });
+            }
+            Loop { blockty } => {
+                let (params, _) = self.block_arity(blockty);
+                let height = self.stack.len() - params;
+                let frame = self.frame();
+                let cont = frame.reader.original_position();
+                frame.labels.push(Label {
+                    kind: FrameKind::Loop,
+                    arity: params,
+                    height,
+                    cont,
+                });
+            }
+            If { blockty } => {
+                let cond = self.pop_i32();
+                let (params, results) = self.block_arity(blockty);
+                let height = self.stack.len() - params;
+                let frame = self.frame();
+                let targets = frame.code.blocks[&offset];
+                let mut label = Label {
+                    kind: FrameKind::If,
+                    arity: results,
+                    height,
+                    cont: targets.end,
+                };
+                if cond != 0 {
+                    frame.labels.push(label);
+                } else if let Some(else_) = targets.else_ {
+                    label.kind = FrameKind::Else;
+                    frame.labels.push(label);
+                    frame.jump(else_);
+                } else {
+                    frame.jump(targets.end);
+                }
+            }
+            Else => {
+                // Reaching `else` means the `then` arm finished, so skip over
+                // the `else` arm.
+                let frame = self.frame();
+                let label = frame.labels.pop().unwrap();
+                frame.jump(label.cont);
+            }
+            End => {
+                let frame = self.frame();
+                frame.labels.pop();
+                if frame.labels.is_empty() {
+                    self.frames.pop();
+                }
+            }
+            Br { relative_depth } => self.branch(relative_depth),
+            BrIf { relative_depth } => {
+                if self.pop_i32() != 0 {
+                    self.branch(relative_depth);
+                }
+            }
+            BrTable { targets } => {
+                let index = self.pop_i32() as u32;
+                let depth = if index < targets.len() {
+                    targets.targets().nth(index as usize).unwrap()?
+                } else {
+                    targets.default()
+                };
+                self.branch(depth);
+            }
+            Return => {
+                let depth = self.frame().labels.len() - 1;
+                self.branch(depth as u32);
+            }
+            Call { function_index } => self.call(self.func(function_index))?,
+            CallIndirect {
+                type_index,
+                table_index,
+            } => {
+                let func = self.indirect_callee(type_index, table_index)?;
+                self.call(func)?;
+            }
+            ReturnCall { function_index } => self.return_call(self.func(function_index))?,
+            ReturnCallIndirect {
+                type_index,
+                table_index,
+            } => {
+                let func = self.indirect_callee(type_index, table_index)?;
+                self.return_call(func)?;
+            }
+            Drop => {
+                self.pop();
+            }
+            Select | TypedSelect { .. } => {
+                let cond = self.pop_i32();
+                let b = self.pop();
+                let a = self.pop();
+                self.push(if cond != 0 { a } else { b });
+            }
+
+            LocalGet { local_index } => {
+                let val = self.frame().locals[local_index as usize];
+                self.push(val);
+            }
+            LocalSet { local_index } => {
+                let val = self.pop();
+                self.frame().locals[local_index as usize] = val;
+            }
+            LocalTee { local_index } => {
+                let val = *self.stack.last().unwrap();
+                self.frame().locals[local_index as usize] = val;
+            }
+            GlobalGet { global_index } => {
+                let global = self.global(global_index);
+                self.push(self.store.globals[global].val);
+            }
+            GlobalSet { global_index } => {
+                let global = self.global(global_index);
+                self.store.globals[global].val = self.pop();
+            }
+
+            TableGet { table } => {
+                let table = self.table(table);
+                let index = self.pop_index(self.store.tables[table].ty.table64);
+                let elems = &self.store.tables[table].elems;
+                match Self::bounds(index, 1, elems.len()) {
+                    Some(i) => self.push(elems[i]),
+                    None => return Err(trap!("out of bounds table access")),
+                }
+            }
+            TableSet { table } => {
+                let table = self.table(table);
+                let val = self.pop();
+                let index = self.pop_index(self.store.tables[table].ty.table64);
+                let elems = &mut self.store.tables[table].elems;
+                match Self::bounds(index, 1, elems.len()) {
+                    Some(i) => elems[i] = val,
+                    None => return Err(trap!("out of bounds table access")),
+                }
+            }
+            TableSize { table } => {
+                let table = &self.store.tables[self.table(table)];
+                let (is64, size) = (table.ty.table64, table.elems.len() as u64);
+                self.push_index(is64, size);
+            }
+            TableGrow { table } => {
+                let table = self.table(table);
+                let is64 = self.store.tables[table].ty.table64;
+                let delta = self.pop_index(is64);
+                let init = self.pop();
+                let result = self.store.tables[table].grow(delta, init);
+                self.push_index(is64, result.unwrap_or(u64::MAX));
+            }
+            TableFill { table } => {
+                let table = self.table(table);
+                let is64 = self.store.tables[table].ty.table64;
+                let len = self.pop_index(is64);
+                let val = self.pop();
+                let dst = self.pop_index(is64);
+                let elems = &mut self.store.tables[table].elems;
+                match Self::bounds(dst, len, elems.len()) {
+                    Some(dst) => elems[dst..][..len as usize].fill(val),
+                    None => return Err(trap!("out of bounds table access")),
+                }
+            }
+            TableCopy {
+                dst_table,
+                src_table,
+            } => {
+                let dst_table = self.table(dst_table);
+                let src_table = self.table(src_table);
+                let dst64 = self.store.tables[dst_table].ty.table64;
+                let src64 = self.store.tables[src_table].ty.table64;
+                let len = self.pop_index(dst64 && src64);
+                let src = self.pop_index(src64);
+                let dst = self.pop_index(dst64);
+                let src_len = self.store.tables[src_table].elems.len();
+                let dst_len = self.store.tables[dst_table].elems.len();
+                match (
+                    Self::bounds(src, len, src_len),
+                    Self::bounds(dst, len, dst_len),
+                ) {
+                    (Some(src), Some(dst)) => {
+                        let items =
+                            self.store.tables[src_table].elems[src..][..len as usize].to_vec();
+                        self.store.tables[dst_table].elems[dst..][..len as usize]
+                            .copy_from_slice(&items);
+                    }
+                    _ => return Err(trap!("out of bounds table access")),
+                }
+            }
+            TableInit { elem_index, table } => {
+                let table = self.table(table);
+                let is64 = self.store.tables[table].ty.table64;
+                let len = self.pop_index(false);
+                let src = self.pop_index(false);
+                let dst = self.pop_index(is64);
+                let instance = self.frame().instance;
+                let items = &self.store.instances[instance].elems[elem_index as usize];
+                let elems = &mut self.store.tables[table].elems;
+                match (
+                    Self::bounds(src, len, items.len()),
+                    Self::bounds(dst, len, elems.len()),
+                ) {
+                    (Some(src), Some(dst)) => {
+                        elems[dst..][..len as usize].copy_from_slice(&items[src..][..len as usize]);
+                    }
+                    _ => return Err(trap!("out of bounds table access")),
+                }
+            }
+            ElemDrop { elem_index } => {
+                let instance = self.frame().instance;
+                self.store.instances[instance].elems[elem_index as usize] = Vec::new();
+            }
+
+            I32Load { memarg } => {
+                let bytes = self.load(&memarg)?;
+                self.push(Val::I32(i32::from_le_bytes(bytes)));
+            }
+            I64Load { memarg } => {
+                let bytes = self.load(&memarg)?;
+                self.push(Val::I64(i64::from_le_bytes(bytes)));
+            }
+            F32Load { memarg } => {
+                let bytes = self.load(&memarg)?;
+                self.push(Val::F32(u32::from_le_bytes(bytes)));
+            }
+            F64Load { memarg } => {
+                let bytes = self.load(&memarg)?;
+                self.push(Val::F64(u64::from_le_bytes(bytes)));
+            }
+            I32Load8S { memarg } => {
+                let bytes = self.load(&memarg)?;
+                self.push(Val::I32(i8::from_le_bytes(bytes).into()));
+            }
+            I32Load8U { memarg } => {
+                let bytes = self.load(&memarg)?;
+                self.push(Val::I32(u8::from_le_bytes(bytes).into()));
+            }
+            I32Load16S { memarg } => {
+                let bytes = self.load(&memarg)?;
+                self.push(Val::I32(i16::from_le_bytes(bytes).into()));
+            }
+            I32Load16U { memarg } => {
+                let bytes = self.load(&memarg)?;
+                self.push(Val::I32(u16::from_le_bytes(bytes).into()));
+            }
+            I64Load8S { memarg } => {
+                let bytes = self.load(&memarg)?;
+                self.push(Val::I64(i8::from_le_bytes(bytes).into()));
+            }
+            I64Load8U { memarg } => {
+                let bytes = self.load(&memarg)?;
+                self.push(Val::I64(u8::from_le_bytes(bytes).into()));
+            }
+            I64Load16S { memarg } => {
+                let bytes = self.load(&memarg)?;
+                self.push(Val::I64(i16::from_le_bytes(bytes).into()));
+            }
+            I64Load16U { memarg } => {
+                let bytes = self.load(&memarg)?;
+                self.push(Val::I64(u16::from_le_bytes(bytes).into()));
+            }
+            I64Load32S { memarg } => {
+                let bytes = self.load(&memarg)?;
+                self.push(Val::I64(i32::from_le_bytes(bytes).into()));
+            }
+            I64Load32U { memarg } => {
+                let bytes = self.load(&memarg)?;
+                self.push(Val::I64(u32::from_le_bytes(bytes).into()));
+            }
+            I32Store { memarg } => {
+                let val = self.pop_i32();
+                self.store(&memarg, val.to_le_bytes())?;
+            }
+            I64Store { memarg } => {
+                let val = self.pop_i64();
+                self.store(&memarg, val.to_le_bytes())?;
+            }
+            F32Store { memarg } => {
+                let val = self.pop_f32_bits();
+                self.store(&memarg, val.to_le_bytes())?;
+            }
+            F64Store { memarg } => {
+                let val = self.pop_f64_bits();
+                self.store(&memarg, val.to_le_bytes())?;
+            }
+            I32Store8 { memarg } => {
+                let val = self.pop_i32();
+                self.store(&memarg, (val as u8).to_le_bytes())?;
+            }
+            I32Store16 { memarg } => {
+                let val = self.pop_i32();
+                self.store(&memarg, (val as u16).to_le_bytes())?;
+            }
+            I64Store8 { memarg } => {
+                let val = self.pop_i64();
+                self.store(&memarg, (val as u8).to_le_bytes())?;
+            }
+            I64Store16 { memarg } => {
+                let val = self.pop_i64();
+                self.store(&memarg, (val as u16).to_le_bytes())?;
+            }
+            I64Store32 { memarg } => {
+                let val = self.pop_i64();
+                self.store(&memarg, (val as u32).to_le_bytes())?;
+            }
+            MemorySize { mem } => {
+                let memory = &self.store.memories[self.memory(mem)];
+                let (is64, pages) = (memory.ty.memory64, memory.pages());
+                self.push_index(is64, pages);
+            }
+            MemoryGrow { mem } => {
+                let memory = self.memory(mem);
+                let is64 = self.store.memories[memory].ty.memory64;
+                let delta = self.pop_index(is64);
+                let result = self.store.memories[memory].grow(delta);
+                self.push_index(is64, result.unwrap_or(u64::MAX));
+            }
+            MemoryFill { mem } => {
+                let memory = self.memory(mem);
+                let is64 = self.store.memories[memory].ty.memory64;
+                let len = self.pop_index(is64);
+                let val = self.pop_i32();
+                let dst = self.pop_index(is64);
+                let data = &mut self.store.memories[memory].data;
+                match Self::bounds(dst, len, data.len()) {
+                    Some(dst) => data[dst..][..len as usize].fill(val as u8),
+                    None => return Err(trap!("out of bounds memory access")),
+                }
+            }
+            MemoryCopy { dst_mem, src_mem } => {
+                let dst_mem = self.memory(dst_mem);
+                let src_mem = self.memory(src_mem);
+                let dst64 = self.store.memories[dst_mem].ty.memory64;
+                let src64 = self.store.memories[src_mem].ty.memory64;
+                let len = self.pop_index(dst64 && src64);
+                let src = self.pop_index(src64);
+                let dst = self.pop_index(dst64);
+                let src_len = self.store.memories[src_mem].data.len();
+                let dst_len = self.store.memories[dst_mem].data.len();
+                match (
+                    Self::bounds(src, len, src_len),
+                    Self::bounds(dst, len, dst_len),
+                ) {
+                    (Some(src), Some(dst)) if src_mem == dst_mem => {
+                        let data = &mut self.store.memories[dst_mem].data;
+                        data.copy_within(src..src + len as usize, dst);
+                    }
+                    (Some(src), Some(dst)) => {
+                        let bytes =
+                            self.store.memories[src_mem].data[src..][..len as usize].to_vec();
+                        self.store.memories[dst_mem].data[dst..][..len as usize]
+                            .copy_from_slice(&bytes);
+                    }
+                    _ => return Err(trap!("out of bounds memory access")),
+                }
+            }
+            MemoryInit { data_index, mem } => {
+                let memory = self.memory(mem);
+                let is64 = self.store.memories[memory].ty.memory64;
+                let len = self.pop_index(false);
+                let src = self.pop_index(false);
+                let dst = self.pop_index(is64);
+                let frame = self.frames.last().unwrap();
+                let range = self.store.instances[frame.instance].datas[data_index as usize].clone();
+                let bytes = &frame.module.bytes[range];
+                let data = &mut self.store.memories[memory].data;
+                match (
+                    Self::bounds(src, len, bytes.len()),
+                    Self::bounds(dst, len, data.len()),
+                ) {
+                    (Some(src), Some(dst)) => {
+                        data[dst..][..len as usize].copy_from_slice(&bytes[src..][..len as usize]);
+                    }
+                    _ => return Err(trap!("out of bounds memory access")),
+                }
+            }
+            DataDrop { data_index } => {
+                let instance = self.frame().instance;
+                self.store.instances[instance].datas[data_index as usize] = 0..0;
+            }
+
+            I32Const { value } => self.push(Val::I32(value)),
+            I64Const { value } => self.push(Val::I64(value)),
+            F32Const { value } => self.push(Val::F32(value.bits())),
+            F64Const { value } => self.push(Val::F64(value.bits())),
+
+            RefNull { hty } => self.push(null_ref(hty)?),
+            RefIsNull => {
+                let is_null = matches!(self.pop(), Val::FuncRef(None) | Val::ExternRef(None));
+                self.push(Val::I32(is_null.into()));
+            }
+            RefFunc { function_index } => {
+                let func = self.func(function_index);
+                self.push(Val::FuncRef(Some(func)));
+            }
+
+            I32Eqz => unop!(self, pop_i32, I32, |a| (a == 0).into()),
+            I32Eq => cmpop!(self, pop_i32, |a, b| a == b),
+            I32Ne => cmpop!(self, pop_i32, |a, b| a != b),
+            I32LtS => cmpop!(self, pop_i32, |a, b| a < b),
+            I32LtU => cmpop!(self, pop_i32, |a, b| (a as u32) < (b as u32)),
+            I32GtS => cmpop!(self, pop_i32, |a, b| a > b),
+            I32GtU => cmpop!(self, pop_i32, |a, b| (a as u32) > (b as u32)),
+            I32LeS => cmpop!(self, pop_i32, |a, b| a <= b),
+            I32LeU => cmpop!(self, pop_i32, |a, b| (a as u32) <= (b as u32)),
+            I32GeS => cmpop!(self, pop_i32, |a, b| a >= b),
+            I32GeU => cmpop!(self, pop_i32, |a, b| (a as u32) >= (b as u32)),
+            I64Eqz => unop!(self, pop_i64, I32, |a| (a == 0).into()),
+            I64Eq => cmpop!(self, pop_i64, |a, b| a == b),
+            I64Ne => cmpop!(self, pop_i64, |a, b| a != b),
+            I64LtS => cmpop!(self, pop_i64, |a, b| a < b),
+            I64LtU => cmpop!(self, pop_i64, |a, b| (a as u64) < (b as u64)),
+            I64GtS => cmpop!(self, pop_i64, |a, b| a > b),
+            I64GtU => cmpop!(self, pop_i64, |a, b| (a as u64) > (b as u64)),
+            I64LeS => cmpop!(self, pop_i64, |a, b| a <= b),
+            I64LeU => cmpop!(self, pop_i64, |a, b| (a as u64) <= (b as u64)),
+            I64GeS => cmpop!(self, pop_i64, |a, b| a >= b),
+            I64GeU => cmpop!(self, pop_i64, |a, b| (a as u64) >= (b as u64)),
+            F32Eq => cmpop!(self, pop_f32, |a, b| a == b),
+            F32Ne => cmpop!(self, pop_f32, |a, b| a != b),
+            F32Lt => cmpop!(self, pop_f32, |a, b| a < b),
+            F32Gt => cmpop!(self, pop_f32, |a, b| a > b),
+            F32Le => cmpop!(self, pop_f32, |a, b| a <= b),
+            F32Ge => cmpop!(self, pop_f32, |a, b| a >= b),
+            F64Eq => cmpop!(self, pop_f64, |a, b| a == b),
+            F64Ne => cmpop!(self, pop_f64, |a, b| a != b),
+            F64Lt => cmpop!(self, pop_f64, |a, b| a < b),
+            F64Gt => cmpop!(self, pop_f64, |a, b| a > b),
+            F64Le => cmpop!(self, pop_f64, |a, b| a <= b),
+            F64Ge => cmpop!(self, pop_f64, |a, b| a >= b),
+
+            I32Clz => unop!(self, pop_i32, I32, |a| a.leading_zeros() as i32),
+            I32Ctz => unop!(self, pop_i32, I32, |a| a.trailing_zeros() as i32),
+            I32Popcnt => unop!(self, pop_i32, I32, |a| a.count_ones() as i32),
+            I32Add => binop!(self, pop_i32, I32, |a, b| a.wrapping_add(b)),
+            I32Sub => binop!(self, pop_i32, I32, |a, b| a.wrapping_sub(b)),
+            I32Mul => binop!(self, pop_i32, I32, |a, b| a.wrapping_mul(b)),
+            I32DivS => binop!(self, pop_i32, I32, |a, b| match b {
+                0 => return Err(trap!("integer divide by zero")),
+                -1 if a == i32::MIN => return Err(trap!("integer overflow")),
+                _ => a / b,
+            }),
+            I32DivU => binop!(self, pop_i32, I32, |a, b| match b {
+                0 => return Err(trap!("integer divide by zero")),
+                _ => ((a as u32) / (b as u32)) as i32,
+            }),
+            I32RemS => binop!(self, pop_i32, I32, |a, b| match b {
+                0 => return Err(trap!("integer divide by zero")),
+                _ => a.wrapping_rem(b),
+            }),
+            I32RemU => binop!(self, pop_i32, I32, |a, b| match b {
+                0 => return Err(trap!("integer divide by zero")),
+                _ => ((a as u32) % (b as u32)) as i32,
+            }),
+            I32And => binop!(self, pop_i32, I32, |a, b| a & b),
+            I32Or => binop!(self, pop_i32, I32, |a, b| a | b),
+            I32Xor => binop!(self, pop_i32, I32, |a, b| a ^ b),
+            I32Shl => binop!(self, pop_i32, I32, |a, b| a.wrapping_shl(b as u32)),
+            I32ShrS => binop!(self, pop_i32, I32, |a, b| a.wrapping_shr(b as u32)),
+            I32ShrU => binop!(self, pop_i32, I32, |a, b| (a as u32).wrapping_shr(b as u32)
+                as i32),
+            I32Rotl => binop!(self, pop_i32, I32, |a, b| a.rotate_left(b as u32)),
+            I32Rotr => binop!(self, pop_i32, I32, |a, b| a.rotate_right(b as u32)),
+
+            I64Clz => unop!(self, pop_i64, I64, |a| a.leading_zeros().into()),
+            I64Ctz => unop!(self, pop_i64, I64, |a| a.trailing_zeros().into()),
+            I64Popcnt => unop!(self, pop_i64, I64, |a| a.count_ones().into()),
+            I64Add => binop!(self, pop_i64, I64, |a, b| a.wrapping_add(b)),
+            I64Sub => binop!(self, pop_i64, I64, |a, b| a.wrapping_sub(b)),
+            I64Mul => binop!(self, pop_i64, I64, |a, b| a.wrapping_mul(b)),
+            I64DivS => binop!(self, pop_i64, I64, |a, b| match b {
+                0 => return Err(trap!("integer divide by zero")),
+                -1 if a == i64::MIN => return Err(trap!("integer overflow")),
+                _ => a / b,
+            }),
+            I64DivU => binop!(self, pop_i64, I64, |a, b| match b {
+                0 => return Err(trap!("integer divide by zero")),
+                _ => ((a as u64) / (b as u64)) as i64,
+            }),
+            I64RemS => binop!(self, pop_i64, I64, |a, b| match b {
+                0 => return Err(trap!("integer divide by zero")),
+                _ => a.wrapping_rem(b),
+            }),
+            I64RemU => binop!(self, pop_i64, I64, |a, b| match b {
+                0 => return Err(trap!("integer divide by zero")),
+                _ => ((a as u64) % (b as u64)) as i64,
+            }),
+            I64And => binop!(self, pop_i64, I64, |a, b| a & b),
+            I64Or => binop!(self, pop_i64, I64, |a, b| a | b),
+            I64Xor => binop!(self, pop_i64, I64, |a, b| a ^ b),
+            I64Shl => binop!(self, pop_i64, I64, |a, b| a.wrapping_shl(b as u32)),
+            I64ShrS => binop!(self, pop_i64, I64, |a, b| a.wrapping_shr(b as u32)),
+            I64ShrU => binop!(self, pop_i64, I64, |a, b| (a as u64).wrapping_shr(b as u32)
+                as i64),
+            I64Rotl => binop!(self, pop_i64, I64, |a, b| a.rotate_left((b & 63) as u32)),
+            I64Rotr => binop!(self, pop_i64, I64, |a, b| a.rotate_right((b & 63) as u32)),
+
+            F32Abs => unop!(self, pop_f32_bits, F32, |a| a & 0x7fff_ffff),
+            F32Neg => unop!(self, pop_f32_bits, F32, |a| a ^ 0x8000_0000),
+            F32Ceil => unop!(self, pop_f32, F32, |a| a.ceil().to_bits()),
+            F32Floor => unop!(self, pop_f32, F32, |a| a.floor().to_bits()),
+            F32Trunc => unop!(self, pop_f32, F32, |a| a.trunc().to_bits()),
+            F32Nearest => unop!(self, pop_f32, F32, |a| num::f32_nearest(a).to_bits()),
+            F32Sqrt => unop!(self, pop_f32, F32, |a| a.sqrt().to_bits()),
+            F32Add => binop!(self, pop_f32, F32, |a, b| (a + b).to_bits()),
+            F32Sub => binop!(self, pop_f32, F32, |a, b| (a - b).to_bits()),
+            F32Mul => binop!(self, pop_f32, F32, |a, b| (a * b).to_bits()),
+            F32Div => binop!(self, pop_f32, F32, |a, b| (a / b).to_bits()),
+            F32Min => binop!(self, pop_f32, F32, |a, b| num::f32_min(a, b).to_bits()),
+            F32Max => binop!(self, pop_f32, F32, |a, b| num::f32_max(a, b).to_bits()),
+            F32Copysign => binop!(self, pop_f32_bits, F32, |a, b| (a & 0x7fff_ffff)
+                | (b & 0x8000_0000)),
+
+            F64Abs => unop!(self, pop_f64_bits, F64, |a| a & (u64::MAX >> 1)),
+            F64Neg => unop!(self, pop_f64_bits, F64, |a| a ^ (1 << 63)),
+            F64Ceil => unop!(self, pop_f64, F64, |a| a.ceil().to_bits()),
+            F64Floor => unop!(self, pop_f64, F64, |a| a.floor().to_bits()),
+            F64Trunc => unop!(self, pop_f64, F64, |a| a.trunc().to_bits()),
+            F64Nearest => unop!(self, pop_f64, F64, |a| num::f64_nearest(a).to_bits()),
+            F64Sqrt => unop!(self, pop_f64, F64, |a| a.sqrt().to_bits()),
+            F64Add => binop!(self, pop_f64, F64, |a, b| (a + b).to_bits()),
+            F64Sub => binop!(self, pop_f64, F64, |a, b| (a - b).to_bits()),
+            F64Mul => binop!(self, pop_f64, F64, |a, b| (a * b).to_bits()),
+            F64Div => binop!(self, pop_f64, F64, |a, b| (a / b).to_bits()),
+            F64Min => binop!(self, pop_f64, F64, |a, b| num::f64_min(a, b).to_bits()),
+            F64Max => binop!(self, pop_f64, F64, |a, b| num::f64_max(a, b).to_bits()),
+            F64Copysign => binop!(self, pop_f64_bits, F64, |a, b| (a & (u64::MAX >> 1))
+                | (b & (1 << 63))),
+
+            I32WrapI64 => unop!(self, pop_i64, I32, |a| a as i32),
+            I32TruncF32S => unop!(self, pop_f32, I32, |a| num::i32_trunc_s(a.into())?),
+            I32TruncF32U => unop!(self, pop_f32, I32, |a| num::i32_trunc_u(a.into())?),
+            I32TruncF64S => unop!(self, pop_f64, I32, |a| num::i32_trunc_s(a)?),
+            I32TruncF64U => unop!(self, pop_f64, I32, |a| num::i32_trunc_u(a)?),
+            I64ExtendI32S => unop!(self, pop_i32, I64, |a| a.into()),
+            I64ExtendI32U => unop!(self, pop_i32, I64, |a| (a as u32).into()),
+            I64TruncF32S => unop!(self, pop_f32, I64, |a| num::i64_trunc_s(a.into())?),
+            I64TruncF32U => unop!(self, pop_f32, I64, |a| num::i64_trunc_u(a.into())?),
+            I64TruncF64S => unop!(self, pop_f64, I64, |a| num::i64_trunc_s(a)?),
+            I64TruncF64U => unop!(self, pop_f64, I64, |a| num::i64_trunc_u(a)?),
+            F32ConvertI32S => unop!(self, pop_i32, F32, |a| (a as f32).to_bits()),
+            F32ConvertI32U => unop!(self, pop_i32, F32, |a| (a as u32 as f32).to_bits()),
+            F32ConvertI64S => unop!(self, pop_i64, F32, |a| (a as f32).to_bits()),
+            F32ConvertI64U => unop!(self, pop_i64, F32, |a| (a as u64 as f32).to_bits()),
+            F32DemoteF64 => unop!(self, pop_f64, F32, |a| (a as f32).to_bits()),
+            F64ConvertI32S => unop!(self, pop_i32, F64, |a| f64::from(a).to_bits()),
+            F64ConvertI32U => unop!(self, pop_i32, F64, |a| f64::from(a as u32).to_bits()),
+            F64ConvertI64S => unop!(self, pop_i64, F64, |a| (a as f64).to_bits()),
+            F64ConvertI64U => unop!(self, pop_i64, F64, |a| (a as u64 as f64).to_bits()),
+            F64PromoteF32 => unop!(self, pop_f32, F64, |a| f64::from(a).to_bits()),
+            I32ReinterpretF32 => unop!(self, pop_f32_bits, I32, |a| a as i32),
+            I64ReinterpretF64 => unop!(self, pop_f64_bits, I64, |a| a as i64),
+            F32ReinterpretI32 => unop!(self, pop_i32, F32, |a| a as u32),
+            F64ReinterpretI64 => unop!(self, pop_i64, F64, |a| a as u64),
+
+            I32Extend8S => unop!(self, pop_i32, I32, |a| (a as i8).into()),
+            I32Extend16S => unop!(self, pop_i32, I32, |a| (a as i16).into()),
+            I64Extend8S => unop!(self, pop_i64, I64, |a| (a as i8).into()),
+            I64Extend16S => unop!(self, pop_i64, I64, |a| (a as i16).into()),
+            I64Extend32S => unop!(self, pop_i64, I64, |a| (a as i32).into()),
+
+            I32TruncSatF32S => unop!(self, pop_f32, I32, |a| a as i32),
+            I32TruncSatF32U => unop!(self, pop_f32, I32, |a| a as u32 as i32),
+            I32TruncSatF64S => unop!(self, pop_f64, I32, |a| a as i32),
+            I32TruncSatF64U => unop!(self, pop_f64, I32, |a| a as u32 as i32),
+            I64TruncSatF32S => unop!(self, pop_f32, I64, |a| a as i64),
+            I64TruncSatF32U => unop!(self, pop_f32, I64, |a| a as u64 as i64),
+            I64TruncSatF64S => unop!(self, pop_f64, I64, |a| a as i64),
+            I64TruncSatF64U => unop!(self, pop_f64, I64, |a| a as u64 as i64),
+
+            op => {
+                if !self.simd(&op)? {
+                    bail!("unsupported instruction: {op:?}");
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'m> Frame<'m> {
+    fn jump(&mut self, offset: usize) {
+        self.reader = reader_at(self.module, self.code, offset);
+    }
+}
+
+fn reader_at<'m>(module: &'m ModuleInner, code: &Code, offset: usize) -> BinaryReader<'m> {
+    BinaryReader::new(&module.bytes[offset..code.body.end], offset)
+}
+
+/// Decodes operators at arbitrary positions within a function body, where
+/// the kind of the innermost frame is provided from the interpreter's labels
+/// instead of being tracked from the start of the body.
+struct ReadOperator(FrameKind);
+
+impl FrameStack for ReadOperator {
+    fn current_frame(&self) -> Option<FrameKind> {
+        Some(self.0)
+    }
+}
+
+macro_rules! define_visit_operator {
+    ($(@$proposal:ident $op:ident $({ $($arg:ident: $argty:ty),* })? => $visit:ident ($($ann:tt)*))*) => {
+        $(
+            fn $visit(&mut self $($(,$arg: $argty)*)?) -> Operator<'a> {
+                Operator::$op $({ $($arg),* })?
+            }
+        )*
+    }
+}
+
+impl<'a> VisitOperator<'a> for ReadOperator {
+    type Output = Operator<'a>;
+
+    fn simd_visitor(&mut self) -> Option<&mut dyn VisitSimdOperator<'a, Output = Self::Output>> {
+        Some(self)
+    }
+
+    wasmparser::for_each_visit_operator!(define_visit_operator);
+}
+
+impl<'a> VisitSimdOperator<'a> for ReadOperator {
+    wasmparser::for_each_visit_simd_operator!(define_visit_operator);
+}
@@ -0,0 +1,179 @@
+//! A small and slow reference interpreter for WebAssembly.
+//!
+//! This crate executes core WebAssembly modules by interpreting the
+//! operators of validated function bodies, as decoded by [`wasmparser`], one
+//! at a time. It's intended to follow the specification closely and to be
+//! easy to audit rather than fast, making it suitable as a test oracle for
+//! `*.wast` scripts and for differential testing of other engines.
+//!
+//! Supported proposals are the MVP, multi-value, sign-extension operators,
+//! non-trapping float-to-int conversions, bulk memory, reference types,
+//! multi-memory, 64-bit memories and tables, extended constant expressions,
+//! SIMD (implemented lane-by-lane with scalar operations), and tail calls.
+//! Modules using other proposals are either rejected when they're created or
+//! fail with an error when an unsupported instruction is executed.
+//!
+//! # Example
+//!
+//! ```
+//! use wasm_interp::{Module, Store, Val};
+//!
+//! let wasm = wat::parse_str(r#"
+//!     (module
+//!         (func (export "add") (param i32 i32) (result i32)
+//!             local.get 0
+//!             local.get 1
+//!             i32.add))
+//! "#)?;
+//! let module = Module::new(Default::default(), &wasm)?;
+//! let mut store = Store::new();
+//! let instance = store.instantiate(&module, &[])?;
+//! let add = store.get_export(instance, "add").unwrap().into_func().unwrap();
+//! let results = store.invoke(add, &[Val::I32(1), Val::I32(2)])?;
+//! assert_eq!(results, [Val::I32(3)]);
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+#![deny(missing_docs)]
+
+use std::fmt;
+
+mod exec;
+mod module;
+mod num;
+mod simd;
+mod store;
+
+pub use crate::module::Module;
+pub use crate::store::Store;
+
+/// A WebAssembly value.
+///
+/// Floating-point values are stored as their raw bits to preserve the exact
+/// payload of NaN values.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Val {
+    /// An `i32` value.
+    I32(i32),
+    /// An `i64` value.
+    I64(i64),
+    /// The bits of an `f32` value.
+    F32(u32),
+    /// The bits of an `f64` value.
+    F64(u64),
+    /// A `v128` value, with lane 0 in the least significant bits.
+    V128(u128),
+    /// A possibly-null reference to a function.
+    FuncRef(Option<Func>),
+    /// A possibly-null reference to a host value, identified by an integer.
+    ExternRef(Option<u32>),
+}
+
+macro_rules! handles {
+    ($($(#[$attr:meta])* $name:ident)*) => {$(
+        $(#[$attr])*
+        #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+        pub struct $name(pub(crate) usize);
+    )*};
+}
+
+handles! {
+    /// A handle to a function within a [`Store`].
+    Func
+    /// A handle to a table within a [`Store`].
+    Table
+    /// A handle to a linear memory within a [`Store`].
+    Memory
+    /// A handle to a global within a [`Store`].
+    Global
+    /// A handle to an instantiated [`Module`] within a [`Store`].
+    Instance
+}
+
+/// An external item which can be imported into or exported from an instance.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Extern {
+    /// A function.
+    Func(Func),
+    /// A table.
+    Table(Table),
+    /// A linear memory.
+    Memory(Memory),
+    /// A global.
+    Global(Global),
+}
+
+impl Extern {
+    /// Returns the function this is, if any.
+    pub fn into_func(self) -> Option<Func> {
+        match self {
+            Extern::Func(f) => Some(f),
+            _ => None,
+        }
+    }
+
+    /// Returns the table this is, if any.
+    pub fn into_table(self) -> Option<Table> {
+        match self {
+            Extern::Table(t) => Some(t),
+            _ => None,
+        }
+    }
+
+    /// Returns the memory this is, if any.
+    pub fn into_memory(self) -> Option<Memory> {
+        match self {
+            Extern::Memory(m) => Some(m),
+            _ => None,
+        }
+    }
+
+    /// Returns the global this is, if any.
+    pub fn into_global(self) -> Option<Global> {
+        match self {
+            Extern::Global(g) => Some(g),
+            _ => None,
+        }
+    }
+}
+
+/// A WebAssembly trap raised during execution.
+///
+/// Traps are returned wrapped in an [`anyhow::Error`] and can be distinguished
+/// from other errors, such as link errors or unsupported features, with
+/// [`anyhow::Error::downcast_ref`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Trap {
+    message: String,
+}
+
+impl Trap {
+    pub(crate) fn new(message: impl Into<String>) -> Trap {
+        Trap {
+            message: message.into(),
+        }
+    }
+
+    /// Returns the message of this trap, which uses the same wording as the
+    /// specification's reference interpreter such as
+    /// `"integer divide by zero"`.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for Trap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "wasm trap: {}", self.message)
+    }
+}
+
+impl std::error::Error for Trap {}
+
+/// Returns an error for a trap with the `message` provided.
+macro_rules! trap {
+    ($($msg:tt)*) => {
+        anyhow::Error::from($crate::Trap::new(format!($($msg)*)))
+    };
+}
+pub(crate) use trap;
@@ -0,0 +1,295 @@
+use anyhow::{Result, bail};
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::Arc;
+use wasmparser::{
+    BinaryReader, DataKind, ElementItems, ElementKind, ExternalKind, FuncType, GlobalType,
+    MemoryType, Operator, OperatorsReader, Parser, Payload, TableInit, TableType, TypeRef, ValType,
+    Validator, WasmFeatures,
+};
+
+/// A validated WebAssembly module which can be instantiated in a
+/// [`Store`](crate::Store).
+///
+/// Modules are cheap to clone as the underlying data is shared.
+#[derive(Clone)]
+pub struct Module {
+    pub(crate) inner: Arc<ModuleInner>,
+}
+
+pub(crate) struct ModuleInner {
+    /// The original bytes of the module, which all ranges below index into.
+    pub bytes: Vec<u8>,
+    pub types: Vec<FuncType>,
+    pub imports: Vec<Import>,
+    /// The type index of all functions, including imported functions.
+    pub funcs: Vec<u32>,
+    pub num_imported_funcs: usize,
+    pub tables: Vec<(TableType, Option<Range<usize>>)>,
+    pub memories: Vec<MemoryType>,
+    pub globals: Vec<(GlobalType, Range<usize>)>,
+    pub exports: Vec<(String, ExternalKind, u32)>,
+    pub start: Option<u32>,
+    pub elems: Vec<Elem>,
+    pub datas: Vec<Data>,
+    pub code: Vec<Code>,
+}
+
+pub(crate) struct Import {
+    pub module: String,
+    pub name: String,
+    pub ty: TypeRef,
+}
+
+pub(crate) struct Elem {
+    pub kind: SegmentKind,
+    pub items: ElemItems,
+}
+
+pub(crate) enum ElemItems {
+    Funcs(Vec<u32>),
+    Exprs(Vec<Range<usize>>),
+}
+
+pub(crate) struct Data {
+    pub kind: SegmentKind,
+    pub data: Range<usize>,
+}
+
+pub(crate) enum SegmentKind {
+    Passive,
+    Active { index: u32, offset: Range<usize> },
+    Declared,
+}
+
+/// A function body along with the side table used to jump around in it.
+pub(crate) struct Code {
+    pub locals: Vec<ValType>,
+    /// The range of the operators of this function.
+    pub body: Range<usize>,
+    /// Map from the offset of each `block`, `loop`, and `if` to the offsets
+    /// the operator can transfer control to.
+    pub blocks: HashMap<usize, BlockTargets>,
+}
+
+#[derive(Copy, Clone)]
+pub(crate) struct BlockTargets {
+    /// The offset of the first operator after `else`, if any.
+    pub else_: Option<usize>,
+    /// The offset of the first operator after the matching `end`.
+    pub end: usize,
+}
+
+impl Module {
+    /// Validates `wasm` with the `features` provided and prepares it for
+    /// instantiation.
+    ///
+    /// This returns an error if the module is invalid, if it's a component, or
+    /// if it uses constructs this interpreter doesn't support such as GC
+    /// types or tags.
+    pub fn new(features: WasmFeatures, wasm: &[u8]) -> Result<Module> {
+        Validator::new_with_features(features).validate_all(wasm)?;
+
+        let mut module = ModuleInner {
+            bytes: wasm.to_vec(),
+            types: Vec::new(),
+            imports: Vec::new(),
+            funcs: Vec::new(),
+            num_imported_funcs: 0,
+            tables: Vec::new(),
+            memories: Vec::new(),
+            globals: Vec::new(),
+            exports: Vec::new(),
+            start: None,
+            elems: Vec::new(),
+            datas: Vec::new(),
+            code: Vec::new(),
+        };
+        for payload in Parser::new(0).parse_all(wasm) {
+            match payload? {
+                Payload::Version { encoding, .. } => {
+                    if encoding != wasmparser::Encoding::Module {
+                        bail!("components are not supported");
+                    }
+                }
+                Payload::TypeSection(s) => {
+                    for ty in s.into_iter_err_on_gc_types() {
+                        module.types.push(ty?);
+                    }
+                }
+                Payload::ImportSection(s) => {
+                    for import in s {
+                        let import = import?;
+                        match import.ty {
+                            TypeRef::Func(ty) => {
+                                module.funcs.push(ty);
+                                module.num_imported_funcs += 1;
+                            }
+                            TypeRef::Tag(_) => bail!("tags are not supported"),
+                            _ => {}
+                        }
+                        module.imports.push(Import {
+                            module: import.module.to_string(),
+                            name: import.name.to_string(),
+                            ty: import.ty,
+                        });
+                    }
+                }
+                Payload::FunctionSection(s) => {
+                    for ty in s {
+                        module.funcs.push(ty?);
+                    }
+                }
+                Payload::TableSection(s) => {
+                    for table in s {
+                        let table = table?;
+                        let init = match table.init {
+                            TableInit::RefNull => None,
+                            TableInit::Expr(e) => Some(e.get_binary_reader().range()),
+                        };
+                        module.tables.push((table.ty, init));
+                    }
+                }
+                Payload::MemorySection(s) => {
+                    for memory in s {
+                        module.memories.push(memory?);
+                    }
+                }
+                Payload::TagSection(_) => bail!("tags are not supported"),
+                Payload::GlobalSection(s) => {
+                    for global in s {
+                        let global = global?;
+                        let init = global.init_expr.get_binary_reader().range();
+                        module.globals.push((global.ty, init));
+                    }
+                }
+                Payload::ExportSection(s) => {
+                    for export in s {
+                        let export = export?;
+                        module
+                            .exports
+                            .push((export.name.to_string(), export.kind, export.index));
+                    }
+                }
+                Payload::StartSection { func, .. } => module.start = Some(func),
+                Payload::ElementSection(s) => {
+                    for elem in s {
+                        let elem = elem?;
+                        let kind = match elem.kind {
+                            ElementKind::Passive => SegmentKind::Passive,
+                            ElementKind::Declared => SegmentKind::Declared,
+                            ElementKind::Active {
+                                table_index,
+                                offset_expr,
+                            } => SegmentKind::Active {
+                                index: table_index.unwrap_or(0),
+                                offset: offset_expr.get_binary_reader().range(),
+                            },
+                        };
+                        let items = match elem.items {
+                            ElementItems::Functions(funcs) => {
+                                ElemItems::Funcs(funcs.into_iter().collect::<Result<_, _>>()?)
+                            }
+                            ElementItems::Expressions(_, exprs) => ElemItems::Exprs(
+                                exprs
+                                    .into_iter()
+                                    .map(|e| Ok(e?.get_binary_reader().range()))
+                                    .collect::<Result<_>>()?,
+                            ),
+                        };
+                        module.elems.push(Elem { kind, items });
+                    }
+                }
+                Payload::DataSection(s) => {
+                    for data in s {
+                        let data = data?;
+                        let kind = match data.kind {
+                            DataKind::Passive => SegmentKind::Passive,
+                            DataKind::Active {
+                                memory_index,
+                                offset_expr,
+                            } => SegmentKind::Active {
+                                index: memory_index,
+                                offset: offset_expr.get_binary_reader().range(),
+                            },
+                        };
+                        let end = data.range.end;
+                        module.datas.push(Data {
+                            kind,
+                            data: end - data.data.len()..end,
+                        });
+                    }
+                }
+                Payload::CodeSectionEntry(body) => {
+                    let mut locals = Vec::new();
+                    for pair in body.get_locals_reader()? {
+                        let (count, ty) = pair?;
+                        locals.extend((0..count).map(|_| ty));
+                    }
+                    let ops = body.get_operators_reader()?;
+                    let start = ops.original_position();
+                    module.code.push(Code {
+                        locals,
+                        body: start..body.range().end,
+                        blocks: block_targets(ops)?,
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        Ok(Module {
+            inner: Arc::new(module),
+        })
+    }
+
+    /// Returns the module and field names of all imports of this module, in
+    /// the order they must be provided to
+    /// [`Store::instantiate`](crate::Store::instantiate).
+    pub fn imports(&self) -> impl ExactSizeIterator<Item = (&str, &str)> + '_ {
+        self.inner
+            .imports
+            .iter()
+            .map(|i| (i.module.as_str(), i.name.as_str()))
+    }
+}
+
+impl ModuleInner {
+    /// Returns a reader for the operators in `range` of this module.
+    pub fn operators(&self, range: &Range<usize>) -> OperatorsReader<'_> {
+        OperatorsReader::new(BinaryReader::new(&self.bytes[range.clone()], range.start))
+    }
+}
+
+/// Calculates where control is transferred to for all blocks in a function.
+fn block_targets(mut ops: OperatorsReader<'_>) -> Result<HashMap<usize, BlockTargets>> {
+    let mut blocks = HashMap::new();
+    let mut stack = Vec::new();
+    while !ops.eof() {
+        let (op, offset) = ops.read_with_offset()?;
+        match op {
+            Operator::Block { .. } | Operator::Loop { .. } | Operator::If { .. } => {
+                stack.push((offset, None));
+            }
+            Operator::Else => {
+                stack.last_mut().unwrap().1 = Some(ops.original_position());
+            }
+            Operator::End => {
+                // The final `end` of the function has no block.
+                if let Some((start, else_)) = stack.pop() {
+                    let end = ops.original_position();
+                    blocks.insert(start, BlockTargets { else_, end });
+                }
+            }
+            Operator::TryTable { .. }
+            | Operator::Try { .. }
+            | Operator::Catch { .. }
+            | Operator::CatchAll
+            | Operator::Delegate { .. } => {
+                bail!("exception handling is not supported")
+            }
+            _ => {}
+        }
+    }
+    Ok(blocks)
+}
@@ -0,0 +1,130 @@
+//! Numeric helpers shared between scalar and SIMD operators which implement
+//! the specification's semantics where Rust's differ.
+
+use crate::trap;
+use anyhow::Result;
+
+macro_rules! float_helpers {
+    ($min:ident $max:ident $nearest:ident $ty:ident) => {
+        /// Returns the minimum of `a` and `b`, propagating NaN and treating
+        /// `-0` as less than `+0`.
+        pub fn $min(a: $ty, b: $ty) -> $ty {
+            if a.is_nan() || b.is_nan() {
+                $ty::NAN
+            } else if a == b {
+                if a.is_sign_negative() { a } else { b }
+            } else if a < b {
+                a
+            } else {
+                b
+            }
+        }
+
+        /// Returns the maximum of `a` and `b`, propagating NaN and treating
+        /// `-0` as less than `+0`.
+        pub fn $max(a: $ty, b: $ty) -> $ty {
+            if a.is_nan() || b.is_nan() {
+                $ty::NAN
+            } else if a == b {
+                if a.is_sign_positive() { a } else { b }
+            } else if a > b {
+                a
+            } else {
+                b
+            }
+        }
+
+        /// Rounds `a` to the nearest integer, with ties rounding to even.
+        pub fn $nearest(a: $ty) -> $ty {
+            if a.is_nan() || a.is_infinite() || a == 0.0 {
+                return a;
+            }
+            let rounded = a.round();
+            let result = if (rounded - a).abs() == 0.5 {
+                2.0 * (a / 2.0).round()
+            } else {
+                rounded
+            };
+            result.copysign(a)
+        }
+    };
+}
+
+float_helpers!(f32_min f32_max f32_nearest f32);
+float_helpers!(f64_min f64_max f64_nearest f64);
+
+/// Truncates `a` towards zero, trapping if it's NaN or not within the range
+/// `min..=max` of the destination integer type.
+pub fn trunc(a: f64, min: f64, max: f64) -> Result<f64> {
+    if a.is_nan() {
+        return Err(trap!("invalid conversion to integer"));
+    }
+    let a = a.trunc();
+    if a < min || a > max {
+        return Err(trap!("integer overflow"));
+    }
+    Ok(a)
+}
+
+/// Truncates the `f32` or `f64`, widened to `f64`, `a` to an `i32`.
+pub fn i32_trunc_s(a: f64) -> Result<i32> {
+    Ok(trunc(a, i32::MIN as f64, i32::MAX as f64)? as i32)
+}
+
+/// Truncates the `f32` or `f64`, widened to `f64`, `a` to a `u32`.
+pub fn i32_trunc_u(a: f64) -> Result<i32> {
+    Ok(trunc(a, 0.0, u32::MAX as f64)? as u32 as i32)
+}
+
+/// Truncates the `f32` or `f64`, widened to `f64`, `a` to an `i64`.
+///
+/// Note that `i64::MAX` isn't representable as an `f64` so the upper bound is
+/// checked as exclusive of 2^63.
+pub fn i64_trunc_s(a: f64) -> Result<i64> {
+    let a = trunc(a, i64::MIN as f64, f64::INFINITY)?;
+    if a >= 9223372036854775808.0 {
+        return Err(trap!("integer overflow"));
+    }
+    Ok(a as i64)
+}
+
+/// Truncates the `f32` or `f64`, widened to `f64`, `a` to a `u64`.
+pub fn i64_trunc_u(a: f64) -> Result<i64> {
+    let a = trunc(a, 0.0, f64::INFINITY)?;
+    if a >= 18446744073709551616.0 {
+        return Err(trap!("integer overflow"));
+    }
+    Ok(a as u64 as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nearest() {
+        assert_eq!(f32_nearest(0.5), 0.0);
+        assert_eq!(f32_nearest(1.5), 2.0);
+        assert_eq!(f32_nearest(2.5), 2.0);
+        assert_eq!(f32_nearest(-3.5), -4.0);
+        assert!(f32_nearest(-0.5).is_sign_negative());
+        assert_eq!(f64_nearest(4503599627370495.5), 4503599627370496.0);
+    }
+
+    #[test]
+    fn min_max() {
+        assert!(f32_min(0.0, -0.0).is_sign_negative());
+        assert!(f32_max(-0.0, 0.0).is_sign_positive());
+        assert!(f64_min(1.0, f64::NAN).is_nan());
+    }
+
+    #[test]
+    fn truncation() {
+        assert_eq!(i32_trunc_s(-2147483648.9).unwrap(), i32::MIN);
+        assert!(i32_trunc_s(2147483648.0).is_err());
+        assert_eq!(i32_trunc_u(-0.9).unwrap(), 0);
+        assert!(i32_trunc_u(-1.0).is_err());
+        assert!(i64_trunc_s(9223372036854775808.0).is_err());
+        assert_eq!(i64_trunc_u(18446744073709549568.0).unwrap(), -2048);
+    }
+}
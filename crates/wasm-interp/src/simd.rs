@@ -0,0 +1,568 @@
+//! SIMD operators, implemented lane-by-lane with scalar operations.
+//!
+//! A `v128` is represented as a `u128` with lane 0 in its least significant
+//! bits, and the helpers below convert it to and from arrays of lanes.
+
+use crate::exec::Exec;
+use crate::{Val, num};
+use anyhow::Result;
+use std::array;
+use wasmparser::Operator;
+
+macro_rules! lanes {
+    ($($get:ident $set:ident $ty:ident $n:tt)*) => {$(
+        fn $get(v: u128) -> [$ty; $n] {
+            const SIZE: usize = 16 / $n;
+            let bytes = v.to_le_bytes();
+            array::from_fn(|i| $ty::from_le_bytes(bytes[i * SIZE..][..SIZE].try_into().unwrap()))
+        }
+
+        fn $set(lanes: [$ty; $n]) -> u128 {
+            const SIZE: usize = 16 / $n;
+            let mut bytes = [0; 16];
+            for (i, lane) in lanes.iter().enumerate() {
+                bytes[i * SIZE..][..SIZE].copy_from_slice(&lane.to_le_bytes());
+            }
+            u128::from_le_bytes(bytes)
+        }
+    )*};
+}
+
+lanes! {
+    i8x16 from_i8x16 i8 16
+    u8x16 from_u8x16 u8 16
+    i16x8 from_i16x8 i16 8
+    u16x8 from_u16x8 u16 8
+    i32x4 from_i32x4 i32 4
+    u32x4 from_u32x4 u32 4
+    i64x2 from_i64x2 i64 2
+    u64x2 from_u64x2 u64 2
+    f32x4 from_f32x4 f32 4
+    f64x2 from_f64x2 f64 2
+}
+
+/// Returns a lane mask of all ones if `b` is true or all zeros otherwise.
+fn mask<T: From<u8> + std::ops::Not<Output = T>>(b: bool) -> T {
+    if b { !T::from(0) } else { T::from(0) }
+}
+
+/// Returns the lanes of `a` followed by the lanes of `b`.
+fn concat<T: Copy + Default, const N: usize, const M: usize>(a: [T; N], b: [T; N]) -> [T; M] {
+    array::from_fn(|i| if i < N { a[i] } else { b[i - N] })
+}
+
+/// Computes the `avgr_u` of two lanes widened to `u32`.
+fn avgr(a: u32, b: u32) -> u32 {
+    (a + b).div_ceil(2)
+}
+
+fn f32_pmin(a: f32, b: f32) -> f32 {
+    if b < a { b } else { a }
+}
+
+fn f32_pmax(a: f32, b: f32) -> f32 {
+    if a < b { b } else { a }
+}
+
+fn f64_pmin(a: f64, b: f64) -> f64 {
+    if b < a { b } else { a }
+}
+
+fn f64_pmax(a: f64, b: f64) -> f64 {
+    if a < b { b } else { a }
+}
+
+macro_rules! unop {
+    ($self:ident, $get:ident, $set:ident, |$a:ident| $e:expr) => {{
+        let v = $get($self.pop_v128());
+        $self.push(Val::V128($set(v.map(|$a| $e))));
+    }};
+}
+
+macro_rules! binop {
+    ($self:ident, $get:ident, $set:ident, |$a:ident, $b:ident| $e:expr) => {{
+        let b = $get($self.pop_v128());
+        let a = $get($self.pop_v128());
+        $self.push(Val::V128($set(array::from_fn(|i| {
+            let ($a, $b) = (a[i], b[i]);
+            $e
+        }))));
+    }};
+}
+
+macro_rules! cmpop {
+    ($self:ident, $get:ident, $set:ident, |$a:ident, $b:ident| $e:expr) => {
+        binop!($self, $get, $set, |$a, $b| mask($e))
+    };
+}
+
+macro_rules! shift {
+    ($self:ident, $get:ident, $set:ident, $method:ident) => {{
+        let amount = $self.pop_i32() as u32;
+        unop!($self, $get, $set, |a| a.$method(amount))
+    }};
+}
+
+macro_rules! splat {
+    ($self:ident, $pop:ident, $set:ident, $ty:ident) => {{
+        let x = $self.$pop() as $ty;
+        $self.push(Val::V128($set([x; _])));
+    }};
+}
+
+macro_rules! extract_lane {
+    ($self:ident, $get:ident, $lane:ident, $ctor:ident) => {{
+        let v = $get($self.pop_v128());
+        $self.push(Val::$ctor(v[usize::from($lane)].into()));
+    }};
+}
+
+macro_rules! replace_lane {
+    ($self:ident, $pop:ident, $get:ident, $set:ident, $lane:ident) => {{
+        let x = $self.$pop();
+        let mut v = $get($self.pop_v128());
+        v[usize::from($lane)] = x as _;
+        $self.push(Val::V128($set(v)));
+    }};
+}
+
+macro_rules! all_true {
+    ($self:ident, $get:ident) => {{
+        let v = $get($self.pop_v128());
+        $self.push(Val::I32(v.iter().all(|x| *x != 0).into()));
+    }};
+}
+
+macro_rules! bitmask {
+    ($self:ident, $get:ident) => {{
+        let v = $get($self.pop_v128());
+        let bits = v
+            .iter()
+            .enumerate()
+            .fold(0, |acc, (i, x)| acc | (i32::from(*x < 0) << i));
+        $self.push(Val::I32(bits));
+    }};
+}
+
+/// Narrows the lanes of two vectors into one with saturation.
+macro_rules! narrow {
+    ($self:ident, $get:ident, $set:ident, $ty:ident) => {{
+        let b = $get($self.pop_v128());
+        let a = $get($self.pop_v128());
+        let lanes = concat(a, b).map(|x| x.clamp($ty::MIN.into(), $ty::MAX.into()) as $ty);
+        $self.push(Val::V128($set(lanes)));
+    }};
+}
+
+/// Widens either the low or high half of the lanes of a vector.
+macro_rules! extend {
+    ($self:ident, $get:ident, $set:ident, $half:literal) => {{
+        let v = $get($self.pop_v128());
+        let n = v.len() / 2;
+        $self.push(Val::V128($set(array::from_fn(|i| v[i + n * $half].into()))));
+    }};
+}
+
+/// Multiplies the widened lanes of either the low or high half of two
+/// vectors.
+macro_rules! extmul {
+    ($self:ident, $get:ident, $set:ident, $wide:ident, $half:literal) => {{
+        let b = $get($self.pop_v128());
+        let a = $get($self.pop_v128());
+        let n = a.len() / 2;
+        $self.push(Val::V128($set(array::from_fn(|i| {
+            let a = $wide::from(a[i + n * $half]);
+            a.wrapping_mul(b[i + n * $half].into())
+        }))));
+    }};
+}
+
+macro_rules! extadd_pairwise {
+    ($self:ident, $get:ident, $set:ident, $wide:ident) => {{
+        let v = $get($self.pop_v128());
+        $self.push(Val::V128($set(array::from_fn(|i| {
+            $wide::from(v[2 * i]) + $wide::from(v[2 * i + 1])
+        }))));
+    }};
+}
+
+macro_rules! load_extend {
+    ($self:ident, $memarg:ident, $from:ident, $set:ident) => {{
+        let bytes: [u8; 8] = $self.load(&$memarg)?;
+        let v = $from(u128::from(u64::from_le_bytes(bytes)));
+        $self.push(Val::V128($set(array::from_fn(|i| v[i].into()))));
+    }};
+}
+
+macro_rules! load_splat {
+    ($self:ident, $memarg:ident, $ty:ident, $set:ident) => {{
+        let x = $ty::from_le_bytes($self.load(&$memarg)?);
+        $self.push(Val::V128($set([x; _])));
+    }};
+}
+
+macro_rules! load_lane {
+    ($self:ident, $memarg:ident, $lane:ident, $ty:ident, $get:ident, $set:ident) => {{
+        let mut v = $get($self.pop_v128());
+        v[usize::from($lane)] = $ty::from_le_bytes($self.load(&$memarg)?);
+        $self.push(Val::V128($set(v)));
+    }};
+}
+
+macro_rules! store_lane {
+    ($self:ident, $memarg:ident, $lane:ident, $get:ident) => {{
+        let v = $get($self.pop_v128());
+        $self.store(&$memarg, v[usize::from($lane)].to_le_bytes())?;
+    }};
+}
+
+impl Exec<'_, '_> {
+    /// Executes `op` if it's a SIMD operator, returning whether it was.
+    pub(crate) fn simd(&mut self, op: &Operator<'_>) -> Result<bool> {
+        use Operator::*;
+
+        match *op {
+            V128Load { memarg } => {
+                let bytes = self.load(&memarg)?;
+                self.push(Val::V128(u128::from_le_bytes(bytes)));
+            }
+            V128Load8x8S { memarg } => load_extend!(self, memarg, i8x16, from_i16x8),
+            V128Load8x8U { memarg } => load_extend!(self, memarg, u8x16, from_u16x8),
+            V128Load16x4S { memarg } => load_extend!(self, memarg, i16x8, from_i32x4),
+            V128Load16x4U { memarg } => load_extend!(self, memarg, u16x8, from_u32x4),
+            V128Load32x2S { memarg } => load_extend!(self, memarg, i32x4, from_i64x2),
+            V128Load32x2U { memarg } => load_extend!(self, memarg, u32x4, from_u64x2),
+            V128Load8Splat { memarg } => load_splat!(self, memarg, u8, from_u8x16),
+            V128Load16Splat { memarg } => load_splat!(self, memarg, u16, from_u16x8),
+            V128Load32Splat { memarg } => load_splat!(self, memarg, u32, from_u32x4),
+            V128Load64Splat { memarg } => load_splat!(self, memarg, u64, from_u64x2),
+            V128Load32Zero { memarg } => {
+                let x = u32::from_le_bytes(self.load(&memarg)?);
+                self.push(Val::V128(x.into()));
+            }
+            V128Load64Zero { memarg } => {
+                let x = u64::from_le_bytes(self.load(&memarg)?);
+                self.push(Val::V128(x.into()));
+            }
+            V128Store { memarg } => {
+                let v = self.pop_v128();
+                self.store(&memarg, v.to_le_bytes())?;
+            }
+            V128Load8Lane { memarg, lane } => load_lane!(self, memarg, lane, u8, u8x16, from_u8x16),
+            V128Load16Lane { memarg, lane } => {
+                load_lane!(self, memarg, lane, u16, u16x8, from_u16x8)
+            }
+            V128Load32Lane { memarg, lane } => {
+                load_lane!(self, memarg, lane, u32, u32x4, from_u32x4)
+            }
+            V128Load64Lane { memarg, lane } => {
+                load_lane!(self, memarg, lane, u64, u64x2, from_u64x2)
+            }
+            V128Store8Lane { memarg, lane } => store_lane!(self, memarg, lane, u8x16),
+            V128Store16Lane { memarg, lane } => store_lane!(self, memarg, lane, u16x8),
+            V128Store32Lane { memarg, lane } => store_lane!(self, memarg, lane, u32x4),
+            V128Store64Lane { memarg, lane } => store_lane!(self, memarg, lane, u64x2),
+            V128Const { value } => self.push(Val::V128(u128::from_le_bytes(*value.bytes()))),
+
+            I8x16Shuffle { lanes } => {
+                let b = u8x16(self.pop_v128());
+                let a = u8x16(self.pop_v128());
+                let ab: [u8; 32] = concat(a, b);
+                let v = lanes.map(|i| ab[usize::from(i)]);
+                self.push(Val::V128(from_u8x16(v)));
+            }
+            I8x16Swizzle => {
+                let s = u8x16(self.pop_v128());
+                let a = u8x16(self.pop_v128());
+                let v = s.map(|i| a.get(usize::from(i)).copied().unwrap_or(0));
+                self.push(Val::V128(from_u8x16(v)));
+            }
+            I8x16ExtractLaneS { lane } => extract_lane!(self, i8x16, lane, I32),
+            I8x16ExtractLaneU { lane } => extract_lane!(self, u8x16, lane, I32),
+            I16x8ExtractLaneS { lane } => extract_lane!(self, i16x8, lane, I32),
+            I16x8ExtractLaneU { lane } => extract_lane!(self, u16x8, lane, I32),
+            I32x4ExtractLane { lane } => extract_lane!(self, i32x4, lane, I32),
+            I64x2ExtractLane { lane } => extract_lane!(self, i64x2, lane, I64),
+            F32x4ExtractLane { lane } => extract_lane!(self, u32x4, lane, F32),
+            F64x2ExtractLane { lane } => extract_lane!(self, u64x2, lane, F64),
+            I8x16ReplaceLane { lane } => replace_lane!(self, pop_i32, u8x16, from_u8x16, lane),
+            I16x8ReplaceLane { lane } => replace_lane!(self, pop_i32, u16x8, from_u16x8, lane),
+            I32x4ReplaceLane { lane } => replace_lane!(self, pop_i32, i32x4, from_i32x4, lane),
+            I64x2ReplaceLane { lane } => replace_lane!(self, pop_i64, i64x2, from_i64x2, lane),
+            F32x4ReplaceLane { lane } => {
+                replace_lane!(self, pop_f32_bits, u32x4, from_u32x4, lane)
+            }
+            F64x2ReplaceLane { lane } => {
+                replace_lane!(self, pop_f64_bits, u64x2, from_u64x2, lane)
+            }
+            I8x16Splat => splat!(self, pop_i32, from_u8x16, u8),
+            I16x8Splat => splat!(self, pop_i32, from_u16x8, u16),
+            I32x4Splat => splat!(self, pop_i32, from_i32x4, i32),
+            I64x2Splat => splat!(self, pop_i64, from_i64x2, i64),
+            F32x4Splat => splat!(self, pop_f32_bits, from_u32x4, u32),
+            F64x2Splat => splat!(self, pop_f64_bits, from_u64x2, u64),
+
+            I8x16Eq => cmpop!(self, u8x16, from_u8x16, |a, b| a == b),
+            I8x16Ne => cmpop!(self, u8x16, from_u8x16, |a, b| a != b),
+            I8x16LtS => cmpop!(self, i8x16, from_u8x16, |a, b| a < b),
+            I8x16LtU => cmpop!(self, u8x16, from_u8x16, |a, b| a < b),
+            I8x16GtS => cmpop!(self, i8x16, from_u8x16, |a, b| a > b),
+            I8x16GtU => cmpop!(self, u8x16, from_u8x16, |a, b| a > b),
+            I8x16LeS => cmpop!(self, i8x16, from_u8x16, |a, b| a <= b),
+            I8x16LeU => cmpop!(self, u8x16, from_u8x16, |a, b| a <= b),
+            I8x16GeS => cmpop!(self, i8x16, from_u8x16, |a, b| a >= b),
+            I8x16GeU => cmpop!(self, u8x16, from_u8x16, |a, b| a >= b),
+            I16x8Eq => cmpop!(self, u16x8, from_u16x8, |a, b| a == b),
+            I16x8Ne => cmpop!(self, u16x8, from_u16x8, |a, b| a != b),
+            I16x8LtS => cmpop!(self, i16x8, from_u16x8, |a, b| a < b),
+            I16x8LtU => cmpop!(self, u16x8, from_u16x8, |a, b| a < b),
+            I16x8GtS => cmpop!(self, i16x8, from_u16x8, |a, b| a > b),
+            I16x8GtU => cmpop!(self, u16x8, from_u16x8, |a, b| a > b),
+            I16x8LeS => cmpop!(self, i16x8, from_u16x8, |a, b| a <= b),
+            I16x8LeU => cmpop!(self, u16x8, from_u16x8, |a, b| a <= b),
+            I16x8GeS => cmpop!(self, i16x8, from_u16x8, |a, b| a >= b),
+            I16x8GeU => cmpop!(self, u16x8, from_u16x8, |a, b| a >= b),
+            I32x4Eq => cmpop!(self, u32x4, from_u32x4, |a, b| a == b),
+            I32x4Ne => cmpop!(self, u32x4, from_u32x4, |a, b| a != b),
+            I32x4LtS => cmpop!(self, i32x4, from_u32x4, |a, b| a < b),
+            I32x4LtU => cmpop!(self, u32x4, from_u32x4, |a, b| a < b),
+            I32x4GtS => cmpop!(self, i32x4, from_u32x4, |a, b| a > b),
+            I32x4GtU => cmpop!(self, u32x4, from_u32x4, |a, b| a > b),
+            I32x4LeS => cmpop!(self, i32x4, from_u32x4, |a, b| a <= b),
+            I32x4LeU => cmpop!(self, u32x4, from_u32x4, |a, b| a <= b),
+            I32x4GeS => cmpop!(self, i32x4, from_u32x4, |a, b| a >= b),
+            I32x4GeU => cmpop!(self, u32x4, from_u32x4, |a, b| a >= b),
+            I64x2Eq => cmpop!(self, u64x2, from_u64x2, |a, b| a == b),
+            I64x2Ne => cmpop!(self, u64x2, from_u64x2, |a, b| a != b),
+            I64x2LtS => cmpop!(self, i64x2, from_u64x2, |a, b| a < b),
+            I64x2GtS => cmpop!(self, i64x2, from_u64x2, |a, b| a > b),
+            I64x2LeS => cmpop!(self, i64x2, from_u64x2, |a, b| a <= b),
+            I64x2GeS => cmpop!(self, i64x2, from_u64x2, |a, b| a >= b),
+            F32x4Eq => cmpop!(self, f32x4, from_u32x4, |a, b| a == b),
+            F32x4Ne => cmpop!(self, f32x4, from_u32x4, |a, b| a != b),
+            F32x4Lt => cmpop!(self, f32x4, from_u32x4, |a, b| a < b),
+            F32x4Gt => cmpop!(self, f32x4, from_u32x4, |a, b| a > b),
+            F32x4Le => cmpop!(self, f32x4, from_u32x4, |a, b| a <= b),
+            F32x4Ge => cmpop!(self, f32x4, from_u32x4, |a, b| a >= b),
+            F64x2Eq => cmpop!(self, f64x2, from_u64x2, |a, b| a == b),
+            F64x2Ne => cmpop!(self, f64x2, from_u64x2, |a, b| a != b),
+            F64x2Lt => cmpop!(self, f64x2, from_u64x2, |a, b| a < b),
+            F64x2Gt => cmpop!(self, f64x2, from_u64x2, |a, b| a > b),
+            F64x2Le => cmpop!(self, f64x2, from_u64x2, |a, b| a <= b),
+            F64x2Ge => cmpop!(self, f64x2, from_u64x2, |a, b| a >= b),
+
+            V128Not => {
+                let v = self.pop_v128();
+                self.push(Val::V128(!v));
+            }
+            V128And => binop!(self, u64x2, from_u64x2, |a, b| a & b),
+            V128AndNot => binop!(self, u64x2, from_u64x2, |a, b| a & !b),
+            V128Or => binop!(self, u64x2, from_u64x2, |a, b| a | b),
+            V128Xor => binop!(self, u64x2, from_u64x2, |a, b| a ^ b),
+            V128Bitselect => {
+                let c = self.pop_v128();
+                let b = self.pop_v128();
+                let a = self.pop_v128();
+                self.push(Val::V128((a & c) | (b & !c)));
+            }
+            V128AnyTrue => {
+                let v = self.pop_v128();
+                self.push(Val::I32((v != 0).into()));
+            }
+
+            I8x16Abs => unop!(self, i8x16, from_i8x16, |a| a.wrapping_abs()),
+            I8x16Neg => unop!(self, i8x16, from_i8x16, |a| a.wrapping_neg()),
+            I8x16Popcnt => unop!(self, u8x16, from_u8x16, |a| a.count_ones() as u8),
+            I8x16AllTrue => all_true!(self, u8x16),
+            I8x16Bitmask => bitmask!(self, i8x16),
+            I8x16NarrowI16x8S => narrow!(self, i16x8, from_i8x16, i8),
+            I8x16NarrowI16x8U => narrow!(self, i16x8, from_u8x16, u8),
+            I8x16Shl => shift!(self, i8x16, from_i8x16, wrapping_shl),
+            I8x16ShrS => shift!(self, i8x16, from_i8x16, wrapping_shr),
+            I8x16ShrU => shift!(self, u8x16, from_u8x16, wrapping_shr),
+            I8x16Add => binop!(self, i8x16, from_i8x16, |a, b| a.wrapping_add(b)),
+            I8x16AddSatS => binop!(self, i8x16, from_i8x16, |a, b| a.saturating_add(b)),
+            I8x16AddSatU => binop!(self, u8x16, from_u8x16, |a, b| a.saturating_add(b)),
+            I8x16Sub => binop!(self, i8x16, from_i8x16, |a, b| a.wrapping_sub(b)),
+            I8x16SubSatS => binop!(self, i8x16, from_i8x16, |a, b| a.saturating_sub(b)),
+            I8x16SubSatU => binop!(self, u8x16, from_u8x16, |a, b| a.saturating_sub(b)),
+            I8x16MinS => binop!(self, i8x16, from_i8x16, |a, b| a.min(b)),
+            I8x16MinU => binop!(self, u8x16, from_u8x16, |a, b| a.min(b)),
+            I8x16MaxS => binop!(self, i8x16, from_i8x16, |a, b| a.max(b)),
+            I8x16MaxU => binop!(self, u8x16, from_u8x16, |a, b| a.max(b)),
+            I8x16AvgrU => binop!(self, u8x16, from_u8x16, |a, b| avgr(a.into(), b.into())
+                as u8),
+
+            I16x8ExtAddPairwiseI8x16S => extadd_pairwise!(self, i8x16, from_i16x8, i16),
+            I16x8ExtAddPairwiseI8x16U => extadd_pairwise!(self, u8x16, from_u16x8, u16),
+            I16x8Abs => unop!(self, i16x8, from_i16x8, |a| a.wrapping_abs()),
+            I16x8Neg => unop!(self, i16x8, from_i16x8, |a| a.wrapping_neg()),
+            I16x8Q15MulrSatS => binop!(self, i16x8, from_i16x8, |a, b| {
+                let product = (i32::from(a) * i32::from(b) + 0x4000) >> 15;
+                product.clamp(i16::MIN.into(), i16::MAX.into()) as i16
+            }),
+            I16x8AllTrue => all_true!(self, u16x8),
+            I16x8Bitmask => bitmask!(self, i16x8),
+            I16x8NarrowI32x4S => narrow!(self, i32x4, from_i16x8, i16),
+            I16x8NarrowI32x4U => narrow!(self, i32x4, from_u16x8, u16),
+            I16x8ExtendLowI8x16S => extend!(self, i8x16, from_i16x8, 0),
+            I16x8ExtendHighI8x16S => extend!(self, i8x16, from_i16x8, 1),
+            I16x8ExtendLowI8x16U => extend!(self, u8x16, from_u16x8, 0),
+            I16x8ExtendHighI8x16U => extend!(self, u8x16, from_u16x8, 1),
+            I16x8Shl => shift!(self, i16x8, from_i16x8, wrapping_shl),
+            I16x8ShrS => shift!(self, i16x8, from_i16x8, wrapping_shr),
+            I16x8ShrU => shift!(self, u16x8, from_u16x8, wrapping_shr),
+            I16x8Add => binop!(self, i16x8, from_i16x8, |a, b| a.wrapping_add(b)),
+            I16x8AddSatS => binop!(self, i16x8, from_i16x8, |a, b| a.saturating_add(b)),
+            I16x8AddSatU => binop!(self, u16x8, from_u16x8, |a, b| a.saturating_add(b)),
+            I16x8Sub => binop!(self, i16x8, from_i16x8, |a, b| a.wrapping_sub(b)),
+            I16x8SubSatS => binop!(self, i16x8, from_i16x8, |a, b| a.saturating_sub(b)),
+            I16x8SubSatU => binop!(self, u16x8, from_u16x8, |a, b| a.saturating_sub(b)),
+            I16x8Mul => binop!(self, i16x8, from_i16x8, |a, b| a.wrapping_mul(b)),
+            I16x8MinS => binop!(self, i16x8, from_i16x8, |a, b| a.min(b)),
+            I16x8MinU => binop!(self, u16x8, from_u16x8, |a, b| a.min(b)),
+            I16x8MaxS => binop!(self, i16x8, from_i16x8, |a, b| a.max(b)),
+            I16x8MaxU => binop!(self, u16x8, from_u16x8, |a, b| a.max(b)),
+            I16x8AvgrU => binop!(self, u16x8, from_u16x8, |a, b| avgr(a.into(), b.into())
+                as u16),
+            I16x8ExtMulLowI8x16S => extmul!(self, i8x16, from_i16x8, i16, 0),
+            I16x8ExtMulHighI8x16S => extmul!(self, i8x16, from_i16x8, i16, 1),
+            I16x8ExtMulLowI8x16U => extmul!(self, u8x16, from_u16x8, u16, 0),
+            I16x8ExtMulHighI8x16U => extmul!(self, u8x16, from_u16x8, u16, 1),
+
+            I32x4ExtAddPairwiseI16x8S => extadd_pairwise!(self, i16x8, from_i32x4, i32),
+            I32x4ExtAddPairwiseI16x8U => extadd_pairwise!(self, u16x8, from_u32x4, u32),
+            I32x4Abs => unop!(self, i32x4, from_i32x4, |a| a.wrapping_abs()),
+            I32x4Neg => unop!(self, i32x4, from_i32x4, |a| a.wrapping_neg()),
+            I32x4AllTrue => all_true!(self, u32x4),
+            I32x4Bitmask => bitmask!(self, i32x4),
+            I32x4ExtendLowI16x8S => extend!(self, i16x8, from_i32x4, 0),
+            I32x4ExtendHighI16x8S => extend!(self, i16x8, from_i32x4, 1),
+            I32x4ExtendLowI16x8U => extend!(self, u16x8, from_u32x4, 0),
+            I32x4ExtendHighI16x8U => extend!(self, u16x8, from_u32x4, 1),
+            I32x4Shl => shift!(self, i32x4, from_i32x4, wrapping_shl),
+            I32x4ShrS => shift!(self, i32x4, from_i32x4, wrapping_shr),
+            I32x4ShrU => shift!(self, u32x4, from_u32x4, wrapping_shr),
+            I32x4Add => binop!(self, i32x4, from_i32x4, |a, b| a.wrapping_add(b)),
+            I32x4Sub => binop!(self, i32x4, from_i32x4, |a, b| a.wrapping_sub(b)),
+            I32x4Mul => binop!(self, i32x4, from_i32x4, |a, b| a.wrapping_mul(b)),
+            I32x4MinS => binop!(self, i32x4, from_i32x4, |a, b| a.min(b)),
+            I32x4MinU => binop!(self, u32x4, from_u32x4, |a, b| a.min(b)),
+            I32x4MaxS => binop!(self, i32x4, from_i32x4, |a, b| a.max(b)),
+            I32x4MaxU => binop!(self, u32x4, from_u32x4, |a, b| a.max(b)),
+            I32x4DotI16x8S => {
+                let b = i16x8(self.pop_v128());
+                let a = i16x8(self.pop_v128());
+                let v = array::from_fn(|i| {
+                    let lo = i32::from(a[2 * i]) * i32::from(b[2 * i]);
+                    let hi = i32::from(a[2 * i + 1]) * i32::from(b[2 * i + 1]);
+                    lo.wrapping_add(hi)
+                });
+                self.push(Val::V128(from_i32x4(v)));
+            }
+            I32x4ExtMulLowI16x8S => extmul!(self, i16x8, from_i32x4, i32, 0),
+            I32x4ExtMulHighI16x8S => extmul!(self, i16x8, from_i32x4, i32, 1),
+            I32x4ExtMulLowI16x8U => extmul!(self, u16x8, from_u32x4, u32, 0),
+            I32x4ExtMulHighI16x8U => extmul!(self, u16x8, from_u32x4, u32, 1),
+
+            I64x2Abs => unop!(self, i64x2, from_i64x2, |a| a.wrapping_abs()),
+            I64x2Neg => unop!(self, i64x2, from_i64x2, |a| a.wrapping_neg()),
+            I64x2AllTrue => all_true!(self, u64x2),
+            I64x2Bitmask => bitmask!(self, i64x2),
+            I64x2ExtendLowI32x4S => extend!(self, i32x4, from_i64x2, 0),
+            I64x2ExtendHighI32x4S => extend!(self, i32x4, from_i64x2, 1),
+            I64x2ExtendLowI32x4U => extend!(self, u32x4, from_u64x2, 0),
+            I64x2ExtendHighI32x4U => extend!(self, u32x4, from_u64x2, 1),
+            I64x2Shl => shift!(self, i64x2, from_i64x2, wrapping_shl),
+            I64x2ShrS => shift!(self, i64x2, from_i64x2, wrapping_shr),
+            I64x2ShrU => shift!(self, u64x2, from_u64x2, wrapping_shr),
+            I64x2Add => binop!(self, i64x2, from_i64x2, |a, b| a.wrapping_add(b)),
+            I64x2Sub => binop!(self, i64x2, from_i64x2, |a, b| a.wrapping_sub(b)),
+            I64x2Mul => binop!(self, i64x2, from_i64x2, |a, b| a.wrapping_mul(b)),
+            I64x2ExtMulLowI32x4S => extmul!(self, i32x4, from_i64x2, i64, 0),
+            I64x2ExtMulHighI32x4S => extmul!(self, i32x4, from_i64x2, i64, 1),
+            I64x2ExtMulLowI32x4U => extmul!(self, u32x4, from_u64x2, u64, 0),
+            I64x2ExtMulHighI32x4U => extmul!(self, u32x4, from_u64x2, u64, 1),
+
+            F32x4Ceil => unop!(self, f32x4, from_f32x4, |a| a.ceil()),
+            F32x4Floor => unop!(self, f32x4, from_f32x4, |a| a.floor()),
+            F32x4Trunc => unop!(self, f32x4, from_f32x4, |a| a.trunc()),
+            F32x4Nearest => unop!(self, f32x4, from_f32x4, |a| num::f32_nearest(a)),
+            F32x4Abs => unop!(self, u32x4, from_u32x4, |a| a & 0x7fff_ffff),
+            F32x4Neg => unop!(self, u32x4, from_u32x4, |a| a ^ 0x8000_0000),
+            F32x4Sqrt => unop!(self, f32x4, from_f32x4, |a| a.sqrt()),
+            F32x4Add => binop!(self, f32x4, from_f32x4, |a, b| a + b),
+            F32x4Sub => binop!(self, f32x4, from_f32x4, |a, b| a - b),
+            F32x4Mul => binop!(self, f32x4, from_f32x4, |a, b| a * b),
+            F32x4Div => binop!(self, f32x4, from_f32x4, |a, b| a / b),
+            F32x4Min => binop!(self, f32x4, from_f32x4, |a, b| num::f32_min(a, b)),
+            F32x4Max => binop!(self, f32x4, from_f32x4, |a, b| num::f32_max(a, b)),
+            F32x4PMin => binop!(self, f32x4, from_f32x4, |a, b| f32_pmin(a, b)),
+            F32x4PMax => binop!(self, f32x4, from_f32x4, |a, b| f32_pmax(a, b)),
+            F64x2Ceil => unop!(self, f64x2, from_f64x2, |a| a.ceil()),
+            F64x2Floor => unop!(self, f64x2, from_f64x2, |a| a.floor()),
+            F64x2Trunc => unop!(self, f64x2, from_f64x2, |a| a.trunc()),
+            F64x2Nearest => unop!(self, f64x2, from_f64x2, |a| num::f64_nearest(a)),
+            F64x2Abs => unop!(self, u64x2, from_u64x2, |a| a & (u64::MAX >> 1)),
+            F64x2Neg => unop!(self, u64x2, from_u64x2, |a| a ^ (1 << 63)),
+            F64x2Sqrt => unop!(self, f64x2, from_f64x2, |a| a.sqrt()),
+            F64x2Add => binop!(self, f64x2, from_f64x2, |a, b| a + b),
+            F64x2Sub => binop!(self, f64x2, from_f64x2, |a, b| a - b),
+            F64x2Mul => binop!(self, f64x2, from_f64x2, |a, b| a * b),
+            F64x2Div => binop!(self, f64x2, from_f64x2, |a, b| a / b),
+            F64x2Min => binop!(self, f64x2, from_f64x2, |a, b| num::f64_min(a, b)),
+            F64x2Max => binop!(self, f64x2, from_f64x2, |a, b| num::f64_max(a, b)),
+            F64x2PMin => binop!(self, f64x2, from_f64x2, |a, b| f64_pmin(a, b)),
+            F64x2PMax => binop!(self, f64x2, from_f64x2, |a, b| f64_pmax(a, b)),
+
+            // Rust's `as` conversions from floats to integers saturate and
+            // turn NaN into zero which matches the `trunc_sat` semantics.
+            I32x4TruncSatF32x4S => unop!(self, f32x4, from_i32x4, |a| a as i32),
+            I32x4TruncSatF32x4U => unop!(self, f32x4, from_u32x4, |a| a as u32),
+            F32x4ConvertI32x4S => unop!(self, i32x4, from_f32x4, |a| a as f32),
+            F32x4ConvertI32x4U => unop!(self, u32x4, from_f32x4, |a| a as f32),
+            I32x4TruncSatF64x2SZero => {
+                let v = f64x2(self.pop_v128());
+                self.push(Val::V128(from_i32x4([v[0] as i32, v[1] as i32, 0, 0])));
+            }
+            I32x4TruncSatF64x2UZero => {
+                let v = f64x2(self.pop_v128());
+                self.push(Val::V128(from_u32x4([v[0] as u32, v[1] as u32, 0, 0])));
+            }
+            F64x2ConvertLowI32x4S => {
+                let v = i32x4(self.pop_v128());
+                self.push(Val::V128(from_f64x2([v[0].into(), v[1].into()])));
+            }
+            F64x2ConvertLowI32x4U => {
+                let v = u32x4(self.pop_v128());
+                self.push(Val::V128(from_f64x2([v[0].into(), v[1].into()])));
+            }
+            F32x4DemoteF64x2Zero => {
+                let v = f64x2(self.pop_v128());
+                self.push(Val::V128(from_f32x4([v[0] as f32, v[1] as f32, 0.0, 0.0])));
+            }
+            F64x2PromoteLowF32x4 => {
+                let v = f32x4(self.pop_v128());
+                self.push(Val::V128(from_f64x2([v[0].into(), v[1].into()])));
+            }
+
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lanes_round_trip() {
+        let v = 0x0f0e0d0c_0b0a0908_07060504_03020100;
+        assert_eq!(u8x16(v)[0], 0);
+        assert_eq!(u8x16(v)[15], 15);
+        assert_eq!(u32x4(v)[1], 0x07060504);
+        assert_eq!(from_i16x8(i16x8(v)), v);
+        assert_eq!(from_f64x2(f64x2(v)), v);
+    }
+}
@@ -0,0 +1,538 @@
+use crate::exec::{Exec, null_ref};
+use crate::module::{ElemItems, ModuleInner, SegmentKind};
+use crate::{Extern, Func, Global, Instance, Memory, Module, Table, Val, trap};
+use anyhow::{Context, Result, bail};
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::Arc;
+use wasmparser::{
+    ExternalKind, FuncType, GlobalType, MemoryType, Operator, TableType, TypeRef, ValType,
+};
+
+/// The maximum number of elements a table may have, regardless of its type.
+const MAX_TABLE_ELEMS: u64 = 10_000_000;
+
+/// The maximum size, in bytes, of a linear memory, regardless of its type.
+const MAX_MEMORY_SIZE: u64 = 1 << 32;
+
+type HostFunc = Arc<dyn Fn(&[Val]) -> Result<Vec<Val>> + Send + Sync>;
+
+/// All state of a set of instances, including their functions, tables,
+/// memories, and globals.
+///
+/// Items created in a store are referred to by handles such as [`Func`] and
+/// [`Memory`] which are only valid for the store they were created in.
+#[derive(Default)]
+pub struct Store {
+    pub(crate) funcs: Vec<FuncInst>,
+    pub(crate) tables: Vec<TableInst>,
+    pub(crate) memories: Vec<MemoryInst>,
+    pub(crate) globals: Vec<GlobalInst>,
+    pub(crate) instances: Vec<InstanceData>,
+    /// The number of operators left to execute, if limited.
+    pub(crate) fuel: Option<u64>,
+}
+
+pub(crate) enum FuncInst {
+    Wasm {
+        ty: FuncType,
+        instance: usize,
+        /// The index of this function's body in its module's code section.
+        index: usize,
+    },
+    Host {
+        ty: FuncType,
+        func: HostFunc,
+    },
+}
+
+pub(crate) struct TableInst {
+    pub ty: TableType,
+    pub elems: Vec<Val>,
+}
+
+pub(crate) struct MemoryInst {
+    pub ty: MemoryType,
+    pub data: Vec<u8>,
+}
+
+pub(crate) struct GlobalInst {
+    pub ty: GlobalType,
+    pub val: Val,
+}
+
+pub(crate) struct InstanceData {
+    pub module: Module,
+    pub funcs: Vec<Func>,
+    pub tables: Vec<Table>,
+    pub memories: Vec<Memory>,
+    pub globals: Vec<Global>,
+    /// The items of each element segment, or empty once dropped.
+    pub elems: Vec<Vec<Val>>,
+    /// The range of each data segment in the module, or empty once dropped.
+    pub datas: Vec<Range<usize>>,
+    pub exports: HashMap<String, Extern>,
+}
+
+impl FuncInst {
+    pub fn ty(&self) -> &FuncType {
+        match self {
+            FuncInst::Wasm { ty, .. } | FuncInst::Host { ty, .. } => ty,
+        }
+    }
+}
+
+impl TableInst {
+    /// Grows this table by `delta` elements of `init`, returning the previous
+    /// size or `None` if the table can't grow.
+    pub fn grow(&mut self, delta: u64, init: Val) -> Option<u64> {
+        let old = self.elems.len() as u64;
+        let new = old.checked_add(delta)?;
+        let max = self.ty.maximum.unwrap_or(u64::MAX);
+        let max = if self.ty.table64 {
+            max
+        } else {
+            max.min(u32::MAX.into())
+        };
+        if new > max || new > MAX_TABLE_ELEMS {
+            return None;
+        }
+        self.elems.resize(usize::try_from(new).ok()?, init);
+        Some(old)
+    }
+}
+
+impl MemoryInst {
+    fn new(ty: MemoryType) -> Result<MemoryInst> {
+        let mut memory = MemoryInst {
+            ty,
+            data: Vec::new(),
+        };
+        if memory.grow(ty.initial).is_none() {
+            bail!("failed to allocate memory of {} pages", ty.initial);
+        }
+        Ok(memory)
+    }
+
+    pub fn page_size(&self) -> u64 {
+        1 << self.ty.page_size_log2.unwrap_or(16)
+    }
+
+    pub fn pages(&self) -> u64 {
+        self.data.len() as u64 / self.page_size()
+    }
+
+    /// Grows this memory by `delta` pages, returning the previous size in
+    /// pages or `None` if the memory can't grow.
+    pub fn grow(&mut self, delta: u64) -> Option<u64> {
+        let old = self.pages();
+        let new = old.checked_add(delta)?;
+        let max = match self.ty.maximum {
+            Some(max) => max,
+            None if self.ty.memory64 => u64::MAX,
+            None => (1 << 32) / self.page_size(),
+        };
+        let bytes = new.checked_mul(self.page_size())?;
+        if new > max || bytes > MAX_MEMORY_SIZE {
+            return None;
+        }
+        let bytes = usize::try_from(bytes).ok()?;
+        self.data.try_reserve_exact(bytes - self.data.len()).ok()?;
+        self.data.resize(bytes, 0);
+        Some(old)
+    }
+}
+
+impl Store {
+    /// Creates a new empty store.
+    pub fn new() -> Store {
+        Store::default()
+    }
+
+    /// Creates a new host function with the type `ty` which is implemented
+    /// by `func`.
+    ///
+    /// The `func` closure is given arguments matching the parameters of `ty`
+    /// and must return values matching the results of `ty`. Errors returned
+    /// from `func`, including [`Trap`](crate::Trap)s, abort execution.
+    pub fn func_new(
+        &mut self,
+        ty: FuncType,
+        func: impl Fn(&[Val]) -> Result<Vec<Val>> + Send + Sync + 'static,
+    ) -> Func {
+        self.funcs.push(FuncInst::Host {
+            ty,
+            func: Arc::new(func),
+        });
+        Func(self.funcs.len() - 1)
+    }
+
+    /// Creates a new table with the type `ty` with all elements initialized
+    /// to `init`.
+    pub fn table_new(&mut self, ty: TableType, init: Val) -> Result<Table> {
+        let mut table = TableInst {
+            ty,
+            elems: Vec::new(),
+        };
+        if table.grow(ty.initial, init).is_none() {
+            bail!("failed to allocate table of {} elements", ty.initial);
+        }
+        self.tables.push(table);
+        Ok(Table(self.tables.len() - 1))
+    }
+
+    /// Creates a new zero-initialized linear memory with the type `ty`.
+    pub fn memory_new(&mut self, ty: MemoryType) -> Result<Memory> {
+        self.memories.push(MemoryInst::new(ty)?);
+        Ok(Memory(self.memories.len() - 1))
+    }
+
+    /// Creates a new global with the type `ty` and the initial value `val`.
+    pub fn global_new(&mut self, ty: GlobalType, val: Val) -> Global {
+        self.globals.push(GlobalInst { ty, val });
+        Global(self.globals.len() - 1)
+    }
+
+    /// Limits execution in this store to `fuel` more operators.
+    ///
+    /// Once all fuel is consumed execution traps with "all fuel consumed".
+    /// By default execution isn't limited.
+    pub fn set_fuel(&mut self, fuel: u64) {
+        self.fuel = Some(fuel);
+    }
+
+    /// Returns the fuel remaining in this store, if it's limited.
+    pub fn get_fuel(&self) -> Option<u64> {
+        self.fuel
+    }
+
+    /// Returns the type of the function `func`.
+    pub fn func_ty(&self, func: Func) -> &FuncType {
+        self.funcs[func.0].ty()
+    }
+
+    /// Returns the current value of the global `global`.
+    pub fn global_get(&self, global: Global) -> Val {
+        self.globals[global.0].val
+    }
+
+    /// Returns the current contents of the linear memory `memory`.
+    pub fn memory_data(&self, memory: Memory) -> &[u8] {
+        &self.memories[memory.0].data
+    }
+
+    /// Returns the export named `name` of `instance`, if any.
+    pub fn get_export(&self, instance: Instance, name: &str) -> Option<Extern> {
+        self.instances[instance.0].exports.get(name).copied()
+    }
+
+    /// Invokes the function `func` with `args`, returning its results.
+    ///
+    /// Returns an error if `args` don't match the type of `func`, if a
+    /// [`Trap`](crate::Trap) is raised during execution, or if execution
+    /// reaches an unsupported instruction.
+    pub fn invoke(&mut self, func: Func, args: &[Val]) -> Result<Vec<Val>> {
+        let ty = self.func_ty(func);
+        if ty.params().len() != args.len() {
+            bail!(
+                "expected {} arguments, found {}",
+                ty.params().len(),
+                args.len()
+            );
+        }
+        for (i, (ty, arg)) in ty.params().iter().zip(args).enumerate() {
+            if !val_matches(arg, *ty) {
+                bail!("argument {i} is {arg:?} which doesn't match the expected type {ty}");
+            }
+        }
+        let modules = self
+            .instances
+            .iter()
+            .map(|i| i.module.clone())
+            .collect::<Vec<_>>();
+        Exec::new(self, &modules).invoke(func, args)
+    }
+
+    /// Instantiates `module` with the `imports` provided, which must be in the
+    /// same order as [`Module::imports`].
+    ///
+    /// Returns an error if the imports don't match the types the module
+    /// expects, or if instantiation traps while initializing segments or
+    /// running the start function. Note that, as with the specification,
+    /// segments initialized before a trap still modify imported tables and
+    /// memories.
+    pub fn instantiate(&mut self, module: &Module, imports: &[Extern]) -> Result<Instance> {
+        let m = &*module.inner;
+        if imports.len() != m.imports.len() {
+            bail!(
+                "expected {} imports, found {}",
+                m.imports.len(),
+                imports.len()
+            );
+        }
+
+        let mut inst = InstanceData {
+            module: module.clone(),
+            funcs: Vec::new(),
+            tables: Vec::new(),
+            memories: Vec::new(),
+            globals: Vec::new(),
+            elems: Vec::new(),
+            datas: Vec::new(),
+            exports: HashMap::new(),
+        };
+        for (import, item) in m.imports.iter().zip(imports) {
+            self.check_import(m, &import.ty, *item).with_context(|| {
+                format!(
+                    "incompatible import type for `{}::{}`",
+                    import.module, import.name
+                )
+            })?;
+            match *item {
+                Extern::Func(f) => inst.funcs.push(f),
+                Extern::Table(t) => inst.tables.push(t),
+                Extern::Memory(m) => inst.memories.push(m),
+                Extern::Global(g) => inst.globals.push(g),
+            }
+        }
+
+        let id = self.instances.len();
+        for (i, ty) in m.funcs.iter().enumerate().skip(m.num_imported_funcs) {
+            inst.funcs.push(Func(self.funcs.len()));
+            self.funcs.push(FuncInst::Wasm {
+                ty: m.types[*ty as usize].clone(),
+                instance: id,
+                index: i - m.num_imported_funcs,
+            });
+        }
+        for (ty, init) in m.globals.iter() {
+            let val = self.eval_const(m, &inst, init)?;
+            inst.globals.push(self.global_new(*ty, val));
+        }
+        for (ty, init) in m.tables.iter() {
+            let init = match init {
+                Some(init) => self.eval_const(m, &inst, init)?,
+                None => null_ref(ty.element_type.heap_type())?,
+            };
+            inst.tables.push(self.table_new(*ty, init)?);
+        }
+        for ty in m.memories.iter() {
+            inst.memories.push(self.memory_new(*ty)?);
+        }
+        for elem in m.elems.iter() {
+            let items = match &elem.items {
+                ElemItems::Funcs(funcs) => funcs
+                    .iter()
+                    .map(|f| Val::FuncRef(Some(inst.funcs[*f as usize])))
+                    .collect(),
+                ElemItems::Exprs(exprs) => exprs
+                    .iter()
+                    .map(|e| self.eval_const(m, &inst, e))
+                    .collect::<Result<_>>()?,
+            };
+            inst.elems.push(items);
+        }
+        inst.datas = m.datas.iter().map(|d| d.data.clone()).collect();
+        for (name, kind, index) in m.exports.iter() {
+            let index = *index as usize;
+            let item = match kind {
+                ExternalKind::Func => Extern::Func(inst.funcs[index]),
+                ExternalKind::Table => Extern::Table(inst.tables[index]),
+                ExternalKind::Memory => Extern::Memory(inst.memories[index]),
+                ExternalKind::Global => Extern::Global(inst.globals[index]),
+                ExternalKind::Tag => unreachable!("tags are rejected by `Module::new`"),
+            };
+            inst.exports.insert(name.clone(), item);
+        }
+        self.instances.push(inst);
+
+        // Initialize tables and memories with active segments, dropping them
+        // afterwards along with declared segments.
+        for (i, elem) in m.elems.iter().enumerate() {
+            match &elem.kind {
+                SegmentKind::Active { index, offset } => {
+                    let inst = &self.instances[id];
+                    let offset = self.eval_const(m, inst, offset)?;
+                    let table = &mut self.tables[inst.tables[*index as usize].0];
+                    let items = &inst.elems[i];
+                    let dst = match offset {
+                        Val::I32(n) => u64::from(n as u32),
+                        Val::I64(n) => n as u64,
+                        _ => unreachable!(),
+                    };
+                    match usize::try_from(dst) {
+                        Ok(dst)
+                            if dst <= table.elems.len()
+                                && items.len() <= table.elems.len() - dst =>
+                        {
+                            table.elems[dst..][..items.len()].copy_from_slice(items);
+                        }
+                        _ => return Err(trap!("out of bounds table access")),
+                    }
+                    self.instances[id].elems[i].clear();
+                }
+                SegmentKind::Declared => self.instances[id].elems[i].clear(),
+                SegmentKind::Passive => {}
+            }
+        }
+        for (i, data) in m.datas.iter().enumerate() {
+            if let SegmentKind::Active { index, offset } = &data.kind {
+                let inst = &self.instances[id];
+                let offset = self.eval_const(m, inst, offset)?;
+                let memory = &mut self.memories[inst.memories[*index as usize].0];
+                let bytes = &m.bytes[data.data.clone()];
+                let dst = match offset {
+                    Val::I32(n) => u64::from(n as u32),
+                    Val::I64(n) => n as u64,
+                    _ => unreachable!(),
+                };
+                match usize::try_from(dst) {
+                    Ok(dst)
+                        if dst <= memory.data.len() && bytes.len() <= memory.data.len() - dst =>
+                    {
+                        memory.data[dst..][..bytes.len()].copy_from_slice(bytes);
+                    }
+                    _ => return Err(trap!("out of bounds memory access")),
+                }
+                self.instances[id].datas[i] = 0..0;
+            }
+        }
+
+        if let Some(start) = m.start {
+            let func = self.instances[id].funcs[start as usize];
+            self.invoke(func, &[])?;
+        }
+        Ok(Instance(id))
+    }
+
+    fn check_import(&self, module: &ModuleInner, expected: &TypeRef, actual: Extern) -> Result<()> {
+        match (expected, actual) {
+            (TypeRef::Func(ty), Extern::Func(f)) => {
+                let expected = &module.types[*ty as usize];
+                let actual = self.func_ty(f);
+                if expected != actual {
+                    bail!("expected function of type `{expected}`, found `{actual}`");
+                }
+            }
+            (TypeRef::Table(expected), Extern::Table(t)) => {
+                let actual = &self.tables[t.0];
+                if expected.element_type != actual.ty.element_type
+                    || expected.table64 != actual.ty.table64
+                {
+                    bail!("table types do not match");
+                }
+                let size = actual.elems.len() as u64;
+                if !limits_match(size, actual.ty.maximum, expected.initial, expected.maximum) {
+                    bail!("table limits do not match");
+                }
+            }
+            (TypeRef::Memory(expected), Extern::Memory(m)) => {
+                let actual = &self.memories[m.0];
+                if expected.memory64 != actual.ty.memory64
+                    || expected.shared != actual.ty.shared
+                    || expected.page_size_log2 != actual.ty.page_size_log2
+                {
+                    bail!("memory types do not match");
+                }
+                let size = actual.pages();
+                if !limits_match(size, actual.ty.maximum, expected.initial, expected.maximum) {
+                    bail!("memory limits do not match");
+                }
+            }
+            (TypeRef::Global(expected), Extern::Global(g)) => {
+                let actual = &self.globals[g.0].ty;
+                if expected.content_type != actual.content_type
+                    || expected.mutable != actual.mutable
+                {
+                    bail!("global types do not match");
+                }
+            }
+            (_, actual) => bail!("import kind does not match, found {actual:?}"),
+        }
+        Ok(())
+    }
+
+    /// Evaluates the constant expression at `range` in `module` for the
+    /// instance `inst`.
+    fn eval_const(
+        &self,
+        module: &ModuleInner,
+        inst: &InstanceData,
+        range: &Range<usize>,
+    ) -> Result<Val> {
+        let mut stack = Vec::new();
+        let mut ops = module.operators(range);
+        loop {
+            let val = match ops.read()? {
+                Operator::I32Const { value } => Val::I32(value),
+                Operator::I64Const { value } => Val::I64(value),
+                Operator::F32Const { value } => Val::F32(value.bits()),
+                Operator::F64Const { value } => Val::F64(value.bits()),
+                Operator::V128Const { value } => Val::V128(u128::from_le_bytes(*value.bytes())),
+                Operator::RefNull { hty } => null_ref(hty)?,
+                Operator::RefFunc { function_index } => {
+                    Val::FuncRef(Some(inst.funcs[function_index as usize]))
+                }
+                Operator::GlobalGet { global_index } => {
+                    self.globals[inst.globals[global_index as usize].0].val
+                }
+                op @ (Operator::I32Add | Operator::I32Sub | Operator::I32Mul) => {
+                    let (Some(Val::I32(b)), Some(Val::I32(a))) = (stack.pop(), stack.pop()) else {
+                        unreachable!()
+                    };
+                    Val::I32(match op {
+                        Operator::I32Add => a.wrapping_add(b),
+                        Operator::I32Sub => a.wrapping_sub(b),
+                        _ => a.wrapping_mul(b),
+                    })
+                }
+                op @ (Operator::I64Add | Operator::I64Sub | Operator::I64Mul) => {
+                    let (Some(Val::I64(b)), Some(Val::I64(a))) = (stack.pop(), stack.pop()) else {
+                        unreachable!()
+                    };
+                    Val::I64(match op {
+                        Operator::I64Add => a.wrapping_add(b),
+                        Operator::I64Sub => a.wrapping_sub(b),
+                        _ => a.wrapping_mul(b),
+                    })
+                }
+                Operator::End => break,
+                op => bail!("unsupported instruction in constant expression: {op:?}"),
+            };
+            stack.push(val);
+        }
+        Ok(stack.pop().unwrap())
+    }
+}
+
+fn limits_match(
+    actual_min: u64,
+    actual_max: Option<u64>,
+    expected_min: u64,
+    expected_max: Option<u64>,
+) -> bool {
+    actual_min >= expected_min
+        && match expected_max {
+            None => true,
+            Some(expected) => matches!(actual_max, Some(actual) if actual <= expected),
+        }
+}
+
+/// Returns whether `val` can be passed where the type `ty` is expected.
+fn val_matches(val: &Val, ty: ValType) -> bool {
+    match (val, ty) {
+        (Val::I32(_), ValType::I32)
+        | (Val::I64(_), ValType::I64)
+        | (Val::F32(_), ValType::F32)
+        | (Val::F64(_), ValType::F64)
+        | (Val::V128(_), ValType::V128) => true,
+        (Val::FuncRef(_) | Val::ExternRef(_), ValType::Ref(r)) => match null_ref(r.heap_type()) {
+            Ok(null) => {
+                std::mem::discriminant(val) == std::mem::discriminant(&null)
+                    && (r.is_nullable() || *val != null)
+            }
+            Err(_) => false,
+        },
+        _ => false,
+    }
+}
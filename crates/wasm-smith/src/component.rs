@@ -1,9 +1,6 @@
 //! Generation of Wasm
 //! [components](https://github.com/WebAssembly/component-model).
 
-// FIXME(#1000): component support in `wasm-smith` is a work in progress.
-#![allow(unused_variables, dead_code)]
-
 use crate::{Config, arbitrary_loop};
 use arbitrary::{Arbitrary, Result, Unstructured};
 use std::collections::BTreeMap;
@@ -12,7 +9,8 @@ use std::{
     rc::Rc,
 };
 use wasm_encoder::{
-    ComponentTypeRef, ComponentValType, HeapType, PrimitiveValType, RefType, TypeBounds, ValType,
+    ComponentExportKind, ComponentTypeRef, ComponentValType, HeapType, PrimitiveValType, RefType,
+    TypeBounds, ValType,
 };
 
 mod encode;
@@ -85,7 +83,7 @@ struct ComponentBuilder {
 
 #[derive(Debug, Clone)]
 enum ComponentOrCoreFuncType {
-    Component(Rc<FuncType>),
+    Component(Rc<FuncSig>, Exportable),
     Core(Rc<crate::core::FuncType>),
 }
 
@@ -93,22 +91,43 @@ impl ComponentOrCoreFuncType {
     fn as_core(&self) -> &Rc<crate::core::FuncType> {
         match self {
             ComponentOrCoreFuncType::Core(t) => t,
-            ComponentOrCoreFuncType::Component(_) => panic!("not a core func type"),
+            ComponentOrCoreFuncType::Component(..) => panic!("not a core func type"),
         }
     }
 
-    fn as_component(&self) -> &Rc<FuncType> {
+    fn as_component(&self) -> (&Rc<FuncSig>, Exportable) {
         match self {
             ComponentOrCoreFuncType::Core(_) => panic!("not a component func type"),
-            ComponentOrCoreFuncType::Component(t) => t,
+            ComponentOrCoreFuncType::Component(t, exportable) => (t, *exportable),
         }
     }
 }
 
+/// Whether a component function may be exported from its component.
+///
+/// Exporting a function requires that every value type it refers to is
+/// "named", i.e. was created by a type import or export, which can't always
+/// be known for functions aliased out of instances.
+#[derive(Debug, Clone, Copy)]
+enum Exportable {
+    Always,
+    Never,
+    // Exportable when the function type at this index in the component's
+    // types scope is named.
+    IfFuncTypeNamed(u32),
+}
+
+/// An entry in a component's instance index space.
 #[derive(Debug, Clone)]
-enum ComponentOrCoreInstanceType {
-    Component(Rc<InstanceType>),
-    Core(BTreeMap<String, crate::core::EntityType>),
+struct ComponentInstance {
+    summary: Rc<InstanceSummary>,
+
+    // Whether functions aliased out of this instance are always exportable.
+    //
+    // This is the case for imported instances, whose types were named when
+    // they were imported, and for instances created from exports, which are
+    // only created from exportable items.
+    aliases_exportable: bool,
 }
 
 /// Metadata (e.g. contents of various index spaces) we keep track of on a
@@ -127,59 +146,56 @@ struct ComponentContext {
     // The set of URLs of imports we've generated thus far.
     import_urls: HashSet<String>,
 
+    // The set of names of exports we've generated thus far.
+    export_names: HashSet<String>,
+
+    // The imports and exports of this component, used to describe it to its
+    // parent once it's finished.
+    imports: Vec<(String, Entity)>,
+    exports: Vec<(String, Entity)>,
+
     // This component's function index space.
     funcs: Vec<ComponentOrCoreFuncType>,
 
     // Which entries in `funcs` are component functions?
     component_funcs: Vec<u32>,
 
-    // Which entries in `component_funcs` are component functions that only use scalar
-    // types?
-    scalar_component_funcs: Vec<u32>,
-
     // Which entries in `funcs` are core Wasm functions?
     //
     // Note that a component can't import core functions, so these entries will
     // never point to a `Section::Import`.
     core_funcs: Vec<u32>,
 
+    // This component's core memory, table, and global index spaces.
+    core_memories: Vec<crate::core::MemoryType>,
+    core_tables: Vec<crate::core::TableType>,
+    core_globals: Vec<crate::core::GlobalType>,
+
     // This component's component index space.
     //
-    // An indirect list of all directly-nested (not transitive) components
-    // inside this component.
-    //
-    // Each entry is of the form `(i, j)` where `component.sections[i]` is
-    // guaranteed to be either
-    //
-    // * a `Section::Component` and we are referencing the component defined in
-    //   that section (in this case `j` must also be `0`, since a component
-    //   section can only contain a single nested component), or
-    //
-    // * a `Section::Import` and we are referencing the `j`th import in that
-    //   section, which is guaranteed to be a component import.
-    components: Vec<(usize, usize)>,
+    // Each entry describes the imports and exports of a directly-nested (not
+    // transitive) component, whether it was defined inline, imported, aliased,
+    // or exported.
+    components: Vec<Rc<ComponentSummary>>,
 
     // This component's module index space.
     //
-    // An indirect list of all directly-nested (not transitive) modules
-    // inside this component.
-    //
-    // Each entry is of the form `(i, j)` where `component.sections[i]` is
-    // guaranteed to be either
-    //
-    // * a `Section::Core` and we are referencing the module defined in that
-    //   section (in this case `j` must also be `0`, since a core section can
-    //   only contain a single nested module), or
-    //
-    // * a `Section::Import` and we are referencing the `j`th import in that
-    //   section, which is guaranteed to be a module import.
-    modules: Vec<(usize, usize)>,
+    // Each entry describes the imports and exports of a core module, whether
+    // it was defined inline, imported, aliased, or exported.
+    modules: Vec<Rc<ModuleSummary>>,
 
     // This component's instance index space.
-    instances: Vec<ComponentOrCoreInstanceType>,
+    instances: Vec<ComponentInstance>,
+
+    // This component's core instance index space, where each entry is the
+    // set of exports of the core instance.
+    core_instances: Vec<Rc<BTreeMap<String, crate::core::EntityType>>>,
 
     // This component's value index space.
     values: Vec<ComponentValType>,
+
+    // Whether this component has a start function yet.
+    has_start: bool,
 }
 
 impl ComponentContext {
@@ -189,35 +205,60 @@ impl ComponentContext {
             num_imports: 0,
             import_names: HashSet::default(),
             import_urls: HashSet::default(),
+            export_names: HashSet::default(),
+            imports: vec![],
+            exports: vec![],
             funcs: vec![],
             component_funcs: vec![],
-            scalar_component_funcs: vec![],
             core_funcs: vec![],
+            core_memories: vec![],
+            core_tables: vec![],
+            core_globals: vec![],
             components: vec![],
             modules: vec![],
             instances: vec![],
+            core_instances: vec![],
             values: vec![],
+            has_start: false,
         }
     }
 
-    fn num_modules(&self) -> usize {
-        self.modules.len()
+    fn num_funcs(&self) -> usize {
+        self.funcs.len()
     }
 
-    fn num_components(&self) -> usize {
-        self.components.len()
+    fn core_function_type(&self, core_func_index: u32) -> &Rc<crate::core::FuncType> {
+        self.funcs[self.core_funcs[core_func_index as usize] as usize].as_core()
     }
 
-    fn num_instances(&self) -> usize {
-        self.instances.len()
+    fn component_function(&self, func_index: u32) -> (&Rc<FuncSig>, Exportable) {
+        self.funcs[self.component_funcs[func_index as usize] as usize].as_component()
     }
 
-    fn num_funcs(&self) -> usize {
-        self.funcs.len()
+    // Indices of core memories which may be used as the canonical ABI's
+    // `memory` option.
+    fn cabi_memories(&self) -> Vec<u32> {
+        (0..u32::try_from(self.core_memories.len()).unwrap())
+            .filter(|i| {
+                let ty = &self.core_memories[*i as usize];
+                !ty.memory64 && !ty.shared && ty.page_size_log2.is_none()
+            })
+            .collect()
     }
 
-    fn num_values(&self) -> usize {
-        self.values.len()
+    // Indices of core functions which may be used as the canonical ABI's
+    // `realloc` option.
+    fn cabi_reallocs(&self) -> Vec<u32> {
+        self.core_funcs_of_type(&crate::core::FuncType {
+            params: vec![ValType::I32, ValType::I32, ValType::I32, ValType::I32],
+            results: vec![ValType::I32],
+        })
+    }
+
+    fn core_funcs_of_type(&self, ty: &crate::core::FuncType) -> Vec<u32> {
+        (0..u32::try_from(self.core_funcs.len()).unwrap())
+            .filter(|i| **self.core_function_type(*i) == *ty)
+            .collect()
     }
 }
 
@@ -249,10 +290,53 @@ struct TypesScope {
 
     // The indices of all the entries in `types` that are instance types.
     instance_types: Vec<u32>,
+
+    // The indices of all the entries in `types` that are resource types.
+    resource_types: Vec<u32>,
+
+    // The canonical ABI representation of each defined value type in `types`.
+    val_types: HashMap<u32, Rc<ValTypeInfo>>,
+
+    // The signature of each func type in `types`.
+    func_sigs: HashMap<u32, Rc<FuncSig>>,
+
+    // The indices of entries in `types` which were aliased from an outer
+    // scope. Type indices within these types refer to that outer scope rather
+    // than this one.
+    aliased_types: HashSet<u32>,
+
+    // The indices of entries in `types` created by type imports and exports
+    // respectively. These are the "named" types: records, variants, flags,
+    // enums and resources must be named to be used by imports and exports.
+    // Imported types are also considered named for exports.
+    imported_types: HashSet<u32>,
+    exported_types: HashSet<u32>,
+
+    // Whether a resource has been defined in this scope by exporting it.
+    // Imports may not refer to such resources, so once one exists imports
+    // avoid resourceful types altogether.
+    defines_resources: bool,
 }
 
 impl TypesScope {
     fn push(&mut self, ty: Rc<Type>) -> u32 {
+        let val_type = match &*ty {
+            Type::Defined(ty) => Some(Rc::new(self.defined_type_info(ty))),
+            _ => None,
+        };
+        let func_sig = match &*ty {
+            Type::Func(ty) => Some(Rc::new(self.func_sig(ty))),
+            _ => None,
+        };
+        self.push_with_info(ty, val_type, func_sig)
+    }
+
+    fn push_with_info(
+        &mut self,
+        ty: Rc<Type>,
+        val_type: Option<Rc<ValTypeInfo>>,
+        func_sig: Option<Rc<FuncSig>>,
+    ) -> u32 {
         let ty_idx = u32::try_from(self.types.len()).unwrap();
 
         let kind_list = match &*ty {
@@ -266,13 +350,34 @@ impl TypesScope {
             }
             Type::Component(_) => &mut self.component_types,
             Type::Instance(_) => &mut self.instance_types,
+            Type::Resource => &mut self.resource_types,
         };
         kind_list.push(ty_idx);
 
+        if let Some(val_type) = val_type {
+            self.val_types.insert(ty_idx, val_type);
+        }
+        if let Some(func_sig) = func_sig {
+            self.func_sigs.insert(ty_idx, func_sig);
+        }
+
         self.types.push(ty);
         ty_idx
     }
 
+    // Pushes a new type which is equal to the type at `index`, as created by
+    // a type import or export.
+    fn push_copy(&mut self, index: u32) -> u32 {
+        let ty = self.get(index).clone();
+        let val_type = self.val_types.get(&index).cloned();
+        let func_sig = self.func_sigs.get(&index).cloned();
+        let copy = self.push_with_info(ty, val_type, func_sig);
+        if self.aliased_types.contains(&index) {
+            self.aliased_types.insert(copy);
+        }
+        copy
+    }
+
     fn push_core(&mut self, ty: Rc<CoreType>) -> u32 {
         let ty_idx = u32::try_from(self.core_types.len()).unwrap();
 
@@ -294,16 +399,164 @@ impl TypesScope {
         &self.core_types[index as usize]
     }
 
-    fn get_func(&self, index: u32) -> &Rc<FuncType> {
+    fn can_ref_type(&self) -> bool {
+        // All component types and core module types may be referenced
+        !self.types.is_empty() || !self.module_types.is_empty()
+    }
+
+    fn val_type_info(&self, ty: &ComponentValType) -> Rc<ValTypeInfo> {
+        match ty {
+            ComponentValType::Primitive(ty) => Rc::new(ValTypeInfo::primitive(*ty)),
+            ComponentValType::Type(index) => self.val_types[index].clone(),
+        }
+    }
+
+    fn defined_type_info(&self, ty: &DefinedType) -> ValTypeInfo {
+        match ty {
+            DefinedType::Primitive(ty) => ValTypeInfo::primitive(*ty),
+            DefinedType::Record(ty) => {
+                ValTypeInfo::concat(ty.fields.iter().map(|(_, ty)| self.val_type_info(ty)))
+            }
+            DefinedType::Variant(ty) => ValTypeInfo::variant(
+                ty.cases
+                    .iter()
+                    .map(|(_, ty, _)| ty.as_ref().map(|ty| self.val_type_info(ty))),
+            ),
+            DefinedType::List(ty) => {
+                let elem = self.val_type_info(&ty.elem_ty);
+                ValTypeInfo {
+                    flat: vec![ValType::I32, ValType::I32],
+                    has_pointer: true,
+                    has_borrow: elem.has_borrow,
+                    has_resource: elem.has_resource,
+                }
+            }
+            DefinedType::Tuple(ty) => {
+                ValTypeInfo::concat(ty.fields.iter().map(|ty| self.val_type_info(ty)))
+            }
+            DefinedType::Flags(ty) => ValTypeInfo {
+                flat: vec![ValType::I32; ty.fields.len().div_ceil(32)],
+                ..ValTypeInfo::default()
+            },
+            DefinedType::Enum(_) => ValTypeInfo {
+                flat: vec![ValType::I32],
+                ..ValTypeInfo::default()
+            },
+            DefinedType::Option(ty) => {
+                ValTypeInfo::variant([None, Some(self.val_type_info(&ty.inner_ty))])
+            }
+            DefinedType::Result(ty) => ValTypeInfo::variant([
+                ty.ok_ty.as_ref().map(|ty| self.val_type_info(ty)),
+                ty.err_ty.as_ref().map(|ty| self.val_type_info(ty)),
+            ]),
+            DefinedType::Own(_) => ValTypeInfo {
+                flat: vec![ValType::I32],
+                has_resource: true,
+                ..ValTypeInfo::default()
+            },
+            DefinedType::Borrow(_) => ValTypeInfo {
+                flat: vec![ValType::I32],
+                has_borrow: true,
+                has_resource: true,
+                ..ValTypeInfo::default()
+            },
+        }
+    }
+
+    fn func_sig(&self, ty: &Rc<FuncType>) -> FuncSig {
+        FuncSig {
+            ty: ty.clone(),
+            params: ValTypeInfo::concat(ty.params.iter().map(|(_, ty)| self.val_type_info(ty))),
+            result: ValTypeInfo::concat(ty.result.iter().map(|ty| self.val_type_info(ty))),
+        }
+    }
+
+    // Whether the value type `ty` may be referred to by an import or export,
+    // given the `named` set of types created by imports and/or exports.
+    fn is_named(&self, ty: &ComponentValType, named: &HashSet<u32>) -> bool {
+        let index = match ty {
+            ComponentValType::Primitive(_) => return true,
+            ComponentValType::Type(index) => *index,
+        };
+        if named.contains(&index) {
+            return true;
+        }
+        if self.aliased_types.contains(&index) {
+            return false;
+        }
         match &**self.get(index) {
-            Type::Func(f) => f,
-            _ => panic!("get_func on non-function type"),
+            Type::Defined(ty) => match ty {
+                DefinedType::Primitive(_) => true,
+                DefinedType::Record(_)
+                | DefinedType::Variant(_)
+                | DefinedType::Flags(_)
+                | DefinedType::Enum(_) => false,
+                DefinedType::List(ty) => self.is_named(&ty.elem_ty, named),
+                DefinedType::Tuple(ty) => ty.fields.iter().all(|ty| self.is_named(ty, named)),
+                DefinedType::Option(ty) => self.is_named(&ty.inner_ty, named),
+                DefinedType::Result(ty) => ty
+                    .ok_ty
+                    .iter()
+                    .chain(&ty.err_ty)
+                    .all(|ty| self.is_named(ty, named)),
+                DefinedType::Own(resource) | DefinedType::Borrow(resource) => {
+                    named.contains(resource)
+                }
+            },
+            _ => false,
         }
     }
 
-    fn can_ref_type(&self) -> bool {
-        // All component types and core module types may be referenced
-        !self.types.is_empty() || !self.module_types.is_empty()
+    // Whether the type at `index` may be imported or exported, i.e. all the
+    // types it refers to are named.
+    fn is_type_importable(&self, index: u32, named: &HashSet<u32>) -> bool {
+        if self.aliased_types.contains(&index) {
+            return false;
+        }
+        match &**self.get(index) {
+            Type::Defined(ty) => match ty {
+                DefinedType::Primitive(_) | DefinedType::Flags(_) | DefinedType::Enum(_) => true,
+                DefinedType::Record(ty) => ty.fields.iter().all(|(_, ty)| self.is_named(ty, named)),
+                DefinedType::Variant(ty) => ty
+                    .cases
+                    .iter()
+                    .filter_map(|(_, ty, _)| ty.as_ref())
+                    .all(|ty| self.is_named(ty, named)),
+                _ => self.is_named(&ComponentValType::Type(index), named),
+            },
+            Type::Func(ty) => ty
+                .params
+                .iter()
+                .map(|(_, ty)| ty)
+                .chain(&ty.result)
+                .all(|ty| self.is_named(ty, named)),
+            Type::Component(_) | Type::Resource => true,
+            // Types exported from the instance type aren't named in this
+            // scope, so only permit instance types which don't export any.
+            Type::Instance(ty) => ty
+                .summary
+                .exports
+                .iter()
+                .all(|(_, entity)| matches!(entity, Entity::Module(_) | Entity::Component(_))),
+        }
+    }
+
+    // Whether the type at `index` transitively refers to any resources.
+    //
+    // Such types may not be aliased into nested components.
+    fn has_resource(&self, index: u32) -> bool {
+        match &**self.get(index) {
+            Type::Defined(_) => self.val_types[&index].has_resource,
+            Type::Func(_) => {
+                let sig = &self.func_sigs[&index];
+                sig.params.has_resource || sig.result.has_resource
+            }
+            Type::Resource => true,
+            // Component and instance types can only refer to resources
+            // defined within themselves, as outer aliases never refer to
+            // resources.
+            Type::Component(_) | Type::Instance(_) => false,
+        }
     }
 }
 
@@ -336,14 +589,14 @@ impl Component {
 
 #[must_use]
 enum Step {
-    Finished(Component),
+    Finished(Component, Rc<ComponentSummary>),
     StillBuilding,
 }
 
 impl Step {
     fn unwrap_still_building(self) {
         match self {
-            Step::Finished(_) => panic!(
+            Step::Finished(..) => panic!(
                 "`Step::unwrap_still_building` called on a `Step` that is not `StillBuilding`"
             ),
             Step::StillBuilding => {}
@@ -387,6 +640,14 @@ impl ComponentBuilder {
                 choices.push(Self::arbitrary_type_section);
                 choices.push(Self::arbitrary_import_section);
                 choices.push(Self::arbitrary_canonical_section);
+                choices.push(Self::arbitrary_core_instance_section);
+                choices.push(Self::arbitrary_instance_section);
+                choices.push(Self::arbitrary_alias_section);
+                choices.push(Self::arbitrary_export_section);
+
+                if self.config.component_model_values_enabled && !self.component().has_start {
+                    choices.push(Self::arbitrary_start_section);
+                }
 
                 if self.total_modules < self.config.max_modules {
                     choices.push(Self::arbitrary_core_module_section);
@@ -397,25 +658,19 @@ impl ComponentBuilder {
                 {
                     choices.push(Self::arbitrary_component_section);
                 }
-
-                // FIXME(#1000)
-                //
-                // choices.push(Self::arbitrary_instance_section);
-                // choices.push(Self::arbitrary_export_section);
-                // choices.push(Self::arbitrary_start_section);
-                // choices.push(Self::arbitrary_alias_section);
             }
 
             let f = u.choose(&choices)?;
             match f(self, u)? {
                 Step::StillBuilding => {}
-                Step::Finished(component) => {
+                Step::Finished(component, summary) => {
                     if self.components.is_empty() {
                         // If we just finished the root component, then return it.
                         return Ok(component);
                     } else {
                         // Otherwise, add it as a nested component in the parent.
                         self.push_section(Section::Component(component));
+                        self.component_mut().components.push(summary);
                     }
                 }
             }
@@ -441,7 +696,12 @@ impl ComponentBuilder {
         self.types
             .pop()
             .expect("should have a types scope for the component we are finishing");
-        Ok(Step::Finished(self.components.pop().unwrap().component))
+        let component = self.components.pop().unwrap();
+        let summary = Rc::new(ComponentSummary {
+            imports: component.imports,
+            exports: component.exports,
+        });
+        Ok(Step::Finished(component.component, summary))
     }
 
     fn component(&self) -> &ComponentContext {
@@ -587,29 +847,41 @@ impl ComponentBuilder {
         for_import: bool,
         for_type_def: bool,
     ) -> Result<Option<ComponentTypeRef>> {
-        let mut choices: Vec<fn(&Self, &mut Unstructured) -> Result<ComponentTypeRef>> = Vec::new();
+        let mut choices: Vec<Vec<ComponentTypeRef>> = Vec::new();
         let scope = self.current_type_scope();
 
+        // Only named types may be referenced by imports and exports.
+        let named = if for_import {
+            &scope.imported_types
+        } else {
+            &scope.exported_types
+        };
+        let is_allowed = |i: u32| {
+            scope.is_type_importable(i, named)
+                && !(for_import && scope.defines_resources && scope.has_resource(i))
+        };
+
         if !scope.module_types.is_empty()
             && (for_type_def || !for_import || self.total_modules < self.config.max_modules)
         {
-            choices.push(|me, u| {
-                Ok(ComponentTypeRef::Module(
-                    *u.choose(&me.current_type_scope().module_types)?,
-                ))
-            });
+            choices.push(
+                scope
+                    .module_types
+                    .iter()
+                    .map(|i| ComponentTypeRef::Module(*i))
+                    .collect(),
+            );
         }
 
-        // Types cannot be imported currently
-        if !for_import
-            && !scope.types.is_empty()
-            && (for_type_def || scope.types.len() < self.config.max_types)
-        {
-            choices.push(|me, u| {
-                Ok(ComponentTypeRef::Type(TypeBounds::Eq(u.int_in_range(
-                    0..=u32::try_from(me.current_type_scope().types.len() - 1).unwrap(),
-                )?)))
-            });
+        if for_type_def || scope.types.len() < self.config.max_types {
+            let types: Vec<_> = (0..u32::try_from(scope.types.len()).unwrap())
+                .filter(|i| is_allowed(*i))
+                .map(|i| ComponentTypeRef::Type(TypeBounds::Eq(i)))
+                .collect();
+            if !types.is_empty() {
+                choices.push(types);
+            }
+            choices.push(vec![ComponentTypeRef::Type(TypeBounds::SubResource)]);
         }
 
         // TODO: wasm-smith needs to ensure that every arbitrary value gets used exactly once.
@@ -618,42 +890,90 @@ impl ComponentBuilder {
         //     choices.push(|me, u| Ok(ComponentTypeRef::Value(me.arbitrary_component_val_type(u)?)));
         // }
 
-        if !scope.func_types.is_empty()
-            && (for_type_def || !for_import || self.component().num_funcs() < self.config.max_funcs)
-        {
-            choices.push(|me, u| {
-                Ok(ComponentTypeRef::Func(
-                    *u.choose(&me.current_type_scope().func_types)?,
-                ))
-            });
+        if for_type_def || !for_import || self.component().num_funcs() < self.config.max_funcs {
+            let funcs: Vec<_> = scope
+                .func_types
+                .iter()
+                .filter(|i| is_allowed(**i))
+                .map(|i| ComponentTypeRef::Func(*i))
+                .collect();
+            if !funcs.is_empty() {
+                choices.push(funcs);
+            }
         }
 
         if !scope.component_types.is_empty()
             && (for_type_def || !for_import || self.total_components < self.config.max_components)
         {
-            choices.push(|me, u| {
-                Ok(ComponentTypeRef::Component(
-                    *u.choose(&me.current_type_scope().component_types)?,
-                ))
-            });
+            choices.push(
+                scope
+                    .component_types
+                    .iter()
+                    .map(|i| ComponentTypeRef::Component(*i))
+                    .collect(),
+            );
         }
 
         if !scope.instance_types.is_empty()
             && (for_type_def || !for_import || self.total_instances < self.config.max_instances)
         {
-            choices.push(|me, u| {
-                Ok(ComponentTypeRef::Instance(
-                    *u.choose(&me.current_type_scope().instance_types)?,
-                ))
-            });
+            choices.push(
+                scope
+                    .instance_types
+                    .iter()
+                    .map(|i| ComponentTypeRef::Instance(*i))
+                    .collect(),
+            );
         }
 
         if choices.is_empty() {
             return Ok(None);
         }
 
-        let f = u.choose(&choices)?;
-        f(self, u).map(Option::Some)
+        let refs = u.choose(&choices)?;
+        Ok(Some(*u.choose(refs)?))
+    }
+
+    // Registers the type, if any, created in the current scope by importing or
+    // exporting `ty`, returning its index.
+    fn push_type_ref(&mut self, ty: ComponentTypeRef, imported: bool) -> Option<u32> {
+        let scope = self.current_type_scope_mut();
+        let index = match ty {
+            ComponentTypeRef::Type(TypeBounds::Eq(index)) => scope.push_copy(index),
+            ComponentTypeRef::Type(TypeBounds::SubResource) => {
+                scope.push_with_info(Rc::new(Type::Resource), None, None)
+            }
+            _ => return None,
+        };
+        scope.exported_types.insert(index);
+        if imported {
+            scope.imported_types.insert(index);
+        } else if ty == ComponentTypeRef::Type(TypeBounds::SubResource) {
+            scope.defines_resources = true;
+        }
+        Some(index)
+    }
+
+    // Describes the entity imported or exported by `ty` in the current scope.
+    fn entity_for_type_ref(&self, ty: ComponentTypeRef) -> Entity {
+        let scope = self.current_type_scope();
+        match ty {
+            ComponentTypeRef::Module(i) => match &**scope.get_core(i) {
+                CoreType::Module(ty) => Entity::Module(Rc::new(ty.summary())),
+                CoreType::Func(_) => unreachable!(),
+            },
+            ComponentTypeRef::Func(i) => Entity::Func(scope.func_sigs[&i].clone()),
+            ComponentTypeRef::Value(_) => Entity::Value,
+            ComponentTypeRef::Type(_) => Entity::Type,
+            ComponentTypeRef::Instance(i) => match &**scope.get(i) {
+                Type::Instance(ty) => Entity::Instance(ty.summary.clone()),
+                _ => unreachable!(),
+            },
+            ComponentTypeRef::Component(i) => match &**scope.get(i) {
+                Type::Component(ty) => Entity::Component(ty.summary.clone()),
+                _ => unreachable!(),
+            },
+        }
     }
 
     fn arbitrary_type(&mut self, u: &mut Unstructured, type_fuel: &mut u32) -> Result<Rc<Type>> {
@@ -918,7 +1238,7 @@ impl ComponentBuilder {
             && self.config.exceptions_enabled
             && counts.tags < self.config.max_tags
         {
-            choices.push(|c, u, counts, types| {
+            choices.push(|_, u, counts, types| {
                 counts.tags += 1;
                 let tag_func_types = types
                     .iter()
@@ -935,7 +1255,7 @@ impl ComponentBuilder {
         }
 
         if !types.is_empty() && counts.funcs < self.config.max_funcs {
-            choices.push(|c, u, counts, types| {
+            choices.push(|_, u, counts, types| {
                 counts.funcs += 1;
                 let ty_idx = u.int_in_range(0..=u32::try_from(types.len() - 1).unwrap())?;
                 let ty = types[ty_idx as usize].clone();
@@ -991,10 +1311,6 @@ impl ComponentBuilder {
         &self.types[self.types.len() - 1 - usize::try_from(count).unwrap()]
     }
 
-    fn outer_type(&self, count: u32, i: u32) -> &Rc<Type> {
-        &self.outer_types_scope(count).types[usize::try_from(i).unwrap()]
-    }
-
     fn arbitrary_component_type(
         &mut self,
         u: &mut Unstructured,
@@ -1005,6 +1321,7 @@ impl ComponentBuilder {
         let mut import_urls = HashSet::new();
         let mut exports = HashSet::new();
         let mut export_urls = HashSet::new();
+        let mut summary = ComponentSummary::default();
 
         self.with_types_scope(|me| {
             arbitrary_loop(u, 0, 100, |u| {
@@ -1022,6 +1339,10 @@ impl ComponentBuilder {
                         } else {
                             None
                         };
+                        summary
+                            .imports
+                            .push((name.clone(), me.entity_for_type_ref(ty)));
+                        me.push_type_ref(ty, true);
                         defs.push(ComponentTypeDef::Import(Import { name, url, ty }));
                         return Ok(true);
                     }
@@ -1032,12 +1353,20 @@ impl ComponentBuilder {
                 // Type definitions, exports, and aliases.
                 let def =
                     me.arbitrary_instance_type_def(u, &mut exports, &mut export_urls, type_fuel)?;
+                if let InstanceTypeDecl::Export { name, ty, .. } = &def {
+                    summary
+                        .exports
+                        .push((name.clone(), me.entity_for_type_ref(*ty)));
+                }
                 defs.push(def.into());
                 Ok(true)
             })
         })?;
 
-        Ok(Rc::new(ComponentType { defs }))
+        Ok(Rc::new(ComponentType {
+            defs,
+            summary: Rc::new(summary),
+        }))
     }
 
     fn arbitrary_instance_type(
@@ -1048,6 +1377,7 @@ impl ComponentBuilder {
         let mut defs = vec![];
         let mut exports = HashSet::new();
         let mut export_urls = HashSet::new();
+        let mut summary = InstanceSummary::default();
 
         self.with_types_scope(|me| {
             arbitrary_loop(u, 0, 100, |u| {
@@ -1056,17 +1386,22 @@ impl ComponentBuilder {
                     return Ok(false);
                 }
 
-                defs.push(me.arbitrary_instance_type_def(
-                    u,
-                    &mut exports,
-                    &mut export_urls,
-                    type_fuel,
-                )?);
+                let def =
+                    me.arbitrary_instance_type_def(u, &mut exports, &mut export_urls, type_fuel)?;
+                if let InstanceTypeDecl::Export { name, ty, .. } = &def {
+                    summary
+                        .exports
+                        .push((name.clone(), me.entity_for_type_ref(*ty)));
+                }
+                defs.push(def);
                 Ok(true)
             })
         })?;
 
-        Ok(Rc::new(InstanceType { defs }))
+        Ok(Rc::new(InstanceType {
+            defs,
+            summary: Rc::new(summary),
+        }))
     }
 
     fn arbitrary_instance_type_def(
@@ -1087,44 +1422,25 @@ impl ComponentBuilder {
         > = Vec::with_capacity(3);
 
         // Export.
-        if self.current_type_scope().can_ref_type() {
-            choices.push(|me, exports, export_urls, u, _type_fuel| {
-                let ty = me.arbitrary_type_ref(u, false, true)?.unwrap();
-                if let ComponentTypeRef::Type(TypeBounds::Eq(idx)) = ty {
-                    let ty = me.current_type_scope().get(idx).clone();
-                    me.current_type_scope_mut().push(ty);
-                }
-                Ok(InstanceTypeDecl::Export {
-                    name: crate::unique_kebab_string(100, exports, u)?,
-                    url: if u.arbitrary()? {
-                        Some(crate::unique_url(100, export_urls, u)?)
-                    } else {
-                        None
-                    },
-                    ty,
-                })
-            });
-        }
+        choices.push(|me, exports, export_urls, u, _type_fuel| {
+            let ty = me.arbitrary_type_ref(u, false, true)?.unwrap();
+            me.push_type_ref(ty, false);
+            Ok(InstanceTypeDecl::Export {
+                name: crate::unique_kebab_string(100, exports, u)?,
+                url: if u.arbitrary()? {
+                    Some(crate::unique_url(100, export_urls, u)?)
+                } else {
+                    None
+                },
+                ty,
+            })
+        });
 
         // Outer type alias.
-        if self
-            .types
-            .iter()
-            .any(|scope| !scope.types.is_empty() || !scope.core_types.is_empty())
-        {
+        if !self.outer_type_alias_candidates().is_empty() {
             choices.push(|me, _exports, _export_urls, u, _type_fuel| {
                 let alias = me.arbitrary_outer_type_alias(u)?;
-                match &alias {
-                    Alias::Outer {
-                        kind: OuterAliasKind::Type(ty),
-                        ..
-                    } => me.current_type_scope_mut().push(ty.clone()),
-                    Alias::Outer {
-                        kind: OuterAliasKind::CoreType(ty),
-                        ..
-                    } => me.current_type_scope_mut().push_core(ty.clone()),
-                    _ => unreachable!(),
-                };
+                me.push_outer_type_alias(&alias);
                 Ok(InstanceTypeDecl::Alias(alias))
             });
         }
@@ -1185,57 +1501,75 @@ impl ComponentBuilder {
         Ok((count, index, CoreOuterAliasKind::Type(ty)))
     }
 
+    // All the types which may be aliased into the current scope.
+    //
+    // Types which refer to resources are never aliased, as they may not be
+    // aliased across component boundaries.
+    fn outer_type_alias_candidates(&self) -> Vec<(u32, u32, OuterAliasKind)> {
+        let mut candidates = Vec::new();
+        for (count, scope) in self.types.iter().rev().enumerate() {
+            let count = u32::try_from(count).unwrap();
+            for i in 0..u32::try_from(scope.types.len()).unwrap() {
+                if !scope.has_resource(i) {
+                    candidates.push((count, i, OuterAliasKind::Type(Rc::clone(scope.get(i)))));
+                }
+            }
+            for i in 0..u32::try_from(scope.core_types.len()).unwrap() {
+                candidates.push((
+                    count,
+                    i,
+                    OuterAliasKind::CoreType(Rc::clone(scope.get_core(i))),
+                ));
+            }
+        }
+        candidates
+    }
+
     fn arbitrary_outer_type_alias(&self, u: &mut Unstructured) -> Result<Alias> {
-        let non_empty_types_scopes: Vec<_> = self
-            .types
-            .iter()
-            .rev()
-            .enumerate()
-            .filter(|(_, scope)| !scope.types.is_empty() || !scope.core_types.is_empty())
-            .collect();
+        let candidates = self.outer_type_alias_candidates();
         assert!(
-            !non_empty_types_scopes.is_empty(),
-            "precondition: there are non-empty types scopes"
+            !candidates.is_empty(),
+            "precondition: there are types to alias"
         );
-
-        let (count, scope) = u.choose(&non_empty_types_scopes)?;
-        let count = u32::try_from(*count).unwrap();
-        assert!(!scope.types.is_empty() || !scope.core_types.is_empty());
-
-        let max_type_in_scope = scope.types.len() + scope.core_types.len() - 1;
-        let i = u.int_in_range(0..=max_type_in_scope)?;
-
-        let (i, kind) = if i < scope.types.len() {
-            let i = u32::try_from(i).unwrap();
-            (i, OuterAliasKind::Type(Rc::clone(scope.get(i))))
-        } else if i - scope.types.len() < scope.core_types.len() {
-            let i = u32::try_from(i - scope.types.len()).unwrap();
-            (i, OuterAliasKind::CoreType(Rc::clone(scope.get_core(i))))
-        } else {
-            unreachable!()
-        };
-
+        let (count, i, kind) = u.choose(&candidates)?.clone();
         Ok(Alias::Outer { count, i, kind })
     }
 
+    fn push_outer_type_alias(&mut self, alias: &Alias) {
+        match alias {
+            Alias::Outer {
+                count,
+                i,
+                kind: OuterAliasKind::Type(ty),
+            } => {
+                let outer = self.outer_types_scope(*count);
+                let val_type = outer.val_types.get(i).cloned();
+                let func_sig = outer.func_sigs.get(i).cloned();
+                let scope = self.current_type_scope_mut();
+                let index = scope.push_with_info(ty.clone(), val_type, func_sig);
+                scope.aliased_types.insert(index);
+            }
+            Alias::Outer {
+                kind: OuterAliasKind::CoreType(ty),
+                ..
+            } => {
+                self.current_type_scope_mut().push_core(ty.clone());
+            }
+            _ => unreachable!(),
+        }
+    }
+
     fn arbitrary_func_type(
         &self,
         u: &mut Unstructured,
         type_fuel: &mut u32,
     ) -> Result<Rc<FuncType>> {
         let mut params = Vec::new();
-        let mut results = Vec::new();
         let mut names = HashSet::new();
 
-        // Note: parameters are currently limited to a maximum of 16
-        // because any additional parameters will require indirect access
-        // via a pointer argument; when this occurs, validation of any
-        // lowered function will fail because it will be missing a
-        // memory option (not yet implemented).
-        //
-        // When options are correctly specified on canonical functions,
-        // we should increase this maximum to test indirect parameter
-        // passing.
+        // Note that parameters which flatten to more than 16 core values are
+        // passed indirectly through memory, and are lifted and lowered with
+        // the `memory` and `realloc` canonical options.
         arbitrary_loop(u, 0, 16, |u| {
             *type_fuel = type_fuel.saturating_sub(1);
             if *type_fuel == 0 {
@@ -1250,41 +1584,15 @@ impl ComponentBuilder {
             Ok(true)
         })?;
 
-        names.clear();
-
-        // Likewise, the limit for results is 1 before the memory option is
-        // required. When the memory option is implemented, this restriction
-        // should be relaxed.
-        arbitrary_loop(u, 0, 1, |u| {
+        // Function results may not contain borrowed handles.
+        let result = if *type_fuel > 0 && u.arbitrary()? {
             *type_fuel = type_fuel.saturating_sub(1);
-            if *type_fuel == 0 {
-                return Ok(false);
-            }
-
-            // If the result list is empty (i.e. first push), then arbitrarily give
-            // the result a name. Otherwise, all of the subsequent items must be named.
-            let name = if results.is_empty() {
-                // Most of the time we should have a single, unnamed result.
-                u.ratio::<u8>(10, 100)?
-                    .then(|| crate::unique_kebab_string(100, &mut names, u))
-                    .transpose()?
-            } else {
-                Some(crate::unique_kebab_string(100, &mut names, u)?)
-            };
-
-            let ty = self.arbitrary_component_val_type(u)?;
-
-            results.push((name, ty));
-
-            // There can be only one unnamed result.
-            if results.len() == 1 && results[0].0.is_none() {
-                return Ok(false);
-            }
-
-            Ok(true)
-        })?;
+            self.arbitrary_result_val_type(u)?
+        } else {
+            None
+        };
 
-        Ok(Rc::new(FuncType { params, results }))
+        Ok(Rc::new(FuncType { params, result }))
     }
 
     fn arbitrary_component_val_type(&self, u: &mut Unstructured) -> Result<ComponentValType> {
@@ -1299,13 +1607,22 @@ impl ComponentBuilder {
             )),
             1 => {
                 let index = *u.choose(&self.current_type_scope().defined_types)?;
-                let ty = Rc::clone(self.current_type_scope().get(index));
                 Ok(ComponentValType::Type(index))
             }
             _ => unreachable!(),
         }
     }
 
+    // Like `arbitrary_component_val_type`, but never refers to a type
+    // containing a `borrow`, returning `None` if that's the type chosen.
+    fn arbitrary_result_val_type(&self, u: &mut Unstructured) -> Result<Option<ComponentValType>> {
+        let ty = self.arbitrary_component_val_type(u)?;
+        let scope = self.current_type_scope();
+        Ok(match ty {
+            ComponentValType::Type(i) if scope.val_types[&i].has_borrow => None,
+            ty => Some(ty),
+        })
+    }
     fn arbitrary_primitive_val_type(&self, u: &mut Unstructured) -> Result<PrimitiveValType> {
         match u.int_in_range(0..=12)? {
             0 => Ok(PrimitiveValType::Bool),
@@ -1332,9 +1649,9 @@ impl ComponentBuilder {
     ) -> Result<RecordType> {
         let mut fields = vec![];
         let mut field_names = HashSet::new();
-        arbitrary_loop(u, 0, 100, |u| {
+        arbitrary_loop(u, 1, 100, |u| {
             *type_fuel = type_fuel.saturating_sub(1);
-            if *type_fuel == 0 {
+            if *type_fuel == 0 && !fields.is_empty() {
                 return Ok(false);
             }
 
@@ -1356,7 +1673,7 @@ impl ComponentBuilder {
         let mut case_names = HashSet::new();
         arbitrary_loop(u, 1, 100, |u| {
             *type_fuel = type_fuel.saturating_sub(1);
-            if *type_fuel == 0 {
+            if *type_fuel == 0 && !cases.is_empty() {
                 return Ok(false);
             }
 
@@ -1389,9 +1706,9 @@ impl ComponentBuilder {
 
     fn arbitrary_tuple_type(&self, u: &mut Unstructured, type_fuel: &mut u32) -> Result<TupleType> {
         let mut fields = vec![];
-        arbitrary_loop(u, 0, 100, |u| {
+        arbitrary_loop(u, 1, 100, |u| {
             *type_fuel = type_fuel.saturating_sub(1);
-            if *type_fuel == 0 {
+            if *type_fuel == 0 && !fields.is_empty() {
                 return Ok(false);
             }
 
@@ -1404,9 +1721,9 @@ impl ComponentBuilder {
     fn arbitrary_flags_type(&self, u: &mut Unstructured, type_fuel: &mut u32) -> Result<FlagsType> {
         let mut fields = vec![];
         let mut field_names = HashSet::new();
-        arbitrary_loop(u, 0, 100, |u| {
+        arbitrary_loop(u, 1, 32, |u| {
             *type_fuel = type_fuel.saturating_sub(1);
-            if *type_fuel == 0 {
+            if *type_fuel == 0 && !fields.is_empty() {
                 return Ok(false);
             }
 
@@ -1421,7 +1738,7 @@ impl ComponentBuilder {
         let mut variant_names = HashSet::new();
         arbitrary_loop(u, 1, 100, |u| {
             *type_fuel = type_fuel.saturating_sub(1);
-            if *type_fuel == 0 {
+            if *type_fuel == 0 && !variants.is_empty() {
                 return Ok(false);
             }
 
@@ -1455,7 +1772,9 @@ impl ComponentBuilder {
         u: &mut Unstructured,
         type_fuel: &mut u32,
     ) -> Result<DefinedType> {
-        match u.int_in_range(0..=8)? {
+        let resources = &self.current_type_scope().resource_types;
+        let max = if resources.is_empty() { 8 } else { 10 };
+        match u.int_in_range(0..=max)? {
             0 => Ok(DefinedType::Primitive(
                 self.arbitrary_primitive_val_type(u)?,
             )),
@@ -1471,122 +1790,87 @@ impl ComponentBuilder {
             6 => Ok(DefinedType::Enum(self.arbitrary_enum_type(u, type_fuel)?)),
             7 => Ok(DefinedType::Option(self.arbitrary_option_type(u)?)),
             8 => Ok(DefinedType::Result(self.arbitrary_result_type(u)?)),
+            9 => Ok(DefinedType::Own(*u.choose(resources)?)),
+            10 => Ok(DefinedType::Borrow(*u.choose(resources)?)),
             _ => unreachable!(),
         }
     }
 
     fn push_import(&mut self, name: String, url: Option<String>, ty: ComponentTypeRef) {
-        let nth = match self.ensure_section(
+        let entity = self.entity_for_type_ref(ty);
+        match self.ensure_section(
             |sec| matches!(sec, Section::Import(_)),
             || Section::Import(ImportSection { imports: vec![] }),
         ) {
-            Section::Import(sec) => {
-                sec.imports.push(Import { name, url, ty });
-                sec.imports.len() - 1
-            }
+            Section::Import(sec) => sec.imports.push(Import {
+                name: name.clone(),
+                url,
+                ty,
+            }),
             _ => unreachable!(),
-        };
-        let section_index = self.component().component.sections.len() - 1;
+        }
+        self.component_mut().num_imports += 1;
+        self.component_mut().imports.push((name, entity.clone()));
 
-        match ty {
-            ComponentTypeRef::Module(_) => {
+        match (ty, entity) {
+            (ComponentTypeRef::Module(_), Entity::Module(module)) => {
                 self.total_modules += 1;
-                self.component_mut().modules.push((section_index, nth));
+                self.component_mut().modules.push(module);
             }
-            ComponentTypeRef::Func(ty_index) => {
-                let func_ty = match self.current_type_scope().get(ty_index).as_ref() {
-                    Type::Func(ty) => ty.clone(),
-                    _ => unreachable!(),
-                };
-
-                if func_ty.is_scalar() {
-                    let func_index = u32::try_from(self.component().component_funcs.len()).unwrap();
-                    self.component_mut().scalar_component_funcs.push(func_index);
-                }
-
-                let func_index = u32::try_from(self.component().funcs.len()).unwrap();
-                self.component_mut()
-                    .funcs
-                    .push(ComponentOrCoreFuncType::Component(func_ty));
-
-                self.component_mut().component_funcs.push(func_index);
+            (ComponentTypeRef::Func(_), Entity::Func(sig)) => {
+                self.push_component_func(sig, Exportable::Always);
             }
-            ComponentTypeRef::Value(ty) => {
+            (ComponentTypeRef::Value(ty), _) => {
                 self.total_values += 1;
                 self.component_mut().values.push(ty);
             }
-            ComponentTypeRef::Type(TypeBounds::Eq(ty_index)) => {
-                let ty = self.current_type_scope().get(ty_index).clone();
-                self.current_type_scope_mut().push(ty);
-            }
-            ComponentTypeRef::Type(TypeBounds::SubResource) => {
-                unimplemented!()
+            (ComponentTypeRef::Type(_), _) => {
+                self.push_type_ref(ty, true);
             }
-            ComponentTypeRef::Instance(ty_index) => {
-                let instance_ty = match self.current_type_scope().get(ty_index).as_ref() {
-                    Type::Instance(ty) => ty.clone(),
-                    _ => unreachable!(),
-                };
-
+            (ComponentTypeRef::Instance(_), Entity::Instance(summary)) => {
                 self.total_instances += 1;
-                self.component_mut()
-                    .instances
-                    .push(ComponentOrCoreInstanceType::Component(instance_ty));
+                self.component_mut().instances.push(ComponentInstance {
+                    summary,
+                    aliases_exportable: true,
+                });
             }
-            ComponentTypeRef::Component(_) => {
+            (ComponentTypeRef::Component(_), Entity::Component(summary)) => {
                 self.total_components += 1;
-                self.component_mut().components.push((section_index, nth));
+                self.component_mut().components.push(summary);
             }
+            _ => unreachable!(),
         }
     }
 
-    fn core_function_type(&self, core_func_index: u32) -> &Rc<crate::core::FuncType> {
-        self.component().funcs[self.component().core_funcs[core_func_index as usize] as usize]
-            .as_core()
-    }
-
-    fn component_function_type(&self, func_index: u32) -> &Rc<FuncType> {
-        self.component().funcs[self.component().component_funcs[func_index as usize] as usize]
-            .as_component()
+    fn push_component_func(&mut self, sig: Rc<FuncSig>, exportable: Exportable) {
+        let func_index = u32::try_from(self.component().funcs.len()).unwrap();
+        self.component_mut()
+            .funcs
+            .push(ComponentOrCoreFuncType::Component(sig, exportable));
+        self.component_mut().component_funcs.push(func_index);
     }
 
-    fn push_func(&mut self, func: Func) {
-        let nth = match self.component_mut().component.sections.last_mut() {
-            Some(Section::Canonical(CanonicalSection { funcs })) => funcs.len(),
-            _ => {
-                self.push_section(Section::Canonical(CanonicalSection { funcs: vec![] }));
-                0
-            }
-        };
-        let section_index = self.component().component.sections.len() - 1;
-
+    fn push_core_func(&mut self, ty: Rc<crate::core::FuncType>) {
         let func_index = u32::try_from(self.component().funcs.len()).unwrap();
+        self.component_mut()
+            .funcs
+            .push(ComponentOrCoreFuncType::Core(ty));
+        self.component_mut().core_funcs.push(func_index);
+    }
 
-        let ty = match &func {
-            Func::CanonLift { func_ty, .. } => {
-                let ty = Rc::clone(self.current_type_scope().get_func(*func_ty));
-                if ty.is_scalar() {
-                    let func_index = u32::try_from(self.component().component_funcs.len()).unwrap();
-                    self.component_mut().scalar_component_funcs.push(func_index);
-                }
-                self.component_mut().component_funcs.push(func_index);
-                ComponentOrCoreFuncType::Component(ty)
-            }
-            Func::CanonLower {
-                func_index: comp_func_index,
-                ..
-            } => {
-                let comp_func_ty = self.component_function_type(*comp_func_index);
-                let core_func_ty = canonical_abi_for(comp_func_ty);
-                self.component_mut().core_funcs.push(func_index);
-                ComponentOrCoreFuncType::Core(core_func_ty)
+    fn push_func(&mut self, func: Func, ty: ComponentOrCoreFuncType) {
+        match ty {
+            ComponentOrCoreFuncType::Component(sig, exportable) => {
+                self.push_component_func(sig, exportable)
             }
-        };
-
-        self.component_mut().funcs.push(ty);
+            ComponentOrCoreFuncType::Core(ty) => self.push_core_func(ty),
+        }
 
-        match self.component_mut().component.sections.last_mut() {
-            Some(Section::Canonical(CanonicalSection { funcs })) => funcs.push(func),
+        match self.ensure_section(
+            |s| matches!(s, Section::Canonical(_)),
+            || Section::Canonical(CanonicalSection { funcs: vec![] }),
+        ) {
+            Section::Canonical(CanonicalSection { funcs }) => funcs.push(func),
             _ => unreachable!(),
         }
     }
@@ -1603,7 +1887,10 @@ impl ComponentBuilder {
             // minimum later.
             0
         };
-        let max = self.config.max_imports - self.component().num_imports;
+        let max = self
+            .config
+            .max_imports
+            .saturating_sub(self.component().num_imports);
 
         crate::arbitrary_loop(u, min, max, |u| {
             match self.arbitrary_type_ref(u, true, false)? {
@@ -1641,83 +1928,35 @@ impl ComponentBuilder {
             // required minimum later.
             0
         };
-        let max = self.config.max_funcs - self.component().funcs.len();
+        let max = self
+            .config
+            .max_funcs
+            .saturating_sub(self.component().funcs.len());
 
-        let mut choices: Vec<fn(&mut Unstructured, &mut ComponentBuilder) -> Result<Option<Func>>> =
-            Vec::with_capacity(2);
+        let mut choices: Vec<
+            fn(
+                &mut ComponentBuilder,
+                &mut Unstructured,
+            ) -> Result<Option<(Func, ComponentOrCoreFuncType)>>,
+        > = Vec::with_capacity(2);
 
         crate::arbitrary_loop(u, min, max, |u| {
             choices.clear();
 
-            // NB: We only lift/lower scalar component functions.
-            //
-            // If we generated lifting and lowering of compound value types,
-            // the probability of generating a corresponding Wasm module that
-            // generates valid instances of the compound value types would
-            // be vanishingly tiny (e.g. for `list<string>` we would have to
-            // generate a core Wasm module that correctly produces a pointer and
-            // length for a memory region that itself is a series of pointers
-            // and lengths of valid strings, as well as `canonical_abi_realloc`
-            // and `canonical_abi_free` functions that do the right thing).
-            //
-            // This is a pretty serious limitation of `wasm-smith`'s component
-            // types support, but it is one we are intentionally
-            // accepting. `wasm-smith` will focus on generating arbitrary
-            // component sections, structures, and import/export topologies; not
-            // component functions and core Wasm implementations of component
-            // functions. In the future, we intend to build a new, distinct test
-            // case generator specifically for exercising component functions
-            // and the canonical ABI. This new generator won't emit arbitrary
-            // component sections, structures, or import/export topologies, and
-            // will instead leave that to `wasm-smith`.
-
-            if !self.component().scalar_component_funcs.is_empty() {
-                choices.push(|u, c| {
-                    let func_index = *u.choose(&c.component().scalar_component_funcs)?;
-                    Ok(Some(Func::CanonLower {
-                        // Scalar component functions don't use any canonical options.
-                        options: vec![],
-                        func_index,
-                    }))
-                });
+            // NB: while we generate lifts and lowers of functions with
+            // compound value types along with the canonical options they
+            // require, the core functions involved are arbitrary and aren't
+            // expected to actually produce valid instances of those types at
+            // runtime. That's left to dedicated canonical ABI test case
+            // generators; here we focus on generating valid component
+            // structures and import/export topologies.
+
+            if !self.component().component_funcs.is_empty() {
+                choices.push(Self::arbitrary_canon_lower);
             }
 
             if !self.component().core_funcs.is_empty() {
-                choices.push(|u, c| {
-                    let core_func_index = u.int_in_range(
-                        0..=u32::try_from(c.component().core_funcs.len() - 1).unwrap(),
-                    )?;
-                    let core_func_ty = c.core_function_type(core_func_index);
-                    let comp_func_ty = inverse_scalar_canonical_abi_for(u, core_func_ty)?;
-
-                    let func_ty = if let Some(indices) = c
-                        .current_type_scope()
-                        .func_type_to_indices
-                        .get(&comp_func_ty)
-                    {
-                        // If we've already defined this component function type
-                        // one or more times, then choose one of those
-                        // definitions arbitrarily.
-                        debug_assert!(!indices.is_empty());
-                        *u.choose(indices)?
-                    } else if c.current_type_scope().types.len() < c.config.max_types {
-                        // If we haven't already defined this component function
-                        // type, and we haven't defined the configured maximum
-                        // amount of types yet, then just define this type.
-                        let ty = Rc::new(Type::Func(Rc::new(comp_func_ty)));
-                        c.push_type(ty)
-                    } else {
-                        // Otherwise, give up on lifting this function.
-                        return Ok(None);
-                    };
-
-                    Ok(Some(Func::CanonLift {
-                        func_ty,
-                        // Scalar functions don't use any canonical options.
-                        options: vec![],
-                        core_func_index,
-                    }))
-                });
+                choices.push(Self::arbitrary_canon_lift);
             }
 
             if choices.is_empty() {
@@ -1725,8 +1964,8 @@ impl ComponentBuilder {
             }
 
             let f = u.choose(&choices)?;
-            if let Some(func) = f(u, self)? {
-                self.push_func(func);
+            if let Some((func, ty)) = f(self, u)? {
+                self.push_func(func, ty);
             }
 
             Ok(true)
@@ -1735,132 +1974,991 @@ impl ComponentBuilder {
         Ok(Step::StillBuilding)
     }
 
+    fn arbitrary_canon_lower(
+        &mut self,
+        u: &mut Unstructured,
+    ) -> Result<Option<(Func, ComponentOrCoreFuncType)>> {
+        let func_index =
+            u.int_in_range(0..=u32::try_from(self.component().component_funcs.len() - 1).unwrap())?;
+        let (sig, _) = self.component().component_function(func_index);
+        let abi = sig.canonical_abi(Abi::Lower);
+        let options = match self.arbitrary_canon_opts(u, &abi)? {
+            Some(options) => options,
+            None => return Ok(None),
+        };
+        Ok(Some((
+            Func::CanonLower {
+                options,
+                func_index,
+            },
+            ComponentOrCoreFuncType::Core(abi.core_ty),
+        )))
+    }
+
+    fn arbitrary_canon_lift(
+        &mut self,
+        u: &mut Unstructured,
+    ) -> Result<Option<(Func, ComponentOrCoreFuncType)>> {
+        let core_func_index =
+            u.int_in_range(0..=u32::try_from(self.component().core_funcs.len() - 1).unwrap())?;
+        let core_func_ty = self.component().core_function_type(core_func_index).clone();
+
+        let scope = self.current_type_scope();
+        let existing: Vec<u32> = scope
+            .func_types
+            .iter()
+            .copied()
+            .filter(|i| scope.func_sigs[i].canonical_abi(Abi::Lift).core_ty == core_func_ty)
+            .collect();
+
+        let func_ty = if !existing.is_empty() && u.arbitrary()? {
+            // Lift with an existing function type whose canonical ABI matches
+            // the core function.
+            *u.choose(&existing)?
+        } else {
+            let has_memory = !self.component().cabi_memories().is_empty();
+            let has_realloc = has_memory && !self.component().cabi_reallocs().is_empty();
+            let comp_func_ty =
+                match self.inverse_canonical_abi_for(u, &core_func_ty, has_memory, has_realloc)? {
+                    Some(ty) => ty,
+                    None => return Ok(None),
+                };
+
+            if let Some(indices) = self
+                .current_type_scope()
+                .func_type_to_indices
+                .get(&comp_func_ty)
+            {
+                // If we've already defined this component function type
+                // one or more times, then choose one of those
+                // definitions arbitrarily.
+                debug_assert!(!indices.is_empty());
+                *u.choose(indices)?
+            } else if self.current_type_scope().types.len() < self.config.max_types {
+                // If we haven't already defined this component function
+                // type, and we haven't defined the configured maximum
+                // amount of types yet, then just define this type.
+                let ty = Rc::new(Type::Func(Rc::new(comp_func_ty)));
+                self.push_type(ty)
+            } else {
+                // Otherwise, give up on lifting this function.
+                return Ok(None);
+            }
+        };
+
+        let sig = self.current_type_scope().func_sigs[&func_ty].clone();
+        let abi = sig.canonical_abi(Abi::Lift);
+        let mut options = match self.arbitrary_canon_opts(u, &abi)? {
+            Some(options) => options,
+            None => return Ok(None),
+        };
+
+        if u.arbitrary()? {
+            let post_returns = self.component().core_funcs_of_type(&crate::core::FuncType {
+                params: abi.core_ty.results.clone(),
+                results: vec![],
+            });
+            if !post_returns.is_empty() {
+                options.push(CanonOpt::PostReturn(*u.choose(&post_returns)?));
+            }
+        }
+
+        Ok(Some((
+            Func::CanonLift {
+                func_ty,
+                options,
+                core_func_index,
+            },
+            ComponentOrCoreFuncType::Component(sig, Exportable::IfFuncTypeNamed(func_ty)),
+        )))
+    }
+
+    // Generates the canonical options for lifting or lowering with `abi`,
+    // returning `None` if its required options aren't available.
+    fn arbitrary_canon_opts(
+        &self,
+        u: &mut Unstructured,
+        abi: &CanonicalAbi,
+    ) -> Result<Option<Vec<CanonOpt>>> {
+        let memories = self.component().cabi_memories();
+        let reallocs = self.component().cabi_reallocs();
+        if (abi.needs_memory && memories.is_empty()) || (abi.needs_realloc && reallocs.is_empty()) {
+            return Ok(None);
+        }
+
+        let mut options = vec![];
+        if !memories.is_empty() && (abi.needs_memory || u.ratio::<u8>(1, 4)?) {
+            options.push(CanonOpt::Memory(*u.choose(&memories)?));
+            if !reallocs.is_empty() && (abi.needs_realloc || u.ratio::<u8>(1, 4)?) {
+                options.push(CanonOpt::Realloc(*u.choose(&reallocs)?));
+            }
+        }
+
+        match u.int_in_range::<u8>(0..=3)? {
+            0 => {}
+            1 => options.push(CanonOpt::StringUtf8),
+            2 => options.push(CanonOpt::StringUtf16),
+            3 => options.push(CanonOpt::StringLatin1Utf16),
+            _ => unreachable!(),
+        }
+
+        Ok(Some(options))
+    }
+
+    // Generates a component function type that can be lifted from a core
+    // function of type `core_func_ty`, using the primitive and defined value
+    // types available in the current scope.
+    fn inverse_canonical_abi_for(
+        &self,
+        u: &mut Unstructured,
+        core_func_ty: &crate::core::FuncType,
+        has_memory: bool,
+        has_realloc: bool,
+    ) -> Result<Option<FuncType>> {
+        if core_func_ty.params.len() > MAX_FLAT_PARAMS {
+            return Ok(None);
+        }
+
+        let scope = self.current_type_scope();
+        let candidates: Vec<(ComponentValType, Rc<ValTypeInfo>)> = PRIMITIVE_VAL_TYPES
+            .iter()
+            .map(|ty| {
+                (
+                    ComponentValType::Primitive(*ty),
+                    Rc::new(ValTypeInfo::primitive(*ty)),
+                )
+            })
+            .chain(
+                scope
+                    .defined_types
+                    .iter()
+                    .map(|i| (ComponentValType::Type(*i), scope.val_types[i].clone())),
+            )
+            .collect();
+
+        let mut names = HashSet::default();
+        let mut params = vec![];
+        let mut rest = &core_func_ty.params[..];
+        while !rest.is_empty() {
+            // Lifting parameters which contain pointers requires `realloc`.
+            let matching: Vec<_> = candidates
+                .iter()
+                .filter(|(_, info)| {
+                    rest.starts_with(&info.flat) && (has_realloc || !info.has_pointer)
+                })
+                .collect();
+            if matching.is_empty() {
+                return Ok(None);
+            }
+            let (ty, info) = u.choose(&matching)?;
+            rest = &rest[info.flat.len()..];
+            params.push((crate::unique_kebab_string(100, &mut names, u)?, *ty));
+        }
+
+        let result = match core_func_ty.results[..] {
+            [] => None,
+            [core_ty] => {
+                // Results which flatten to more than one core value are
+                // returned through a pointer into memory.
+                let matching: Vec<_> = candidates
+                    .iter()
+                    .filter(|(_, info)| {
+                        !info.has_borrow
+                            && (info.flat == [core_ty]
+                                || (core_ty == ValType::I32
+                                    && has_memory
+                                    && info.flat.len() > MAX_FLAT_RESULTS))
+                    })
+                    .collect();
+                if matching.is_empty() {
+                    return Ok(None);
+                }
+                Some(u.choose(&matching)?.0)
+            }
+            _ => return Ok(None),
+        };
+
+        Ok(Some(FuncType { params, result }))
+    }
+
     fn arbitrary_core_module_section(&mut self, u: &mut Unstructured) -> Result<Step> {
         let module = crate::core::Module::new_internal(
             self.config.clone(),
             u,
             crate::core::DuplicateImportsBehavior::Disallowed,
         )?;
+        // Function types which are part of larger rec groups or subtyping
+        // hierarchies can't be matched structurally, so exports of such
+        // functions are forgotten and imports of them are unsatisfiable.
+        let is_canonical = |ty: &crate::core::EntityType| match ty {
+            crate::core::EntityType::Func(idx, _) => module.is_canonical_func_type(*idx),
+            _ => true,
+        };
+        let summary = ModuleSummary {
+            imports: module.imports().to_vec(),
+            exports: module
+                .export_types()
+                .filter(|(_, ty)| is_canonical(ty))
+                .map(|(name, ty)| (name.to_string(), ty))
+                .collect(),
+            instantiable: module
+                .imports()
+                .iter()
+                .all(|import| is_canonical(&import.entity_type)),
+        };
         self.push_section(Section::CoreModule(module));
+        self.component_mut().modules.push(Rc::new(summary));
         self.total_modules += 1;
         Ok(Step::StillBuilding)
     }
 
-    fn arbitrary_component_section(&mut self, u: &mut Unstructured) -> Result<Step> {
+    fn arbitrary_component_section(&mut self, _u: &mut Unstructured) -> Result<Step> {
         self.types.push(TypesScope::default());
         self.components.push(ComponentContext::empty());
         self.total_components += 1;
         Ok(Step::StillBuilding)
     }
 
-    fn arbitrary_instance_section(&mut self, u: &mut Unstructured) -> Result<()> {
-        todo!()
+    // Finds the core items in the current component which may be supplied
+    // for a core import of type `ty`.
+    fn core_items_of_type(
+        &self,
+        ty: &crate::core::EntityType,
+    ) -> Vec<(wasm_encoder::ExportKind, u32)> {
+        let component = self.component();
+        let indices = |n: usize| 0..u32::try_from(n).unwrap();
+        match ty {
+            crate::core::EntityType::Func(_, ty) if is_closed_func_type(ty) => component
+                .core_funcs_of_type(ty)
+                .into_iter()
+                .map(|i| (wasm_encoder::ExportKind::Func, i))
+                .collect(),
+            crate::core::EntityType::Memory(ty) => indices(component.core_memories.len())
+                .filter(|i| component.core_memories[*i as usize] == *ty)
+                .map(|i| (wasm_encoder::ExportKind::Memory, i))
+                .collect(),
+            crate::core::EntityType::Table(ty) if is_closed_ref_type(ty.element_type) => {
+                indices(component.core_tables.len())
+                    .filter(|i| component.core_tables[*i as usize] == *ty)
+                    .map(|i| (wasm_encoder::ExportKind::Table, i))
+                    .collect()
+            }
+            crate::core::EntityType::Global(ty) if is_closed_valtype(ty.val_type) => {
+                indices(component.core_globals.len())
+                    .filter(|i| component.core_globals[*i as usize] == *ty)
+                    .map(|i| (wasm_encoder::ExportKind::Global, i))
+                    .collect()
+            }
+            _ => vec![],
+        }
+    }
+
+    // The type of the core item `index` of the given `kind`.
+    fn core_item_type(
+        &self,
+        kind: wasm_encoder::ExportKind,
+        index: u32,
+    ) -> crate::core::EntityType {
+        let component = self.component();
+        match kind {
+            wasm_encoder::ExportKind::Func => {
+                crate::core::EntityType::Func(u32::MAX, component.core_function_type(index).clone())
+            }
+            wasm_encoder::ExportKind::Memory => {
+                crate::core::EntityType::Memory(component.core_memories[index as usize])
+            }
+            wasm_encoder::ExportKind::Table => {
+                crate::core::EntityType::Table(component.core_tables[index as usize])
+            }
+            wasm_encoder::ExportKind::Global => {
+                crate::core::EntityType::Global(component.core_globals[index as usize])
+            }
+            wasm_encoder::ExportKind::Tag => unreachable!("core tags are not tracked"),
+        }
+    }
+
+    fn push_core_instance(
+        &mut self,
+        instance: CoreInstance,
+        exports: BTreeMap<String, crate::core::EntityType>,
+    ) -> u32 {
+        match self.ensure_section(
+            |s| matches!(s, Section::CoreInstance(_)),
+            || Section::CoreInstance(CoreInstanceSection { instances: vec![] }),
+        ) {
+            Section::CoreInstance(sec) => sec.instances.push(instance),
+            _ => unreachable!(),
+        }
+        self.total_instances += 1;
+        let index = u32::try_from(self.component().core_instances.len()).unwrap();
+        self.component_mut().core_instances.push(Rc::new(exports));
+        index
+    }
+
+    fn arbitrary_core_instance_section(&mut self, u: &mut Unstructured) -> Result<Step> {
+        self.push_section(Section::CoreInstance(CoreInstanceSection {
+            instances: vec![],
+        }));
+
+        let mut choices: Vec<fn(&mut ComponentBuilder, &mut Unstructured) -> Result<()>> =
+            Vec::with_capacity(2);
+
+        arbitrary_loop(u, 0, self.config.max_instances, |u| {
+            if self.total_instances >= self.config.max_instances {
+                return Ok(false);
+            }
+
+            choices.clear();
+            if !self.component().modules.is_empty() {
+                choices.push(Self::arbitrary_core_instantiation);
+            }
+            choices.push(Self::arbitrary_core_instance_from_exports);
+
+            let f = u.choose(&choices)?;
+            f(self, u)?;
+            Ok(true)
+        })?;
+
+        Ok(Step::StillBuilding)
+    }
+
+    fn arbitrary_core_instantiation(&mut self, u: &mut Unstructured) -> Result<()> {
+        let module_index =
+            u.int_in_range(0..=u32::try_from(self.component().modules.len() - 1).unwrap())?;
+        let module = self.component().modules[module_index as usize].clone();
+        if !module.instantiable {
+            return Ok(());
+        }
+
+        // Each module name imported is supplied by an instance created from
+        // core items of the right types, if there are any.
+        let mut arg_exports: Vec<(String, Vec<(String, wasm_encoder::ExportKind, u32)>)> = vec![];
+        for import in &module.imports {
+            let items = self.core_items_of_type(&import.entity_type);
+            if items.is_empty() {
+                return Ok(());
+            }
+            let (kind, index) = *u.choose(&items)?;
+            let item = (import.field.clone(), kind, index);
+            match arg_exports
+                .iter_mut()
+                .find(|(name, _)| *name == import.module)
+            {
+                Some((_, exports)) => exports.push(item),
+                None => arg_exports.push((import.module.clone(), vec![item])),
+            }
+        }
+
+        let mut args = vec![];
+        for (name, exports) in arg_exports {
+            let types = exports
+                .iter()
+                .map(|(field, kind, index)| (field.clone(), self.core_item_type(*kind, *index)))
+                .collect();
+            let instance = self.push_core_instance(CoreInstance::FromExports(exports), types);
+            args.push((name, instance));
+        }
+
+        let exports = module.exports.iter().cloned().collect();
+        self.push_core_instance(
+            CoreInstance::Instantiate {
+                module: module_index,
+                args,
+            },
+            exports,
+        );
+        Ok(())
     }
 
-    fn arbitrary_export_section(&mut self, u: &mut Unstructured) -> Result<()> {
-        todo!()
+    fn arbitrary_core_instance_from_exports(&mut self, u: &mut Unstructured) -> Result<()> {
+        let component = self.component();
+        let mut items = vec![];
+        let mut push_items = |kind, n: usize| {
+            items.extend((0..u32::try_from(n).unwrap()).map(|i| (kind, i)));
+        };
+        push_items(wasm_encoder::ExportKind::Func, component.core_funcs.len());
+        push_items(
+            wasm_encoder::ExportKind::Memory,
+            component.core_memories.len(),
+        );
+        push_items(wasm_encoder::ExportKind::Table, component.core_tables.len());
+        push_items(
+            wasm_encoder::ExportKind::Global,
+            component.core_globals.len(),
+        );
+
+        let mut names = HashSet::new();
+        let mut exports = vec![];
+        let mut types = BTreeMap::new();
+        if !items.is_empty() {
+            arbitrary_loop(u, 0, 10, |u| {
+                let (kind, index) = *u.choose(&items)?;
+                let name = crate::unique_string(100, &mut names, u)?;
+                types.insert(name.clone(), self.core_item_type(kind, index));
+                exports.push((name, kind, index));
+                Ok(true)
+            })?;
+        }
+
+        self.push_core_instance(CoreInstance::FromExports(exports), types);
+        Ok(())
     }
 
-    fn arbitrary_start_section(&mut self, u: &mut Unstructured) -> Result<()> {
-        todo!()
+    // Whether the component function `func_index` may be exported right now.
+    fn is_func_exportable(&self, func_index: u32) -> bool {
+        match self.component().component_function(func_index).1 {
+            Exportable::Always => true,
+            Exportable::Never => false,
+            Exportable::IfFuncTypeNamed(ty) => {
+                let scope = self.current_type_scope();
+                scope.is_type_importable(ty, &scope.exported_types)
+            }
+        }
     }
 
-    fn arbitrary_alias_section(&mut self, u: &mut Unstructured) -> Result<()> {
-        todo!()
+    fn push_instance(&mut self, instance: Instance, summary: ComponentInstance) {
+        match self.ensure_section(
+            |s| matches!(s, Section::Instance(_)),
+            || Section::Instance(InstanceSection { instances: vec![] }),
+        ) {
+            Section::Instance(sec) => sec.instances.push(instance),
+            _ => unreachable!(),
+        }
+        self.total_instances += 1;
+        self.component_mut().instances.push(summary);
     }
-}
 
-fn canonical_abi_for(func_ty: &FuncType) -> Rc<crate::core::FuncType> {
-    let to_core_ty = |ty| match ty {
-        ComponentValType::Primitive(prim_ty) => match prim_ty {
-            PrimitiveValType::Char
-            | PrimitiveValType::Bool
-            | PrimitiveValType::S8
-            | PrimitiveValType::U8
-            | PrimitiveValType::S16
-            | PrimitiveValType::U16
-            | PrimitiveValType::S32
-            | PrimitiveValType::U32 => ValType::I32,
-            PrimitiveValType::S64 | PrimitiveValType::U64 => ValType::I64,
-            PrimitiveValType::F32 => ValType::F32,
-            PrimitiveValType::F64 => ValType::F64,
-            PrimitiveValType::String | PrimitiveValType::ErrorContext => {
-                unimplemented!("non-scalar types are not supported yet")
-            }
-        },
-        ComponentValType::Type(_) => unimplemented!("non-scalar types are not supported yet"),
-    };
-
-    Rc::new(crate::core::FuncType {
-        params: func_ty
-            .params
-            .iter()
-            .map(|(_, ty)| to_core_ty(*ty))
-            .collect(),
-        results: func_ty
-            .results
-            .iter()
-            .map(|(_, ty)| to_core_ty(*ty))
-            .collect(),
-    })
-}
+    fn arbitrary_instance_section(&mut self, u: &mut Unstructured) -> Result<Step> {
+        self.push_section(Section::Instance(InstanceSection { instances: vec![] }));
 
-fn inverse_scalar_canonical_abi_for(
-    u: &mut Unstructured,
-    core_func_ty: &crate::core::FuncType,
-) -> Result<FuncType> {
-    let from_core_ty = |u: &mut Unstructured, core_ty| match core_ty {
-        ValType::I32 => u
-            .choose(&[
-                ComponentValType::Primitive(PrimitiveValType::Char),
-                ComponentValType::Primitive(PrimitiveValType::Bool),
-                ComponentValType::Primitive(PrimitiveValType::S8),
-                ComponentValType::Primitive(PrimitiveValType::U8),
-                ComponentValType::Primitive(PrimitiveValType::S16),
-                ComponentValType::Primitive(PrimitiveValType::U16),
-                ComponentValType::Primitive(PrimitiveValType::S32),
-                ComponentValType::Primitive(PrimitiveValType::U32),
-            ])
-            .cloned(),
-        ValType::I64 => u
-            .choose(&[
-                ComponentValType::Primitive(PrimitiveValType::S64),
-                ComponentValType::Primitive(PrimitiveValType::U64),
-            ])
-            .cloned(),
-        ValType::F32 => Ok(ComponentValType::Primitive(PrimitiveValType::F32)),
-        ValType::F64 => Ok(ComponentValType::Primitive(PrimitiveValType::F64)),
-        ValType::V128 | ValType::Ref(_) => {
-            unreachable!("not used in canonical ABI")
-        }
-    };
-
-    let mut names = HashSet::default();
-    let mut params = vec![];
+        let mut choices: Vec<fn(&mut ComponentBuilder, &mut Unstructured) -> Result<()>> =
+            Vec::with_capacity(2);
+
+        arbitrary_loop(u, 0, self.config.max_instances, |u| {
+            if self.total_instances >= self.config.max_instances {
+                return Ok(false);
+            }
+
+            choices.clear();
+            if !self.component().components.is_empty() {
+                choices.push(Self::arbitrary_instantiation);
+            }
+            choices.push(Self::arbitrary_instance_from_exports);
 
-    for core_ty in &core_func_ty.params {
-        params.push((
-            crate::unique_kebab_string(100, &mut names, u)?,
-            from_core_ty(u, *core_ty)?,
-        ));
+            let f = u.choose(&choices)?;
+            f(self, u)?;
+            Ok(true)
+        })?;
+
+        Ok(Step::StillBuilding)
     }
 
-    names.clear();
+    fn arbitrary_instantiation(&mut self, u: &mut Unstructured) -> Result<()> {
+        let component_index =
+            u.int_in_range(0..=u32::try_from(self.component().components.len() - 1).unwrap())?;
+        let component = self.component().components[component_index as usize].clone();
 
-    let results = match core_func_ty.results.len() {
-        0 => Vec::new(),
-        1 => vec![(
-            if u.arbitrary()? {
-                Some(crate::unique_kebab_string(100, &mut names, u)?)
-            } else {
-                None
+        // Only components which import functions of primitive types can be
+        // instantiated, as their types mean the same thing in this scope.
+        let mut args = vec![];
+        for (name, entity) in &component.imports {
+            let sig = match entity {
+                Entity::Func(sig) if sig.is_primitive() => sig,
+                _ => return Ok(()),
+            };
+            let funcs: Vec<u32> = (0..u32::try_from(self.component().component_funcs.len())
+                .unwrap())
+                .filter(|i| self.component().component_function(*i).0.ty == sig.ty)
+                .collect();
+            if funcs.is_empty() {
+                return Ok(());
+            }
+            args.push((name.clone(), ComponentExportKind::Func, *u.choose(&funcs)?));
+        }
+
+        self.push_instance(
+            Instance::Instantiate {
+                component: component_index,
+                args,
+            },
+            ComponentInstance {
+                summary: Rc::new(InstanceSummary {
+                    exports: component.exports.clone(),
+                }),
+                aliases_exportable: false,
             },
-            from_core_ty(u, core_func_ty.results[0])?,
-        )],
-        _ => unimplemented!("non-scalar types are not supported yet"),
-    };
+        );
+        Ok(())
+    }
+
+    fn arbitrary_instance_from_exports(&mut self, u: &mut Unstructured) -> Result<()> {
+        // Only items which could also be exported from this component are
+        // bundled, so the resulting instance can always be exported too.
+        let component = self.component();
+        let mut items = vec![];
+        for i in 0..u32::try_from(component.component_funcs.len()).unwrap() {
+            if self.is_func_exportable(i) {
+                items.push((ComponentExportKind::Func, i));
+            }
+        }
+        for i in 0..u32::try_from(component.modules.len()).unwrap() {
+            items.push((ComponentExportKind::Module, i));
+        }
+        for i in 0..u32::try_from(component.components.len()).unwrap() {
+            items.push((ComponentExportKind::Component, i));
+        }
+        for (i, instance) in component.instances.iter().enumerate() {
+            if instance.aliases_exportable {
+                items.push((ComponentExportKind::Instance, u32::try_from(i).unwrap()));
+            }
+        }
+
+        let mut names = HashSet::new();
+        let mut exports = vec![];
+        let mut summary = InstanceSummary::default();
+        if !items.is_empty() {
+            arbitrary_loop(u, 0, 10, |u| {
+                let (kind, index) = *u.choose(&items)?;
+                let name = crate::unique_kebab_string(100, &mut names, u)?;
+                summary
+                    .exports
+                    .push((name.clone(), self.entity(kind, index)));
+                exports.push((name, kind, index));
+                Ok(true)
+            })?;
+        }
+
+        self.push_instance(
+            Instance::FromExports(exports),
+            ComponentInstance {
+                summary: Rc::new(summary),
+                aliases_exportable: true,
+            },
+        );
+        Ok(())
+    }
+
+    // Describes the item `index` of the given `kind` in the current component.
+    fn entity(&self, kind: ComponentExportKind, index: u32) -> Entity {
+        let component = self.component();
+        match kind {
+            ComponentExportKind::Module => {
+                Entity::Module(component.modules[index as usize].clone())
+            }
+            ComponentExportKind::Func => {
+                Entity::Func(component.component_function(index).0.clone())
+            }
+            ComponentExportKind::Value => Entity::Value,
+            ComponentExportKind::Type => Entity::Type,
+            ComponentExportKind::Instance => {
+                Entity::Instance(component.instances[index as usize].summary.clone())
+            }
+            ComponentExportKind::Component => {
+                Entity::Component(component.components[index as usize].clone())
+            }
+        }
+    }
+
+    fn push_alias(&mut self, alias: Alias) {
+        match self.ensure_section(
+            |s| matches!(s, Section::Alias(_)),
+            || Section::Alias(AliasSection { aliases: vec![] }),
+        ) {
+            Section::Alias(sec) => sec.aliases.push(alias),
+            _ => unreachable!(),
+        }
+    }
 
-    Ok(FuncType { params, results })
+    fn arbitrary_alias_section(&mut self, u: &mut Unstructured) -> Result<Step> {
+        self.push_section(Section::Alias(AliasSection { aliases: vec![] }));
+
+        let mut choices: Vec<fn(&mut ComponentBuilder, &mut Unstructured) -> Result<()>> =
+            Vec::with_capacity(4);
+
+        arbitrary_loop(u, 0, 100, |u| {
+            choices.clear();
+
+            if !self.core_instance_export_alias_candidates().is_empty() {
+                choices.push(Self::arbitrary_core_instance_export_alias);
+            }
+            if !self.instance_export_alias_candidates().is_empty() {
+                choices.push(Self::arbitrary_instance_export_alias);
+            }
+            if !self.outer_alias_candidates().is_empty() {
+                choices.push(Self::arbitrary_outer_alias);
+            }
+            if self.current_type_scope().types.len() < self.config.max_types
+                && !self.outer_type_alias_candidates().is_empty()
+            {
+                choices.push(|me, u| {
+                    let alias = me.arbitrary_outer_type_alias(u)?;
+                    me.push_outer_type_alias(&alias);
+                    me.push_alias(alias);
+                    Ok(())
+                });
+            }
+
+            if choices.is_empty() {
+                return Ok(false);
+            }
+
+            let f = u.choose(&choices)?;
+            f(self, u)?;
+            Ok(true)
+        })?;
+
+        Ok(Step::StillBuilding)
+    }
+
+    fn core_instance_export_alias_candidates(
+        &self,
+    ) -> Vec<(u32, &String, &crate::core::EntityType)> {
+        let mut candidates = vec![];
+        for (i, exports) in self.component().core_instances.iter().enumerate() {
+            for (name, ty) in exports.iter() {
+                let aliasable = match ty {
+                    crate::core::EntityType::Func(_, ty) => {
+                        is_closed_func_type(ty)
+                            && self.component().num_funcs() < self.config.max_funcs
+                    }
+                    crate::core::EntityType::Memory(_) => true,
+                    crate::core::EntityType::Table(ty) => is_closed_ref_type(ty.element_type),
+                    crate::core::EntityType::Global(ty) => is_closed_valtype(ty.val_type),
+                    crate::core::EntityType::Tag(_) => false,
+                };
+                if aliasable {
+                    candidates.push((u32::try_from(i).unwrap(), name, ty));
+                }
+            }
+        }
+        candidates
+    }
+
+    fn arbitrary_core_instance_export_alias(&mut self, u: &mut Unstructured) -> Result<()> {
+        let candidates = self.core_instance_export_alias_candidates();
+        let (instance, name, ty) = *u.choose(&candidates)?;
+        let (name, ty) = (name.clone(), ty.clone());
+
+        let kind = match ty {
+            crate::core::EntityType::Func(_, ty) => {
+                self.push_core_func(ty);
+                CoreInstanceExportAliasKind::Func
+            }
+            crate::core::EntityType::Memory(ty) => {
+                self.component_mut().core_memories.push(ty);
+                CoreInstanceExportAliasKind::Memory
+            }
+            crate::core::EntityType::Table(ty) => {
+                self.component_mut().core_tables.push(ty);
+                CoreInstanceExportAliasKind::Table
+            }
+            crate::core::EntityType::Global(ty) => {
+                self.component_mut().core_globals.push(ty);
+                CoreInstanceExportAliasKind::Global
+            }
+            crate::core::EntityType::Tag(_) => unreachable!(),
+        };
+
+        self.push_alias(Alias::CoreInstanceExport {
+            instance,
+            name,
+            kind,
+        });
+        Ok(())
+    }
+
+    fn instance_export_alias_candidates(&self) -> Vec<(u32, &String, &Entity)> {
+        let mut candidates = vec![];
+        for (i, instance) in self.component().instances.iter().enumerate() {
+            for (name, entity) in instance.summary.exports.iter() {
+                let aliasable = match entity {
+                    Entity::Module(_) => self.total_modules < self.config.max_modules,
+                    Entity::Func(_) => self.component().num_funcs() < self.config.max_funcs,
+                    Entity::Instance(_) => self.total_instances < self.config.max_instances,
+                    Entity::Component(_) => self.total_components < self.config.max_components,
+                    Entity::Value | Entity::Type => false,
+                };
+                if aliasable {
+                    candidates.push((u32::try_from(i).unwrap(), name, entity));
+                }
+            }
+        }
+        candidates
+    }
+
+    fn arbitrary_instance_export_alias(&mut self, u: &mut Unstructured) -> Result<()> {
+        let candidates = self.instance_export_alias_candidates();
+        let (instance, name, entity) = *u.choose(&candidates)?;
+        let (name, entity) = (name.clone(), entity.clone());
+        let aliases_exportable = self.component().instances[instance as usize].aliases_exportable;
+
+        let kind = match entity {
+            Entity::Module(module) => {
+                self.total_modules += 1;
+                self.component_mut().modules.push(module);
+                InstanceExportAliasKind::Module
+            }
+            Entity::Func(sig) => {
+                let exportable = if aliases_exportable || sig.is_primitive() {
+                    Exportable::Always
+                } else {
+                    Exportable::Never
+                };
+                self.push_component_func(sig, exportable);
+                InstanceExportAliasKind::Func
+            }
+            Entity::Instance(summary) => {
+                self.total_instances += 1;
+                self.component_mut().instances.push(ComponentInstance {
+                    summary,
+                    aliases_exportable,
+                });
+                InstanceExportAliasKind::Instance
+            }
+            Entity::Component(summary) => {
+                self.total_components += 1;
+                self.component_mut().components.push(summary);
+                InstanceExportAliasKind::Component
+            }
+            Entity::Value | Entity::Type => unreachable!(),
+        };
+
+        self.push_alias(Alias::InstanceExport {
+            instance,
+            name,
+            kind,
+        });
+        Ok(())
+    }
+
+    // Modules and components of this or enclosing components which may be
+    // aliased into this one.
+    fn outer_alias_candidates(&self) -> Vec<(u32, u32, OuterAliasKind)> {
+        let mut candidates = vec![];
+        for (count, component) in self.components.iter().rev().enumerate() {
+            let count = u32::try_from(count).unwrap();
+            if self.total_modules < self.config.max_modules {
+                for i in 0..u32::try_from(component.modules.len()).unwrap() {
+                    candidates.push((count, i, OuterAliasKind::Module));
+                }
+            }
+            if self.total_components < self.config.max_components {
+                for i in 0..u32::try_from(component.components.len()).unwrap() {
+                    candidates.push((count, i, OuterAliasKind::Component));
+                }
+            }
+        }
+        candidates
+    }
+
+    fn arbitrary_outer_alias(&mut self, u: &mut Unstructured) -> Result<()> {
+        let candidates = self.outer_alias_candidates();
+        let (count, i, kind) = u.choose(&candidates)?.clone();
+        let outer = &self.components[self.components.len() - 1 - usize::try_from(count).unwrap()];
+
+        match kind {
+            OuterAliasKind::Module => {
+                let module = outer.modules[i as usize].clone();
+                self.total_modules += 1;
+                self.component_mut().modules.push(module);
+            }
+            OuterAliasKind::Component => {
+                let component = outer.components[i as usize].clone();
+                self.total_components += 1;
+                self.component_mut().components.push(component);
+            }
+            OuterAliasKind::Type(_) | OuterAliasKind::CoreType(_) => unreachable!(),
+        }
+
+        self.push_alias(Alias::Outer { count, i, kind });
+        Ok(())
+    }
+
+    fn arbitrary_export_section(&mut self, u: &mut Unstructured) -> Result<Step> {
+        self.push_section(Section::Export(ExportSection { exports: vec![] }));
+
+        let max = self
+            .config
+            .max_exports
+            .saturating_sub(self.component().exports.len());
+
+        arbitrary_loop(u, 0, max, |u| {
+            let component = self.component();
+            let scope = self.current_type_scope();
+            let mut choices: Vec<Vec<(ComponentExportKind, u32)>> = vec![];
+
+            if component.num_funcs() < self.config.max_funcs {
+                let funcs: Vec<_> = (0..u32::try_from(component.component_funcs.len()).unwrap())
+                    .filter(|i| self.is_func_exportable(*i))
+                    .map(|i| (ComponentExportKind::Func, i))
+                    .collect();
+                if !funcs.is_empty() {
+                    choices.push(funcs);
+                }
+            }
+            if !component.modules.is_empty() && self.total_modules < self.config.max_modules {
+                choices.push(
+                    (0..u32::try_from(component.modules.len()).unwrap())
+                        .map(|i| (ComponentExportKind::Module, i))
+                        .collect(),
+                );
+            }
+            if !component.components.is_empty()
+                && self.total_components < self.config.max_components
+            {
+                choices.push(
+                    (0..u32::try_from(component.components.len()).unwrap())
+                        .map(|i| (ComponentExportKind::Component, i))
+                        .collect(),
+                );
+            }
+            if !component.instances.is_empty() && self.total_instances < self.config.max_instances {
+                choices.push(
+                    (0..u32::try_from(component.instances.len()).unwrap())
+                        .map(|i| (ComponentExportKind::Instance, i))
+                        .collect(),
+                );
+            }
+            if scope.types.len() < self.config.max_types {
+                let types: Vec<_> = (0..u32::try_from(scope.types.len()).unwrap())
+                    .filter(|i| scope.is_type_importable(*i, &scope.exported_types))
+                    .map(|i| (ComponentExportKind::Type, i))
+                    .collect();
+                if !types.is_empty() {
+                    choices.push(types);
+                }
+            }
+
+            if choices.is_empty() {
+                return Ok(false);
+            }
+
+            let items = u.choose(&choices)?;
+            let (kind, index) = *u.choose(items)?;
+            let name = crate::unique_kebab_string(100, &mut self.component_mut().export_names, u)?;
+            let entity = self.entity(kind, index);
+
+            // Exports introduce a new item into the corresponding index space.
+            match (kind, entity.clone()) {
+                (ComponentExportKind::Func, Entity::Func(sig)) => {
+                    self.push_component_func(sig, Exportable::Always);
+                }
+                (ComponentExportKind::Module, Entity::Module(module)) => {
+                    self.total_modules += 1;
+                    self.component_mut().modules.push(module);
+                }
+                (ComponentExportKind::Component, Entity::Component(component)) => {
+                    self.total_components += 1;
+                    self.component_mut().components.push(component);
+                }
+                (ComponentExportKind::Instance, Entity::Instance(_)) => {
+                    self.total_instances += 1;
+                    let instance = self.component().instances[index as usize].clone();
+                    self.component_mut().instances.push(instance);
+                }
+                (ComponentExportKind::Type, Entity::Type) => {
+                    self.push_type_ref(ComponentTypeRef::Type(TypeBounds::Eq(index)), false);
+                }
+                _ => unreachable!(),
+            }
+
+            self.component_mut().exports.push((name.clone(), entity));
+            match self.last_section_mut() {
+                Some(Section::Export(sec)) => sec.exports.push(Export { name, kind, index }),
+                _ => unreachable!(),
+            }
+            Ok(true)
+        })?;
+
+        Ok(Step::StillBuilding)
+    }
+
+    fn arbitrary_start_section(&mut self, u: &mut Unstructured) -> Result<Step> {
+        // Values produced by the start function must all be consumed, and
+        // values aren't otherwise generated, so only use functions without
+        // parameters or results.
+        let funcs: Vec<u32> = (0..u32::try_from(self.component().component_funcs.len()).unwrap())
+            .filter(|i| {
+                let ty = &self.component().component_function(*i).0.ty;
+                ty.params.is_empty() && ty.result.is_none()
+            })
+            .collect();
+        if funcs.is_empty() {
+            return Ok(Step::StillBuilding);
+        }
+
+        let func_index = *u.choose(&funcs)?;
+        self.push_section(Section::Start(StartSection {
+            func_index,
+            args: vec![],
+            results: 0,
+        }));
+        self.component_mut().has_start = true;
+        Ok(Step::StillBuilding)
+    }
+}
+
+const MAX_FLAT_PARAMS: usize = 16;
+const MAX_FLAT_RESULTS: usize = 1;
+
+// The primitive value types that we generate.
+const PRIMITIVE_VAL_TYPES: [PrimitiveValType; 13] = [
+    PrimitiveValType::Bool,
+    PrimitiveValType::S8,
+    PrimitiveValType::U8,
+    PrimitiveValType::S16,
+    PrimitiveValType::U16,
+    PrimitiveValType::S32,
+    PrimitiveValType::U32,
+    PrimitiveValType::S64,
+    PrimitiveValType::U64,
+    PrimitiveValType::F32,
+    PrimitiveValType::F64,
+    PrimitiveValType::Char,
+    PrimitiveValType::String,
+];
+
+// Whether `ty` means the same thing regardless of which core module it's used
+// in, i.e. doesn't refer to a concrete type index.
+fn is_closed_valtype(ty: ValType) -> bool {
+    match ty {
+        ValType::Ref(ty) => is_closed_ref_type(ty),
+        _ => true,
+    }
+}
+
+fn is_closed_ref_type(ty: RefType) -> bool {
+    !matches!(ty.heap_type, HeapType::Concrete(_))
+}
+
+fn is_closed_func_type(ty: &crate::core::FuncType) -> bool {
+    ty.params
+        .iter()
+        .chain(&ty.results)
+        .all(|ty| is_closed_valtype(*ty))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Abi {
+    Lift,
+    Lower,
+}
+
+/// The core function type of a lifted or lowered component function, along
+/// with the canonical options it requires.
+#[derive(Debug)]
+struct CanonicalAbi {
+    core_ty: Rc<crate::core::FuncType>,
+    needs_memory: bool,
+    needs_realloc: bool,
+}
+
+fn join_flat_types(a: ValType, b: ValType) -> ValType {
+    match (a, b) {
+        _ if a == b => a,
+        (ValType::I32, ValType::F32) | (ValType::F32, ValType::I32) => ValType::I32,
+        _ => ValType::I64,
+    }
 }
 
 #[derive(Debug)]
@@ -1930,6 +3028,9 @@ enum Type {
     Func(Rc<FuncType>),
     Component(Rc<ComponentType>),
     Instance(Rc<InstanceType>),
+    // A resource type introduced by an import or export. Resources are only
+    // ever created this way, never defined within a type section.
+    Resource,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -1938,7 +3039,6 @@ enum CoreInstanceExportAliasKind {
     Table,
     Memory,
     Global,
-    Tag,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -1971,7 +3071,6 @@ enum InstanceExportAliasKind {
     Component,
     Instance,
     Func,
-    Value,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -1985,6 +3084,7 @@ enum OuterAliasKind {
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 struct ComponentType {
     defs: Vec<ComponentTypeDef>,
+    summary: Rc<ComponentSummary>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -2014,6 +3114,7 @@ impl From<InstanceTypeDecl> for ComponentTypeDef {
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 struct InstanceType {
     defs: Vec<InstanceTypeDecl>,
+    summary: Rc<InstanceSummary>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -2031,30 +3132,89 @@ enum InstanceTypeDecl {
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 struct FuncType {
     params: Vec<(String, ComponentValType)>,
-    results: Vec<(Option<String>, ComponentValType)>,
+    result: Option<ComponentValType>,
 }
 
-impl FuncType {
-    fn unnamed_result_ty(&self) -> Option<ComponentValType> {
-        if self.results.len() == 1 {
-            let (name, ty) = &self.results[0];
-            if name.is_none() {
-                return Some(*ty);
+/// A component function type along with the flattened canonical ABI
+/// representation of its parameters and result.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct FuncSig {
+    ty: Rc<FuncType>,
+    params: ValTypeInfo,
+    result: ValTypeInfo,
+}
+
+impl FuncSig {
+    // Whether this signature only refers to primitive value types, in which
+    // case it means the same thing in every scope.
+    fn is_primitive(&self) -> bool {
+        self.ty
+            .params
+            .iter()
+            .map(|(_, ty)| ty)
+            .chain(&self.ty.result)
+            .all(|ty| matches!(ty, ComponentValType::Primitive(_)))
+    }
+
+    fn canonical_abi(&self, abi: Abi) -> CanonicalAbi {
+        let mut needs_memory = false;
+        let mut needs_realloc = false;
+
+        let mut params = if self.params.flat.len() > MAX_FLAT_PARAMS {
+            needs_memory = true;
+            if abi == Abi::Lift {
+                needs_realloc = true;
+            }
+            vec![ValType::I32]
+        } else {
+            self.params.flat.clone()
+        };
+        match abi {
+            Abi::Lift => needs_realloc |= self.params.has_pointer,
+            Abi::Lower => {
+                needs_memory |= self.params.has_pointer;
+                needs_realloc |= self.result.has_pointer;
             }
         }
-        None
-    }
 
-    fn is_scalar(&self) -> bool {
-        self.params.iter().all(|(_, ty)| is_scalar(ty))
-            && self.results.len() == 1
-            && is_scalar(&self.results[0].1)
+        let results = if self.result.flat.len() > MAX_FLAT_RESULTS {
+            needs_memory = true;
+            match abi {
+                Abi::Lift => vec![ValType::I32],
+                Abi::Lower => {
+                    params.push(ValType::I32);
+                    vec![]
+                }
+            }
+        } else {
+            self.result.flat.clone()
+        };
+
+        CanonicalAbi {
+            core_ty: Rc::new(crate::core::FuncType { params, results }),
+            needs_memory: needs_memory || needs_realloc,
+            needs_realloc,
+        }
     }
 }
 
-fn is_scalar(ty: &ComponentValType) -> bool {
-    match ty {
-        ComponentValType::Primitive(prim) => match prim {
+/// The flattened canonical ABI representation of a component value type, or
+/// of a sequence of them.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+struct ValTypeInfo {
+    flat: Vec<ValType>,
+    // Whether the value contains strings or lists, which live in linear
+    // memory.
+    has_pointer: bool,
+    // Whether the value contains `borrow` handles.
+    has_borrow: bool,
+    // Whether the value contains `own` or `borrow` handles.
+    has_resource: bool,
+}
+
+impl ValTypeInfo {
+    fn primitive(ty: PrimitiveValType) -> Self {
+        let flat = match ty {
             PrimitiveValType::Bool
             | PrimitiveValType::S8
             | PrimitiveValType::U8
@@ -2062,17 +3222,105 @@ fn is_scalar(ty: &ComponentValType) -> bool {
             | PrimitiveValType::U16
             | PrimitiveValType::S32
             | PrimitiveValType::U32
-            | PrimitiveValType::S64
-            | PrimitiveValType::U64
-            | PrimitiveValType::F32
-            | PrimitiveValType::F64
-            | PrimitiveValType::Char => true,
-            PrimitiveValType::String | PrimitiveValType::ErrorContext => false,
-        },
-        ComponentValType::Type(_) => false,
+            | PrimitiveValType::Char
+            | PrimitiveValType::ErrorContext => vec![ValType::I32],
+            PrimitiveValType::S64 | PrimitiveValType::U64 => vec![ValType::I64],
+            PrimitiveValType::F32 => vec![ValType::F32],
+            PrimitiveValType::F64 => vec![ValType::F64],
+            PrimitiveValType::String => vec![ValType::I32, ValType::I32],
+        };
+        ValTypeInfo {
+            flat,
+            has_pointer: ty == PrimitiveValType::String,
+            ..ValTypeInfo::default()
+        }
+    }
+
+    fn concat(tys: impl IntoIterator<Item = Rc<ValTypeInfo>>) -> Self {
+        let mut info = ValTypeInfo::default();
+        for ty in tys {
+            info.flat.extend_from_slice(&ty.flat);
+            info.merge_flags(&ty);
+        }
+        info
+    }
+
+    fn variant(cases: impl IntoIterator<Item = Option<Rc<ValTypeInfo>>>) -> Self {
+        let mut info = ValTypeInfo {
+            flat: vec![ValType::I32],
+            ..ValTypeInfo::default()
+        };
+        for case in cases.into_iter().flatten() {
+            for (i, ty) in case.flat.iter().enumerate() {
+                match info.flat.get_mut(i + 1) {
+                    Some(prev) => *prev = join_flat_types(*prev, *ty),
+                    None => info.flat.push(*ty),
+                }
+            }
+            info.merge_flags(&case);
+        }
+        info
+    }
+
+    fn merge_flags(&mut self, other: &ValTypeInfo) {
+        self.has_pointer |= other.has_pointer;
+        self.has_borrow |= other.has_borrow;
+        self.has_resource |= other.has_resource;
+    }
+}
+
+/// The imports and exports of a core module.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+struct ModuleSummary {
+    imports: Vec<crate::core::Import>,
+    exports: Vec<(String, crate::core::EntityType)>,
+    // Whether the types of `imports` can be matched structurally against
+    // the core items of a component.
+    instantiable: bool,
+}
+
+impl ModuleType {
+    fn summary(&self) -> ModuleSummary {
+        let mut summary = ModuleSummary {
+            instantiable: true,
+            ..ModuleSummary::default()
+        };
+        for def in &self.defs {
+            match def {
+                ModuleTypeDef::Import(import) => summary.imports.push(import.clone()),
+                ModuleTypeDef::Export(name, ty) => summary.exports.push((name.clone(), ty.clone())),
+                ModuleTypeDef::TypeDef(_) | ModuleTypeDef::OuterAlias { .. } => {}
+            }
+        }
+        summary
     }
 }
 
+/// The imports and exports of a component.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+struct ComponentSummary {
+    imports: Vec<(String, Entity)>,
+    exports: Vec<(String, Entity)>,
+}
+
+/// The exports of a component instance.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+struct InstanceSummary {
+    exports: Vec<(String, Entity)>,
+}
+
+/// An item imported or exported by a component, described independently of
+/// the types scope it was defined in.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum Entity {
+    Module(Rc<ModuleSummary>),
+    Func(Rc<FuncSig>),
+    Value,
+    Type,
+    Instance(Rc<InstanceSummary>),
+    Component(Rc<ComponentSummary>),
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 enum DefinedType {
     Primitive(PrimitiveValType),
@@ -2084,6 +3332,8 @@ enum DefinedType {
     Enum(EnumType),
     Option(OptionType),
     Result(ResultType),
+    Own(u32),
+    Borrow(u32),
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -2168,19 +3418,56 @@ enum CanonOpt {
 }
 
 #[derive(Debug)]
-struct InstanceSection {}
+struct InstanceSection {
+    instances: Vec<Instance>,
+}
+
+#[derive(Debug)]
+enum Instance {
+    Instantiate {
+        component: u32,
+        args: Vec<(String, ComponentExportKind, u32)>,
+    },
+    FromExports(Vec<(String, ComponentExportKind, u32)>),
+}
+
+#[derive(Debug)]
+struct ExportSection {
+    exports: Vec<Export>,
+}
+
+#[derive(Debug)]
+struct Export {
+    name: String,
+    kind: ComponentExportKind,
+    index: u32,
+}
 
 #[derive(Debug)]
-struct ExportSection {}
+struct StartSection {
+    func_index: u32,
+    args: Vec<u32>,
+    results: u32,
+}
 
 #[derive(Debug)]
-struct StartSection {}
+struct AliasSection {
+    aliases: Vec<Alias>,
+}
 
 #[derive(Debug)]
-struct AliasSection {}
+struct CoreInstanceSection {
+    instances: Vec<CoreInstance>,
+}
 
 #[derive(Debug)]
-struct CoreInstanceSection {}
+enum CoreInstance {
+    Instantiate {
+        module: u32,
+        args: Vec<(String, u32)>,
+    },
+    FromExports(Vec<(String, wasm_encoder::ExportKind, u32)>),
+}
 
 #[derive(Debug)]
 struct CoreTypeSection {
@@ -31,7 +31,7 @@ impl Section {
                     data: &bytes,
                 });
             }
-            Self::CoreInstance(_) => todo!(),
+            Self::CoreInstance(sec) => sec.encode(component),
             Self::CoreType(sec) => sec.encode(component),
             Self::Component(comp) => {
                 let bytes = comp.to_bytes();
@@ -40,13 +40,13 @@ impl Section {
                     data: &bytes,
                 });
             }
-            Self::Instance(_) => todo!(),
-            Self::Alias(_) => todo!(),
+            Self::Instance(sec) => sec.encode(component),
+            Self::Alias(sec) => sec.encode(component),
             Self::Type(sec) => sec.encode(component),
             Self::Canonical(sec) => sec.encode(component),
-            Self::Start(_) => todo!(),
+            Self::Start(sec) => sec.encode(component),
             Self::Import(sec) => sec.encode(component),
-            Self::Export(_) => todo!(),
+            Self::Export(sec) => sec.encode(component),
         }
     }
 }
@@ -106,6 +106,87 @@ impl CanonicalSection {
     }
 }
 
+impl CoreInstanceSection {
+    fn encode(&self, component: &mut wasm_encoder::Component) {
+        let mut sec = wasm_encoder::InstanceSection::new();
+        for instance in &self.instances {
+            match instance {
+                CoreInstance::Instantiate { module, args } => {
+                    sec.instantiate(
+                        *module,
+                        args.iter().map(|(name, instance)| {
+                            (name.as_str(), wasm_encoder::ModuleArg::Instance(*instance))
+                        }),
+                    );
+                }
+                CoreInstance::FromExports(exports) => {
+                    sec.export_items(
+                        exports
+                            .iter()
+                            .map(|(name, kind, index)| (name.as_str(), *kind, *index)),
+                    );
+                }
+            }
+        }
+        component.section(&sec);
+    }
+}
+
+impl InstanceSection {
+    fn encode(&self, component: &mut wasm_encoder::Component) {
+        let mut sec = wasm_encoder::ComponentInstanceSection::new();
+        for instance in &self.instances {
+            match instance {
+                Instance::Instantiate { component, args } => {
+                    sec.instantiate(
+                        *component,
+                        args.iter()
+                            .map(|(name, kind, index)| (name.as_str(), *kind, *index)),
+                    );
+                }
+                Instance::FromExports(exports) => {
+                    sec.export_items(
+                        exports
+                            .iter()
+                            .map(|(name, kind, index)| (name.as_str(), *kind, *index)),
+                    );
+                }
+            }
+        }
+        component.section(&sec);
+    }
+}
+
+impl AliasSection {
+    fn encode(&self, component: &mut wasm_encoder::Component) {
+        let mut sec = wasm_encoder::ComponentAliasSection::new();
+        for alias in &self.aliases {
+            sec.alias(translate_alias(alias));
+        }
+        component.section(&sec);
+    }
+}
+
+impl StartSection {
+    fn encode(&self, component: &mut wasm_encoder::Component) {
+        component.section(&wasm_encoder::ComponentStartSection {
+            function_index: self.func_index,
+            args: &self.args,
+            results: self.results,
+        });
+    }
+}
+
+impl ExportSection {
+    fn encode(&self, component: &mut wasm_encoder::Component) {
+        let mut sec = wasm_encoder::ComponentExportSection::new();
+        for export in &self.exports {
+            sec.export(&export.name, export.kind, export.index, None);
+        }
+        component.section(&sec);
+    }
+}
+
 impl CoreTypeSection {
     fn encode(&self, component: &mut wasm_encoder::Component) {
         let mut sec = wasm_encoder::CoreTypeSection::new();
@@ -172,7 +253,7 @@ impl Type {
                 let mut f = enc.function();
 
                 f.params(func_ty.params.iter().map(|(name, ty)| (name.as_str(), *ty)));
-                f.result(func_ty.unnamed_result_ty());
+                f.result(func_ty.result);
             }
             Self::Component(comp_ty) => {
                 let mut enc_comp_ty = wasm_encoder::ComponentType::new();
@@ -217,6 +298,7 @@ impl Type {
                 }
                 enc.instance(&enc_inst_ty);
             }
+            Self::Resource => unreachable!("resources are only introduced by imports and exports"),
         }
     }
 }
@@ -253,6 +335,8 @@ impl DefinedType {
             Self::Result(ty) => {
                 enc.result(ty.ok_ty, ty.err_ty);
             }
+            Self::Own(idx) => enc.own(*idx),
+            Self::Borrow(idx) => enc.borrow(*idx),
         }
     }
 }
@@ -285,7 +369,6 @@ fn translate_alias(alias: &Alias) -> wasm_encoder::Alias<'_> {
                 InstanceExportAliasKind::Component => ComponentExportKind::Component,
                 InstanceExportAliasKind::Instance => ComponentExportKind::Instance,
                 InstanceExportAliasKind::Func => ComponentExportKind::Func,
-                InstanceExportAliasKind::Value => ComponentExportKind::Value,
             },
         },
         Alias::CoreInstanceExport {
@@ -300,7 +383,6 @@ fn translate_alias(alias: &Alias) -> wasm_encoder::Alias<'_> {
                 CoreInstanceExportAliasKind::Table => ExportKind::Table,
                 CoreInstanceExportAliasKind::Global => ExportKind::Global,
                 CoreInstanceExportAliasKind::Memory => ExportKind::Memory,
-                CoreInstanceExportAliasKind::Tag => ExportKind::Tag,
             },
         },
         Alias::Outer { count, i, kind } => wasm_encoder::Alias::Outer {
@@ -1,8 +1,9 @@
 //! Configuring the shape of generated Wasm modules.
 
-use crate::InstructionKinds;
+use crate::{InstructionKind, InstructionKinds};
 use anyhow::bail;
 use arbitrary::{Arbitrary, Result, Unstructured};
+use std::collections::BTreeMap;
 
 macro_rules! define_config {
     (
@@ -99,6 +100,52 @@ macro_rules! define_config {
             /// ```
             pub exports: Option<Vec<u8>>,
 
+            /// If provided, the generated module is the given WebAssembly
+            /// module with each of its holes filled in with random, well-typed
+            /// code. The module's types, imports, exports and everything else
+            /// are taken from the template as-is, and the other settings
+            /// describing the shape of the module are ignored.
+            ///
+            /// A hole is a `block` whose label is named `hole` or `hole:N` in
+            /// the module's `name` section, where `N` is the maximum number of
+            /// instructions to generate for it. It defaults to
+            /// [`Self::max_instructions`]. The block's body is replaced with
+            /// code of the block's type, which may use the function's locals
+            /// and branch to any enclosing label. A hole without a block type
+            /// which is directly followed by the `end` (or `else`) of its
+            /// enclosing block instead consumes that block's operands and
+            /// produces its results, so that it can make up the rest of the
+            /// block.
+            ///
+            /// Defaults to `None` which means the whole module is generated.
+            ///
+            /// The provided value must be a valid binary encoding of a
+            /// WebAssembly module, apart from the bodies of its holes.
+            /// `wasm-smith` will panic if the module cannot be parsed or isn't
+            /// valid with the enabled proposals.
+            ///
+            /// # Example
+            ///
+            /// Templates are most easily written with the `(hole N)` shorthand
+            /// which [`expand_holes`][crate::expand_holes] turns into the
+            /// blocks above, which can then be parsed with the `wat` crate:
+            ///
+            /// ```rust
+            /// Some(wat::parse_str(wasm_smith::expand_holes(r#"
+            ///     (module
+            ///         (func (export "f") (param i32) (result i32)
+            ///             (hole 50))
+            ///         (func (export "g") (param i64) (result i64)
+            ///             (loop $l (param i64) (result i64)
+            ///                 (hole 10 (param i64) (result i64 i32))
+            ///                 br_if $l)
+            ///             i64.const 1
+            ///             i64.add)
+            ///     )
+            /// "#)));
+            /// ```
+            pub module_template: Option<Vec<u8>>,
+
             $(
                 $(#[$field_attr])*
                 pub $field: $field_ty,
@@ -110,6 +157,7 @@ macro_rules! define_config {
                 Config {
                     available_imports: None,
                     exports: None,
+                    module_template: None,
 
                     $(
                         $field: $default,
@@ -173,6 +221,18 @@ macro_rules! define_config {
             #[cfg_attr(feature = "clap", clap(long))]
             exports: Option<std::path::PathBuf>,
 
+            /// If provided, the generated module is the given template in the
+            /// WebAssembly text format with each of its `(hole N)`s filled in
+            /// with random, well-typed code of up to `N` instructions.
+            ///
+            /// A hole may be given a block type, as in `(hole 50 (param i32)
+            /// (result i64))`, and defaults to `[] -> []`. Holes without a
+            /// type at the end of a block instead produce that block's
+            /// results. Everything else in the module is taken from the
+            /// template as-is.
+            #[cfg_attr(feature = "clap", clap(long))]
+            module_template: Option<std::path::PathBuf>,
+
             $(
                 $(#[$field_attr])*
                 #[cfg_attr(feature = "clap", clap(long))]
@@ -185,6 +245,7 @@ macro_rules! define_config {
                 Self {
                     available_imports: self.available_imports.or(other.available_imports),
                     exports: self.exports.or(other.exports),
+                    module_template: self.module_template.or(other.module_template),
 
                     $(
                         $field: self.$field.or(other.$field),
@@ -213,6 +274,19 @@ macro_rules! define_config {
                         } else {
                             None
                         },
+                    module_template: if let Some(file) = config
+                        .module_template
+                        .as_ref() {
+                            let template = std::fs::read_to_string(file).map_err(|e| {
+                                anyhow::anyhow!("failed to read `{}`: {e}", file.display())
+                            })?;
+                            Some(wat::Parser::new().parse_str(
+                                Some(file.as_path()),
+                                crate::expand_holes(&template),
+                            )?)
+                        } else {
+                            None
+                        },
 
                     $(
                         $field: config.$field.unwrap_or(default.$field),
@@ -230,9 +304,13 @@ macro_rules! define_config {
                 if config.exports.is_some() {
                     bail!("cannot serialize configuration with `exports`");
                 }
+                if config.module_template.is_some() {
+                    bail!("cannot serialize configuration with `module_template`");
+                }
                 Ok(InternalOptionalConfig {
                     available_imports: None,
                     exports: None,
+                    module_template: None,
                     $( $field: Some(config.$field.clone()), )*
                 })
             }
@@ -256,6 +334,10 @@ define_config! {
     /// testing]. This will dynamically -- but still deterministically -- choose
     /// configuration options for you.
     ///
+    /// Alternatively [`Config::swarm`] starts from an existing configuration
+    /// and randomly disables some of its proposals, instruction kinds and
+    /// section kinds, which keeps the rest of that configuration intact.
+    ///
     /// [swarm testing]: https://www.cs.utah.edu/~regehr/papers/swarm12.pdf
     ///
     /// Note that we pick only *maximums*, not minimums, here because it is more
@@ -313,6 +395,14 @@ define_config! {
         /// instructions.
         pub canonicalize_nans: bool = false,
 
+        /// Determines whether the component model's value imports, exports and
+        /// start functions may be used when generating components.
+        ///
+        /// Currently this only enables generating component start sections.
+        ///
+        /// Defaults to `false`.
+        pub component_model_values_enabled: bool = false,
+
         /// Returns whether we should avoid generating code that will possibly
         /// trap.
         ///
@@ -359,6 +449,31 @@ define_config! {
         /// to false.
         pub generate_custom_sections: bool = false,
 
+        /// Determines whether a `name` custom section is generated for
+        /// modules, naming the module along with some of its functions,
+        /// locals, labels, types and struct fields.
+        ///
+        /// Defaults to `false`.
+        pub generate_names: bool = false,
+
+        /// Determines whether a `metadata.code.branch_hint` custom section is
+        /// generated, hinting whether some of the `if` and `br_if`
+        /// instructions in function bodies are likely to be taken.
+        ///
+        /// Defaults to `false`.
+        pub generate_branch_hints: bool = false,
+
+        /// Relative weights for how often instructions are generated, by
+        /// [`InstructionKind`](crate::InstructionKind) or by name.
+        ///
+        /// This can be used to focus generated modules on particular
+        /// instructions, such as the ones a given compiler target lowers in an
+        /// unusual way. See [`InstructionWeights`] for details.
+        ///
+        /// Defaults to weighting every instruction equally, apart from a few
+        /// built-in adjustments such as generating `unreachable` rarely.
+        pub instruction_weights: InstructionWeights = InstructionWeights::default(),
+
         /// Returns the maximal size of the `alias` section. Defaults to 1000.
         pub max_aliases: usize = 1000,
 
@@ -607,6 +722,20 @@ define_config! {
         /// Defaults to `true`.
         pub simd_enabled: bool = true,
 
+        /// Determines whether the [stack-switching proposal] is enabled.
+        ///
+        /// This enables generating continuation types, `contref` and
+        /// `nocontref` reference types, tags with results, and the
+        /// `cont.new`, `cont.bind`, `suspend`, `resume`, `resume_throw` and
+        /// `switch` instructions. It builds on the [GC proposal], so it is
+        /// ignored unless [`Config::gc_enabled`] is also set.
+        ///
+        /// [stack-switching proposal]: https://github.com/WebAssembly/stack-switching
+        /// [GC proposal]: https://github.com/WebAssembly/gc
+        ///
+        /// Defaults to `false`.
+        pub stack_switching_enabled: bool = false,
+
         /// Determines whether the tail calls proposal is enabled for generating
         /// instructions.
         ///
@@ -713,6 +842,128 @@ impl std::str::FromStr for MemoryOffsetChoices {
     }
 }
 
+/// Relative weights for choosing which instruction to generate next.
+///
+/// Each entry maps either an instruction kind, named as in
+/// [`InstructionKinds`]'s `FromStr` implementation (e.g. `vector` or
+/// `numeric_non_float`), or a single instruction, named as in the text format
+/// (e.g. `i8x16.shuffle` or `i64.div_s`), to a weight. An instruction's
+/// weight is the product of the weights of every entry that applies to it,
+/// and instructions without any entries have a weight of 1. A weight of 0
+/// means the instruction is never generated.
+///
+/// Among the instructions which are valid at some point in a function body,
+/// each is then chosen with probability proportional to its weight.
+///
+/// Only instructions which `wasm-smith` can generate may be given a weight,
+/// and [`InstructionWeights::set`] rejects any other name. Notably this
+/// excludes the atomic instructions of the threads proposal and the legacy
+/// exception handling instructions, which are never generated. The signed and
+/// unsigned variants of `array.get`, `struct.get` and `i31.get` are generated
+/// together, so a weight given to either one applies to both.
+///
+/// # Example
+///
+/// ```
+/// use wasm_smith::InstructionWeights;
+///
+/// // Generate integer SIMD instructions, and shuffles in particular, as well
+/// // as 64-bit division far more often than other instructions, and never
+/// // generate `memory.grow`.
+/// let mut weights = InstructionWeights::default();
+/// weights.set("vector_non_float", 10).unwrap();
+/// weights.set("i8x16.shuffle", 5).unwrap();
+/// weights.set("i64.div_s", 20).unwrap();
+/// weights.set("i64.div_u", 20).unwrap();
+/// weights.set("memory.grow", 0).unwrap();
+/// # assert!(weights.set("i65.div_s", 1).is_err());
+/// # assert!(weights.set("array.get_u", 2).is_ok());
+/// # assert!(weights.set("i32.atomic.rmw.add", 2).is_err());
+/// ```
+///
+/// The same weights can be given in `wasm-tools smith`'s JSON configuration
+/// file:
+///
+/// ```json
+/// {
+///     "instruction-weights": {
+///         "vector_non_float": 10,
+///         "i8x16.shuffle": 5,
+///         "i64.div_s": 20,
+///         "i64.div_u": 20,
+///         "memory.grow": 0
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_derive::Deserialize, serde_derive::Serialize),
+    serde(try_from = "BTreeMap<String, u32>", into = "BTreeMap<String, u32>")
+)]
+pub struct InstructionWeights(BTreeMap<String, u32>);
+
+impl InstructionWeights {
+    /// Sets the weight of the instruction kind or instruction called `name`.
+    ///
+    /// Returns an error if `name` is neither, or names an instruction which is
+    /// never generated.
+    pub fn set(&mut self, name: &str, weight: u32) -> Result<(), String> {
+        if name.parse::<InstructionKind>().is_err() && !crate::core::is_weighted_instruction(name) {
+            return Err(format!("unknown instruction or instruction kind: {name}"));
+        }
+        self.0.insert(name.to_string(), weight);
+        Ok(())
+    }
+
+    /// The weights given to instruction kinds.
+    pub(crate) fn kinds(&self) -> impl Iterator<Item = (InstructionKind, u32)> + '_ {
+        self.0
+            .iter()
+            .filter_map(|(name, weight)| Some((name.parse().ok()?, *weight)))
+    }
+
+    /// The weights given to individual instructions.
+    pub(crate) fn instructions(&self) -> impl Iterator<Item = (&str, u32)> + '_ {
+        self.0
+            .iter()
+            .filter(|(name, _)| name.parse::<InstructionKind>().is_err())
+            .map(|(name, weight)| (name.as_str(), *weight))
+    }
+}
+
+impl TryFrom<BTreeMap<String, u32>> for InstructionWeights {
+    type Error = String;
+    fn try_from(weights: BTreeMap<String, u32>) -> Result<Self, Self::Error> {
+        let mut result = InstructionWeights::default();
+        for (name, weight) in weights {
+            result.set(&name, weight)?;
+        }
+        Ok(result)
+    }
+}
+
+impl From<InstructionWeights> for BTreeMap<String, u32> {
+    fn from(weights: InstructionWeights) -> Self {
+        weights.0
+    }
+}
+
+impl std::str::FromStr for InstructionWeights {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut weights = InstructionWeights::default();
+        for part in s.split(",") {
+            let (name, weight) = part
+                .split_once("=")
+                .ok_or_else(|| format!("expected `name=weight`, found `{part}`"))?;
+            let weight = <u32 as std::str::FromStr>::from_str(weight).map_err(|e| e.to_string())?;
+            weights.set(name, weight)?;
+        }
+        Ok(weights)
+    }
+}
+
 impl<'a> Arbitrary<'a> for Config {
     fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
         const MAX_MAXIMUM: usize = 1000;
@@ -782,20 +1033,26 @@ impl<'a> Arbitrary<'a> for Config {
             max_modules: 0,
             max_components: 0,
             max_values: 0,
+            component_model_values_enabled: false,
             memory_offset_choices: MemoryOffsetChoices::default(),
+            instruction_weights: InstructionWeights::default(),
             allow_start_export: true,
             max_type_size: 1000,
             canonicalize_nans: false,
             available_imports: None,
             exports: None,
+            module_template: None,
             export_everything: false,
             generate_custom_sections: false,
+            generate_names: false,
+            generate_branch_hints: false,
             allow_invalid_funcs: false,
 
             // Proposals that are not stage4+ are disabled by default.
             custom_page_sizes_enabled: false,
             wide_arithmetic_enabled: false,
             shared_everything_threads_enabled: false,
+            stack_switching_enabled: false,
         };
         config.sanitize();
         Ok(config)
@@ -803,6 +1060,88 @@ impl<'a> Arbitrary<'a> for Config {
 }
 
 impl Config {
+    /// Derives a [swarm testing] configuration from this one.
+    ///
+    /// Starting from `self`, each enabled proposal, each allowed
+    /// [`InstructionKind`](crate::InstructionKind) and each kind of module
+    /// section is independently either kept or disabled based on `u`. Modules
+    /// generated with the returned configuration then only use the surviving
+    /// features, so a series of test cases each explore a different corner of
+    /// the feature space instead of all mixing every feature together.
+    ///
+    /// This never enables anything that `self` doesn't already enable. Types
+    /// and functions are always kept, as are sections with a nonzero minimum
+    /// or that are needed to satisfy [`Config::exports`] or
+    /// [`Config::available_imports`].
+    ///
+    /// [swarm testing]: https://www.cs.utah.edu/~regehr/papers/swarm12.pdf
+    pub fn swarm(&self, u: &mut Unstructured<'_>) -> Result<Config> {
+        let mut config = self.clone();
+
+        for enabled in [
+            &mut config.allow_floats,
+            &mut config.bulk_memory_enabled,
+            &mut config.custom_page_sizes_enabled,
+            &mut config.exceptions_enabled,
+            &mut config.extended_const_enabled,
+            &mut config.gc_enabled,
+            &mut config.memory64_enabled,
+            &mut config.multi_value_enabled,
+            &mut config.reference_types_enabled,
+            &mut config.relaxed_simd_enabled,
+            &mut config.saturating_float_to_int_enabled,
+            &mut config.shared_everything_threads_enabled,
+            &mut config.sign_extension_ops_enabled,
+            &mut config.simd_enabled,
+            &mut config.stack_switching_enabled,
+            &mut config.tail_call_enabled,
+            &mut config.threads_enabled,
+            &mut config.wide_arithmetic_enabled,
+        ] {
+            *enabled = *enabled && u.arbitrary()?;
+        }
+
+        use flagset::Flags;
+        let mut allowed = Vec::new();
+        for kind in crate::core::InstructionKind::LIST {
+            if config.allowed_instructions.contains(*kind) && u.arbitrary()? {
+                allowed.push(*kind);
+            }
+        }
+        config.allowed_instructions = InstructionKinds::new(&allowed);
+
+        // Required exports may refer to any kind of item, so section kinds
+        // are only disabled when there aren't any.
+        if config.exports.is_none() {
+            // Available imports are always imported, so treat them like a
+            // minimum.
+            let min_imports = match config.available_imports {
+                Some(_) => config.min_imports.max(1),
+                None => config.min_imports,
+            };
+            for (min, max) in [
+                (min_imports, &mut config.max_imports),
+                (config.min_tags, &mut config.max_tags),
+                (config.min_globals, &mut config.max_globals),
+                (config.min_exports, &mut config.max_exports),
+                (
+                    config.min_element_segments,
+                    &mut config.max_element_segments,
+                ),
+                (config.min_data_segments, &mut config.max_data_segments),
+                (config.min_memories as usize, &mut config.max_memories),
+                (config.min_tables as usize, &mut config.max_tables),
+            ] {
+                if min == 0 && u.arbitrary()? {
+                    *max = 0;
+                }
+            }
+        }
+
+        config.sanitize();
+        Ok(config)
+    }
+
     /// "Shrink" this `Config` where appropriate to ensure its configuration is
     /// valid for wasm-smith.
     ///
@@ -824,10 +1163,11 @@ impl Config {
             self.shared_everything_threads_enabled = false;
         }
 
-        // shared-everything-threads depends on GC, so if gc is disabled then
-        // also disable shared-everything-threads.
+        // shared-everything-threads and stack switching depend on GC, so if gc
+        // is disabled then also disable both of them.
         if !self.gc_enabled {
             self.shared_everything_threads_enabled = false;
+            self.stack_switching_enabled = false;
         }
 
         // If simd is disabled then disable all relaxed simd instructions as
@@ -886,6 +1226,7 @@ impl Config {
         );
         features.set(WasmFeatures::EXTENDED_CONST, self.extended_const_enabled);
         features.set(WasmFeatures::WIDE_ARITHMETIC, self.wide_arithmetic_enabled);
+        features.set(WasmFeatures::STACK_SWITCHING, self.stack_switching_enabled);
 
         features
     }
@@ -2,11 +2,13 @@
 
 mod code_builder;
 pub(crate) mod encode;
+mod template;
 mod terminate;
 
 use crate::{Config, arbitrary_loop, limited_string, unique_string};
 use arbitrary::{Arbitrary, Result, Unstructured};
 use code_builder::CodeBuilderAllocations;
+pub(crate) use code_builder::is_weighted_instruction;
 use flagset::{FlagSet, flags};
 use std::collections::{HashMap, HashSet};
 use std::fmt;
@@ -20,6 +22,8 @@ use wasm_encoder::{
 };
 pub(crate) use wasm_encoder::{GlobalType, MemoryType, TableType};
 
+pub use template::expand_holes;
+
 // NB: these constants are used to control the rate at which various events
 // occur. For more information see where these constants are used. Their values
 // are somewhat random in the sense that they're not scientifically determined
@@ -88,6 +92,13 @@ pub struct Module {
     /// Indices within `types that are struct types.
     struct_types: Vec<u32>,
 
+    /// Indices within `types` that are continuation types.
+    cont_types: Vec<u32>,
+
+    /// Whether a non-null reference to each of `types` can be created with
+    /// constant instructions, see `heap_type_is_constructible`.
+    constructible_types: Vec<bool>,
+
     /// Number of imported items into this module.
     num_imports: usize,
 
@@ -137,6 +148,9 @@ pub struct Module {
     code: Vec<Code>,
     data: Vec<DataSegment>,
 
+    /// The contents of this module's `name` section, if one is generated.
+    names: Option<Names>,
+
     /// The predicted size of the effective type of this module, based on this
     /// module's size of the types of imports/exports.
     type_size: u32,
@@ -212,6 +226,42 @@ impl Module {
         Ok(module)
     }
 
+    /// The imports of this module.
+    #[cfg(feature = "component-model")]
+    pub(crate) fn imports(&self) -> &[Import] {
+        &self.imports
+    }
+
+    /// The exports of this module along with the type of each exported
+    /// entity.
+    #[cfg(feature = "component-model")]
+    pub(crate) fn export_types(&self) -> impl Iterator<Item = (&str, EntityType)> + '_ {
+        self.exports.iter().map(|(name, kind, idx)| {
+            let ty = match kind {
+                ExportKind::Func => {
+                    let (type_idx, ty) = &self.funcs[*idx as usize];
+                    EntityType::Func(*type_idx, ty.clone())
+                }
+                _ => self.type_of(*kind, *idx),
+            };
+            (name.as_str(), ty)
+        })
+    }
+
+    /// Whether the type at `idx` is a final, unshared function type with no
+    /// supertype in a rec group of its own, and is therefore equivalent to
+    /// any other such function type with the same signature.
+    #[cfg(feature = "component-model")]
+    pub(crate) fn is_canonical_func_type(&self, idx: u32) -> bool {
+        let idx = idx as usize;
+        let ty = &self.types[idx];
+        matches!(ty.composite_type.inner, CompositeInnerType::Func(_))
+            && ty.is_final
+            && ty.supertype.is_none()
+            && !ty.composite_type.shared
+            && self.rec_groups.iter().any(|group| *group == (idx..idx + 1))
+    }
+
     fn empty(mut config: Config, duplicate_imports_behavior: DuplicateImportsBehavior) -> Self {
         config.sanitize();
         Module {
@@ -228,6 +278,8 @@ impl Module {
             array_types: Vec::new(),
             func_types: Vec::new(),
             struct_types: Vec::new(),
+            cont_types: Vec::new(),
+            constructible_types: Vec::new(),
             num_imports: 0,
             num_defined_tags: 0,
             num_defined_funcs: 0,
@@ -244,6 +296,7 @@ impl Module {
             elems: Vec::new(),
             code: Vec::new(),
             data: Vec::new(),
+            names: None,
             type_size: 0,
             export_names: HashSet::new(),
             const_expr_choices: Vec::new(),
@@ -277,6 +330,10 @@ impl SubType {
     fn unwrap_array(&self) -> &ArrayType {
         self.composite_type.unwrap_array()
     }
+
+    fn unwrap_cont(&self) -> u32 {
+        self.composite_type.unwrap_cont()
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -314,6 +371,13 @@ impl CompositeType {
             _ => panic!("not a struct"),
         }
     }
+
+    fn unwrap_cont(&self) -> u32 {
+        match &self.inner {
+            CompositeInnerType::Cont(f) => *f,
+            _ => panic!("not a cont"),
+        }
+    }
 }
 
 impl From<&CompositeType> for wasm_encoder::CompositeType {
@@ -324,6 +388,9 @@ impl From<&CompositeType> for wasm_encoder::CompositeType {
                 wasm_encoder::FuncType::new(f.params.iter().cloned(), f.results.iter().cloned()),
             ),
             CompositeInnerType::Struct(s) => wasm_encoder::CompositeInnerType::Struct(s.clone()),
+            CompositeInnerType::Cont(f) => {
+                wasm_encoder::CompositeInnerType::Cont(wasm_encoder::ContType(*f))
+            }
         };
         wasm_encoder::CompositeType {
             shared: ty.shared,
@@ -337,6 +404,8 @@ pub(crate) enum CompositeInnerType {
     Array(ArrayType),
     Func(Rc<FuncType>),
     Struct(StructType),
+    /// A continuation type, holding the index of its function type.
+    Cont(u32),
 }
 
 /// A function signature.
@@ -410,6 +479,10 @@ enum Elements {
 struct Code {
     locals: Vec<ValType>,
     instructions: Instructions,
+
+    /// Hints for whether branches are likely to be taken, keyed by the
+    /// ordinal of the `if` or `br_if` instruction within `instructions`.
+    branch_hints: Vec<(u32, bool)>,
 }
 
 #[derive(Debug)]
@@ -418,6 +491,17 @@ enum Instructions {
     Arbitrary(Vec<u8>),
 }
 
+/// Names for a subset of each index space, sorted by index.
+#[derive(Debug, Default)]
+struct Names {
+    module: Option<String>,
+    funcs: Vec<(u32, String)>,
+    locals: Vec<(u32, Vec<(u32, String)>)>,
+    labels: Vec<(u32, Vec<(u32, String)>)>,
+    types: Vec<(u32, String)>,
+    fields: Vec<(u32, Vec<(u32, String)>)>,
+}
+
 #[derive(Debug)]
 struct DataSegment {
     kind: DataSegmentKind,
@@ -435,12 +519,21 @@ pub(crate) enum Offset {
     Const32(i32),
     Const64(i64),
     Global(u32),
+    /// An arbitrary constant expression, taken from a module template.
+    #[cfg(feature = "wasmparser")]
+    Expr(ConstExpr),
 }
 
 impl Module {
     fn build(&mut self, u: &mut Unstructured) -> Result<()> {
         self.valtypes = configured_valtypes(&self.config);
 
+        // A template fixes everything about the module apart from the code in
+        // its holes.
+        if self.build_from_template(u)? {
+            return Ok(());
+        }
+
         // We attempt to figure out our available imports *before* creating the types section here,
         // because the types for the imports are already well-known (specified by the user) and we
         // must have those populated for all function/etc. imports, no matter what.
@@ -468,6 +561,7 @@ impl Module {
         self.arbitrary_elems(u)?;
         self.arbitrary_data(u)?;
         self.arbitrary_code(u)?;
+        self.arbitrary_names(u)?;
         Ok(())
     }
 
@@ -480,6 +574,14 @@ impl Module {
         }
     }
 
+    /// Is each of `a` a subtype of the corresponding type in `b`?
+    fn val_types_are_sub_types(&self, a: &[ValType], b: &[ValType]) -> bool {
+        a.len() == b.len()
+            && a.iter()
+                .zip(b)
+                .all(|(a, b)| self.val_type_is_sub_type(*a, *b))
+    }
+
     /// Is `a` a subtype of `b`?
     fn ref_type_is_sub_type(&self, a: RefType, b: RefType) -> bool {
         if a == b {
@@ -518,6 +620,7 @@ impl Module {
                         (NoFunc, Func) => true,
                         (None, I31 | Array | Struct) => true,
                         (NoExn, Exn) => true,
+                        (NoCont, Cont) => true,
                         _ => false,
                     }
             }
@@ -532,6 +635,7 @@ impl Module {
                     Struct => matches!(a_ty.inner, CT::Struct(_)),
                     Array => matches!(a_ty.inner, CT::Array(_)),
                     Func => matches!(a_ty.inner, CT::Func(_)),
+                    Cont => matches!(a_ty.inner, CT::Cont(_)),
                     _ => false,
                 }
             }
@@ -544,6 +648,7 @@ impl Module {
                 match ty {
                     None => matches!(b_ty.inner, CT::Array(_) | CT::Struct(_)),
                     NoFunc => matches!(b_ty.inner, CT::Func(_)),
+                    NoCont => matches!(b_ty.inner, CT::Cont(_)),
                     _ => false,
                 }
             }
@@ -579,6 +684,10 @@ impl Module {
     fn add_type(&mut self, ty: SubType) -> u32 {
         let index = u32::try_from(self.types.len()).unwrap();
 
+        // This must happen before the type is added to any of the lists below,
+        // since it may look at other types through them.
+        let constructible = self.composite_type_is_constructible(&ty.composite_type);
+
         if let Some(supertype) = ty.supertype {
             assert_eq!(self.is_shared_type(supertype), ty.composite_type.shared);
             self.super_to_sub_types
@@ -591,6 +700,7 @@ impl Module {
             CompositeInnerType::Array(_) => &mut self.array_types,
             CompositeInnerType::Func(_) => &mut self.func_types,
             CompositeInnerType::Struct(_) => &mut self.struct_types,
+            CompositeInnerType::Cont(_) => &mut self.cont_types,
         };
         list.push(index);
 
@@ -606,10 +716,60 @@ impl Module {
             self.can_subtype.push(index);
         }
 
+        self.constructible_types.push(constructible);
         self.types.push(ty);
         index
     }
 
+    /// Can a non-null reference to `ty` be created from scratch with constant
+    /// instructions?
+    ///
+    /// Non-nullable reference types are only generated for heap types where
+    /// this holds, which guarantees that there's always some way to produce a
+    /// value of them, for example in a global's initializer or when filling in
+    /// the results of a block. Function and continuation references can only
+    /// be created from existing functions, so they are never considered
+    /// constructible, and neither are the bottom types which have no values.
+    fn heap_type_is_constructible(&self, ty: HeapType) -> bool {
+        use AbstractHeapType::*;
+        match ty {
+            HeapType::Abstract { shared, ty } => match ty {
+                Any | Eq | I31 | Extern => true,
+                Struct => self.struct_types.iter().any(|&i| {
+                    self.constructible_types[i as usize] && self.is_shared_type(i) == shared
+                }),
+                Array => self
+                    .array_types
+                    .iter()
+                    .any(|&i| self.is_shared_type(i) == shared),
+                Func | Exn | Cont | None | NoFunc | NoExtern | NoExn | NoCont => false,
+            },
+            // Types which aren't defined yet, such as later types in the rec
+            // group currently being generated, are conservatively not
+            // constructible. This also means that non-null references only
+            // ever point to earlier types, so construction always terminates.
+            HeapType::Concrete(i) => self
+                .constructible_types
+                .get(i as usize)
+                .copied()
+                .unwrap_or(false),
+        }
+    }
+
+    fn composite_type_is_constructible(&self, ty: &CompositeType) -> bool {
+        match &ty.inner {
+            // `array.new_fixed` with no elements can always create an array.
+            CompositeInnerType::Array(_) => true,
+            CompositeInnerType::Struct(s) => s.fields.iter().all(|f| match f.element_type {
+                StorageType::Val(ValType::Ref(r)) => {
+                    r.nullable || self.heap_type_is_constructible(r.heap_type)
+                }
+                _ => true,
+            }),
+            CompositeInnerType::Func(_) | CompositeInnerType::Cont(_) => false,
+        }
+    }
+
     fn arbitrary_rec_group(
         &mut self,
         u: &mut Unstructured,
@@ -697,7 +857,7 @@ impl Module {
             });
         }
 
-        if !self.can_subtype.is_empty() && u.ratio(1, 32_u8)? {
+        if !self.can_subtype.is_empty() && u.ratio(1, 8_u8)? {
             self.arbitrary_sub_type_of_super_type(u)
         } else {
             Ok(SubType {
@@ -710,7 +870,14 @@ impl Module {
     }
 
     fn arbitrary_sub_type_of_super_type(&mut self, u: &mut Unstructured) -> Result<SubType> {
-        let supertype = *u.choose(&self.can_subtype)?;
+        // Half of the time extend the most recently defined type that can be
+        // subtyped, which builds up deep chains of subtypes for casts to
+        // exercise rather than only wide and shallow hierarchies.
+        let supertype = if u.arbitrary()? {
+            *self.can_subtype.last().unwrap()
+        } else {
+            *u.choose(&self.can_subtype)?
+        };
         let mut composite_type = self.types[usize::try_from(supertype).unwrap()]
             .composite_type
             .clone();
@@ -726,6 +893,9 @@ impl Module {
                     m.arbitrary_matching_struct_type(u, s)
                 })?;
             }
+            CompositeInnerType::Cont(f) => {
+                *f = self.arbitrary_matching_cont_func_type(u, *f)?;
+            }
         }
         Ok(SubType {
             is_final: u.arbitrary()?,
@@ -735,6 +905,15 @@ impl Module {
         })
     }
 
+    /// Continuation types are subtypes of each other when their function
+    /// types are, so pick either the same function type or one of its
+    /// subtypes.
+    fn arbitrary_matching_cont_func_type(&self, u: &mut Unstructured, ty: u32) -> Result<u32> {
+        let mut choices = vec![ty];
+        choices.extend(self.transitive_sub_types(ty));
+        Ok(*u.choose(&choices)?)
+    }
+
     fn arbitrary_matching_struct_type(
         &mut self,
         u: &mut Unstructured,
@@ -796,17 +975,32 @@ impl Module {
     }
 
     fn arbitrary_matching_ref_type(&self, u: &mut Unstructured, ty: RefType) -> Result<RefType> {
+        let mut choices = self.matching_heap_types(ty.heap_type);
+        // A non-null reference must remain constructible, so only keep the
+        // original heap type or its constructible subtypes.
+        if !ty.nullable {
+            choices.retain(|h| *h == ty.heap_type || self.heap_type_is_constructible(*h));
+        }
+        let heap_type = *u.choose(&choices)?;
         Ok(RefType {
-            nullable: ty.nullable,
-            heap_type: self.arbitrary_matching_heap_type(u, ty.heap_type)?,
+            nullable: ty.nullable
+                && (!self.config.gc_enabled
+                    || !self.heap_type_is_constructible(heap_type)
+                    || u.arbitrary()?),
+            heap_type,
         })
     }
 
     fn arbitrary_matching_heap_type(&self, u: &mut Unstructured, ty: HeapType) -> Result<HeapType> {
+        Ok(*u.choose(&self.matching_heap_types(ty))?)
+    }
+
+    /// Returns `ty` along with all of its subtypes.
+    fn matching_heap_types(&self, ty: HeapType) -> Vec<HeapType> {
         use {AbstractHeapType as AHT, CompositeInnerType as CT, HeapType as HT};
 
         if !self.config.gc_enabled {
-            return Ok(ty);
+            return vec![ty];
         }
 
         let mut choices = vec![ty];
@@ -853,13 +1047,15 @@ impl Module {
                     Extern => {
                         add_abstract(&mut choices, &[NoExtern]);
                     }
-                    Exn | NoExn | None | NoExtern | NoFunc | Cont | NoCont => {}
+                    Cont => {
+                        add_abstract(&mut choices, &[NoCont]);
+                        add_concrete(&mut choices, &self.cont_types);
+                    }
+                    Exn | NoExn | None | NoExtern | NoFunc | NoCont => {}
                 }
             }
             HT::Concrete(idx) => {
-                if let Some(subs) = self.super_to_sub_types.get(&idx) {
-                    choices.extend(subs.iter().copied().map(HT::Concrete));
-                }
+                choices.extend(self.transitive_sub_types(idx).map(HT::Concrete));
                 match self
                     .types
                     .get(usize::try_from(idx).unwrap())
@@ -873,6 +1069,10 @@ impl Module {
                         shared,
                         ty: AbstractHeapType::NoFunc,
                     }),
+                    Some((shared, CT::Cont(_))) => choices.push(HT::Abstract {
+                        shared,
+                        ty: AbstractHeapType::NoCont,
+                    }),
                     None => {
                         // The referenced type might be part of this same rec
                         // group we are currently generating, but not generated
@@ -883,7 +1083,21 @@ impl Module {
                 }
             }
         }
-        Ok(*u.choose(&choices)?)
+        choices
+    }
+
+    /// Iterates over all strict subtypes of the type at `idx`, however deeply
+    /// nested in the subtyping hierarchy they are.
+    fn transitive_sub_types(&self, idx: u32) -> impl Iterator<Item = u32> + '_ {
+        let mut stack = vec![idx];
+        std::iter::from_fn(move || {
+            let idx = stack.pop()?;
+            if let Some(subs) = self.super_to_sub_types.get(&idx) {
+                stack.extend(subs.iter().copied());
+            }
+            Some(idx)
+        })
+        .skip(1)
     }
 
     fn arbitrary_matching_func_type(
@@ -925,15 +1139,12 @@ impl Module {
         u: &mut Unstructured,
         ty: RefType,
     ) -> Result<RefType> {
+        let heap_type = self.arbitrary_super_type_of_heap_type(u, ty.heap_type)?;
         Ok(RefType {
-            // TODO: For now, only create allow nullable reference
-            // types. Eventually we should support non-nullable reference types,
-            // but this means that we will also need to recognize when it is
-            // impossible to create an instance of the reference (eg `(ref
-            // nofunc)` has no instances, and self-referential types that
-            // contain a non-null self-reference are also impossible to create).
-            nullable: true,
-            heap_type: self.arbitrary_super_type_of_heap_type(u, ty.heap_type)?,
+            nullable: ty.nullable
+                || !self.heap_type_is_constructible(heap_type)
+                || u.arbitrary()?,
+            heap_type,
         })
     }
 
@@ -987,6 +1198,7 @@ impl Module {
                     }
                     NoCont => {
                         add_abstract(&mut choices, &[Cont]);
+                        add_concrete(&mut choices, &self.cont_types);
                     }
                     Exn | Any | Func | Extern | Cont => {}
                 }
@@ -1008,6 +1220,9 @@ impl Module {
                         CT::Struct(_) => {
                             choices.extend([ht(Any), ht(Eq), ht(Struct)]);
                         }
+                        CT::Cont(_) => {
+                            choices.push(ht(Cont));
+                        }
                     }
                 } else {
                     // Same as in `arbitrary_matching_heap_type`: this was a
@@ -1041,7 +1256,20 @@ impl Module {
             });
         }
 
-        match u.int_in_range(0..=2)? {
+        // Continuation types may only refer to function types which have
+        // already been defined.
+        let cont_func_types = if self.config.stack_switching_enabled && !shared {
+            self.func_types
+                .iter()
+                .copied()
+                .filter(|&idx| !self.is_shared_type(idx))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let max_choice = if cont_func_types.is_empty() { 2 } else { 3 };
+
+        match u.int_in_range(0..=max_choice)? {
             0 => Ok(CompositeType {
                 shared,
                 inner: CT::Array(ArrayType(
@@ -1056,6 +1284,10 @@ impl Module {
                 shared,
                 inner: CT::Struct(self.propagate_shared(shared, |m| m.arbitrary_struct_type(u))?),
             }),
+            3 => Ok(CompositeType {
+                shared,
+                inner: CT::Cont(*u.choose(&cont_func_types)?),
+            }),
             _ => unreachable!(),
         }
     }
@@ -1091,9 +1323,12 @@ impl Module {
         if !self.config.reference_types_enabled {
             return Ok(RefType::FUNCREF);
         }
+        let heap_type = self.arbitrary_heap_type(u)?;
         Ok(RefType {
-            nullable: true,
-            heap_type: self.arbitrary_heap_type(u)?,
+            nullable: !self.config.gc_enabled
+                || !self.heap_type_is_constructible(heap_type)
+                || u.arbitrary()?,
+            heap_type,
         })
     }
 
@@ -1133,6 +1368,9 @@ impl Module {
                     .copied(),
             );
         }
+        if self.config.stack_switching_enabled {
+            choices.extend([Cont, NoCont]);
+        }
 
         Ok(HeapType::Abstract {
             shared: self.arbitrary_shared(u)?,
@@ -1495,6 +1733,10 @@ impl Module {
         }
     }
 
+    fn cont_func_type(&self, idx: u32) -> &Rc<FuncType> {
+        self.func_type(self.ty(idx).unwrap_cont())
+    }
+
     fn tags(&self) -> impl Iterator<Item = (u32, &TagType)> + '_ {
         self.tags
             .iter()
@@ -1514,10 +1756,11 @@ impl Module {
     }
 
     fn tag_func_types(&self) -> impl Iterator<Item = u32> + '_ {
-        self.func_types
-            .iter()
-            .copied()
-            .filter(move |i| self.func_type(*i).results.is_empty())
+        // Stack switching allows tags with results, which are used by
+        // `suspend` and resumption handlers.
+        self.func_types.iter().copied().filter(move |i| {
+            self.config.stack_switching_enabled || self.func_type(*i).results.is_empty()
+        })
     }
 
     fn arbitrary_valtype(&self, u: &mut Unstructured) -> Result<ValType> {
@@ -1794,10 +2037,24 @@ impl Module {
                         }
                     }
 
-                    // TODO: fill out more GC types e.g `array.new` and
-                    // `struct.new`
                     _ => {}
                 }
+
+                // GC objects, `i31` references and the like are all created
+                // with constant instructions too.
+                if self.config.gc_enabled
+                    && self.heap_type_is_constructible(ty.heap_type)
+                    && (choices.is_empty() || u.arbitrary()?)
+                {
+                    self.const_expr_choices = choices;
+                    let mut instrs = Vec::new();
+                    let ty = RefType {
+                        nullable: false,
+                        ..ty
+                    };
+                    self.arbitrary_const_ref(u, ty, MAX_CONST_REF_DEPTH, &mut instrs)?;
+                    return Ok(ConstExpr::extended(instrs));
+                }
             }
         }
 
@@ -2325,12 +2582,84 @@ impl Module {
             Instructions::Generated(builder.arbitrary(u, self)?)
         };
 
+        let mut branch_hints = Vec::new();
+        if self.config.generate_branch_hints {
+            if let Instructions::Generated(instrs) = &instructions {
+                let branches = instrs
+                    .iter()
+                    .filter(|i| matches!(i, Instruction::If(_) | Instruction::BrIf(_)))
+                    .count();
+                for i in 0..branches {
+                    if u.arbitrary()? {
+                        branch_hints.push((u32::try_from(i).unwrap(), u.arbitrary()?));
+                    }
+                }
+            }
+        }
+
         Ok(Code {
             locals,
             instructions,
+            branch_hints,
         })
     }
 
+    fn arbitrary_names(&mut self, u: &mut Unstructured) -> Result<()> {
+        if !self.config.generate_names {
+            return Ok(());
+        }
+
+        let mut names = Names::default();
+        if u.arbitrary()? {
+            names.module = Some(limited_string(100, u)?);
+        }
+        names.funcs = arbitrary_name_map(u, self.funcs.len())?;
+
+        let first_defined_func = self.funcs.len() - self.num_defined_funcs;
+        for (i, code) in self.code.iter().enumerate() {
+            let func = u32::try_from(first_defined_func + i).unwrap();
+            let num_params = self.funcs[first_defined_func + i].1.params.len();
+            let locals = arbitrary_name_map(u, num_params + code.locals.len())?;
+            if !locals.is_empty() {
+                names.locals.push((func, locals));
+            }
+
+            // Labels are numbered in the order that their block-like
+            // instructions appear in the function body.
+            if let Instructions::Generated(instrs) = &code.instructions {
+                let num_labels = instrs
+                    .iter()
+                    .filter(|i| {
+                        matches!(
+                            i,
+                            Instruction::Block(_)
+                                | Instruction::Loop(_)
+                                | Instruction::If(_)
+                                | Instruction::TryTable(..)
+                        )
+                    })
+                    .count();
+                let labels = arbitrary_name_map(u, num_labels)?;
+                if !labels.is_empty() {
+                    names.labels.push((func, labels));
+                }
+            }
+        }
+
+        names.types = arbitrary_name_map(u, self.types.len())?;
+        for (i, ty) in self.types.iter().enumerate() {
+            if let CompositeInnerType::Struct(s) = &ty.composite_type.inner {
+                let fields = arbitrary_name_map(u, s.fields.len())?;
+                if !fields.is_empty() {
+                    names.fields.push((u32::try_from(i).unwrap(), fields));
+                }
+            }
+        }
+
+        self.names = Some(names);
+        Ok(())
+    }
+
     fn arbitrary_locals(&self, u: &mut Unstructured) -> Result<Vec<ValType>> {
         let mut ret = Vec::new();
         arbitrary_loop(u, 0, 100, |u| {
@@ -2436,6 +2765,8 @@ impl Module {
                                     *x = (*x as u64).min(max_offset) as i64;
                                 }
                                 Offset::Global(_) => unreachable!(),
+                                #[cfg(feature = "wasmparser")]
+                                Offset::Expr(_) => unreachable!(),
                             }
                         }
                         DataSegmentKind::Active {
@@ -2655,6 +2986,188 @@ impl Module {
         }
     }
 
+    /// Pushes constant instructions onto `instructions` which produce a value
+    /// of type `ty`, or of one of its subtypes.
+    ///
+    /// Nullable references are sometimes null, and otherwise references are
+    /// created with `struct.new`, `array.new`, `ref.i31` and friends. Once
+    /// `depth` runs out the smallest possible value is created instead, which
+    /// bounds the size of nested objects.
+    pub(crate) fn arbitrary_const_ref(
+        &self,
+        u: &mut Unstructured,
+        ty: RefType,
+        depth: u32,
+        instructions: &mut Vec<Instruction>,
+    ) -> Result<()> {
+        if ty.nullable
+            && (depth == 0
+                || !self.config.gc_enabled
+                || !self.heap_type_is_constructible(ty.heap_type)
+                || !u.arbitrary()?)
+        {
+            instructions.push(Instruction::RefNull(ty.heap_type));
+            return Ok(());
+        }
+        debug_assert!(self.heap_type_is_constructible(ty.heap_type));
+
+        let (shared, ty) = match ty.heap_type {
+            HeapType::Concrete(idx) => {
+                return self.arbitrary_const_concrete(u, idx, depth, instructions);
+            }
+            HeapType::Abstract { shared, ty } => (shared, ty),
+        };
+        let shared_types = |types: &[u32]| {
+            types
+                .iter()
+                .copied()
+                .filter(|&i| {
+                    self.constructible_types[i as usize] && self.is_shared_type(i) == shared
+                })
+                .collect::<Vec<_>>()
+        };
+        use AbstractHeapType::*;
+        match ty {
+            Any | Eq => {
+                let mut choices = shared_types(&self.struct_types);
+                choices.extend(shared_types(&self.array_types));
+                if depth == 0 || choices.is_empty() || u.arbitrary()? {
+                    self.const_i31(u, shared, instructions)?;
+                } else {
+                    let idx = *u.choose(&choices)?;
+                    self.arbitrary_const_concrete(u, idx, depth, instructions)?;
+                }
+            }
+            I31 => self.const_i31(u, shared, instructions)?,
+            Extern => {
+                let any = RefType {
+                    nullable: false,
+                    heap_type: HeapType::Abstract { shared, ty: Any },
+                };
+                self.arbitrary_const_ref(u, any, depth, instructions)?;
+                instructions.push(Instruction::ExternConvertAny);
+            }
+            Struct | Array => {
+                let choices = shared_types(if ty == Struct {
+                    &self.struct_types
+                } else {
+                    &self.array_types
+                });
+                // At the depth limit use the first type, which can't refer
+                // back to other abstract structs or arrays and so is
+                // guaranteed to bottom out.
+                let idx = if depth == 0 {
+                    choices[0]
+                } else {
+                    *u.choose(&choices)?
+                };
+                self.arbitrary_const_concrete(u, idx, depth, instructions)?;
+            }
+            Func | Exn | Cont | None | NoFunc | NoExtern | NoExn | NoCont => unreachable!(),
+        }
+        Ok(())
+    }
+
+    fn arbitrary_const_concrete(
+        &self,
+        u: &mut Unstructured,
+        idx: u32,
+        depth: u32,
+        instructions: &mut Vec<Instruction>,
+    ) -> Result<()> {
+        let inner_depth = depth.saturating_sub(1);
+        match &self.ty(idx).composite_type.inner {
+            CompositeInnerType::Struct(s) => {
+                let defaultable = s.fields.iter().all(|f| f.element_type.is_defaultable());
+                if defaultable && depth > 0 && u.arbitrary()? {
+                    instructions.push(Instruction::StructNewDefault(idx));
+                } else {
+                    for field in s.fields.iter() {
+                        self.arbitrary_const_storage(
+                            u,
+                            field.element_type,
+                            inner_depth,
+                            instructions,
+                        )?;
+                    }
+                    instructions.push(Instruction::StructNew(idx));
+                }
+            }
+            CompositeInnerType::Array(a) => {
+                let elem = a.0.element_type;
+                let choice = if depth == 0 {
+                    0
+                } else {
+                    u.int_in_range(0..=2)?
+                };
+                match choice {
+                    0 => {
+                        let array_size = if depth == 0 {
+                            0
+                        } else {
+                            u.int_in_range(0..=3)?
+                        };
+                        for _ in 0..array_size {
+                            self.arbitrary_const_storage(u, elem, inner_depth, instructions)?;
+                        }
+                        instructions.push(Instruction::ArrayNewFixed {
+                            array_type_index: idx,
+                            array_size,
+                        });
+                    }
+                    1 if elem.is_defaultable() => {
+                        instructions.push(Instruction::I32Const(u.int_in_range(0..=10)?));
+                        instructions.push(Instruction::ArrayNewDefault(idx));
+                    }
+                    _ => {
+                        self.arbitrary_const_storage(u, elem, inner_depth, instructions)?;
+                        instructions.push(Instruction::I32Const(u.int_in_range(0..=10)?));
+                        instructions.push(Instruction::ArrayNew(idx));
+                    }
+                }
+            }
+            CompositeInnerType::Func(_) | CompositeInnerType::Cont(_) => unreachable!(),
+        }
+        Ok(())
+    }
+
+    fn arbitrary_const_storage(
+        &self,
+        u: &mut Unstructured,
+        ty: StorageType,
+        depth: u32,
+        instructions: &mut Vec<Instruction>,
+    ) -> Result<()> {
+        instructions.push(match ty {
+            StorageType::I8 | StorageType::I16 | StorageType::Val(ValType::I32) => {
+                Instruction::I32Const(u.arbitrary()?)
+            }
+            StorageType::Val(ValType::I64) => Instruction::I64Const(u.arbitrary()?),
+            StorageType::Val(ValType::F32) => Instruction::F32Const(u.arbitrary::<f32>()?.into()),
+            StorageType::Val(ValType::F64) => Instruction::F64Const(u.arbitrary::<f64>()?.into()),
+            StorageType::Val(ValType::V128) => Instruction::V128Const(u.arbitrary()?),
+            StorageType::Val(ValType::Ref(r)) => {
+                return self.arbitrary_const_ref(u, r, depth, instructions);
+            }
+        });
+        Ok(())
+    }
+
+    fn const_i31(
+        &self,
+        u: &mut Unstructured,
+        shared: bool,
+        instructions: &mut Vec<Instruction>,
+    ) -> Result<()> {
+        instructions.push(Instruction::I32Const(u.arbitrary()?));
+        instructions.push(if shared {
+            Instruction::RefI31Shared
+        } else {
+            Instruction::RefI31
+        });
+        Ok(())
+    }
+
     fn propagate_shared<T>(&mut self, must_share: bool, mut f: impl FnMut(&mut Self) -> T) -> T {
         let tmp = mem::replace(&mut self.must_share, must_share);
         let result = f(self);
@@ -2727,31 +3240,28 @@ pub(crate) fn configured_valtypes(config: &Config) -> Vec<ValType> {
         valtypes.push(ValType::V128);
     }
     if config.gc_enabled && config.reference_types_enabled {
-        for nullable in [
-            // TODO: For now, only create allow nullable reference
-            // types. Eventually we should support non-nullable reference types,
-            // but this means that we will also need to recognize when it is
-            // impossible to create an instance of the reference (eg `(ref
-            // nofunc)` has no instances, and self-referential types that
-            // contain a non-null self-reference are also impossible to create).
-            true,
-        ] {
-            use AbstractHeapType::*;
-            let abs_ref_types = [
-                Any, Eq, I31, Array, Struct, None, Func, NoFunc, Extern, NoExtern,
-            ];
+        // Only nullable references are listed here: this list is used to pick
+        // the class of a value type, and `Module::arbitrary_ref_type` decides
+        // on nullability separately.
+        let nullable = true;
+        use AbstractHeapType::*;
+        let mut abs_ref_types = vec![
+            Any, Eq, I31, Array, Struct, None, Func, NoFunc, Extern, NoExtern,
+        ];
+        if config.stack_switching_enabled {
+            abs_ref_types.extend([Cont, NoCont]);
+        }
+        valtypes.extend(
+            abs_ref_types
+                .iter()
+                .map(|&ty| ValType::Ref(RefType::new_abstract(ty, nullable, false))),
+        );
+        if config.shared_everything_threads_enabled {
             valtypes.extend(
                 abs_ref_types
                     .iter()
-                    .map(|&ty| ValType::Ref(RefType::new_abstract(ty, nullable, false))),
+                    .map(|&ty| ValType::Ref(RefType::new_abstract(ty, nullable, true))),
             );
-            if config.shared_everything_threads_enabled {
-                valtypes.extend(
-                    abs_ref_types
-                        .iter()
-                        .map(|&ty| ValType::Ref(RefType::new_abstract(ty, nullable, true))),
-                );
-            }
         }
     } else if config.reference_types_enabled {
         valtypes.push(ValType::EXTERNREF);
@@ -3008,11 +3518,26 @@ fn unique_import_strings(max_size: usize, u: &mut Unstructured) -> Result<(Strin
     Ok((module, field))
 }
 
+/// How deeply nested the GC objects created by
+/// `Module::arbitrary_const_ref` may be.
+pub(crate) const MAX_CONST_REF_DEPTH: u32 = 3;
+
 fn arbitrary_vec_u8(u: &mut Unstructured) -> Result<Vec<u8>> {
     let size = u.arbitrary_len::<u8>()?;
     Ok(u.bytes(size)?.to_vec())
 }
 
+/// Names an arbitrary subset of the indices `0..count`, in increasing order.
+fn arbitrary_name_map(u: &mut Unstructured, count: usize) -> Result<Vec<(u32, String)>> {
+    let mut names = Vec::new();
+    for i in 0..count {
+        if u.arbitrary()? {
+            names.push((u32::try_from(i).unwrap(), limited_string(100, u)?));
+        }
+    }
+    Ok(names)
+}
+
 impl EntityType {
     fn size(&self) -> u32 {
         match self {
@@ -3131,6 +3656,7 @@ impl FromStr for InstructionKind {
             "memory_non_float" => Ok(InstructionKind::MemoryInt),
             "memory" => Ok(InstructionKind::Memory),
             "control" => Ok(InstructionKind::Control),
+            "aggregate" => Ok(InstructionKind::Aggregate),
             _ => Err(format!("unknown instruction kind: {s}")),
         }
     }
@@ -3175,8 +3701,8 @@ impl TryFrom<wasmparser::CompositeType> for CompositeType {
             wasmparser::CompositeInnerType::Struct(struct_type) => {
                 CompositeInnerType::Struct(struct_type.try_into().map_err(|_| ())?)
             }
-            wasmparser::CompositeInnerType::Cont(_) => {
-                panic!("continuation type is not supported by wasm-smith currently.")
+            wasmparser::CompositeInnerType::Cont(cont_type) => {
+                CompositeInnerType::Cont(cont_type.0.as_module_index().ok_or(())?)
             }
         };
 
@@ -1,14 +1,15 @@
 use super::{
-    CompositeInnerType, Elements, FuncType, Instruction, InstructionKind::*, InstructionKinds,
-    Module, ValType,
+    CompositeInnerType, Elements, FuncType, Instruction, InstructionKind, InstructionKind::*,
+    InstructionKinds, Module, ValType,
 };
-use crate::{MemoryOffsetChoices, unique_string};
+use crate::{InstructionWeights, MemoryOffsetChoices, unique_string};
 use arbitrary::{Result, Unstructured};
+use flagset::FlagSet;
 use std::collections::{BTreeMap, BTreeSet};
 use std::rc::Rc;
 use wasm_encoder::{
     AbstractHeapType, ArrayType, BlockType, Catch, ConstExpr, ExportKind, FieldType, GlobalType,
-    HeapType, MemArg, RefType, StorageType, StructType,
+    Handle, HeapType, MemArg, RefType, StorageType, StructType,
 };
 mod no_traps;
 
@@ -18,11 +19,16 @@ macro_rules! instructions {
             ($predicate:expr, $generator_fn:ident, $instruction_kind:ident $(, $cost:tt)?),
         )*
     ) => {
-        static NUM_OPTIONS: usize = instructions!(
+        const NUM_OPTIONS: usize = instructions!(
             @count;
             $( $generator_fn )*
         );
 
+        // The name, kind and default weight of each option, in order.
+        static OPTIONS: [(&str, InstructionKind, u64); NUM_OPTIONS] = [
+            $( (stringify!($generator_fn), $instruction_kind, 1000 $(- $cost)?), )*
+        ];
+
         fn choose_instruction(
             u: &mut Unstructured<'_>,
             module: &Module,
@@ -33,6 +39,7 @@ macro_rules! instructions {
         > {
             builder.allocs.options.clear();
             let mut cost = 0;
+            let mut option = 0;
             // Unroll the loop that checks whether each instruction is valid in
             // the current context and, if it is valid, pushes it onto our
             // options. Unrolling this loops lets us avoid dynamic calls through
@@ -40,13 +47,17 @@ macro_rules! instructions {
             // predicted and even inlined. This saved us about 30% of time in
             // the `corpus` benchmark.
             $(
+                let weight = builder.allocs.weights[option];
+                option += 1;
                 let predicate: Option<fn(&Module, &mut CodeBuilder) -> bool> = $predicate;
-                if predicate.map_or(true, |f| f(module, builder))
+                if weight > 0
+                    && predicate.map_or(true, |f| f(module, builder))
                     && allowed_instructions.contains($instruction_kind) {
                     builder.allocs.options.push(($generator_fn, cost));
-                    cost += 1000 $(- $cost)?;
+                    cost += weight;
                 }
             )*
+            debug_assert_eq!(option, NUM_OPTIONS);
 
             // If there aren't actually any candidate instructions due to
             // various filters in place then return `None` to indicate the
@@ -73,6 +84,70 @@ macro_rules! instructions {
     };
 }
 
+/// Instructions in the text format which are generated by the same option as
+/// other instructions, and that option's name.
+const INSTRUCTION_ALIASES: &[(&str, &str)] = &[
+    ("array.get_s", "array_get"),
+    ("array.get_u", "array_get"),
+    ("struct.get_s", "struct_get"),
+    ("struct.get_u", "struct_get"),
+    ("i31.get_s", "i31_get"),
+    ("i31.get_u", "i31_get"),
+];
+
+/// The index into `OPTIONS` of the option which generates the instruction
+/// called `name`, if any.
+fn option_index(name: &str) -> Option<usize> {
+    let name = normalize_instruction_name(name);
+    let name = INSTRUCTION_ALIASES
+        .iter()
+        .find(|(alias, _)| normalize_instruction_name(alias) == name)
+        .map_or(name, |(_, option)| normalize_instruction_name(option));
+    OPTIONS
+        .iter()
+        .position(|(option, _, _)| normalize_instruction_name(option) == name)
+}
+
+/// Whether `name` names an instruction that can be given a weight in an
+/// [`InstructionWeights`].
+pub(crate) fn is_weighted_instruction(name: &str) -> bool {
+    option_index(name).is_some()
+}
+
+/// Computes the weight of each option given the module's configured
+/// instruction weights.
+fn option_weights(weights: &InstructionWeights) -> Vec<u64> {
+    let mut result = OPTIONS
+        .iter()
+        .map(|(_, _, weight)| *weight)
+        .collect::<Vec<_>>();
+    for (kind, weight) in weights.kinds() {
+        for (option, (_, option_kind, _)) in OPTIONS.iter().enumerate() {
+            if FlagSet::from(kind).contains(*option_kind) {
+                result[option] = result[option].saturating_mul(u64::from(weight));
+            }
+        }
+    }
+    for (name, weight) in weights.instructions() {
+        let option = option_index(name).unwrap();
+        result[option] = result[option].saturating_mul(u64::from(weight));
+    }
+    for weight in &mut result {
+        *weight = (*weight).min(u64::from(u32::MAX));
+    }
+    result
+}
+
+/// Instructions are named either as in the text format or after their
+/// generator function, so ignore the differences between the two.
+fn normalize_instruction_name(name: &str) -> String {
+    name.trim_start_matches("r#")
+        .chars()
+        .filter(|c| *c != '.' && *c != '_')
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
 // The static set of options of instruction to generate that could be valid at
 // some given time. One entry per Wasm instruction.
 //
@@ -89,7 +164,8 @@ macro_rules! instructions {
 //
 // 4. An optional number used to weight how often this instruction is chosen.
 //    Higher numbers are less likely to be chosen, and number specified must be
-//    less than 1000.
+//    less than 1000. The resulting weight is further scaled by the module's
+//    `Config::instruction_weights`.
 instructions! {
     // Control instructions.
     (Some(unreachable_valid), unreachable, Control, 990),
@@ -116,6 +192,12 @@ instructions! {
     (Some(br_on_non_null_valid), br_on_non_null, Control),
     (Some(br_on_cast_valid), br_on_cast, Control),
     (Some(br_on_cast_fail_valid), br_on_cast_fail, Control),
+    (Some(cont_new_valid), cont_new, Control),
+    (Some(cont_bind_valid), cont_bind, Control),
+    (Some(suspend_valid), suspend, Control),
+    (Some(resume_valid), resume, Control),
+    (Some(resume_throw_valid), resume_throw, Control),
+    (Some(switch_valid), switch, Control),
     // Parametric instructions.
     (Some(drop_valid), drop, Parametric, 990),
     (Some(select_valid), select, Parametric),
@@ -607,9 +689,13 @@ pub(crate) struct CodeBuilderAllocations {
     // be valid right now.
     options: Vec<(
         fn(&mut Unstructured, &Module, &mut CodeBuilder, &mut Vec<Instruction>) -> Result<()>,
-        u32,
+        u64,
     )>,
 
+    // The weight of each of the static options above, from the module's
+    // configured instruction weights.
+    weights: Vec<u64>,
+
     // Cached information about the module that we're generating functions for,
     // used to speed up validity checks. The mutable globals map is a map of the
     // type of global to the global indices which have that type (and they're
@@ -620,8 +706,8 @@ pub(crate) struct CodeBuilderAllocations {
     // of functions that have that function type.
     functions: BTreeMap<Rc<FuncType>, Vec<u32>>,
 
-    // Like functions above this is a map from tag types to the list of tags
-    // have that tag type.
+    // Like functions above this is a map from tag types to the list of
+    // exception tags (tags without results) that have that tag type.
     tags: BTreeMap<Vec<ValType>, Vec<u32>>,
 
     // Tables in this module which have a funcref element type.
@@ -665,6 +751,12 @@ pub(crate) struct CodeBuilderAllocations {
     // Indicates that additional exports cannot be generated. This will be true
     // if the `Config` specifies exactly which exports should be present.
     disallow_exporting: bool,
+
+    // Locals with non-defaultable types, such as non-null references, which
+    // have been set and may therefore be read. Like in validation, these
+    // locals become uninitialized again at the end of the control frame that
+    // set them.
+    initialized_locals: Vec<u32>,
 }
 
 pub(crate) struct CodeBuilder<'a> {
@@ -682,6 +774,20 @@ pub(crate) struct CodeBuilder<'a> {
     f32_scratch: Option<usize>,
     f64_scratch: Option<usize>,
     v128_scratch: Option<usize>,
+
+    // The number of control frames at the bottom of `allocs.controls` which
+    // belong to the code surrounding a hole in a module template. These can
+    // be branched to but are never ended by this builder.
+    outer_controls: usize,
+}
+
+/// A control frame surrounding a hole in a module template, see
+/// [`CodeBuilderAllocations::hole_builder`].
+#[cfg(feature = "wasmparser")]
+pub(crate) struct OuterControl {
+    pub(crate) is_loop: bool,
+    pub(crate) params: Vec<ValType>,
+    pub(crate) results: Vec<ValType>,
 }
 
 /// A control frame.
@@ -695,6 +801,9 @@ struct Control {
     /// How far down the operand stack instructions inside this control frame
     /// can reach.
     height: usize,
+    /// How many locals were in `initialized_locals` when entering this
+    /// control frame.
+    init_height: usize,
 }
 
 impl Control {
@@ -734,8 +843,13 @@ impl CodeBuilderAllocations {
             }
         }
 
+        // Only tags without results can be used for exceptions; the others
+        // are only used by stack switching instructions.
         let mut tags = BTreeMap::new();
         for (idx, tag_type) in module.tags() {
+            if !tag_type.func_type.results.is_empty() {
+                continue;
+            }
             tags.entry(tag_type.func_type.params.to_vec())
                 .or_insert(Vec::new())
                 .push(idx);
@@ -873,6 +987,7 @@ impl CodeBuilderAllocations {
             controls: Vec::with_capacity(4),
             operands: Vec::with_capacity(16),
             options: Vec::with_capacity(NUM_OPTIONS),
+            weights: option_weights(&module.config.instruction_weights),
             functions,
             tags,
             mutable_globals,
@@ -898,6 +1013,7 @@ impl CodeBuilderAllocations {
             globals_cnt: module.globals.len() as u32,
             new_globals: Vec::new(),
             disallow_exporting,
+            initialized_locals: Vec::new(),
         }
     }
 
@@ -913,10 +1029,12 @@ impl CodeBuilderAllocations {
             params: vec![],
             results: func_ty.results.to_vec(),
             height: 0,
+            init_height: 0,
         });
 
         self.operands.clear();
         self.options.clear();
+        self.initialized_locals.clear();
 
         CodeBuilder {
             shared,
@@ -927,7 +1045,54 @@ impl CodeBuilderAllocations {
             f32_scratch: None,
             f64_scratch: None,
             v128_scratch: None,
+            outer_controls: 0,
+        }
+    }
+
+    /// Creates a builder for the code filling a hole in a module template.
+    ///
+    /// The hole is a block of type `[params] -> [results]` nested within the
+    /// `outer` control frames, outermost (the function's) first. The
+    /// generated code may branch to any of these but leaves the operands
+    /// beneath the hole's parameters alone.
+    #[cfg(feature = "wasmparser")]
+    pub(crate) fn hole_builder<'a>(
+        &'a mut self,
+        func_ty: &'a FuncType,
+        locals: &'a mut Vec<ValType>,
+        shared: bool,
+        outer: Vec<OuterControl>,
+        params: &[ValType],
+        results: &[ValType],
+    ) -> CodeBuilder<'a> {
+        let mut builder = self.builder(func_ty, locals, shared);
+        builder.outer_controls = outer.len();
+        builder.allocs.controls.clear();
+        for control in outer {
+            builder.allocs.controls.push(Control {
+                kind: if control.is_loop {
+                    ControlKind::Loop
+                } else {
+                    ControlKind::Block
+                },
+                params: control.params,
+                results: control.results,
+                height: 0,
+                init_height: 0,
+            });
         }
+        builder
+            .allocs
+            .operands
+            .extend(params.iter().copied().map(Some));
+        builder.allocs.controls.push(Control {
+            kind: ControlKind::Block,
+            params: params.to_vec(),
+            results: results.to_vec(),
+            height: 0,
+            init_height: 0,
+        });
+        builder
     }
 
     pub fn finish(self, u: &mut Unstructured<'_>, module: &mut Module) -> arbitrary::Result<()> {
@@ -959,6 +1124,7 @@ impl CodeBuilderAllocations {
 impl CodeBuilder<'_> {
     fn pop_control(&mut self) -> Control {
         let control = self.allocs.controls.pop().unwrap();
+        self.allocs.initialized_locals.truncate(control.init_height);
 
         // Pop the actual types on the stack (which could be subtypes of the
         // declared types) and then push the declared types. This avoids us
@@ -993,14 +1159,31 @@ impl CodeBuilder<'_> {
         self.push_operands(&params);
 
         let height = self.allocs.operands.len() - params.len();
+        let init_height = self.allocs.initialized_locals.len();
         self.allocs.controls.push(Control {
             kind,
             params,
             results,
             height,
+            init_height,
         });
     }
 
+    /// Can the `i`th local, counting parameters, be read with `local.get`?
+    fn local_is_initialized(&self, i: usize) -> bool {
+        let num_params = self.func_ty.params.len();
+        i < num_params
+            || self.locals[i - num_params].is_defaultable()
+            || self.allocs.initialized_locals.contains(&(i as u32))
+    }
+
+    /// Records that the `i`th local, counting parameters, was just set.
+    fn set_local_initialized(&mut self, i: usize) {
+        if !self.local_is_initialized(i) {
+            self.allocs.initialized_locals.push(i as u32);
+        }
+    }
+
     /// Get the operands that are in-scope within the current control frame.
     #[inline]
     fn operands(&self) -> &[Option<ValType>] {
@@ -1088,12 +1271,18 @@ impl CodeBuilder<'_> {
     /// Are the given types on top of the stack?
     #[inline]
     fn types_on_stack(&self, module: &Module, types: &[ValType]) -> bool {
-        self.operands().len() >= types.len()
+        self.types_on_stack_at(module, 0, types)
+    }
+
+    /// Are the given types on the stack just beneath the top `at` operands?
+    #[inline]
+    fn types_on_stack_at(&self, module: &Module, at: usize, types: &[ValType]) -> bool {
+        self.operands().len() >= at + types.len()
             && types
                 .iter()
                 .rev()
                 .enumerate()
-                .all(|(idx, ty)| self.type_on_stack_at(module, idx, *ty))
+                .all(|(idx, ty)| self.type_on_stack_at(module, at + idx, *ty))
     }
 
     /// Are the given field types on top of the stack?
@@ -1173,6 +1362,21 @@ impl CodeBuilder<'_> {
         }
     }
 
+    /// Is there a `(ref null? <index>)` at the given stack position that
+    /// references a concrete continuation type? If so return the function
+    /// type of the continuation as well.
+    fn concrete_cont_ref_type_on_stack_at<'a>(
+        &self,
+        module: &'a Module,
+        at: usize,
+    ) -> Option<(bool, u32, &'a FuncType)> {
+        let (nullable, ty) = self.concrete_ref_type_on_stack_at(at)?;
+        match &module.ty(ty).composite_type.inner {
+            CompositeInnerType::Cont(f) => Some((nullable, ty, module.func_type(*f))),
+            _ => None,
+        }
+    }
+
     /// Pop a reference type from the stack and return it.
     ///
     /// When in unreachable code and the stack is polymorphic, returns `None`.
@@ -1199,7 +1403,9 @@ impl CodeBuilder<'_> {
             ValType::Ref(r) => match r.heap_type {
                 HeapType::Concrete(idx) => match &module.ty(idx).composite_type.inner {
                     CompositeInnerType::Func(_) => Some(r),
-                    CompositeInnerType::Struct(_) | CompositeInnerType::Array(_) => None,
+                    CompositeInnerType::Struct(_)
+                    | CompositeInnerType::Array(_)
+                    | CompositeInnerType::Cont(_) => None,
                 },
                 _ => None,
             },
@@ -1207,23 +1413,6 @@ impl CodeBuilder<'_> {
         }
     }
 
-    /// Is there a `(ref null? <index>)` on the top of the stack that references
-    /// a struct type with at least one field?
-    fn non_empty_struct_ref_on_stack(&self, module: &Module, allow_null_refs: bool) -> bool {
-        match self.operands().last() {
-            Some(Some(ValType::Ref(RefType {
-                nullable,
-                heap_type: HeapType::Concrete(idx),
-            }))) => match &module.ty(*idx).composite_type.inner {
-                CompositeInnerType::Struct(s) => {
-                    !s.fields.is_empty() && (!nullable || allow_null_refs)
-                }
-                _ => false,
-            },
-            _ => false,
-        }
-    }
-
     #[inline(never)]
     fn arbitrary_block_type(&self, u: &mut Unstructured, module: &Module) -> Result<BlockType> {
         let mut options: Vec<Box<dyn Fn(&mut Unstructured) -> Result<BlockType>>> = vec![
@@ -1242,11 +1431,22 @@ impl CodeBuilder<'_> {
     }
 
     pub(crate) fn arbitrary(
-        mut self,
+        self,
         u: &mut Unstructured,
         module: &Module,
     ) -> Result<Vec<Instruction>> {
         let max_instructions = module.config.max_instructions;
+        self.arbitrary_up_to(u, module, max_instructions)
+    }
+
+    /// Like `arbitrary` but generates at most `max_instructions`, ignoring
+    /// the limit in the module's configuration.
+    pub(crate) fn arbitrary_up_to(
+        mut self,
+        u: &mut Unstructured,
+        module: &Module,
+        max_instructions: usize,
+    ) -> Result<Vec<Instruction>> {
         let allowed_instructions = if module.config.allow_floats {
             module.config.allowed_instructions
         } else {
@@ -1254,7 +1454,7 @@ impl CodeBuilder<'_> {
         };
         let mut instructions = vec![];
 
-        while !self.allocs.controls.is_empty() {
+        while self.allocs.controls.len() > self.outer_controls {
             let keep_going = instructions.len() < max_instructions && u.arbitrary::<u8>()? != 0;
             if !keep_going {
                 self.end_active_control_frames(
@@ -1426,7 +1626,7 @@ impl CodeBuilder<'_> {
         instructions: &mut Vec<Instruction>,
         disallow_traps: bool,
     ) -> Result<()> {
-        while !self.allocs.controls.is_empty() {
+        while self.allocs.controls.len() > self.outer_controls {
             // Ensure that this label is valid by placing the right types onto
             // the operand stack for the end of the label.
             self.guarantee_label_results(u, module, instructions, disallow_traps)?;
@@ -1435,6 +1635,7 @@ impl CodeBuilder<'_> {
             // been removed.
             let label = self.allocs.controls.pop().unwrap();
             self.allocs.operands.truncate(label.height);
+            self.allocs.initialized_locals.truncate(label.init_height);
 
             // If this is an `if` that is not stack neutral, then it
             // must have an `else`. Generate synthetic results here in the same
@@ -1513,7 +1714,10 @@ impl CodeBuilder<'_> {
                 }
                 operands = &[];
             }
-            instructions.push(module.arbitrary_const_instruction(*expected, u)?);
+            match expected {
+                ValType::Ref(ty) => module.arbitrary_const_ref(u, *ty, 0, instructions)?,
+                _ => instructions.push(module.arbitrary_const_instruction(*expected, u)?),
+            }
         }
         Ok(())
     }
@@ -1797,8 +2001,9 @@ fn r#else(
 #[inline]
 fn end_valid(module: &Module, builder: &mut CodeBuilder) -> bool {
     // Note: first control frame is the function return's control frame, which
-    // does not have an associated `end`.
-    if builder.allocs.controls.len() <= 1 {
+    // does not have an associated `end`, and the frames surrounding a hole
+    // in a module template can't be ended from within it.
+    if builder.allocs.controls.len() <= builder.outer_controls.max(1) {
         return false;
     }
     let control = builder.allocs.controls.last().unwrap();
@@ -2181,7 +2386,9 @@ fn return_call_ref_valid(module: &Module, builder: &mut CodeBuilder) -> bool {
     };
     let func_ty = match &module.ty(idx).composite_type.inner {
         CompositeInnerType::Func(f) => f,
-        CompositeInnerType::Array(_) | CompositeInnerType::Struct(_) => return false,
+        CompositeInnerType::Array(_)
+        | CompositeInnerType::Struct(_)
+        | CompositeInnerType::Cont(_) => return false,
     };
 
     let ty = builder.allocs.operands.pop().unwrap();
@@ -2422,8 +2629,15 @@ fn br_on_non_null(
         .unwrap();
     let target = u32::try_from(target).unwrap();
 
-    builder.pop_push_label_types(module, target);
+    // The label's last type is the non-null version of the reference on top
+    // of the stack, which isn't necessarily a supertype of it, so pop the
+    // reference first and then pop and push the rest of the label types.
     builder.pop_ref_type();
+    let control = &builder.allocs.controls[builder.allocs.controls.len() - 1 - target as usize];
+    let label_types = control.label_types();
+    let rest = label_types[..label_types.len() - 1].to_vec();
+    builder.pop_operands(module, &rest);
+    builder.push_operands(&rest);
     instructions.push(Instruction::BrOnNonNull(target));
     Ok(())
 }
@@ -2627,6 +2841,319 @@ fn br_on_cast_fail(
     Ok(())
 }
 
+#[inline]
+fn cont_new_valid(module: &Module, builder: &mut CodeBuilder) -> bool {
+    module.config.stack_switching_enabled && cont_new_candidates(module, builder).next().is_some()
+}
+
+fn cont_new_candidates<'a>(
+    module: &'a Module,
+    builder: &CodeBuilder,
+) -> impl Iterator<Item = u32> + 'a {
+    let func_ref = builder
+        .concrete_funcref_on_stack(module)
+        // `cont.new` traps on a null function reference.
+        .filter(|r| !r.nullable || !module.config.disallow_traps);
+    module.cont_types.iter().copied().filter(move |ct| {
+        func_ref.is_some_and(|r| {
+            let func_ty = HeapType::Concrete(module.ty(*ct).unwrap_cont());
+            module.heap_type_is_sub_type(r.heap_type, func_ty)
+        })
+    })
+}
+
+fn cont_new(
+    u: &mut Unstructured,
+    module: &Module,
+    builder: &mut CodeBuilder,
+    instructions: &mut Vec<Instruction>,
+) -> Result<()> {
+    let candidates = cont_new_candidates(module, builder).collect::<Vec<_>>();
+    let type_index = *u.choose(&candidates)?;
+    builder.pop_operand();
+    builder.push_operand(Some(ValType::Ref(RefType {
+        nullable: false,
+        heap_type: HeapType::Concrete(type_index),
+    })));
+    instructions.push(Instruction::ContNew(type_index));
+    Ok(())
+}
+
+#[inline]
+fn cont_bind_valid(module: &Module, builder: &mut CodeBuilder) -> bool {
+    // Binding a null or an already consumed continuation traps, and we don't
+    // track which continuations have been consumed.
+    module.config.stack_switching_enabled
+        && !module.config.disallow_traps
+        && cont_bind_candidates(module, builder).next().is_some()
+}
+
+fn cont_bind_candidates<'a>(
+    module: &'a Module,
+    builder: &'a CodeBuilder,
+) -> impl Iterator<Item = u32> + 'a {
+    let arg = builder.concrete_cont_ref_type_on_stack_at(module, 0);
+    module.cont_types.iter().copied().filter(move |ct| {
+        let Some((_, _, arg_ty)) = arg else {
+            return false;
+        };
+        // The result continuation takes a suffix of the argument
+        // continuation's parameters; the rest are bound from the stack.
+        let res_ty = module.cont_func_type(*ct);
+        let Some(num_bound) = arg_ty.params.len().checked_sub(res_ty.params.len()) else {
+            return false;
+        };
+        module.val_types_are_sub_types(&res_ty.params, &arg_ty.params[num_bound..])
+            && module.val_types_are_sub_types(&arg_ty.results, &res_ty.results)
+            && builder.types_on_stack_at(module, 1, &arg_ty.params[..num_bound])
+    })
+}
+
+fn cont_bind(
+    u: &mut Unstructured,
+    module: &Module,
+    builder: &mut CodeBuilder,
+    instructions: &mut Vec<Instruction>,
+) -> Result<()> {
+    let candidates = cont_bind_candidates(module, builder).collect::<Vec<_>>();
+    let result_index = *u.choose(&candidates)?;
+    let (_, argument_index) = builder.pop_concrete_ref_type();
+    let arg_ty = module.cont_func_type(argument_index);
+    let num_bound = arg_ty.params.len() - module.cont_func_type(result_index).params.len();
+    builder.pop_operands(module, &arg_ty.params[..num_bound]);
+    builder.push_operand(Some(ValType::Ref(RefType {
+        nullable: false,
+        heap_type: HeapType::Concrete(result_index),
+    })));
+    instructions.push(Instruction::ContBind {
+        argument_index,
+        result_index,
+    });
+    Ok(())
+}
+
+#[inline]
+fn suspend_valid(module: &Module, builder: &mut CodeBuilder) -> bool {
+    // Suspending without an enclosing handler for the tag traps.
+    module.config.stack_switching_enabled
+        && !module.config.disallow_traps
+        && module
+            .tags()
+            .any(|(_, t)| builder.types_on_stack(module, &t.func_type.params))
+}
+
+fn suspend(
+    u: &mut Unstructured,
+    module: &Module,
+    builder: &mut CodeBuilder,
+    instructions: &mut Vec<Instruction>,
+) -> Result<()> {
+    let candidates = module
+        .tags()
+        .filter(|(_, t)| builder.types_on_stack(module, &t.func_type.params))
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+    let tag_index = *u.choose(&candidates)?;
+    let tag_ty = &module.tags[tag_index as usize].func_type;
+    builder.pop_operands(module, &tag_ty.params);
+    builder.push_operands(&tag_ty.results);
+    instructions.push(Instruction::Suspend(tag_index));
+    Ok(())
+}
+
+#[inline]
+fn resume_valid(module: &Module, builder: &mut CodeBuilder) -> bool {
+    // Like `cont.bind`, resuming a null or consumed continuation traps.
+    if !module.config.stack_switching_enabled || module.config.disallow_traps {
+        return false;
+    }
+    match builder.concrete_cont_ref_type_on_stack_at(module, 0) {
+        Some((_, _, ty)) => builder.types_on_stack_at(module, 1, &ty.params),
+        None => false,
+    }
+}
+
+fn resume(
+    u: &mut Unstructured,
+    module: &Module,
+    builder: &mut CodeBuilder,
+    instructions: &mut Vec<Instruction>,
+) -> Result<()> {
+    let (_, cont_type_index) = builder.pop_concrete_ref_type();
+    let ty = module.cont_func_type(cont_type_index);
+    let resume_table = arbitrary_resume_table(u, module, builder, &ty.results)?;
+    builder.pop_operands(module, &ty.params);
+    builder.push_operands(&ty.results);
+    instructions.push(Instruction::Resume {
+        cont_type_index,
+        resume_table: resume_table.into(),
+    });
+    Ok(())
+}
+
+#[inline]
+fn resume_throw_valid(module: &Module, builder: &mut CodeBuilder) -> bool {
+    module.config.stack_switching_enabled
+        && !module.config.disallow_traps
+        && builder
+            .concrete_cont_ref_type_on_stack_at(module, 0)
+            .is_some()
+        && builder
+            .allocs
+            .tags
+            .keys()
+            .any(|k| builder.types_on_stack_at(module, 1, k))
+}
+
+fn resume_throw(
+    u: &mut Unstructured,
+    module: &Module,
+    builder: &mut CodeBuilder,
+    instructions: &mut Vec<Instruction>,
+) -> Result<()> {
+    let (_, cont_type_index) = builder.pop_concrete_ref_type();
+    let candidates = builder
+        .allocs
+        .tags
+        .iter()
+        .filter(|(k, _)| builder.types_on_stack(module, k))
+        .flat_map(|(_, v)| v.iter().copied())
+        .collect::<Vec<_>>();
+    let tag_index = *u.choose(&candidates)?;
+    let ty = module.cont_func_type(cont_type_index);
+    let resume_table = arbitrary_resume_table(u, module, builder, &ty.results)?;
+    builder.pop_operands(module, &module.tags[tag_index as usize].func_type.params);
+    builder.push_operands(&ty.results);
+    instructions.push(Instruction::ResumeThrow {
+        cont_type_index,
+        tag_index,
+        resume_table: resume_table.into(),
+    });
+    Ok(())
+}
+
+/// Generate the handlers for a `resume` or `resume_throw` of a continuation
+/// that returns `results`.
+fn arbitrary_resume_table(
+    u: &mut Unstructured,
+    module: &Module,
+    builder: &CodeBuilder,
+    results: &[ValType],
+) -> Result<Vec<Handle>> {
+    let mut options = Vec::new();
+    for (tag, tag_ty) in module.tags() {
+        // Tags without parameters may be used to handle `switch`.
+        if tag_ty.func_type.params.is_empty() {
+            options.push(Handle::OnSwitch { tag });
+        }
+        for (label, ctrl) in builder.allocs.controls.iter().rev().enumerate() {
+            if is_valid_resume_handler_control(module, ctrl, &tag_ty.func_type, results) {
+                let label = u32::try_from(label).unwrap();
+                options.push(Handle::OnLabel { tag, label });
+            }
+        }
+    }
+
+    let mut handlers = Vec::new();
+    if options.len() > 0 {
+        for _ in 0..u.int_in_range(0..=10)? {
+            handlers.push(u.choose(&options)?.clone());
+        }
+    }
+    Ok(handlers)
+}
+
+/// Can a handler for `tag` branch to the given control's label?
+///
+/// When the resumed continuation suspends with `tag`, the label receives the
+/// tag's parameters followed by a new continuation which takes the tag's
+/// results and returns the same `results` as the resumed continuation.
+fn is_valid_resume_handler_control(
+    module: &Module,
+    control: &Control,
+    tag: &FuncType,
+    results: &[ValType],
+) -> bool {
+    let Some((&cont, params)) = control.label_types().split_last() else {
+        return false;
+    };
+    let Some(cont_ty) = cont_func_type_of_ref(module, cont) else {
+        return false;
+    };
+    module.val_types_are_sub_types(&tag.params, params)
+        && module.val_types_are_sub_types(&cont_ty.params, &tag.results)
+        && module.val_types_are_sub_types(results, &cont_ty.results)
+}
+
+/// If `ty` is a reference to a concrete continuation type, get the
+/// continuation's function type.
+fn cont_func_type_of_ref(module: &Module, ty: ValType) -> Option<&FuncType> {
+    match ty {
+        ValType::Ref(RefType {
+            heap_type: HeapType::Concrete(idx),
+            ..
+        }) => match &module.ty(idx).composite_type.inner {
+            CompositeInnerType::Cont(f) => Some(module.func_type(*f)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[inline]
+fn switch_valid(module: &Module, builder: &mut CodeBuilder) -> bool {
+    module.config.stack_switching_enabled
+        && !module.config.disallow_traps
+        && switch_candidates(module, builder).next().is_some()
+}
+
+fn switch_candidates<'a>(
+    module: &'a Module,
+    builder: &'a CodeBuilder,
+) -> impl Iterator<Item = u32> + 'a {
+    // The switched-to continuation takes the current continuation as its
+    // last parameter, which in turn is resumed with the tag's results.
+    let cont = builder.concrete_cont_ref_type_on_stack_at(module, 0);
+    module
+        .tags()
+        .filter(move |(_, tag)| {
+            let Some((_, _, ty)) = cont else {
+                return false;
+            };
+            let Some((&other, params)) = ty.params.split_last() else {
+                return false;
+            };
+            let Some(other_ty) = cont_func_type_of_ref(module, other) else {
+                return false;
+            };
+            tag.func_type.params.is_empty()
+                && module.val_types_are_sub_types(&ty.results, &tag.func_type.results)
+                && module.val_types_are_sub_types(&tag.func_type.results, &other_ty.results)
+                && builder.types_on_stack_at(module, 1, params)
+        })
+        .map(|(i, _)| i)
+}
+
+fn switch(
+    u: &mut Unstructured,
+    module: &Module,
+    builder: &mut CodeBuilder,
+    instructions: &mut Vec<Instruction>,
+) -> Result<()> {
+    let candidates = switch_candidates(module, builder).collect::<Vec<_>>();
+    let tag_index = *u.choose(&candidates)?;
+    let (_, cont_type_index) = builder.pop_concrete_ref_type();
+    let ty = module.cont_func_type(cont_type_index);
+    let (&other, params) = ty.params.split_last().unwrap();
+    builder.pop_operands(module, params);
+    builder.push_operands(&cont_func_type_of_ref(module, other).unwrap().params);
+    instructions.push(Instruction::Switch {
+        cont_type_index,
+        tag_index,
+    });
+    Ok(())
+}
+
 #[inline]
 fn drop_valid(_module: &Module, builder: &mut CodeBuilder) -> bool {
     !builder.operands().is_empty()
@@ -2674,7 +3201,8 @@ fn select(
 
 #[inline]
 fn local_get_valid(_module: &Module, builder: &mut CodeBuilder) -> bool {
-    !builder.func_ty.params.is_empty() || !builder.locals.is_empty()
+    let n = builder.func_ty.params.len() + builder.locals.len();
+    (0..n).any(|i| builder.local_is_initialized(i))
 }
 
 fn local_get(
@@ -2685,8 +3213,11 @@ fn local_get(
 ) -> Result<()> {
     let num_params = builder.func_ty.params.len();
     let n = num_params + builder.locals.len();
-    debug_assert!(n > 0);
-    let i = u.int_in_range(0..=n - 1)?;
+    let initialized = (0..n)
+        .filter(|i| builder.local_is_initialized(*i))
+        .collect::<Vec<_>>();
+    debug_assert!(!initialized.is_empty());
+    let i = *u.choose(&initialized)?;
     builder.allocs.operands.push(Some(if i < num_params {
         builder.func_ty.params[i]
     } else {
@@ -2731,6 +3262,7 @@ fn local_set(
         .nth(i)
         .unwrap();
     builder.allocs.operands.pop();
+    builder.set_local_initialized(j);
     instructions.push(Instruction::LocalSet(j as u32));
     Ok(())
 }
@@ -2759,9 +3291,11 @@ fn local_tee(
         .filter(|(_, ty)| builder.type_on_stack(module, **ty))
         .nth(i)
         .unwrap();
+    let ty = *ty;
     builder.allocs.operands.pop();
+    builder.set_local_initialized(j);
     instructions.push(Instruction::LocalTee(j as u32));
-    builder.push_operand(Some(*ty));
+    builder.push_operand(Some(ty));
     Ok(())
 }
 
@@ -3345,7 +3879,6 @@ fn memory_grow(
 fn memory_init_valid(module: &Module, builder: &mut CodeBuilder) -> bool {
     module.config.bulk_memory_enabled
         && have_data(module, builder)
-        && !module.config.disallow_traps // Non-trapping memory init not yet implemented
         && (builder.allocs.memory32.len() > 0
             && builder.types_on_stack(module, &[ValType::I32, ValType::I32, ValType::I32])
             || (builder.allocs.memory64.len() > 0
@@ -3367,14 +3900,18 @@ fn memory_init(
     let mem = memory_index(u, builder, ty)?;
     let data_index = data_index(u, module)?;
     builder.pop_operands(module, &[ty]);
-    instructions.push(Instruction::MemoryInit { mem, data_index });
+    let inst = Instruction::MemoryInit { mem, data_index };
+    if module.config.disallow_traps {
+        no_traps::memory_init(inst, module, builder, instructions);
+    } else {
+        instructions.push(inst);
+    }
     Ok(())
 }
 
 #[inline]
 fn memory_fill_valid(module: &Module, builder: &mut CodeBuilder) -> bool {
     module.config.bulk_memory_enabled
-        && !module.config.disallow_traps // Non-trapping memory fill generation not yet implemented
         && (builder.allocs.memory32.len() > 0
             && builder.types_on_stack(module, &[ValType::I32, ValType::I32, ValType::I32])
             || (builder.allocs.memory64.len() > 0
@@ -3394,7 +3931,11 @@ fn memory_fill(
     };
     let mem = memory_index(u, builder, ty)?;
     builder.pop_operands(module, &[ty, ValType::I32, ty]);
-    instructions.push(Instruction::MemoryFill(mem));
+    if module.config.disallow_traps {
+        no_traps::memory_fill(Instruction::MemoryFill(mem), module, builder, instructions);
+    } else {
+        instructions.push(Instruction::MemoryFill(mem));
+    }
     Ok(())
 }
 
@@ -3404,12 +3945,6 @@ fn memory_copy_valid(module: &Module, builder: &mut CodeBuilder) -> bool {
         return false;
     }
 
-    // The non-trapping case for memory copy has not yet been implemented,
-    // so we are excluding them for now
-    if module.config.disallow_traps {
-        return false;
-    }
-
     let n32 = builder.allocs.memory32.len();
     let n64 = builder.allocs.memory64.len();
 
@@ -3444,7 +3979,12 @@ fn memory_copy(
     let (src, dst) = gen_copy_src_and_dst(module, builder);
     let src_mem = src.choose(u, &builder.allocs.memory32, &builder.allocs.memory64)?;
     let dst_mem = dst.choose(u, &builder.allocs.memory32, &builder.allocs.memory64)?;
-    instructions.push(Instruction::MemoryCopy { dst_mem, src_mem });
+    let inst = Instruction::MemoryCopy { dst_mem, src_mem };
+    if module.config.disallow_traps {
+        no_traps::memory_copy(inst, module, builder, instructions);
+    } else {
+        instructions.push(inst);
+    }
     Ok(())
 }
 
@@ -3485,7 +4025,9 @@ fn gen_copy_src_and_dst(
 
 #[inline]
 fn data_drop_valid(module: &Module, builder: &mut CodeBuilder) -> bool {
-    have_data(module, builder) && module.config.bulk_memory_enabled
+    // See `elem_drop_valid` for why segments are never dropped when traps are
+    // disallowed.
+    have_data(module, builder) && module.config.bulk_memory_enabled && !module.config.disallow_traps
 }
 
 fn data_drop(
@@ -5456,7 +5998,14 @@ fn ref_func(
 
 #[inline]
 fn ref_as_non_null_valid(module: &Module, builder: &mut CodeBuilder) -> bool {
-    module.config.gc_enabled && builder.ref_type_on_stack().is_some()
+    module.config.gc_enabled
+        && match builder.ref_type_on_stack() {
+            None => false,
+            // There's no non-null value to produce instead of trapping on a
+            // null reference.
+            Some(Some(r)) if module.config.disallow_traps => !r.nullable,
+            Some(_) => true,
+        }
 }
 
 fn ref_as_non_null(
@@ -5524,9 +6073,7 @@ fn ref_test(
 
 #[inline]
 fn ref_cast_valid(module: &Module, builder: &mut CodeBuilder) -> bool {
-    !module.config.disallow_traps
-        && module.config.gc_enabled
-        && builder.ref_type_on_stack().is_some()
+    module.config.gc_enabled && builder.ref_type_on_stack().is_some()
 }
 
 fn ref_cast(
@@ -5540,7 +6087,10 @@ fn ref_cast(
         None => module.arbitrary_ref_type(u)?,
     };
     let sub_ty = RefType {
-        nullable: if !ref_ty.nullable {
+        nullable: if module.config.disallow_traps {
+            // A failed cast produces a null reference instead of trapping.
+            true
+        } else if !ref_ty.nullable {
             false
         } else {
             u.arbitrary()?
@@ -5549,11 +6099,15 @@ fn ref_cast(
     };
     builder.push_operand(Some(ValType::Ref(sub_ty)));
 
-    instructions.push(if !sub_ty.nullable {
-        Instruction::RefCastNonNull(sub_ty.heap_type)
+    if module.config.disallow_traps {
+        no_traps::ref_cast(ref_ty, sub_ty.heap_type, builder, instructions);
     } else {
-        Instruction::RefCastNullable(sub_ty.heap_type)
-    });
+        instructions.push(if !sub_ty.nullable {
+            Instruction::RefCastNonNull(sub_ty.heap_type)
+        } else {
+            Instruction::RefCastNullable(sub_ty.heap_type)
+        });
+    }
     Ok(())
 }
 
@@ -5580,7 +6134,6 @@ fn ref_is_null(
 fn table_fill_valid(module: &Module, builder: &mut CodeBuilder) -> bool {
     module.config.reference_types_enabled
         && module.config.bulk_memory_enabled
-        && !module.config.disallow_traps // Non-trapping table fill generation not yet implemented
         && table_fill_candidates(module, builder).next().is_some()
 }
 
@@ -5613,15 +6166,17 @@ fn table_fill(
         module,
         &[ty.index_type(), ty.element_type.into(), ty.index_type()],
     );
-    instructions.push(Instruction::TableFill(table));
+    if module.config.disallow_traps {
+        no_traps::table_fill(Instruction::TableFill(table), module, builder, instructions);
+    } else {
+        instructions.push(Instruction::TableFill(table));
+    }
     Ok(())
 }
 
 #[inline]
 fn table_set_valid(module: &Module, builder: &mut CodeBuilder) -> bool {
-    module.config.reference_types_enabled
-    && !module.config.disallow_traps // Non-trapping table.set generation not yet implemented
-    && table_set_candidates(module, builder).next().is_some()
+    module.config.reference_types_enabled && table_set_candidates(module, builder).next().is_some()
 }
 
 fn table_set_candidates<'a>(
@@ -5647,7 +6202,11 @@ fn table_set(
     let table = *u.choose(&table_set_candidates(module, builder).collect::<Vec<_>>())?;
     let ty = &module.tables[table as usize];
     builder.pop_operands(module, &[ty.index_type(), ty.element_type.into()]);
-    instructions.push(Instruction::TableSet(table));
+    if module.config.disallow_traps {
+        no_traps::table_set(Instruction::TableSet(table), module, builder, instructions);
+    } else {
+        instructions.push(Instruction::TableSet(table));
+    }
     Ok(())
 }
 
@@ -5656,36 +6215,59 @@ fn table_get_valid(module: &Module, builder: &mut CodeBuilder) -> bool {
     if !module.config.reference_types_enabled {
         return false;
     }
-    // Non-trapping table.get generation not yet implemented
-    if module.config.disallow_traps {
-        return false;
-    }
-    if builder.type_on_stack(module, ValType::I32) && builder.allocs.table32.len() > 0 {
+    if builder.type_on_stack(module, ValType::I32)
+        && table_get_candidates(module, &builder.allocs.table32)
+            .next()
+            .is_some()
+    {
         return true;
     }
-    if builder.type_on_stack(module, ValType::I64) && builder.allocs.table64.len() > 0 {
+    if builder.type_on_stack(module, ValType::I64)
+        && table_get_candidates(module, &builder.allocs.table64)
+            .next()
+            .is_some()
+    {
         return true;
     }
     false
 }
 
+fn table_get_candidates<'a>(
+    module: &'a Module,
+    tables: &'a [u32],
+) -> impl Iterator<Item = u32> + 'a {
+    // When traps are disallowed an out-of-bounds `table.get` produces a null
+    // reference instead, which requires a nullable element type.
+    tables.iter().copied().filter(|t| {
+        !module.config.disallow_traps || module.tables[*t as usize].element_type.nullable
+    })
+}
+
 fn table_get(
     u: &mut Unstructured,
     module: &Module,
     builder: &mut CodeBuilder,
     instructions: &mut Vec<Instruction>,
 ) -> Result<()> {
-    let candidates = if builder.type_on_stack(module, ValType::I32) {
+    let candidates = if builder.type_on_stack(module, ValType::I32)
+        && table_get_candidates(module, &builder.allocs.table32)
+            .next()
+            .is_some()
+    {
         builder.pop_operands(module, &[ValType::I32]);
-        &builder.allocs.table32
+        table_get_candidates(module, &builder.allocs.table32).collect::<Vec<_>>()
     } else {
         builder.pop_operands(module, &[ValType::I64]);
-        &builder.allocs.table64
+        table_get_candidates(module, &builder.allocs.table64).collect::<Vec<_>>()
     };
-    let idx = *u.choose(candidates)?;
+    let idx = *u.choose(&candidates)?;
     let ty = module.tables[idx as usize].element_type;
     builder.push_operands(&[ty.into()]);
-    instructions.push(Instruction::TableGet(idx));
+    if module.config.disallow_traps {
+        no_traps::table_get(Instruction::TableGet(idx), module, builder, instructions);
+    } else {
+        instructions.push(Instruction::TableGet(idx));
+    }
     Ok(())
 }
 
@@ -5745,8 +6327,8 @@ fn table_copy_valid(module: &Module, builder: &mut CodeBuilder) -> bool {
     if !module.config.bulk_memory_enabled {
         return false;
     }
-    // Non-trapping table.copy generation not yet implemented
-    if module.config.disallow_traps {
+    // Bounds checks use `table.size`.
+    if module.config.disallow_traps && !module.config.reference_types_enabled {
         return false;
     }
     if builder.types_on_stack(module, &[ValType::I64, ValType::I64, ValType::I64]) {
@@ -5778,10 +6360,15 @@ fn table_copy(
         (I64, I32) => *u.choose(&builder.allocs.table_copy_64_to_32)?,
         (I64, I64) => *u.choose(&builder.allocs.table_copy_64_to_64)?,
     };
-    instructions.push(Instruction::TableCopy {
+    let inst = Instruction::TableCopy {
         src_table,
         dst_table,
-    });
+    };
+    if module.config.disallow_traps {
+        no_traps::table_copy(inst, module, builder, instructions);
+    } else {
+        instructions.push(inst);
+    }
     Ok(())
 }
 
@@ -5790,8 +6377,8 @@ fn table_init_valid(module: &Module, builder: &mut CodeBuilder) -> bool {
     if !module.config.bulk_memory_enabled {
         return false;
     }
-    // Non-trapping table.init generation not yet implemented.
-    if module.config.disallow_traps {
+    // Bounds checks use `table.size`.
+    if module.config.disallow_traps && !module.config.reference_types_enabled {
         return false;
     }
     if builder.allocs.table32_init.len() > 0
@@ -5822,13 +6409,20 @@ fn table_init(
         &builder.allocs.table32_init
     };
     let (elem_index, table) = *u.choose(&candidates)?;
-    instructions.push(Instruction::TableInit { elem_index, table });
+    let inst = Instruction::TableInit { elem_index, table };
+    if module.config.disallow_traps {
+        no_traps::table_init(inst, module, builder, instructions);
+    } else {
+        instructions.push(inst);
+    }
     Ok(())
 }
 
 #[inline]
 fn elem_drop_valid(module: &Module, _builder: &mut CodeBuilder) -> bool {
-    module.config.bulk_memory_enabled && module.elems.len() > 0
+    // Dropping a segment would make later uses of it trap, and the guards for
+    // those uses assume that passive segments are never dropped.
+    module.config.bulk_memory_enabled && !module.config.disallow_traps && module.elems.len() > 0
 }
 
 fn elem_drop(
@@ -5944,9 +6538,18 @@ fn struct_new_default(
 
 #[inline]
 fn struct_get_valid(module: &Module, builder: &mut CodeBuilder) -> bool {
-    module.config.gc_enabled
-        && !module.config.disallow_traps
-        && builder.non_empty_struct_ref_on_stack(module, !module.config.disallow_traps)
+    if !module.config.gc_enabled {
+        return false;
+    }
+    match builder.concrete_struct_ref_type_on_stack_at(module, 0) {
+        None => false,
+        // A null struct produces a default value instead of trapping, so the
+        // field has to be defaultable.
+        Some((true, _, ty)) if module.config.disallow_traps => {
+            ty.fields.iter().any(|f| f.element_type.is_defaultable())
+        }
+        Some((_, _, ty)) => !ty.fields.is_empty(),
+    }
 }
 
 fn struct_get(
@@ -5955,17 +6558,28 @@ fn struct_get(
     builder: &mut CodeBuilder,
     instructions: &mut Vec<Instruction>,
 ) -> Result<()> {
-    let (_, struct_type_index) = builder.pop_concrete_ref_type();
+    let (nullable, struct_type_index) = builder.pop_concrete_ref_type();
     let struct_ty = module.ty(struct_type_index).unwrap_struct();
-    let num_fields = u32::try_from(struct_ty.fields.len()).unwrap();
-    debug_assert!(num_fields > 0);
-    let field_index = u.int_in_range(0..=num_fields - 1)?;
-    let (val_ty, ext) = match struct_ty.fields[usize::try_from(field_index).unwrap()].element_type {
+    let guard = nullable && module.config.disallow_traps;
+    let valid_field = |f: &FieldType| !guard || f.element_type.is_defaultable();
+
+    let n = struct_ty.fields.iter().filter(|f| valid_field(f)).count();
+    debug_assert!(n > 0);
+    let i = u.int_in_range(0..=n - 1)?;
+    let (field_index, field) = struct_ty
+        .fields
+        .iter()
+        .enumerate()
+        .filter(|(_, f)| valid_field(f))
+        .nth(i)
+        .unwrap();
+    let field_index = u32::try_from(field_index).unwrap();
+    let (val_ty, ext) = match field.element_type {
         StorageType::I8 | StorageType::I16 => (ValType::I32, Some(u.arbitrary()?)),
         StorageType::Val(v) => (v, None),
     };
     builder.push_operand(Some(val_ty));
-    instructions.push(match ext {
+    let inst = match ext {
         None => Instruction::StructGet {
             struct_type_index,
             field_index,
@@ -5978,7 +6592,12 @@ fn struct_get(
             struct_type_index,
             field_index,
         },
-    });
+    };
+    if guard {
+        no_traps::struct_get(inst, module, builder, instructions);
+    } else {
+        instructions.push(inst);
+    }
     Ok(())
 }
 
@@ -5989,7 +6608,6 @@ fn struct_set_valid(module: &Module, builder: &mut CodeBuilder) -> bool {
     }
     match builder.concrete_struct_ref_type_on_stack_at(module, 1) {
         None => return false,
-        Some((true, _, _)) if module.config.disallow_traps => return false,
         Some((_, _, ty)) => ty
             .fields
             .iter()
@@ -6004,7 +6622,7 @@ fn struct_set(
     instructions: &mut Vec<Instruction>,
 ) -> Result<()> {
     let val_ty = builder.pop_operand();
-    let (_, struct_type_index) = builder.pop_concrete_ref_type();
+    let (nullable, struct_type_index) = builder.pop_concrete_ref_type();
     let struct_ty = module.ty(struct_type_index).unwrap_struct();
 
     let valid_field = |f: &FieldType| -> bool {
@@ -6028,10 +6646,15 @@ fn struct_set(
         .unwrap();
     let field_index = u32::try_from(field_index).unwrap();
 
-    instructions.push(Instruction::StructSet {
+    let inst = Instruction::StructSet {
         struct_type_index,
         field_index,
-    });
+    };
+    if nullable && module.config.disallow_traps {
+        no_traps::struct_set(inst, module, builder, instructions);
+    } else {
+        instructions.push(inst);
+    }
     Ok(())
 }
 
@@ -6186,7 +6809,6 @@ fn array_new_default(
 fn array_new_data_valid(module: &Module, builder: &mut CodeBuilder) -> bool {
     module.config.gc_enabled
         && module.config.bulk_memory_enabled // Requires data count section
-        && !module.config.disallow_traps
         && !module.data.is_empty()
         && builder.types_on_stack(module, &[ValType::I32, ValType::I32])
         && module.array_types.iter().any(|i| {
@@ -6233,10 +6855,15 @@ fn array_new_data(
         nullable: false,
         heap_type: HeapType::Concrete(array_type_index),
     })));
-    instructions.push(Instruction::ArrayNewData {
+    let inst = Instruction::ArrayNewData {
         array_type_index,
         array_data_index,
-    });
+    };
+    if module.config.disallow_traps {
+        no_traps::array_new_data(inst, module, builder, instructions);
+    } else {
+        instructions.push(inst);
+    }
     Ok(())
 }
 
@@ -6250,7 +6877,6 @@ fn module_has_elem_segment_of_array_type(module: &Module, ty: &ArrayType) -> boo
 #[inline]
 fn array_new_elem_valid(module: &Module, builder: &mut CodeBuilder) -> bool {
     module.config.gc_enabled
-        && !module.config.disallow_traps
         && builder.types_on_stack(module, &[ValType::I32, ValType::I32])
         && module
             .array_types
@@ -6308,19 +6934,29 @@ fn array_new_elem(
         heap_type: HeapType::Concrete(array_type_index),
     })));
 
-    instructions.push(Instruction::ArrayNewElem {
+    let inst = Instruction::ArrayNewElem {
         array_type_index,
         array_elem_index,
-    });
+    };
+    if module.config.disallow_traps {
+        no_traps::array_new_elem(inst, module, builder, instructions);
+    } else {
+        instructions.push(inst);
+    }
     Ok(())
 }
 
 #[inline]
 fn array_get_valid(module: &Module, builder: &mut CodeBuilder) -> bool {
     module.config.gc_enabled
-        && !module.config.disallow_traps // TODO: add support for disallowing traps
         && builder.type_on_stack(module, ValType::I32)
-        && builder.concrete_array_ref_type_on_stack_at(module, 1).is_some()
+        && match builder.concrete_array_ref_type_on_stack_at(module, 1) {
+            None => false,
+            // An out-of-bounds or null access produces a default value
+            // instead of trapping, so the element type has to be defaultable.
+            Some((_, _, ty)) if module.config.disallow_traps => ty.0.element_type.is_defaultable(),
+            Some(_) => true,
+        }
 }
 
 fn array_get(
@@ -6330,10 +6966,10 @@ fn array_get(
     instructions: &mut Vec<Instruction>,
 ) -> Result<()> {
     builder.pop_operand();
-    let (_, array_type_index) = builder.pop_concrete_ref_type();
+    let (nullable, array_type_index) = builder.pop_concrete_ref_type();
     let elem_ty = module.ty(array_type_index).unwrap_array().0.element_type;
     builder.push_operand(Some(elem_ty.unpack()));
-    instructions.push(match elem_ty {
+    let inst = match elem_ty {
         StorageType::I8 | StorageType::I16 => {
             if u.arbitrary()? {
                 Instruction::ArrayGetS(array_type_index)
@@ -6342,17 +6978,18 @@ fn array_get(
             }
         }
         StorageType::Val(_) => Instruction::ArrayGet(array_type_index),
-    });
+    };
+    if module.config.disallow_traps {
+        no_traps::array_get(inst, nullable, module, builder, instructions);
+    } else {
+        instructions.push(inst);
+    }
     Ok(())
 }
 
 #[inline]
 fn array_set_valid(module: &Module, builder: &mut CodeBuilder) -> bool {
-    if !module.config.gc_enabled
-        // TODO: implement disallowing traps.
-        || module.config.disallow_traps
-        || !builder.type_on_stack_at(module, 1, ValType::I32)
-    {
+    if !module.config.gc_enabled || !builder.type_on_stack_at(module, 1, ValType::I32) {
         return false;
     }
     match builder.concrete_array_ref_type_on_stack_at(module, 2) {
@@ -6365,14 +7002,24 @@ fn array_set_valid(module: &Module, builder: &mut CodeBuilder) -> bool {
 
 fn array_set(
     _u: &mut Unstructured,
-    _module: &Module,
+    module: &Module,
     builder: &mut CodeBuilder,
     instructions: &mut Vec<Instruction>,
 ) -> Result<()> {
     builder.pop_operand();
     builder.pop_operand();
-    let (_, ty) = builder.pop_concrete_ref_type();
-    instructions.push(Instruction::ArraySet(ty));
+    let (nullable, ty) = builder.pop_concrete_ref_type();
+    if module.config.disallow_traps {
+        no_traps::array_set(
+            Instruction::ArraySet(ty),
+            nullable,
+            module,
+            builder,
+            instructions,
+        );
+    } else {
+        instructions.push(Instruction::ArraySet(ty));
+    }
     Ok(())
 }
 
@@ -6383,21 +7030,23 @@ fn array_len_valid(module: &Module, builder: &mut CodeBuilder) -> bool {
 
 fn array_len(
     _u: &mut Unstructured,
-    _module: &Module,
+    module: &Module,
     builder: &mut CodeBuilder,
     instructions: &mut Vec<Instruction>,
 ) -> Result<()> {
-    builder.pop_operand();
+    let nullable = builder.pop_ref_type().map_or(true, |r| r.nullable);
     builder.push_operand(Some(ValType::I32));
-    instructions.push(Instruction::ArrayLen);
+    if nullable && module.config.disallow_traps {
+        no_traps::array_len(builder, instructions);
+    } else {
+        instructions.push(Instruction::ArrayLen);
+    }
     Ok(())
 }
 
 #[inline]
 fn array_fill_valid(module: &Module, builder: &mut CodeBuilder) -> bool {
     if !module.config.gc_enabled
-        // TODO: add support for disallowing traps
-        || module.config.disallow_traps
         || !builder.type_on_stack_at(module, 0, ValType::I32)
         || !builder.type_on_stack_at(module, 2, ValType::I32)
     {
@@ -6413,23 +7062,31 @@ fn array_fill_valid(module: &Module, builder: &mut CodeBuilder) -> bool {
 
 fn array_fill(
     _u: &mut Unstructured,
-    _module: &Module,
+    module: &Module,
     builder: &mut CodeBuilder,
     instructions: &mut Vec<Instruction>,
 ) -> Result<()> {
     builder.pop_operand();
     builder.pop_operand();
     builder.pop_operand();
-    let (_, ty) = builder.pop_concrete_ref_type();
-    instructions.push(Instruction::ArrayFill(ty));
+    let (nullable, ty) = builder.pop_concrete_ref_type();
+    if module.config.disallow_traps {
+        no_traps::array_fill(
+            Instruction::ArrayFill(ty),
+            nullable,
+            module,
+            builder,
+            instructions,
+        );
+    } else {
+        instructions.push(Instruction::ArrayFill(ty));
+    }
     Ok(())
 }
 
 #[inline]
 fn array_copy_valid(module: &Module, builder: &mut CodeBuilder) -> bool {
     if !module.config.gc_enabled
-        // TODO: add support for disallowing traps
-        || module.config.disallow_traps
         || !builder.type_on_stack_at(module, 0, ValType::I32)
         || !builder.type_on_stack_at(module, 1, ValType::I32)
         || !builder.type_on_stack_at(module, 3, ValType::I32)
@@ -6459,19 +7116,24 @@ fn array_copy_valid(module: &Module, builder: &mut CodeBuilder) -> bool {
 
 fn array_copy(
     _u: &mut Unstructured,
-    _module: &Module,
+    module: &Module,
     builder: &mut CodeBuilder,
     instructions: &mut Vec<Instruction>,
 ) -> Result<()> {
     builder.pop_operand();
     builder.pop_operand();
-    let (_, array_type_index_src) = builder.pop_concrete_ref_type();
+    let (src_nullable, array_type_index_src) = builder.pop_concrete_ref_type();
     builder.pop_operand();
-    let (_, array_type_index_dst) = builder.pop_concrete_ref_type();
-    instructions.push(Instruction::ArrayCopy {
+    let (dst_nullable, array_type_index_dst) = builder.pop_concrete_ref_type();
+    let inst = Instruction::ArrayCopy {
         array_type_index_dst,
         array_type_index_src,
-    });
+    };
+    if module.config.disallow_traps {
+        no_traps::array_copy(inst, dst_nullable, src_nullable, builder, instructions);
+    } else {
+        instructions.push(inst);
+    }
     Ok(())
 }
 
@@ -6479,7 +7141,6 @@ fn array_copy(
 fn array_init_data_valid(module: &Module, builder: &mut CodeBuilder) -> bool {
     if !module.config.gc_enabled
         || !module.config.bulk_memory_enabled // Requires data count section
-        || module.config.disallow_traps
         || module.data.is_empty()
         || !builder.types_on_stack(module, &[ValType::I32, ValType::I32, ValType::I32])
     {
@@ -6503,24 +7164,28 @@ fn array_init_data(
     builder.pop_operand();
     builder.pop_operand();
     builder.pop_operand();
-    let (_, array_type_index) = builder.pop_concrete_ref_type();
+    let (nullable, array_type_index) = builder.pop_concrete_ref_type();
 
     let n = module.data.len();
     debug_assert!(n > 0);
     let array_data_index = u.int_in_range(0..=n - 1)?;
     let array_data_index = u32::try_from(array_data_index).unwrap();
 
-    instructions.push(Instruction::ArrayInitData {
+    let inst = Instruction::ArrayInitData {
         array_type_index,
         array_data_index,
-    });
+    };
+    if module.config.disallow_traps {
+        no_traps::array_init_data(inst, nullable, module, builder, instructions);
+    } else {
+        instructions.push(inst);
+    }
     Ok(())
 }
 
 #[inline]
 fn array_init_elem_valid(module: &Module, builder: &mut CodeBuilder) -> bool {
     if !module.config.gc_enabled
-        || module.config.disallow_traps
         || !builder.types_on_stack(module, &[ValType::I32, ValType::I32, ValType::I32])
     {
         return false;
@@ -6542,7 +7207,7 @@ fn array_init_elem(
     builder.pop_operand();
     builder.pop_operand();
     builder.pop_operand();
-    let (_, array_type_index) = builder.pop_concrete_ref_type();
+    let (nullable, array_type_index) = builder.pop_concrete_ref_type();
 
     let elem_ty = module
         .ty(array_type_index)
@@ -6567,10 +7232,15 @@ fn array_init_elem(
         .unwrap();
     let array_elem_index = u32::try_from(array_elem_index).unwrap();
 
-    instructions.push(Instruction::ArrayInitElem {
+    let inst = Instruction::ArrayInitElem {
         array_type_index,
         array_elem_index,
-    });
+    };
+    if module.config.disallow_traps {
+        no_traps::array_init_elem(inst, nullable, module, builder, instructions);
+    } else {
+        instructions.push(inst);
+    }
     Ok(())
 }
 
@@ -6605,17 +7275,22 @@ fn i31_get_valid(module: &Module, builder: &mut CodeBuilder) -> bool {
 
 fn i31_get(
     u: &mut Unstructured,
-    _module: &Module,
+    module: &Module,
     builder: &mut CodeBuilder,
     instructions: &mut Vec<Instruction>,
 ) -> Result<()> {
-    builder.pop_operand();
+    let nullable = builder.pop_ref_type().map_or(true, |r| r.nullable);
     builder.push_operand(Some(ValType::I32));
-    instructions.push(if u.arbitrary()? {
+    let inst = if u.arbitrary()? {
         Instruction::I31GetS
     } else {
         Instruction::I31GetU
-    });
+    };
+    if nullable && module.config.disallow_traps {
+        no_traps::i31_get(inst, builder, instructions);
+    } else {
+        instructions.push(inst);
+    }
     Ok(())
 }
 
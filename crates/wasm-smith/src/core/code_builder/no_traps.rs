@@ -355,6 +355,963 @@ pub(crate) fn signed_div_rem<'a>(
     insts.push(inst);
 }
 
+// The GC, table and bulk memory instructions below all trap on null
+// references or out-of-bounds accesses. They are all guarded the same way:
+// their operands are saved into temporary locals, a series of checks branch
+// out of an inner block if the instruction would trap, and otherwise the
+// instruction is executed on the saved operands. When the instruction is
+// skipped, a dummy value is produced for its result, if it has one:
+//
+//     local.set $operand_n
+//     ...
+//     local.set $operand_0
+//     block
+//       block
+//         <checks, each ending in `br_if 0`>
+//         local.get $operand_0
+//         ...
+//         local.get $operand_n
+//         <inst>
+//         local.set $result
+//         br 1
+//       end
+//       <dummy value>
+//       local.set $result
+//     end
+//     local.get $result
+
+// `array.get` traps on a null array or an out-of-bounds index.
+pub(crate) fn array_get<'a>(
+    inst: Instruction<'a>,
+    nullable: bool,
+    module: &Module,
+    builder: &mut CodeBuilder,
+    insts: &mut Vec<Instruction<'a>>,
+) {
+    let ty = match inst {
+        Instruction::ArrayGet(ty) | Instruction::ArrayGetS(ty) | Instruction::ArrayGetU(ty) => ty,
+        _ => panic!("not an array.get instruction"),
+    };
+    let result = module.ty(ty).unwrap_array().0.element_type.unpack();
+    let operands = save_operands(&[concrete_ref(nullable, ty), ValType::I32], builder, insts);
+    let (array, index) = (operands[0], operands[1]);
+    guarded(inst, &operands, Some(result), builder, insts, |insts| {
+        br_if_null(array, nullable, insts);
+        br_if_out_of_bounds(
+            &[Instruction::LocalGet(index)],
+            &len_of_array(array),
+            ValType::I32,
+            insts,
+        );
+    });
+}
+
+// `array.set` traps on a null array or an out-of-bounds index.
+pub(crate) fn array_set<'a>(
+    inst: Instruction<'a>,
+    nullable: bool,
+    module: &Module,
+    builder: &mut CodeBuilder,
+    insts: &mut Vec<Instruction<'a>>,
+) {
+    let ty = match inst {
+        Instruction::ArraySet(ty) => ty,
+        _ => panic!("not an array.set instruction"),
+    };
+    let elem_ty = module.ty(ty).unwrap_array().0.element_type.unpack();
+    let operands = save_operands(
+        &[concrete_ref(nullable, ty), ValType::I32, elem_ty],
+        builder,
+        insts,
+    );
+    let (array, index) = (operands[0], operands[1]);
+    guarded(inst, &operands, None, builder, insts, |insts| {
+        br_if_null(array, nullable, insts);
+        br_if_out_of_bounds(
+            &[Instruction::LocalGet(index)],
+            &len_of_array(array),
+            ValType::I32,
+            insts,
+        );
+    });
+}
+
+// `array.len` traps on a null array.
+pub(crate) fn array_len<'a>(builder: &mut CodeBuilder, insts: &mut Vec<Instruction<'a>>) {
+    let operands = save_operands(&[ValType::Ref(RefType::ARRAYREF)], builder, insts);
+    let array = operands[0];
+    guarded(
+        Instruction::ArrayLen,
+        &operands,
+        Some(ValType::I32),
+        builder,
+        insts,
+        |insts| br_if_null(array, true, insts),
+    );
+}
+
+// `array.fill` traps on a null array or when the filled range is out of
+// bounds.
+pub(crate) fn array_fill<'a>(
+    inst: Instruction<'a>,
+    nullable: bool,
+    module: &Module,
+    builder: &mut CodeBuilder,
+    insts: &mut Vec<Instruction<'a>>,
+) {
+    let ty = match inst {
+        Instruction::ArrayFill(ty) => ty,
+        _ => panic!("not an array.fill instruction"),
+    };
+    let elem_ty = module.ty(ty).unwrap_array().0.element_type.unpack();
+    let operands = save_operands(
+        &[
+            concrete_ref(nullable, ty),
+            ValType::I32,
+            elem_ty,
+            ValType::I32,
+        ],
+        builder,
+        insts,
+    );
+    let (array, offset, len) = (operands[0], operands[1], operands[3]);
+    guarded(inst, &operands, None, builder, insts, |insts| {
+        br_if_null(array, nullable, insts);
+        br_if_range_out_of_bounds(
+            &[Instruction::LocalGet(offset)],
+            &[Instruction::LocalGet(len)],
+            &len_of_array(array),
+            ValType::I32,
+            insts,
+        );
+    });
+}
+
+// `array.copy` traps on a null source or destination array, or when either
+// range is out of bounds.
+pub(crate) fn array_copy<'a>(
+    inst: Instruction<'a>,
+    dst_nullable: bool,
+    src_nullable: bool,
+    builder: &mut CodeBuilder,
+    insts: &mut Vec<Instruction<'a>>,
+) {
+    let (dst_ty, src_ty) = match inst {
+        Instruction::ArrayCopy {
+            array_type_index_dst,
+            array_type_index_src,
+        } => (array_type_index_dst, array_type_index_src),
+        _ => panic!("not an array.copy instruction"),
+    };
+    let operands = save_operands(
+        &[
+            concrete_ref(dst_nullable, dst_ty),
+            ValType::I32,
+            concrete_ref(src_nullable, src_ty),
+            ValType::I32,
+            ValType::I32,
+        ],
+        builder,
+        insts,
+    );
+    let (dst, dst_offset, src, src_offset, len) = (
+        operands[0],
+        operands[1],
+        operands[2],
+        operands[3],
+        operands[4],
+    );
+    guarded(inst, &operands, None, builder, insts, |insts| {
+        br_if_null(dst, dst_nullable, insts);
+        br_if_null(src, src_nullable, insts);
+        br_if_range_out_of_bounds(
+            &[Instruction::LocalGet(dst_offset)],
+            &[Instruction::LocalGet(len)],
+            &len_of_array(dst),
+            ValType::I32,
+            insts,
+        );
+        br_if_range_out_of_bounds(
+            &[Instruction::LocalGet(src_offset)],
+            &[Instruction::LocalGet(len)],
+            &len_of_array(src),
+            ValType::I32,
+            insts,
+        );
+    });
+}
+
+// `array.new_data` traps when the data segment is too short. Its result is
+// non-nullable so there is no dummy value to produce instead; the offset and
+// length are clamped to zero when the segment is too short.
+pub(crate) fn array_new_data<'a>(
+    inst: Instruction<'a>,
+    module: &Module,
+    builder: &mut CodeBuilder,
+    insts: &mut Vec<Instruction<'a>>,
+) {
+    let (ty, data) = match inst {
+        Instruction::ArrayNewData {
+            array_type_index,
+            array_data_index,
+        } => (array_type_index, array_data_index),
+        _ => panic!("not an array.new_data instruction"),
+    };
+    let data_len = data_segment_len(module, data);
+    let elem_size = size_of_array_element(module, ty);
+    let operands = save_operands(&[ValType::I32, ValType::I32], builder, insts);
+    let (offset, len) = (operands[0], operands[1]);
+    clamped(inst, &operands, insts, |insts| {
+        br_if_data_out_of_bounds(offset, len, elem_size, data_len, insts);
+    });
+}
+
+// `array.new_elem` traps when the element segment is too short, and is
+// clamped in the same way as `array.new_data`.
+pub(crate) fn array_new_elem<'a>(
+    inst: Instruction<'a>,
+    module: &Module,
+    builder: &mut CodeBuilder,
+    insts: &mut Vec<Instruction<'a>>,
+) {
+    let elem = match inst {
+        Instruction::ArrayNewElem {
+            array_elem_index, ..
+        } => array_elem_index,
+        _ => panic!("not an array.new_elem instruction"),
+    };
+    let elem_len = elem_segment_len(module, elem);
+    let operands = save_operands(&[ValType::I32, ValType::I32], builder, insts);
+    let (offset, len) = (operands[0], operands[1]);
+    clamped(inst, &operands, insts, |insts| {
+        br_if_range_out_of_bounds(
+            &[Instruction::LocalGet(offset)],
+            &[Instruction::LocalGet(len)],
+            &[Instruction::I32Const(elem_len as i32)],
+            ValType::I32,
+            insts,
+        );
+    });
+}
+
+// `array.init_data` traps on a null array, or when either the array range or
+// the data segment range is out of bounds.
+pub(crate) fn array_init_data<'a>(
+    inst: Instruction<'a>,
+    nullable: bool,
+    module: &Module,
+    builder: &mut CodeBuilder,
+    insts: &mut Vec<Instruction<'a>>,
+) {
+    let (ty, data) = match inst {
+        Instruction::ArrayInitData {
+            array_type_index,
+            array_data_index,
+        } => (array_type_index, array_data_index),
+        _ => panic!("not an array.init_data instruction"),
+    };
+    let data_len = data_segment_len(module, data);
+    let elem_size = size_of_array_element(module, ty);
+    let operands = save_operands(
+        &[
+            concrete_ref(nullable, ty),
+            ValType::I32,
+            ValType::I32,
+            ValType::I32,
+        ],
+        builder,
+        insts,
+    );
+    let (array, dst_offset, src_offset, len) = (operands[0], operands[1], operands[2], operands[3]);
+    guarded(inst, &operands, None, builder, insts, |insts| {
+        br_if_null(array, nullable, insts);
+        br_if_range_out_of_bounds(
+            &[Instruction::LocalGet(dst_offset)],
+            &[Instruction::LocalGet(len)],
+            &len_of_array(array),
+            ValType::I32,
+            insts,
+        );
+        br_if_data_out_of_bounds(src_offset, len, elem_size, data_len, insts);
+    });
+}
+
+// `array.init_elem` traps on a null array, or when either the array range or
+// the element segment range is out of bounds.
+pub(crate) fn array_init_elem<'a>(
+    inst: Instruction<'a>,
+    nullable: bool,
+    module: &Module,
+    builder: &mut CodeBuilder,
+    insts: &mut Vec<Instruction<'a>>,
+) {
+    let (ty, elem) = match inst {
+        Instruction::ArrayInitElem {
+            array_type_index,
+            array_elem_index,
+        } => (array_type_index, array_elem_index),
+        _ => panic!("not an array.init_elem instruction"),
+    };
+    let elem_len = elem_segment_len(module, elem);
+    let operands = save_operands(
+        &[
+            concrete_ref(nullable, ty),
+            ValType::I32,
+            ValType::I32,
+            ValType::I32,
+        ],
+        builder,
+        insts,
+    );
+    let (array, dst_offset, src_offset, len) = (operands[0], operands[1], operands[2], operands[3]);
+    guarded(inst, &operands, None, builder, insts, |insts| {
+        br_if_null(array, nullable, insts);
+        br_if_range_out_of_bounds(
+            &[Instruction::LocalGet(dst_offset)],
+            &[Instruction::LocalGet(len)],
+            &len_of_array(array),
+            ValType::I32,
+            insts,
+        );
+        br_if_range_out_of_bounds(
+            &[Instruction::LocalGet(src_offset)],
+            &[Instruction::LocalGet(len)],
+            &[Instruction::I32Const(elem_len as i32)],
+            ValType::I32,
+            insts,
+        );
+    });
+}
+
+// `struct.get` traps on a null struct.
+pub(crate) fn struct_get<'a>(
+    inst: Instruction<'a>,
+    module: &Module,
+    builder: &mut CodeBuilder,
+    insts: &mut Vec<Instruction<'a>>,
+) {
+    let (ty, field) = match inst {
+        Instruction::StructGet {
+            struct_type_index,
+            field_index,
+        }
+        | Instruction::StructGetS {
+            struct_type_index,
+            field_index,
+        }
+        | Instruction::StructGetU {
+            struct_type_index,
+            field_index,
+        } => (struct_type_index, field_index),
+        _ => panic!("not a struct.get instruction"),
+    };
+    let result = module.ty(ty).unwrap_struct().fields[field as usize]
+        .element_type
+        .unpack();
+    let operands = save_operands(&[concrete_ref(true, ty)], builder, insts);
+    let object = operands[0];
+    guarded(inst, &operands, Some(result), builder, insts, |insts| {
+        br_if_null(object, true, insts);
+    });
+}
+
+// `struct.set` traps on a null struct.
+pub(crate) fn struct_set<'a>(
+    inst: Instruction<'a>,
+    module: &Module,
+    builder: &mut CodeBuilder,
+    insts: &mut Vec<Instruction<'a>>,
+) {
+    let (ty, field) = match inst {
+        Instruction::StructSet {
+            struct_type_index,
+            field_index,
+        } => (struct_type_index, field_index),
+        _ => panic!("not a struct.set instruction"),
+    };
+    let field_ty = module.ty(ty).unwrap_struct().fields[field as usize]
+        .element_type
+        .unpack();
+    let operands = save_operands(&[concrete_ref(true, ty), field_ty], builder, insts);
+    let object = operands[0];
+    guarded(inst, &operands, None, builder, insts, |insts| {
+        br_if_null(object, true, insts);
+    });
+}
+
+// `ref.cast` traps when the reference is not of the target type. The cast is
+// only performed when `ref.test` succeeds, and a null reference of the target
+// type is produced otherwise.
+pub(crate) fn ref_cast<'a>(
+    ref_ty: RefType,
+    heap_type: HeapType,
+    builder: &mut CodeBuilder,
+    insts: &mut Vec<Instruction<'a>>,
+) {
+    let result = ValType::Ref(RefType {
+        nullable: true,
+        heap_type,
+    });
+    let operands = save_operands(&[ValType::Ref(ref_ty)], builder, insts);
+    let reference = operands[0];
+    guarded(
+        Instruction::RefCastNullable(heap_type),
+        &operands,
+        Some(result),
+        builder,
+        insts,
+        |insts| {
+            // []
+            insts.push(Instruction::LocalGet(reference));
+            // [ref:ref_ty]
+            insts.push(Instruction::RefTestNullable(heap_type));
+            // [is_heap_type:i32]
+            insts.push(Instruction::I32Eqz);
+            // [cast_will_trap:i32]
+            insts.push(Instruction::BrIf(0));
+            // []
+        },
+    );
+}
+
+// `i31.get_s` and `i31.get_u` trap on a null reference.
+pub(crate) fn i31_get<'a>(
+    inst: Instruction<'a>,
+    builder: &mut CodeBuilder,
+    insts: &mut Vec<Instruction<'a>>,
+) {
+    let operands = save_operands(&[ValType::Ref(RefType::I31REF)], builder, insts);
+    let i31 = operands[0];
+    guarded(
+        inst,
+        &operands,
+        Some(ValType::I32),
+        builder,
+        insts,
+        |insts| {
+            br_if_null(i31, true, insts);
+        },
+    );
+}
+
+// `table.get` traps on an out-of-bounds index.
+pub(crate) fn table_get<'a>(
+    inst: Instruction<'a>,
+    module: &Module,
+    builder: &mut CodeBuilder,
+    insts: &mut Vec<Instruction<'a>>,
+) {
+    let table = match inst {
+        Instruction::TableGet(table) => table,
+        _ => panic!("not a table.get instruction"),
+    };
+    let ty = &module.tables[table as usize];
+    let operands = save_operands(&[ty.index_type()], builder, insts);
+    let index = operands[0];
+    guarded(
+        inst,
+        &operands,
+        Some(ty.element_type.into()),
+        builder,
+        insts,
+        |insts| {
+            br_if_out_of_bounds(
+                &[Instruction::LocalGet(index)],
+                &[Instruction::TableSize(table)],
+                ty.index_type(),
+                insts,
+            );
+        },
+    );
+}
+
+// `table.set` traps on an out-of-bounds index.
+pub(crate) fn table_set<'a>(
+    inst: Instruction<'a>,
+    module: &Module,
+    builder: &mut CodeBuilder,
+    insts: &mut Vec<Instruction<'a>>,
+) {
+    let table = match inst {
+        Instruction::TableSet(table) => table,
+        _ => panic!("not a table.set instruction"),
+    };
+    let ty = &module.tables[table as usize];
+    let operands = save_operands(&[ty.index_type(), ty.element_type.into()], builder, insts);
+    let index = operands[0];
+    guarded(inst, &operands, None, builder, insts, |insts| {
+        br_if_out_of_bounds(
+            &[Instruction::LocalGet(index)],
+            &[Instruction::TableSize(table)],
+            ty.index_type(),
+            insts,
+        );
+    });
+}
+
+// `table.fill` traps when the filled range is out of bounds.
+pub(crate) fn table_fill<'a>(
+    inst: Instruction<'a>,
+    module: &Module,
+    builder: &mut CodeBuilder,
+    insts: &mut Vec<Instruction<'a>>,
+) {
+    let table = match inst {
+        Instruction::TableFill(table) => table,
+        _ => panic!("not a table.fill instruction"),
+    };
+    let ty = &module.tables[table as usize];
+    let operands = save_operands(
+        &[ty.index_type(), ty.element_type.into(), ty.index_type()],
+        builder,
+        insts,
+    );
+    let (offset, len) = (operands[0], operands[2]);
+    guarded(inst, &operands, None, builder, insts, |insts| {
+        br_if_range_out_of_bounds(
+            &[Instruction::LocalGet(offset)],
+            &[Instruction::LocalGet(len)],
+            &[Instruction::TableSize(table)],
+            ty.index_type(),
+            insts,
+        );
+    });
+}
+
+// `table.copy` traps when either range is out of bounds.
+pub(crate) fn table_copy<'a>(
+    inst: Instruction<'a>,
+    module: &Module,
+    builder: &mut CodeBuilder,
+    insts: &mut Vec<Instruction<'a>>,
+) {
+    let (dst, src) = match inst {
+        Instruction::TableCopy {
+            dst_table,
+            src_table,
+        } => (dst_table, src_table),
+        _ => panic!("not a table.copy instruction"),
+    };
+    let dst_ty = module.tables[dst as usize].index_type();
+    let src_ty = module.tables[src as usize].index_type();
+    let len_ty = narrowest_index_type(dst_ty, src_ty);
+    let operands = save_operands(&[dst_ty, src_ty, len_ty], builder, insts);
+    let (dst_offset, src_offset, len) = (operands[0], operands[1], operands[2]);
+    guarded(inst, &operands, None, builder, insts, |insts| {
+        br_if_range_out_of_bounds(
+            &[Instruction::LocalGet(dst_offset)],
+            &extend_index(len, len_ty, dst_ty),
+            &[Instruction::TableSize(dst)],
+            dst_ty,
+            insts,
+        );
+        br_if_range_out_of_bounds(
+            &[Instruction::LocalGet(src_offset)],
+            &extend_index(len, len_ty, src_ty),
+            &[Instruction::TableSize(src)],
+            src_ty,
+            insts,
+        );
+    });
+}
+
+// `table.init` traps when either the table range or the element segment
+// range is out of bounds.
+pub(crate) fn table_init<'a>(
+    inst: Instruction<'a>,
+    module: &Module,
+    builder: &mut CodeBuilder,
+    insts: &mut Vec<Instruction<'a>>,
+) {
+    let (elem, table) = match inst {
+        Instruction::TableInit { elem_index, table } => (elem_index, table),
+        _ => panic!("not a table.init instruction"),
+    };
+    let elem_len = elem_segment_len(module, elem);
+    let dst_ty = module.tables[table as usize].index_type();
+    let operands = save_operands(&[dst_ty, ValType::I32, ValType::I32], builder, insts);
+    let (dst_offset, src_offset, len) = (operands[0], operands[1], operands[2]);
+    guarded(inst, &operands, None, builder, insts, |insts| {
+        br_if_range_out_of_bounds(
+            &[Instruction::LocalGet(dst_offset)],
+            &extend_index(len, ValType::I32, dst_ty),
+            &[Instruction::TableSize(table)],
+            dst_ty,
+            insts,
+        );
+        br_if_range_out_of_bounds(
+            &[Instruction::LocalGet(src_offset)],
+            &[Instruction::LocalGet(len)],
+            &[Instruction::I32Const(elem_len as i32)],
+            ValType::I32,
+            insts,
+        );
+    });
+}
+
+// `memory.fill` traps when the filled range is out of bounds.
+pub(crate) fn memory_fill<'a>(
+    inst: Instruction<'a>,
+    module: &Module,
+    builder: &mut CodeBuilder,
+    insts: &mut Vec<Instruction<'a>>,
+) {
+    let mem = match inst {
+        Instruction::MemoryFill(mem) => mem,
+        _ => panic!("not a memory.fill instruction"),
+    };
+    let address_type = memory_address_type(module, mem);
+    let operands = save_operands(&[address_type, ValType::I32, address_type], builder, insts);
+    let (offset, len) = (operands[0], operands[2]);
+    guarded(inst, &operands, None, builder, insts, |insts| {
+        br_if_range_out_of_bounds(
+            &[Instruction::LocalGet(offset)],
+            &[Instruction::LocalGet(len)],
+            &memory_size_in_bytes(module, mem),
+            address_type,
+            insts,
+        );
+    });
+}
+
+// `memory.copy` traps when either range is out of bounds.
+pub(crate) fn memory_copy<'a>(
+    inst: Instruction<'a>,
+    module: &Module,
+    builder: &mut CodeBuilder,
+    insts: &mut Vec<Instruction<'a>>,
+) {
+    let (dst, src) = match inst {
+        Instruction::MemoryCopy { dst_mem, src_mem } => (dst_mem, src_mem),
+        _ => panic!("not a memory.copy instruction"),
+    };
+    let dst_ty = memory_address_type(module, dst);
+    let src_ty = memory_address_type(module, src);
+    let len_ty = narrowest_index_type(dst_ty, src_ty);
+    let operands = save_operands(&[dst_ty, src_ty, len_ty], builder, insts);
+    let (dst_offset, src_offset, len) = (operands[0], operands[1], operands[2]);
+    guarded(inst, &operands, None, builder, insts, |insts| {
+        br_if_range_out_of_bounds(
+            &[Instruction::LocalGet(dst_offset)],
+            &extend_index(len, len_ty, dst_ty),
+            &memory_size_in_bytes(module, dst),
+            dst_ty,
+            insts,
+        );
+        br_if_range_out_of_bounds(
+            &[Instruction::LocalGet(src_offset)],
+            &extend_index(len, len_ty, src_ty),
+            &memory_size_in_bytes(module, src),
+            src_ty,
+            insts,
+        );
+    });
+}
+
+// `memory.init` traps when either the memory range or the data segment range
+// is out of bounds.
+pub(crate) fn memory_init<'a>(
+    inst: Instruction<'a>,
+    module: &Module,
+    builder: &mut CodeBuilder,
+    insts: &mut Vec<Instruction<'a>>,
+) {
+    let (mem, data) = match inst {
+        Instruction::MemoryInit { mem, data_index } => (mem, data_index),
+        _ => panic!("not a memory.init instruction"),
+    };
+    let data_len = data_segment_len(module, data);
+    let dst_ty = memory_address_type(module, mem);
+    let operands = save_operands(&[dst_ty, ValType::I32, ValType::I32], builder, insts);
+    let (dst_offset, src_offset, len) = (operands[0], operands[1], operands[2]);
+    guarded(inst, &operands, None, builder, insts, |insts| {
+        br_if_range_out_of_bounds(
+            &[Instruction::LocalGet(dst_offset)],
+            &extend_index(len, ValType::I32, dst_ty),
+            &memory_size_in_bytes(module, mem),
+            dst_ty,
+            insts,
+        );
+        br_if_range_out_of_bounds(
+            &[Instruction::LocalGet(src_offset)],
+            &[Instruction::LocalGet(len)],
+            &[Instruction::I32Const(data_len as i32)],
+            ValType::I32,
+            insts,
+        );
+    });
+}
+
+// Pops operands of the given types off the stack into fresh locals, returning
+// the locals in the same order as `tys`.
+fn save_operands<'a>(
+    tys: &[ValType],
+    builder: &mut CodeBuilder,
+    insts: &mut Vec<Instruction<'a>>,
+) -> Vec<u32> {
+    let locals = tys
+        .iter()
+        .map(|ty| builder.alloc_local(*ty))
+        .collect::<Vec<_>>();
+    for local in locals.iter().rev() {
+        insts.push(Instruction::LocalSet(*local));
+    }
+    locals
+}
+
+// Executes `inst` on the saved `operands` unless one of the branches emitted
+// by `checks` is taken, in which case a dummy value of type `result` is used
+// instead of the instruction's result.
+fn guarded<'a>(
+    inst: Instruction<'a>,
+    operands: &[u32],
+    result: Option<ValType>,
+    builder: &mut CodeBuilder,
+    insts: &mut Vec<Instruction<'a>>,
+    checks: impl FnOnce(&mut Vec<Instruction<'a>>),
+) {
+    // The result local is set inside of the blocks below but read after them,
+    // which validation doesn't allow for non-nullable locals.
+    debug_assert!(!matches!(result, Some(ValType::Ref(r)) if !r.nullable));
+    let result_local = result.map(|ty| builder.alloc_local(ty));
+
+    insts.push(Instruction::Block(BlockType::Empty));
+    {
+        insts.push(Instruction::Block(BlockType::Empty));
+        {
+            checks(insts);
+            for operand in operands {
+                insts.push(Instruction::LocalGet(*operand));
+            }
+            insts.push(inst);
+            if let Some(result_local) = result_local {
+                insts.push(Instruction::LocalSet(result_local));
+            }
+            insts.push(Instruction::Br(1));
+        }
+        insts.push(Instruction::End);
+        if let (Some(ty), Some(result_local)) = (result, result_local) {
+            insts.push(dummy_value_inst(ty));
+            insts.push(Instruction::LocalSet(result_local));
+        }
+    }
+    insts.push(Instruction::End);
+    if let Some(result_local) = result_local {
+        insts.push(Instruction::LocalGet(result_local));
+    }
+}
+
+// Executes `inst` on the saved `operands`, but first resets all of them to
+// zero if one of the branches emitted by `checks` is taken.
+fn clamped<'a>(
+    inst: Instruction<'a>,
+    operands: &[u32],
+    insts: &mut Vec<Instruction<'a>>,
+    checks: impl FnOnce(&mut Vec<Instruction<'a>>),
+) {
+    insts.push(Instruction::Block(BlockType::Empty));
+    {
+        insts.push(Instruction::Block(BlockType::Empty));
+        {
+            checks(insts);
+            insts.push(Instruction::Br(1));
+        }
+        insts.push(Instruction::End);
+        for operand in operands {
+            insts.push(Instruction::I32Const(0));
+            insts.push(Instruction::LocalSet(*operand));
+        }
+    }
+    insts.push(Instruction::End);
+    for operand in operands {
+        insts.push(Instruction::LocalGet(*operand));
+    }
+    insts.push(inst);
+}
+
+// Branches out of the enclosing block if `local` holds a null reference.
+fn br_if_null<'a>(local: u32, nullable: bool, insts: &mut Vec<Instruction<'a>>) {
+    if !nullable {
+        return;
+    }
+    // []
+    insts.push(Instruction::LocalGet(local));
+    // [ref]
+    insts.push(Instruction::RefIsNull);
+    // [is_null:i32]
+    insts.push(Instruction::BrIf(0));
+    // []
+}
+
+// Branches out of the enclosing block if `index >= size`.
+fn br_if_out_of_bounds<'a>(
+    index: &[Instruction<'a>],
+    size: &[Instruction<'a>],
+    ty: ValType,
+    insts: &mut Vec<Instruction<'a>>,
+) {
+    // []
+    insts.extend_from_slice(index);
+    // [index:ty]
+    insts.extend_from_slice(size);
+    // [index:ty size:ty]
+    insts.push(int_ge_u_inst(ty));
+    // [will_trap:i32]
+    insts.push(Instruction::BrIf(0));
+    // []
+}
+
+// Branches out of the enclosing block if `offset + len > size`, without
+// overflowing in the addition.
+fn br_if_range_out_of_bounds<'a>(
+    offset: &[Instruction<'a>],
+    len: &[Instruction<'a>],
+    size: &[Instruction<'a>],
+    ty: ValType,
+    insts: &mut Vec<Instruction<'a>>,
+) {
+    // []
+    insts.extend_from_slice(len);
+    // [len:ty]
+    insts.extend_from_slice(size);
+    // [len:ty size:ty]
+    insts.push(int_gt_u_inst(ty));
+    // [will_trap:i32]
+    insts.push(Instruction::BrIf(0));
+    // []
+    insts.extend_from_slice(offset);
+    // [offset:ty]
+    insts.extend_from_slice(size);
+    // [offset:ty size:ty]
+    insts.extend_from_slice(len);
+    // [offset:ty size:ty len:ty]
+    insts.push(int_sub_inst(ty));
+    // [offset:ty remaining:ty]
+    insts.push(int_gt_u_inst(ty));
+    // [will_trap:i32]
+    insts.push(Instruction::BrIf(0));
+    // []
+}
+
+// Branches out of the enclosing block if reading `len` array elements of
+// `elem_size` bytes each, starting at byte `offset`, would go past the end of
+// a data segment of `data_len` bytes.
+fn br_if_data_out_of_bounds<'a>(
+    offset: u32,
+    len: u32,
+    elem_size: u32,
+    data_len: u32,
+    insts: &mut Vec<Instruction<'a>>,
+) {
+    // Check the number of elements first so that computing the length in
+    // bytes cannot overflow.
+    // []
+    insts.push(Instruction::LocalGet(len));
+    // [len:i32]
+    insts.push(Instruction::I32Const((data_len / elem_size) as i32));
+    // [len:i32 max_len:i32]
+    insts.push(Instruction::I32GtU);
+    // [will_trap:i32]
+    insts.push(Instruction::BrIf(0));
+    // []
+    br_if_range_out_of_bounds(
+        &[Instruction::LocalGet(offset)],
+        &[
+            Instruction::LocalGet(len),
+            Instruction::I32Const(elem_size as i32),
+            Instruction::I32Mul,
+        ],
+        &[Instruction::I32Const(data_len as i32)],
+        ValType::I32,
+        insts,
+    );
+}
+
+// The instructions computing the length of the array in `local`.
+fn len_of_array<'a>(local: u32) -> [Instruction<'a>; 2] {
+    [Instruction::LocalGet(local), Instruction::ArrayLen]
+}
+
+// The instructions computing the size of a memory in bytes.
+fn memory_size_in_bytes<'a>(module: &Module, mem: u32) -> [Instruction<'a>; 3] {
+    let memory = &module.memories[mem as usize];
+    let address_type = memory_address_type(module, mem);
+    [
+        Instruction::MemorySize(mem),
+        int_const_inst(address_type, crate::page_size(memory).into()),
+        int_mul_inst(address_type),
+    ]
+}
+
+// The instructions reading the index in `local`, of type `from`, as an index
+// of type `to`.
+fn extend_index<'a>(local: u32, from: ValType, to: ValType) -> Vec<Instruction<'a>> {
+    match (from, to) {
+        (ValType::I32, ValType::I64) => {
+            vec![Instruction::LocalGet(local), Instruction::I64ExtendI32U]
+        }
+        _ => {
+            assert_eq!(from, to);
+            vec![Instruction::LocalGet(local)]
+        }
+    }
+}
+
+// The type of the length operand of a copy between two tables or memories.
+fn narrowest_index_type(a: ValType, b: ValType) -> ValType {
+    if a == ValType::I32 || b == ValType::I32 {
+        ValType::I32
+    } else {
+        ValType::I64
+    }
+}
+
+fn memory_address_type(module: &Module, mem: u32) -> ValType {
+    if module.memories[mem as usize].memory64 {
+        ValType::I64
+    } else {
+        ValType::I32
+    }
+}
+
+fn concrete_ref(nullable: bool, ty: u32) -> ValType {
+    ValType::Ref(RefType {
+        nullable,
+        heap_type: HeapType::Concrete(ty),
+    })
+}
+
+// Active and declared segments are dropped during instantiation, so only
+// passive segments have a non-zero length at runtime. Passive segments are
+// never dropped when traps are disallowed.
+fn data_segment_len(module: &Module, data: u32) -> u32 {
+    let segment = &module.data[data as usize];
+    match segment.kind {
+        DataSegmentKind::Passive => u32::try_from(segment.init.len()).unwrap(),
+        DataSegmentKind::Active { .. } => 0,
+    }
+}
+
+fn elem_segment_len(module: &Module, elem: u32) -> u32 {
+    let segment = &module.elems[elem as usize];
+    let len = match &segment.items {
+        Elements::Functions(f) => f.len(),
+        Elements::Expressions(e) => e.len(),
+    };
+    match segment.kind {
+        ElementKind::Passive => u32::try_from(len).unwrap(),
+        ElementKind::Declared | ElementKind::Active { .. } => 0,
+    }
+}
+
+fn size_of_array_element(module: &Module, ty: u32) -> u32 {
+    match module.ty(ty).unwrap_array().0.element_type {
+        StorageType::I8 => 1,
+        StorageType::I16 => 2,
+        StorageType::Val(ty) => size_of_type_in_memory(ty) as u32,
+    }
+}
+
 fn get_memarg(inst: &Instruction) -> wasm_encoder::MemArg {
     match *inst {
         Instruction::I32Load(memarg)
@@ -588,6 +1545,30 @@ fn int_le_u_inst<'a>(ty: ValType) -> Instruction<'a> {
     }
 }
 
+fn int_sub_inst<'a>(ty: ValType) -> Instruction<'a> {
+    match ty {
+        ValType::I32 => Instruction::I32Sub,
+        ValType::I64 => Instruction::I64Sub,
+        _ => panic!("not an int type"),
+    }
+}
+
+fn int_gt_u_inst<'a>(ty: ValType) -> Instruction<'a> {
+    match ty {
+        ValType::I32 => Instruction::I32GtU,
+        ValType::I64 => Instruction::I64GtU,
+        _ => panic!("not an int type"),
+    }
+}
+
+fn int_ge_u_inst<'a>(ty: ValType) -> Instruction<'a> {
+    match ty {
+        ValType::I32 => Instruction::I32GeU,
+        ValType::I64 => Instruction::I64GeU,
+        _ => panic!("not an int type"),
+    }
+}
+
 fn int_le_s_inst<'a>(ty: ValType) -> Instruction<'a> {
     match ty {
         ValType::I32 => Instruction::I32LeS,
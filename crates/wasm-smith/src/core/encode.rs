@@ -22,6 +22,7 @@ impl Module {
         self.encode_data_count(&mut module);
         self.encode_code(&mut module);
         self.encode_data(&mut module);
+        self.encode_names(&mut module);
 
         module
     }
@@ -172,10 +173,12 @@ impl Module {
             };
             match &el.kind {
                 ElementKind::Active { table, offset } => {
-                    let offset = match *offset {
-                        Offset::Const32(n) => ConstExpr::i32_const(n),
-                        Offset::Const64(n) => ConstExpr::i64_const(n),
-                        Offset::Global(g) => ConstExpr::global_get(g),
+                    let offset = match offset {
+                        Offset::Const32(n) => ConstExpr::i32_const(*n),
+                        Offset::Const64(n) => ConstExpr::i64_const(*n),
+                        Offset::Global(g) => ConstExpr::global_get(*g),
+                        #[cfg(feature = "wasmparser")]
+                        Offset::Expr(e) => e.clone(),
                     };
                     elems.active(*table, &offset, elements);
                 }
@@ -209,13 +212,27 @@ impl Module {
             return;
         }
         let mut code = wasm_encoder::CodeSection::new();
-        for c in &self.code {
+        let mut branch_hints = wasm_encoder::BranchHints::new();
+        let first_defined_func = self.funcs.len() - self.num_defined_funcs;
+        for (i, c) in self.code.iter().enumerate() {
             // Skip the run-length encoding because it is a little
             // annoying to compute; use a length of one for every local.
             let mut func = wasm_encoder::Function::new(c.locals.iter().map(|l| (1, *l)));
+            let mut hints = Vec::new();
             match &c.instructions {
                 Instructions::Generated(instrs) => {
+                    let mut next_hint = c.branch_hints.iter().peekable();
+                    let mut branch = 0;
                     for instr in instrs {
+                        if let Instruction::If(_) | Instruction::BrIf(_) = instr {
+                            if let Some((_, taken)) = next_hint.next_if(|(b, _)| *b == branch) {
+                                hints.push(wasm_encoder::BranchHint {
+                                    branch_func_offset: u32::try_from(func.byte_len()).unwrap(),
+                                    branch_hint_value: u32::from(*taken),
+                                });
+                            }
+                            branch += 1;
+                        }
                         func.instruction(instr);
                     }
                     func.instructions().end();
@@ -225,10 +242,63 @@ impl Module {
                 }
             }
             code.function(&func);
+            if !hints.is_empty() {
+                let func_idx = u32::try_from(first_defined_func + i).unwrap();
+                branch_hints.function_hints(func_idx, hints);
+            }
+        }
+
+        // The branch hints section has to come before the code section.
+        if !branch_hints.is_empty() {
+            module.section(&branch_hints);
         }
         module.section(&code);
     }
 
+    fn encode_names(&self, module: &mut wasm_encoder::Module) {
+        let Some(names) = &self.names else {
+            return;
+        };
+
+        fn name_map(names: &[(u32, String)]) -> wasm_encoder::NameMap {
+            let mut map = wasm_encoder::NameMap::new();
+            for (idx, name) in names {
+                map.append(*idx, name);
+            }
+            map
+        }
+
+        fn indirect_name_map(names: &[(u32, Vec<(u32, String)>)]) -> wasm_encoder::IndirectNameMap {
+            let mut map = wasm_encoder::IndirectNameMap::new();
+            for (idx, names) in names {
+                map.append(*idx, &name_map(names));
+            }
+            map
+        }
+
+        // Subsections must be emitted in order of their id.
+        let mut section = wasm_encoder::NameSection::new();
+        if let Some(name) = &names.module {
+            section.module(name);
+        }
+        if !names.funcs.is_empty() {
+            section.functions(&name_map(&names.funcs));
+        }
+        if !names.locals.is_empty() {
+            section.locals(&indirect_name_map(&names.locals));
+        }
+        if !names.labels.is_empty() {
+            section.labels(&indirect_name_map(&names.labels));
+        }
+        if !names.types.is_empty() {
+            section.types(&name_map(&names.types));
+        }
+        if !names.fields.is_empty() {
+            section.fields(&indirect_name_map(&names.fields));
+        }
+        module.section(&section);
+    }
+
     fn encode_data(&self, module: &mut wasm_encoder::Module) {
         if self.data.is_empty() {
             return;
@@ -240,10 +310,12 @@ impl Module {
                     memory_index,
                     offset,
                 } => {
-                    let offset = match *offset {
-                        Offset::Const32(n) => ConstExpr::i32_const(n),
-                        Offset::Const64(n) => ConstExpr::i64_const(n),
-                        Offset::Global(g) => ConstExpr::global_get(g),
+                    let offset = match offset {
+                        Offset::Const32(n) => ConstExpr::i32_const(*n),
+                        Offset::Const64(n) => ConstExpr::i64_const(*n),
+                        Offset::Global(g) => ConstExpr::global_get(*g),
+                        #[cfg(feature = "wasmparser")]
+                        Offset::Expr(e) => e.clone(),
                     };
                     data.active(*memory_index, &offset, seg.init.iter().copied());
                 }
@@ -0,0 +1,617 @@
+use super::*;
+
+/// Expands the `(hole ...)` forms in a module template written in the
+/// WebAssembly text format into the blocks which
+/// [`Config::module_template`] recognizes as holes.
+///
+/// A hole is written as `(hole N)`, where `N` is the maximum number of
+/// instructions to fill it with, and may be followed by a block type such as
+/// `(hole 50 (param i32) (result i64))`. `N` may be omitted to use
+/// [`Config::max_instructions`] instead. Holes may appear wherever a folded
+/// instruction can.
+///
+/// The result can be parsed with the `wat` crate.
+///
+/// ```
+/// let wat = wasm_smith::expand_holes(r#"
+///     (module
+///         (func (param i32) (result i32)
+///             (hole 50))
+///     )
+/// "#);
+/// assert!(wat.contains("(block $hole:50 unreachable)"));
+/// ```
+pub fn expand_holes(wat: &str) -> String {
+    let mut expanded = String::with_capacity(wat.len());
+    let mut rest = wat;
+    while let Some(c) = rest.chars().next() {
+        let len = if let Some(hole) = rest.strip_prefix("(hole").filter(|s| {
+            s.chars()
+                .next()
+                .map_or(true, |c| c.is_whitespace() || c == '(' || c == ')')
+        }) {
+            let body_len = list_len(hole);
+            let body = hole[..body_len].trim_start();
+            let body = body.strip_suffix(')').unwrap_or(body);
+            let size_len = body
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(body.len());
+            let (size, ty) = body.split_at(size_len);
+            expanded.push_str("(block $hole");
+            if !size.is_empty() {
+                expanded.push(':');
+                expanded.push_str(size);
+            }
+            if !ty.trim().is_empty() {
+                expanded.push(' ');
+                expanded.push_str(ty.trim());
+            }
+            expanded.push_str(" unreachable)");
+            rest = &hole[body_len..];
+            continue;
+        } else if rest.starts_with(";;") {
+            rest.find('\n').map_or(rest.len(), |i| i + 1)
+        } else if rest.starts_with("(;") {
+            block_comment_len(rest)
+        } else if c == '"' {
+            string_len(rest)
+        } else {
+            c.len_utf8()
+        };
+        expanded.push_str(&rest[..len]);
+        rest = &rest[len..];
+    }
+    expanded
+}
+
+/// The length of the rest of an s-expression whose opening parenthesis has
+/// already been consumed, including its closing parenthesis.
+fn list_len(s: &str) -> usize {
+    let mut depth = 0;
+    let mut i = 0;
+    while let Some(c) = s[i..].chars().next() {
+        let rest = &s[i..];
+        i += if rest.starts_with(";;") {
+            rest.find('\n').map_or(rest.len(), |i| i + 1)
+        } else if rest.starts_with("(;") {
+            block_comment_len(rest)
+        } else if c == '"' {
+            string_len(rest)
+        } else if c == '(' {
+            depth += 1;
+            1
+        } else if c == ')' {
+            if depth == 0 {
+                return i + 1;
+            }
+            depth -= 1;
+            1
+        } else {
+            c.len_utf8()
+        };
+    }
+    s.len()
+}
+
+/// The length of the (possibly nested) block comment at the start of `s`.
+fn block_comment_len(s: &str) -> usize {
+    let mut depth = 0;
+    let mut i = 0;
+    while i < s.len() {
+        if s[i..].starts_with("(;") {
+            depth += 1;
+            i += 2;
+        } else if s[i..].starts_with(";)") {
+            depth -= 1;
+            i += 2;
+            if depth == 0 {
+                return i;
+            }
+        } else {
+            i += s[i..].chars().next().unwrap().len_utf8();
+        }
+    }
+    s.len()
+}
+
+/// The length of the string literal at the start of `s`.
+fn string_len(s: &str) -> usize {
+    let mut chars = s.char_indices().skip(1);
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '"' => return i + 1,
+            _ => {}
+        }
+    }
+    s.len()
+}
+
+impl Module {
+    /// Builds this module from the configured template, if any.
+    ///
+    /// Returns `true` if there was a template configured. Otherwise `false`
+    /// and the caller should generate the module from scratch.
+    pub(super) fn build_from_template(&mut self, u: &mut Unstructured) -> Result<bool> {
+        let template = if let Some(wasm) = self.config.module_template.clone() {
+            wasm
+        } else {
+            return Ok(false);
+        };
+
+        #[cfg(feature = "wasmparser")]
+        {
+            self._build_from_template(u, &template)?;
+            Ok(true)
+        }
+        #[cfg(not(feature = "wasmparser"))]
+        {
+            let _ = (template, u);
+            panic!("support for `module_template` was disabled at compile time");
+        }
+    }
+
+    #[cfg(feature = "wasmparser")]
+    fn _build_from_template(&mut self, u: &mut Unstructured, template: &[u8]) -> Result<()> {
+        use wasm_encoder::reencode::{RoundtripReencoder, utils};
+        use wasmparser::{Payload, ValidPayload};
+
+        let holes = template_holes(template);
+
+        // Copy everything but the code from the template, validating it along
+        // the way. Function bodies are held back until the data segments,
+        // which come after them, are known too.
+        let const_expr =
+            |e: wasmparser::ConstExpr<'_>| utils::const_expr(&mut RoundtripReencoder, e).unwrap();
+        let mut validator = wasmparser::Validator::new_with_features(self.config.features());
+        let mut bodies = Vec::new();
+        for payload in wasmparser::Parser::new(0).parse_all(template) {
+            let payload = payload.expect("Failed to read `module_template` Wasm");
+            if let ValidPayload::Func(func, body) = validator
+                .payload(&payload)
+                .unwrap_or_else(|e| panic!("Failed to validate `module_template` Wasm: {e}"))
+            {
+                bodies.push((func, body));
+            }
+            match payload {
+                Payload::TypeSection(reader) => {
+                    for rec_group in reader {
+                        let rec_group = rec_group.unwrap();
+                        let start = self.types.len();
+                        self.rec_groups.push(start..start + rec_group.types().len());
+                        for ty in rec_group.into_types() {
+                            let mut ty: SubType = ty
+                                .try_into()
+                                .expect("unsupported type in `module_template` Wasm");
+                            if let Some(supertype) = ty.supertype {
+                                ty.depth = self.ty(supertype).depth + 1;
+                            }
+                            self.add_type(ty);
+                        }
+                    }
+                }
+                Payload::ImportSection(reader) => {
+                    for import in reader {
+                        let import = import.unwrap();
+                        let entity_type = match import.ty {
+                            wasmparser::TypeRef::Func(idx) => {
+                                let ty = self.func_type(idx).clone();
+                                self.funcs.push((idx, ty.clone()));
+                                EntityType::Func(idx, ty)
+                            }
+                            wasmparser::TypeRef::Tag(tag) => {
+                                let ty = self.tag_type(tag.func_type_idx);
+                                self.tags.push(ty.clone());
+                                EntityType::Tag(ty)
+                            }
+                            wasmparser::TypeRef::Table(ty) => {
+                                let ty = TableType::try_from(ty).unwrap();
+                                self.tables.push(ty);
+                                EntityType::Table(ty)
+                            }
+                            wasmparser::TypeRef::Memory(ty) => {
+                                let ty = MemoryType::from(ty);
+                                self.memories.push(ty);
+                                EntityType::Memory(ty)
+                            }
+                            wasmparser::TypeRef::Global(ty) => {
+                                let ty = GlobalType::try_from(ty).unwrap();
+                                self.globals.push(ty);
+                                EntityType::Global(ty)
+                            }
+                        };
+                        self.imports.push(Import {
+                            module: import.module.to_string(),
+                            field: import.name.to_string(),
+                            entity_type,
+                        });
+                        self.num_imports += 1;
+                    }
+                }
+                Payload::FunctionSection(reader) => {
+                    for idx in reader {
+                        let idx = idx.unwrap();
+                        self.funcs.push((idx, self.func_type(idx).clone()));
+                        self.num_defined_funcs += 1;
+                    }
+                }
+                Payload::TableSection(reader) => {
+                    for table in reader {
+                        let table = table.unwrap();
+                        self.tables.push(TableType::try_from(table.ty).unwrap());
+                        self.defined_tables.push(match table.init {
+                            wasmparser::TableInit::RefNull => None,
+                            wasmparser::TableInit::Expr(e) => Some(const_expr(e)),
+                        });
+                    }
+                }
+                Payload::MemorySection(reader) => {
+                    for ty in reader {
+                        self.memories.push(ty.unwrap().into());
+                        self.num_defined_memories += 1;
+                    }
+                }
+                Payload::TagSection(reader) => {
+                    for tag in reader {
+                        let ty = self.tag_type(tag.unwrap().func_type_idx);
+                        self.tags.push(ty);
+                        self.num_defined_tags += 1;
+                    }
+                }
+                Payload::GlobalSection(reader) => {
+                    for global in reader {
+                        let global = global.unwrap();
+                        let idx = self.globals.len() as u32;
+                        self.globals.push(GlobalType::try_from(global.ty).unwrap());
+                        self.defined_globals
+                            .push((idx, const_expr(global.init_expr)));
+                    }
+                }
+                Payload::ExportSection(reader) => {
+                    for export in reader {
+                        let export = export.unwrap();
+                        self.exports.push((
+                            export.name.to_string(),
+                            export.kind.into(),
+                            export.index,
+                        ));
+                        self.export_names.insert(export.name.to_string());
+                    }
+                }
+                Payload::StartSection { func, .. } => self.start = Some(func),
+                Payload::ElementSection(reader) => {
+                    for elem in reader {
+                        let elem = elem.unwrap();
+                        let kind = match elem.kind {
+                            wasmparser::ElementKind::Passive => ElementKind::Passive,
+                            wasmparser::ElementKind::Declared => ElementKind::Declared,
+                            wasmparser::ElementKind::Active {
+                                table_index,
+                                offset_expr,
+                            } => ElementKind::Active {
+                                table: table_index,
+                                offset: Offset::Expr(const_expr(offset_expr)),
+                            },
+                        };
+                        let (ty, items) = match elem.items {
+                            wasmparser::ElementItems::Functions(reader) => (
+                                RefType::FUNCREF,
+                                Elements::Functions(
+                                    reader.into_iter().map(|f| f.unwrap()).collect(),
+                                ),
+                            ),
+                            wasmparser::ElementItems::Expressions(ty, reader) => (
+                                ty.try_into().unwrap(),
+                                Elements::Expressions(
+                                    reader.into_iter().map(|e| const_expr(e.unwrap())).collect(),
+                                ),
+                            ),
+                        };
+                        self.elems.push(ElementSegment { kind, ty, items });
+                    }
+                }
+                Payload::DataSection(reader) => {
+                    for data in reader {
+                        let data = data.unwrap();
+                        let kind = match data.kind {
+                            wasmparser::DataKind::Passive => DataSegmentKind::Passive,
+                            wasmparser::DataKind::Active {
+                                memory_index,
+                                offset_expr,
+                            } => DataSegmentKind::Active {
+                                memory_index,
+                                offset: Offset::Expr(const_expr(offset_expr)),
+                            },
+                        };
+                        self.data.push(DataSegment {
+                            kind,
+                            init: data.data.to_vec(),
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+        self.should_encode_types = !self.types.is_empty();
+        self.should_encode_imports = !self.imports.is_empty();
+
+        // Then copy each function body, filling in its holes.
+        self.compute_interesting_values();
+        self.code.reserve(bodies.len());
+        let mut allocs = CodeBuilderAllocations::new(self, true);
+        let mut func_allocs = wasmparser::FuncValidatorAllocations::default();
+        for (func, body) in bodies {
+            let mut validator = func.into_validator(func_allocs);
+            let code = self.template_func_body(u, &mut allocs, &mut validator, &body, &holes)?;
+            self.code.push(code);
+            func_allocs = validator.into_allocations();
+        }
+        allocs.finish(u, self)?;
+        Ok(())
+    }
+
+    #[cfg(feature = "wasmparser")]
+    fn template_func_body(
+        &mut self,
+        u: &mut Unstructured,
+        allocs: &mut CodeBuilderAllocations,
+        validator: &mut wasmparser::FuncValidator<wasmparser::ValidatorResources>,
+        body: &wasmparser::FunctionBody<'_>,
+        holes: &HashMap<(u32, u32), Option<usize>>,
+    ) -> Result<Code> {
+        use wasm_encoder::reencode::{RoundtripReencoder, utils};
+        use wasmparser::Operator;
+
+        let invalid = |e: wasmparser::BinaryReaderError| -> ! {
+            panic!("Failed to validate `module_template` Wasm: {e}")
+        };
+        let func_index = validator.index();
+        let (type_index, func_ty) = self.funcs[func_index as usize].clone();
+        let shared = self.is_shared_type(type_index);
+
+        let mut locals = Vec::new();
+        let mut reader = body.get_locals_reader().unwrap_or_else(|e| invalid(e));
+        for _ in 0..reader.get_count() {
+            let offset = reader.original_position();
+            let (count, ty) = reader.read().unwrap_or_else(|e| invalid(e));
+            validator
+                .define_locals(offset, count, ty)
+                .unwrap_or_else(|e| invalid(e));
+            let ty: ValType = ty
+                .try_into()
+                .expect("unsupported local type in `module_template` Wasm");
+            locals.extend((0..count).map(|_| ty));
+        }
+
+        let mut reader = body.get_operators_reader().unwrap_or_else(|e| invalid(e));
+        let mut ops = Vec::new();
+        while !reader.eof() {
+            let offset = reader.original_position();
+            ops.push((offset, reader.read().unwrap_or_else(|e| invalid(e))));
+        }
+        reader.finish().unwrap_or_else(|e| invalid(e));
+
+        let is_label = |op: &Operator| {
+            matches!(
+                op,
+                Operator::Block { .. }
+                    | Operator::Loop { .. }
+                    | Operator::If { .. }
+                    | Operator::Try { .. }
+                    | Operator::TryTable { .. }
+            )
+        };
+
+        let mut instructions = Vec::new();
+        let mut label = 0;
+        let mut i = 0;
+        while i < ops.len() {
+            let (offset, op) = &ops[i];
+            let hole = match op {
+                Operator::Block { blockty } => holes
+                    .get(&(func_index, label))
+                    .map(|size| (*blockty, *size)),
+                _ => None,
+            };
+            if is_label(op) {
+                label += 1;
+            }
+            let Some((blockty, size)) = hole else {
+                validator.op(*offset, op).unwrap_or_else(|e| invalid(e));
+                // The function's final `end` is implicit in `Code`.
+                if i + 1 < ops.len() {
+                    instructions.push(
+                        utils::owned_instruction(&mut RoundtripReencoder, op.clone()).unwrap(),
+                    );
+                }
+                i += 1;
+                continue;
+            };
+
+            // Skip over the template's body for this hole.
+            let mut end = i + 1;
+            let mut depth = 0;
+            loop {
+                match &ops[end].1 {
+                    Operator::End if depth == 0 => break,
+                    Operator::End => depth -= 1,
+                    op if is_label(op) => {
+                        depth += 1;
+                        label += 1;
+                    }
+                    _ => {}
+                }
+                end += 1;
+            }
+            let ends_block = blockty == wasmparser::BlockType::Empty
+                && matches!(
+                    ops.get(end + 1),
+                    Some((
+                        _,
+                        Operator::End
+                            | Operator::Else
+                            | Operator::Catch { .. }
+                            | Operator::CatchAll
+                            | Operator::Delegate { .. }
+                    ))
+                );
+
+            // Describe the labels around this hole to the code builder,
+            // outermost first. Note that the function's own frame has no
+            // parameters as far as the code builder is concerned.
+            let frames = validator.control_stack_height() as usize;
+            let outer = (0..frames)
+                .rev()
+                .map(|depth| {
+                    let frame = validator.get_control_frame(depth).unwrap();
+                    let (params, results) = self.template_block_type(frame.block_type);
+                    code_builder::OuterControl {
+                        is_loop: frame.kind == wasmparser::FrameKind::Loop,
+                        params: if depth == frames - 1 { vec![] } else { params },
+                        results,
+                    }
+                })
+                .collect();
+
+            let (block_type, params, results) = if ends_block {
+                let frame = validator.get_control_frame(0).unwrap();
+                let height = validator.operand_stack_height() as usize - frame.height;
+                let params = (0..height)
+                    .rev()
+                    .map(|depth| {
+                        validator
+                            .get_operand_type(depth)
+                            .unwrap()
+                            .expect(
+                                "hole in `module_template` Wasm follows an operand of unknown type",
+                            )
+                            .try_into()
+                            .unwrap()
+                    })
+                    .collect::<Vec<_>>();
+                let (_, results) = self.template_block_type(frame.block_type);
+                (self.hole_block_type(&params, &results), params, results)
+            } else {
+                let (params, results) = self.template_block_type(blockty);
+                let block_type = utils::block_type(&mut RoundtripReencoder, blockty).unwrap();
+                (block_type, params, results)
+            };
+
+            let max_instructions = size.unwrap_or(self.config.max_instructions);
+            let builder =
+                allocs.hole_builder(&func_ty, &mut locals, shared, outer, &params, &results);
+            instructions.push(Instruction::Block(block_type));
+            instructions.extend(builder.arbitrary_up_to(u, self, max_instructions)?);
+
+            // Finally, let the validator know about the hole. If it makes up
+            // the rest of its block then the stack can be anything afterwards.
+            if ends_block {
+                validator
+                    .op(*offset, &Operator::Unreachable)
+                    .unwrap_or_else(|e| invalid(e));
+            } else {
+                for op in [op, &Operator::Unreachable, &Operator::End] {
+                    validator.op(*offset, op).unwrap_or_else(|e| invalid(e));
+                }
+            }
+            i = end + 1;
+        }
+
+        Ok(Code {
+            locals,
+            instructions: Instructions::Generated(instructions),
+            branch_hints: Vec::new(),
+        })
+    }
+
+    /// The parameters and results of a block type in the template.
+    #[cfg(feature = "wasmparser")]
+    fn template_block_type(&self, ty: wasmparser::BlockType) -> (Vec<ValType>, Vec<ValType>) {
+        match ty {
+            wasmparser::BlockType::Empty => (vec![], vec![]),
+            wasmparser::BlockType::Type(ty) => (vec![], vec![ty.try_into().unwrap()]),
+            wasmparser::BlockType::FuncType(idx) => {
+                let ty = self.func_type(idx);
+                (ty.params.clone(), ty.results.clone())
+            }
+        }
+    }
+
+    /// A block type for `[params] -> [results]`, adding a new function type
+    /// to the module if there isn't one already.
+    #[cfg(feature = "wasmparser")]
+    fn hole_block_type(&mut self, params: &[ValType], results: &[ValType]) -> BlockType {
+        match (params, results) {
+            ([], []) => return BlockType::Empty,
+            ([], [ty]) => return BlockType::Result(*ty),
+            _ => {}
+        }
+        let existing = self
+            .func_types()
+            .find(|(idx, ty)| {
+                !self.is_shared_type(*idx) && ty.params == params && ty.results == results
+            })
+            .map(|(idx, _)| idx);
+        if let Some(idx) = existing {
+            return BlockType::FunctionType(idx);
+        }
+        let ty = Rc::new(FuncType {
+            params: params.to_vec(),
+            results: results.to_vec(),
+        });
+        self.rec_groups.push(self.types.len()..self.types.len() + 1);
+        self.should_encode_types = true;
+        BlockType::FunctionType(self.add_type(SubType {
+            is_final: true,
+            supertype: None,
+            composite_type: CompositeType::new_func(ty, false),
+            depth: 1,
+        }))
+    }
+
+    #[cfg(feature = "wasmparser")]
+    fn tag_type(&self, func_type_idx: u32) -> TagType {
+        TagType {
+            func_type_idx,
+            func_type: self.func_type(func_type_idx).clone(),
+        }
+    }
+}
+
+/// Finds the holes in a module template, keyed by function and label index,
+/// along with their maximum sizes.
+#[cfg(feature = "wasmparser")]
+fn template_holes(template: &[u8]) -> HashMap<(u32, u32), Option<usize>> {
+    let mut holes = HashMap::new();
+    for payload in wasmparser::Parser::new(0).parse_all(template) {
+        let wasmparser::Payload::CustomSection(section) =
+            payload.expect("Failed to read `module_template` Wasm")
+        else {
+            continue;
+        };
+        let wasmparser::KnownCustom::Name(reader) = section.as_known() else {
+            continue;
+        };
+        for name in reader {
+            let Ok(wasmparser::Name::Label(map)) = name else {
+                continue;
+            };
+            for func in map.into_iter().flatten() {
+                for naming in func.names.into_iter().flatten() {
+                    let size = match naming.name.strip_prefix("hole") {
+                        Some("") => None,
+                        Some(size) => match size.strip_prefix(':').map(str::parse) {
+                            Some(Ok(size)) => Some(size),
+                            _ => continue,
+                        },
+                        None => continue,
+                    };
+                    holes.insert((func.index, naming.index), size);
+                }
+            }
+        }
+    }
+    holes
+}
@@ -60,11 +60,11 @@ mod component;
 mod config;
 mod core;
 
-pub use crate::core::{InstructionKind, InstructionKinds, Module};
+pub use crate::core::{InstructionKind, InstructionKinds, Module, expand_holes};
 use arbitrary::{Result, Unstructured};
 #[cfg(feature = "component-model")]
 pub use component::Component;
-pub use config::{Config, MemoryOffsetChoices};
+pub use config::{Config, InstructionWeights, MemoryOffsetChoices};
 use std::{collections::HashSet, fmt::Write, str};
 use wasm_encoder::MemoryType;
 
@@ -120,6 +120,37 @@ fn smoke_test_imports_config() {
     assert!(n_partial > 0);
 }
 
+#[test]
+fn available_imports_with_self_referential_struct() {
+    // Whether a non-null `(ref struct)` field can be constructed depends on
+    // the struct types defined so far, which mustn't include this one yet.
+    let mut config = Config::default();
+    config.gc_enabled = true;
+    config.available_imports = Some(
+        wat::parse_str(
+            r#"
+            (module
+                (type $s (struct (field (ref struct))))
+                (import "env" "g" (global (ref null $s)))
+            )
+            "#,
+        )
+        .unwrap(),
+    );
+    let features = config.features();
+
+    let mut rng = SmallRng::seed_from_u64(0);
+    let mut buf = vec![0; 512];
+    for _ in 0..256 {
+        rng.fill_bytes(&mut buf);
+        let mut u = Unstructured::new(&buf);
+        if let Ok(module) = Module::new(config.clone(), &mut u) {
+            let mut validator = Validator::new_with_features(features);
+            validate(&mut validator, &module.to_bytes());
+        }
+    }
+}
+
 #[derive(Debug)]
 enum AvailableImportKind {
     Func(Vec<ValType>, Vec<ValType>),
@@ -2,10 +2,9 @@
 
 use arbitrary::{Arbitrary, Unstructured};
 use rand::{RngCore, SeedableRng, rngs::SmallRng};
-use wasm_smith::Component;
+use wasm_smith::{Component, Config};
 
 #[test]
-#[ignore] // FIXME(#1000): need to update wasm-smith's support for components
 fn smoke_test_component() {
     const NUM_RUNS: usize = 4096;
 
@@ -40,3 +39,34 @@ fn smoke_test_component() {
         ok_count as f64 / NUM_RUNS as f64 * 100.0
     );
 }
+
+#[test]
+fn smoke_test_component_with_start() {
+    const NUM_RUNS: usize = 1024;
+
+    let mut rng = SmallRng::seed_from_u64(0);
+    let mut buf = vec![0; 4096];
+
+    for _ in 0..NUM_RUNS {
+        rng.fill_bytes(&mut buf);
+        let mut u = Unstructured::new(&buf);
+        let mut config = Config::default();
+        config.component_model_values_enabled = true;
+        if let Ok(component) = Component::new(config, &mut u) {
+            let component = component.to_bytes();
+
+            let mut validator = wasmparser::Validator::new_with_features(
+                wasmparser::WasmFeatures::default()
+                    | wasmparser::WasmFeatures::COMPONENT_MODEL
+                    | wasmparser::WasmFeatures::CM_VALUES,
+            );
+            if let Err(e) = validator.validate_all(&component) {
+                std::fs::write("component.wasm", &component).unwrap();
+                panic!(
+                    "generated component should be valid; failing binary written \
+                     to `component.wasm`. Error: {e}"
+                );
+            }
+        }
+    }
+}
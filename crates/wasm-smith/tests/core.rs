@@ -1,6 +1,6 @@
 use arbitrary::{Arbitrary, Unstructured};
 use rand::{RngCore, SeedableRng, rngs::SmallRng};
-use wasm_smith::{Config, Module};
+use wasm_smith::{Config, InstructionWeights, Module};
 use wasmparser::{Validator, WasmFeatures};
 
 mod common;
@@ -127,6 +127,48 @@ fn smoke_test_no_trapping_mode() {
     }
 }
 
+#[test]
+fn smoke_test_no_trapping_mode_gc() {
+    let mut rng = SmallRng::seed_from_u64(0);
+    let mut buf = vec![0; 2048];
+    let mut guarded = 0;
+    for _ in 0..1024 {
+        rng.fill_bytes(&mut buf);
+        let mut u = Unstructured::new(&buf);
+        let mut cfg = Config::arbitrary(&mut u).unwrap();
+        cfg.disallow_traps = true;
+        cfg.gc_enabled = true;
+        cfg.reference_types_enabled = true;
+        cfg.bulk_memory_enabled = true;
+        if let Ok(module) = Module::new(cfg, &mut u) {
+            let wasm_bytes = module.to_bytes();
+            let mut validator = Validator::new_with_features(WasmFeatures::all());
+            validate(&mut validator, &wasm_bytes);
+
+            for payload in wasmparser::Parser::new(0).parse_all(&wasm_bytes) {
+                let wasmparser::Payload::CodeSectionEntry(body) = payload.unwrap() else {
+                    continue;
+                };
+                for op in body.get_operators_reader().unwrap() {
+                    match op.unwrap() {
+                        wasmparser::Operator::ArrayGet { .. }
+                        | wasmparser::Operator::ArrayGetS { .. }
+                        | wasmparser::Operator::ArrayGetU { .. }
+                        | wasmparser::Operator::ArraySet { .. }
+                        | wasmparser::Operator::ArrayFill { .. }
+                        | wasmparser::Operator::ArrayCopy { .. }
+                        | wasmparser::Operator::ArrayInitData { .. }
+                        | wasmparser::Operator::ArrayInitElem { .. } => guarded += 1,
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+    // Trapping array accesses are guarded rather than never generated.
+    assert!(guarded > 0);
+}
+
 #[test]
 fn smoke_test_disallow_floats() {
     let mut rng = SmallRng::seed_from_u64(0);
@@ -186,6 +228,71 @@ fn smoke_test_wasm_gc() {
     }
 }
 
+#[test]
+fn smoke_test_wasm_gc_non_nullable() {
+    let mut rng = SmallRng::seed_from_u64(0);
+    let mut buf = vec![0; 2048];
+    let mut non_nullable = 0;
+    let mut constructed = 0;
+    for i in 0..1024 {
+        rng.fill_bytes(&mut buf);
+        let mut u = Unstructured::new(&buf);
+        let config = Config {
+            gc_enabled: true,
+            reference_types_enabled: true,
+            shared_everything_threads_enabled: true,
+            disallow_traps: i % 2 == 0,
+            ..Config::default()
+        };
+        if let Ok(module) = Module::new(config, &mut u) {
+            let wasm_bytes = module.to_bytes();
+            let mut validator = Validator::new_with_features(WasmFeatures::all());
+            validate(&mut validator, &wasm_bytes);
+
+            for payload in wasmparser::Parser::new(0).parse_all(&wasm_bytes) {
+                let ops = match payload.unwrap() {
+                    wasmparser::Payload::GlobalSection(reader) => {
+                        let mut ops = Vec::new();
+                        for global in reader {
+                            let global = global.unwrap();
+                            if let wasmparser::ValType::Ref(r) = global.ty.content_type {
+                                non_nullable += usize::from(!r.is_nullable());
+                            }
+                            for op in global.init_expr.get_operators_reader() {
+                                ops.push(op.unwrap());
+                            }
+                        }
+                        ops
+                    }
+                    wasmparser::Payload::CodeSectionEntry(body) => body
+                        .get_operators_reader()
+                        .unwrap()
+                        .into_iter()
+                        .collect::<Result<_, _>>()
+                        .unwrap(),
+                    _ => continue,
+                };
+                for op in ops {
+                    match op {
+                        wasmparser::Operator::StructNew { .. }
+                        | wasmparser::Operator::StructNewDefault { .. }
+                        | wasmparser::Operator::ArrayNew { .. }
+                        | wasmparser::Operator::ArrayNewDefault { .. }
+                        | wasmparser::Operator::ArrayNewFixed { .. }
+                        | wasmparser::Operator::RefI31
+                        | wasmparser::Operator::RefI31Shared => constructed += 1,
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+    // Non-nullable references are generated, along with the GC objects needed
+    // to initialize them.
+    assert!(non_nullable > 0);
+    assert!(constructed > 0);
+}
+
 #[test]
 fn smoke_test_wasm_custom_page_sizes() {
     let mut rng = SmallRng::seed_from_u64(0);
@@ -204,3 +311,160 @@ fn smoke_test_wasm_custom_page_sizes() {
         }
     }
 }
+
+#[test]
+fn smoke_test_wasm_stack_switching() {
+    let mut rng = SmallRng::seed_from_u64(0);
+    let mut buf = vec![0; 2048];
+    let mut cont_instructions = 0;
+    for _ in 0..1024 {
+        rng.fill_bytes(&mut buf);
+        let mut u = Unstructured::new(&buf);
+        let config = Config {
+            gc_enabled: true,
+            reference_types_enabled: true,
+            exceptions_enabled: true,
+            stack_switching_enabled: true,
+            ..Config::default()
+        };
+        if let Ok(module) = Module::new(config, &mut u) {
+            let wasm_bytes = module.to_bytes();
+            let mut validator = Validator::new_with_features(WasmFeatures::all());
+            validate(&mut validator, &wasm_bytes);
+
+            for payload in wasmparser::Parser::new(0).parse_all(&wasm_bytes) {
+                let wasmparser::Payload::CodeSectionEntry(body) = payload.unwrap() else {
+                    continue;
+                };
+                for op in body.get_operators_reader().unwrap() {
+                    match op.unwrap() {
+                        wasmparser::Operator::ContNew { .. }
+                        | wasmparser::Operator::ContBind { .. }
+                        | wasmparser::Operator::Suspend { .. }
+                        | wasmparser::Operator::Resume { .. }
+                        | wasmparser::Operator::ResumeThrow { .. }
+                        | wasmparser::Operator::Switch { .. } => cont_instructions += 1,
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+    assert!(cont_instructions > 0);
+}
+
+#[test]
+fn smoke_test_names_and_branch_hints() {
+    let mut rng = SmallRng::seed_from_u64(0);
+    let mut buf = vec![0; 2048];
+    let mut hints = 0;
+    for _ in 0..1024 {
+        rng.fill_bytes(&mut buf);
+        let mut u = Unstructured::new(&buf);
+        let mut cfg = Config::arbitrary(&mut u).unwrap();
+        cfg.generate_names = true;
+        cfg.generate_branch_hints = true;
+        if let Ok(module) = Module::new(cfg, &mut u) {
+            let wasm_bytes = module.to_bytes();
+            let mut validator = Validator::new_with_features(WasmFeatures::all());
+            validate(&mut validator, &wasm_bytes);
+
+            let mut has_names = false;
+            for payload in wasmparser::Parser::new(0).parse_all(&wasm_bytes) {
+                let wasmparser::Payload::CustomSection(section) = payload.unwrap() else {
+                    continue;
+                };
+                match section.as_known() {
+                    wasmparser::KnownCustom::Name(reader) => {
+                        for subsection in reader {
+                            subsection.unwrap();
+                        }
+                        has_names = true;
+                    }
+                    wasmparser::KnownCustom::BranchHints(reader) => {
+                        for func in reader {
+                            for hint in func.unwrap().hints {
+                                hint.unwrap();
+                                hints += 1;
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            assert!(has_names);
+
+            // Names and hints survive a trip through the text format.
+            let wat = wasmprinter::print_bytes(&wasm_bytes).unwrap();
+            let wasm_bytes = wat::parse_str(&wat).unwrap();
+            let mut validator = Validator::new_with_features(WasmFeatures::all());
+            validate(&mut validator, &wasm_bytes);
+        }
+    }
+    assert!(hints > 0);
+}
+
+#[test]
+#[cfg(feature = "wasmparser")]
+fn smoke_test_swarm_mode() {
+    let mut rng = SmallRng::seed_from_u64(0);
+    let mut buf = vec![0; 2048];
+    let mut disabled_simd = false;
+    for _ in 0..1024 {
+        rng.fill_bytes(&mut buf);
+        let mut u = Unstructured::new(&buf);
+        let config = Config::default().swarm(&mut u).unwrap();
+        disabled_simd |= !config.simd_enabled;
+        if let Ok(module) = Module::new(config.clone(), &mut u) {
+            let wasm_bytes = module.to_bytes();
+            // Only the features which survived are used.
+            let mut validator = Validator::new_with_features(config.features());
+            validate(&mut validator, &wasm_bytes);
+        }
+    }
+    assert!(disabled_simd);
+}
+
+#[test]
+fn smoke_test_instruction_weights() {
+    let mut weights = InstructionWeights::default();
+    weights.set("i64.div_s", 1000).unwrap();
+    weights.set("i64.div_u", 1000).unwrap();
+    weights.set("i64.popcnt", 0).unwrap();
+    weights.set("vector", 0).unwrap();
+    assert!(weights.set("i64.div", 1).is_err());
+
+    let mut rng = SmallRng::seed_from_u64(0);
+    let mut buf = vec![0; 2048];
+    let mut divs = 0;
+    for _ in 0..1024 {
+        rng.fill_bytes(&mut buf);
+        let mut u = Unstructured::new(&buf);
+        let mut cfg = Config::arbitrary(&mut u).unwrap();
+        cfg.instruction_weights = weights.clone();
+        if let Ok(module) = Module::new(cfg, &mut u) {
+            let wasm_bytes = module.to_bytes();
+            let mut validator = Validator::new_with_features(WasmFeatures::all());
+            validate(&mut validator, &wasm_bytes);
+
+            for payload in wasmparser::Parser::new(0).parse_all(&wasm_bytes) {
+                let wasmparser::Payload::CodeSectionEntry(body) = payload.unwrap() else {
+                    continue;
+                };
+                let mut reader = body.get_operators_reader().unwrap();
+                while !reader.eof() {
+                    match reader.read().unwrap() {
+                        wasmparser::Operator::I64DivS | wasmparser::Operator::I64DivU => divs += 1,
+                        op @ (wasmparser::Operator::I64Popcnt
+                        | wasmparser::Operator::F32x4Sqrt
+                        | wasmparser::Operator::I8x16Shuffle { .. }) => {
+                            panic!("generated {op:?} with a weight of zero")
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+    assert!(divs > 100, "only generated {divs} divisions");
+}
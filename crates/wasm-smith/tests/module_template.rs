@@ -0,0 +1,154 @@
+#![cfg(feature = "wasmparser")]
+
+use arbitrary::Unstructured;
+use rand::{RngCore, SeedableRng, rngs::SmallRng};
+use wasm_smith::{Config, Module, expand_holes};
+use wasmparser::{Operator, Parser, Payload, Validator};
+
+mod common;
+use common::validate;
+
+const TEMPLATE: &str = r#"
+(module
+  (memory (export "m") 1)
+  (global $g (mut i32) (i32.const 0))
+  (table $t 2 funcref)
+  (elem (table $t) (i32.const 0) func $f $g)
+  ;; (hole) in a comment is left alone
+  (data (i32.const 8) "(hole 2)")
+
+  ;; A hole making up a whole function body.
+  (func $f (export "f") (param i32) (result i32)
+    (hole 50))
+
+  ;; A typed hole in a loop which may branch back to the loop.
+  (func $g (export "g") (param i64) (result i64)
+    (block $out (result i64)
+      local.get 0
+      (loop $l (param i64) (result i64)
+        (hole 10 (param i64) (result i64 i32))
+        br_if $l))
+    i64.const 1
+    i64.add)
+
+  ;; Holes inside of an `if` and following an operand.
+  (func (export "h") (result f32)
+    i32.const 1
+    (if (result f32) (then (hole 5)) (else f32.const 2))
+    (hole 4 (param f32) (result f32)))
+
+  ;; Holes in the middle of a block, both around template code.
+  (func (export "i") (param i32)
+    local.get 0
+    (hole)
+    drop
+    (hole 3)
+    global.get $g
+    i32.eqz
+    global.set $g)
+
+  ;; A hole at the end of a block with operands already on the stack.
+  (func (export "j") (param i32 i32) (result i32)
+    local.get 0
+    local.get 1
+    (hole 8))
+)
+"#;
+
+#[test]
+fn smoke_test_module_template() {
+    let template = wat::parse_str(expand_holes(TEMPLATE)).unwrap();
+    let template_ops = count_operators(&template);
+
+    let mut rng = SmallRng::seed_from_u64(0);
+    let mut buf = vec![0; 2048];
+    let mut n_filled = 0;
+    for _ in 0..512 {
+        rng.fill_bytes(&mut buf);
+        let mut u = Unstructured::new(&buf);
+
+        let mut config = Config::default();
+        config.module_template = Some(template.clone());
+        config.disallow_traps = u.arbitrary().unwrap();
+        config.canonicalize_nans = u.arbitrary().unwrap();
+        let features = config.features();
+
+        let Ok(mut module) = Module::new(config, &mut u) else {
+            continue;
+        };
+        if u.arbitrary().unwrap() {
+            module.ensure_termination(1000).unwrap();
+        }
+        let wasm_bytes = module.to_bytes();
+        let mut validator = Validator::new_with_features(features);
+        validate(&mut validator, &wasm_bytes);
+
+        let exports = export_names(&wasm_bytes);
+        assert_eq!(exports, ["m", "f", "g", "h", "i", "j"]);
+        if count_operators(&wasm_bytes) > template_ops {
+            n_filled += 1;
+        }
+    }
+    assert!(
+        n_filled > 256,
+        "only {n_filled} modules had their holes filled"
+    );
+}
+
+#[test]
+fn module_template_with_no_holes() {
+    let template = wat::parse_str(
+        r#"
+          (module
+            (func (export "answer") (result i32)
+              (block $not_a_hole (result i32) i32.const 42)))
+        "#,
+    )
+    .unwrap();
+
+    let mut config = Config::default();
+    config.module_template = Some(template.clone());
+    let module = Module::new(config, &mut Unstructured::new(&[1; 64])).unwrap();
+    let wasm_bytes = module.to_bytes();
+    validate(&mut Validator::new(), &wasm_bytes);
+    assert_eq!(count_operators(&wasm_bytes), count_operators(&template));
+}
+
+#[test]
+fn expand_holes_text() {
+    assert_eq!(
+        expand_holes(r#"(func (hole) (hole 7 (result i32)) ;; (hole)"#),
+        r#"(func (block $hole unreachable) (block $hole:7 (result i32) unreachable) ;; (hole)"#,
+    );
+    assert_eq!(
+        expand_holes(r#"(data "(hole)") (; (hole 1) ;) (holes)"#),
+        r#"(data "(hole)") (; (hole 1) ;) (holes)"#,
+    );
+}
+
+fn export_names(wasm: &[u8]) -> Vec<String> {
+    let mut names = Vec::new();
+    for payload in Parser::new(0).parse_all(wasm) {
+        if let Payload::ExportSection(reader) = payload.unwrap() {
+            for export in reader {
+                names.push(export.unwrap().name.to_string());
+            }
+        }
+    }
+    names
+}
+
+fn count_operators(wasm: &[u8]) -> usize {
+    let mut count = 0;
+    for payload in Parser::new(0).parse_all(wasm) {
+        if let Payload::CodeSectionEntry(body) = payload.unwrap() {
+            let mut reader = body.get_operators_reader().unwrap();
+            while !reader.eof() {
+                if !matches!(reader.read().unwrap(), Operator::Nop) {
+                    count += 1;
+                }
+            }
+        }
+    }
+    count
+}
@@ -0,0 +1,58 @@
+use crate::{BinaryReader, FromReader, Result, SectionLimited};
+
+/// A reader for a generic `metadata.code.*` custom section.
+///
+/// Code metadata sections attach opaque bytes to the offsets of instructions
+/// within function bodies, where the meaning of the bytes depends on the name
+/// of the section. The `metadata.code.branch_hint` section follows this
+/// format as well but is parsed by the more specific
+/// [`BranchHintSectionReader`](crate::BranchHintSectionReader).
+pub type CodeMetadataSectionReader<'a> = SectionLimited<'a, CodeMetadataFunction<'a>>;
+
+/// Code metadata for a single function.
+///
+/// Produced from [`CodeMetadataSectionReader`].
+#[derive(Debug, Clone)]
+pub struct CodeMetadataFunction<'a> {
+    /// The function that this metadata applies to.
+    pub func: u32,
+    /// The metadata available for instructions in this function.
+    pub metadata: SectionLimited<'a, CodeMetadata<'a>>,
+}
+
+impl<'a> FromReader<'a> for CodeMetadataFunction<'a> {
+    fn from_reader(reader: &mut BinaryReader<'a>) -> Result<Self> {
+        let func = reader.read_var_u32()?;
+        // FIXME(#188) ideally wouldn't have to do skips here
+        let metadata = reader.skip(|reader| {
+            let items_count = reader.read_var_u32()?;
+            for _ in 0..items_count {
+                reader.read::<CodeMetadata>()?;
+            }
+            Ok(())
+        })?;
+        Ok(CodeMetadataFunction {
+            func,
+            metadata: SectionLimited::new(metadata)?,
+        })
+    }
+}
+
+/// Metadata for a single instruction.
+#[derive(Debug, Copy, Clone)]
+pub struct CodeMetadata<'a> {
+    /// The byte offset, from the start of the function's body, of where the
+    /// instruction that this metadata applies to lives.
+    pub func_offset: u32,
+    /// The payload of this metadata.
+    pub data: &'a [u8],
+}
+
+impl<'a> FromReader<'a> for CodeMetadata<'a> {
+    fn from_reader(reader: &mut BinaryReader<'a>) -> Result<Self> {
+        let func_offset = reader.read_var_u32()?;
+        let size = reader.read_var_u32()? as usize;
+        let data = reader.read_bytes(size)?;
+        Ok(CodeMetadata { func_offset, data })
+    }
+}
@@ -0,0 +1,63 @@
+use std::fmt;
+
+/// An item whose contents may be elided when printing, passed to the
+/// predicate configured with [`Config::filter`](crate::Config::filter).
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct FilterItem<'a> {
+    /// What kind of item this is.
+    pub kind: FilterKind,
+    /// The index of this item within its index space.
+    pub index: u32,
+    /// The name of this item from the `name` section, if any.
+    pub name: Option<&'a str>,
+    /// The names that this item is exported under from its module.
+    pub exports: &'a [String],
+    /// The modules and components that this item is nested within,
+    /// outermost first.
+    ///
+    /// This is empty for items of the top-level module.
+    pub scopes: &'a [FilterScope],
+}
+
+/// The kinds of items described by [`FilterItem`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FilterKind {
+    /// A function, whose contents are its body.
+    Func,
+    /// A data segment, whose contents are its bytes.
+    Data,
+    /// An element segment, whose contents are its items.
+    Elem,
+}
+
+/// A module or component which contains a [`FilterItem`].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct FilterScope {
+    /// Whether this is a core module or a component.
+    pub kind: FilterScopeKind,
+    /// The index of this module or component within its parent component.
+    pub index: u32,
+    /// The name of this module or component from the `name` section of its
+    /// parent, if any.
+    pub name: Option<String>,
+}
+
+/// The kinds of scopes described by [`FilterScope`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FilterScopeKind {
+    /// A core wasm module.
+    Module,
+    /// A component.
+    Component,
+}
+
+pub(crate) struct Filter(pub(crate) Box<dyn Fn(&FilterItem<'_>) -> bool + Send + Sync>);
+
+impl fmt::Debug for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Filter").finish_non_exhaustive()
+    }
+}
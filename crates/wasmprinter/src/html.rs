@@ -0,0 +1,198 @@
+use crate::{ItemId, Print};
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write as _;
+use std::io;
+
+/// A [`Print`] implementation which renders the text format as a standalone
+/// HTML document.
+///
+/// Syntax is highlighted with CSS classes and the name of every item links
+/// to where the item is defined. Definitions have an `id` attribute of the
+/// form `s{scope}-{namespace}-{index}` (with the parent index inserted
+/// before `index` for locals and fields, see [`ItemId`]) so they can also be
+/// linked to from elsewhere. References to types additionally show the
+/// definition of the type when hovered over.
+///
+/// After printing [`PrintHtml::finish`] must be called to complete the
+/// document.
+///
+/// Note that when instructions are printed in folded form only the
+/// instructions themselves are highlighted and their operands aren't linked.
+pub struct PrintHtml<T> {
+    dst: T,
+    started: bool,
+    close: Option<&'static str>,
+    line: String,
+    type_def: Option<ItemId>,
+    type_defs: HashMap<ItemId, String>,
+    defined: HashSet<ItemId>,
+}
+
+const HEADER: &str = "\
+<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<style>
+body { background: #fff; color: #000; }
+a { color: inherit; }
+a:target, span:target { background: #ff0; }
+.name { color: #a0a; }
+.literal { color: #a00; }
+.keyword { color: #a50; font-weight: bold; }
+.type { color: #070; font-weight: bold; }
+.comment { color: #077; }
+</style>
+</head>
+<body>
+<pre>";
+
+const FOOTER: &str = "</pre>
+</body>
+</html>
+";
+
+impl<T: io::Write> PrintHtml<T> {
+    /// Creates a new printer which writes the HTML document to `dst`.
+    pub fn new(dst: T) -> PrintHtml<T> {
+        PrintHtml {
+            dst,
+            started: false,
+            close: None,
+            line: String::new(),
+            type_def: None,
+            type_defs: HashMap::new(),
+            defined: HashSet::new(),
+        }
+    }
+
+    /// Completes the HTML document and returns the underlying writer.
+    pub fn finish(mut self) -> io::Result<T> {
+        self.start()?;
+        self.close()?;
+        self.dst.write_all(FOOTER.as_bytes())?;
+        Ok(self.dst)
+    }
+
+    fn start(&mut self) -> io::Result<()> {
+        if !self.started {
+            self.started = true;
+            self.dst.write_all(HEADER.as_bytes())?;
+        }
+        Ok(())
+    }
+
+    fn close(&mut self) -> io::Result<()> {
+        if let Some(close) = self.close.take() {
+            self.dst.write_all(close.as_bytes())?;
+        }
+        Ok(())
+    }
+
+    fn open(&mut self, tag: &str, close: &'static str) -> io::Result<()> {
+        self.start()?;
+        self.close()?;
+        self.dst.write_all(tag.as_bytes())?;
+        self.close = Some(close);
+        Ok(())
+    }
+
+    fn span(&mut self, class: &str) -> io::Result<()> {
+        self.open(&format!("<span class=\"{class}\">"), "</span>")
+    }
+}
+
+fn anchor(item: &ItemId) -> String {
+    let mut ret = format!("s{}-{}", item.scope, item.namespace);
+    if let Some(parent) = item.parent {
+        write!(ret, "-{parent}").unwrap();
+    }
+    write!(ret, "-{}", item.index).unwrap();
+    ret
+}
+
+fn is_type(item: &ItemId) -> bool {
+    matches!(item.namespace, "type" | "component-type")
+}
+
+fn escape(s: &str) -> String {
+    let mut ret = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => ret.push_str("&amp;"),
+            '<' => ret.push_str("&lt;"),
+            '>' => ret.push_str("&gt;"),
+            '"' => ret.push_str("&quot;"),
+            c => ret.push(c),
+        }
+    }
+    ret
+}
+
+impl<T: io::Write> Print for PrintHtml<T> {
+    fn write_str(&mut self, s: &str) -> io::Result<()> {
+        self.start()?;
+        self.line.push_str(s);
+        self.dst.write_all(escape(s).as_bytes())
+    }
+
+    fn newline(&mut self) -> io::Result<()> {
+        // Types are printed on a single line, so once the line with a type's
+        // definition is finished remember it to show when hovering over
+        // references to the type.
+        if let Some(item) = self.type_def.take() {
+            self.type_defs.insert(item, self.line.trim().to_string());
+        }
+        self.line.truncate(0);
+        self.write_str("\n")
+    }
+
+    fn start_definition(&mut self, item: &ItemId) -> io::Result<()> {
+        if is_type(item) {
+            self.type_def = Some(*item);
+        }
+        self.defined.insert(*item);
+        self.open(
+            &format!("<span class=\"name\" id=\"{}\">", anchor(item)),
+            "</span>",
+        )
+    }
+
+    fn start_reference(&mut self, item: &ItemId) -> io::Result<()> {
+        // Only link locals once their definition has been seen since unnamed
+        // locals have no definition to link to.
+        if item.namespace == "local" && !self.defined.contains(item) {
+            return self.start_name();
+        }
+        let mut tag = format!("<a class=\"name\" href=\"#{}\"", anchor(item));
+        if let Some(def) = self.type_defs.get(item) {
+            write!(tag, " title=\"{}\"", escape(def)).unwrap();
+        }
+        tag.push('>');
+        self.open(&tag, "</a>")
+    }
+
+    fn start_literal(&mut self) -> io::Result<()> {
+        self.span("literal")
+    }
+
+    fn start_name(&mut self) -> io::Result<()> {
+        self.span("name")
+    }
+
+    fn start_keyword(&mut self) -> io::Result<()> {
+        self.span("keyword")
+    }
+
+    fn start_type(&mut self) -> io::Result<()> {
+        self.span("type")
+    }
+
+    fn start_comment(&mut self) -> io::Result<()> {
+        self.span("comment")
+    }
+
+    fn reset_color(&mut self) -> io::Result<()> {
+        self.close()
+    }
+}
@@ -26,6 +26,8 @@ const MAX_WASM_FUNCTION_SIZE: u32 = 128 * 1024;
 
 #[cfg(feature = "component-model")]
 mod component;
+mod filter;
+mod html;
 #[cfg(feature = "validate")]
 mod operand_stack;
 #[cfg(not(feature = "validate"))]
@@ -34,8 +36,12 @@ mod operand_stack_disabled;
 use operand_stack_disabled as operand_stack;
 mod operator;
 mod print;
+mod source_lines;
 
+pub use self::filter::*;
+pub use self::html::*;
 pub use self::print::*;
+pub use self::source_lines::*;
 
 /// Reads a WebAssembly `file` from the filesystem and then prints it into an
 /// in-memory `String`.
@@ -65,6 +71,8 @@ pub struct Config {
     fold_instructions: bool,
     indent_text: String,
     print_operand_stack: bool,
+    source_lines: Option<Box<dyn SourceLines + Send + Sync>>,
+    filter: Option<filter::Filter>,
 }
 
 impl Default for Config {
@@ -76,6 +84,8 @@ impl Default for Config {
             fold_instructions: false,
             indent_text: "  ".to_string(),
             print_operand_stack: false,
+            source_lines: None,
+            filter: None,
         }
     }
 }
@@ -87,7 +97,19 @@ struct Printer<'cfg, 'env> {
     nesting: u32,
     line: usize,
     group_lines: Vec<usize>,
-    code_section_hints: Vec<(u32, Vec<(usize, BranchHint)>)>,
+    code_metadata: HashMap<u32, Vec<CodeAnnotation>>,
+    scope: u32,
+}
+
+/// An item from a `metadata.code.*` section, such as a branch hint, which
+/// is printed as an annotation before the instruction at `func_offset`.
+struct CodeAnnotation {
+    /// The offset of this item within the binary.
+    offset: usize,
+    func_offset: u32,
+    /// The name of the section without the `metadata.code.` prefix.
+    name: String,
+    data: Vec<u8>,
 }
 
 #[derive(Default)]
@@ -95,6 +117,7 @@ struct CoreState {
     types: Vec<Option<SubType>>,
     funcs: u32,
     func_to_type: Vec<Option<u32>>,
+    func_exports: HashMap<u32, Vec<String>>,
     memories: u32,
     tags: u32,
     tag_to_type: Vec<Option<u32>>,
@@ -135,6 +158,13 @@ struct NamingMap<T, K> {
     _marker: marker::PhantomData<K>,
 }
 
+impl<K> NamingMap<u32, K> {
+    /// Returns the original name of `index` from the `name` section, if any.
+    fn name(&self, index: u32) -> Option<&str> {
+        self.index_to_name.get(&index).map(|n| &n.name[..])
+    }
+}
+
 impl<T, K> Default for NamingMap<T, K> {
     fn default() -> NamingMap<T, K> {
         NamingMap {
@@ -152,10 +182,10 @@ struct ComponentState {
     instances: u32,
     components: u32,
     values: u32,
-    type_names: NamingMap<u32, NameType>,
-    func_names: NamingMap<u32, NameFunc>,
+    type_names: NamingMap<u32, NameComponentType>,
+    func_names: NamingMap<u32, NameComponentFunc>,
     component_names: NamingMap<u32, NameComponent>,
-    instance_names: NamingMap<u32, NameInstance>,
+    instance_names: NamingMap<u32, NameComponentInstance>,
     value_names: NamingMap<u32, NameValue>,
 }
 
@@ -165,6 +195,8 @@ struct State {
     core: CoreState,
     #[cfg(feature = "component-model")]
     component: ComponentState,
+    scopes: Vec<FilterScope>,
+    scope: u32,
     custom_section_place: Option<(&'static str, usize)>,
     // `custom_section_place` stores the text representation of the location where
     // a custom section should be serialized in the binary format.
@@ -183,6 +215,8 @@ impl State {
             core: CoreState::default(),
             #[cfg(feature = "component-model")]
             component: ComponentState::default(),
+            scopes: Vec::new(),
+            scope: 0,
             custom_section_place: None,
         }
     }
@@ -220,6 +254,26 @@ impl Config {
         self
     }
 
+    /// Only print the contents of items for which `filter` returns `true`.
+    ///
+    /// This is a more selective version of [`Config::print_skeleton`] where
+    /// the contents of functions, data segments and element segments are
+    /// only printed if `filter` matches them. All other items are printed as
+    /// a skeleton with their contents replaced by `...`, as are custom
+    /// sections. Each item is described by a [`FilterItem`] which includes
+    /// its name, its export names, and the path of modules and components
+    /// that it's nested within.
+    ///
+    /// If [`Config::print_skeleton`] is enabled then it takes precedence and
+    /// no contents are printed.
+    pub fn filter(
+        &mut self,
+        filter: impl Fn(&FilterItem<'_>) -> bool + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.filter = Some(filter::Filter(Box::new(filter)));
+        self
+    }
+
     /// Assign names to all unnamed items.
     ///
     /// If enabled then any previously unnamed item will have a name synthesized
@@ -279,6 +333,24 @@ impl Config {
         self
     }
 
+    /// Annotate instructions with their location in the original source code.
+    ///
+    /// The `source_lines` provider is consulted for each instruction in a
+    /// function body and the location is printed as a comment next to the
+    /// instruction, for example `;; src/foo.c:42`. To keep the output
+    /// readable a location is only printed when it differs from that of the
+    /// previous instruction in the same function. Locations within inlined
+    /// functions additionally mention the call site they were inlined at.
+    ///
+    /// By default no source locations are printed.
+    pub fn source_lines(
+        &mut self,
+        source_lines: impl SourceLines + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.source_lines = Some(Box::new(source_lines));
+        self
+    }
+
     /// Select the string to use when indenting.
     ///
     /// The indent allowed here are arbitrary and unchecked. You should enter
@@ -298,10 +370,11 @@ impl Config {
         Printer {
             config: self,
             result,
-            code_section_hints: Vec::new(),
+            code_metadata: HashMap::new(),
             group_lines: Vec::new(),
             line: 0,
             nesting: 0,
+            scope: 0,
         }
         .print_contents(wasm)
     }
@@ -364,6 +437,7 @@ impl Printer<'_, '_> {
         mut parser: Parser,
         state: &mut State,
     ) -> Result<()> {
+        self.code_metadata.clear();
         loop {
             let payload = match parser.parse(bytes, true)? {
                 Chunk::NeedMoreData(_) => unreachable!(),
@@ -410,6 +484,10 @@ impl Printer<'_, '_> {
                         KnownCustom::BranchHints(reader) => {
                             drop(self.register_branch_hint_section(reader));
                         }
+                        KnownCustom::CodeMetadata(reader) => {
+                            let name = &c.name()["metadata.code.".len()..];
+                            drop(self.register_code_metadata_section(name, reader));
+                        }
                         _ => {}
                     }
                 }
@@ -444,6 +522,7 @@ impl Printer<'_, '_> {
 
         let mut expected = None;
         let mut states: Vec<State> = Vec::new();
+        let mut next_scope = 0;
         let mut parser = Parser::new(0);
         #[cfg(feature = "component-model")]
         let mut parsers = Vec::new();
@@ -525,8 +604,39 @@ impl Printer<'_, '_> {
                         }
                     }
 
+                    #[cfg(feature = "component-model")]
+                    if states.len() > 1 && self.config.filter.is_some() {
+                        let parent = &states[states.len() - 2];
+                        let scope = match encoding {
+                            Encoding::Module => FilterScope {
+                                kind: FilterScopeKind::Module,
+                                index: parent.core.modules,
+                                name: parent
+                                    .core
+                                    .module_names
+                                    .name(parent.core.modules)
+                                    .map(String::from),
+                            },
+                            Encoding::Component => FilterScope {
+                                kind: FilterScopeKind::Component,
+                                index: parent.component.components,
+                                name: parent
+                                    .component
+                                    .component_names
+                                    .name(parent.component.components)
+                                    .map(String::from),
+                            },
+                        };
+                        let mut scopes = parent.scopes.clone();
+                        scopes.push(scope);
+                        states.last_mut().unwrap().scopes = scopes;
+                    }
+
                     let len = states.len();
                     let state = states.last_mut().unwrap();
+                    state.scope = next_scope;
+                    self.scope = next_scope;
+                    next_scope += 1;
 
                     // First up try to find the `name` subsection which we'll use to print
                     // pretty names everywhere.
@@ -565,7 +675,8 @@ impl Printer<'_, '_> {
                                 nesting: 0,
                                 line: 0,
                                 group_lines: Vec::new(),
-                                code_section_hints: Vec::new(),
+                                code_metadata: HashMap::new(),
+                                scope: self.scope,
                             })
                             .print_known_custom_section(c.clone())
                             {
@@ -633,7 +744,7 @@ impl Printer<'_, '_> {
                 }
                 Payload::ExportSection(s) => {
                     Self::ensure_module(&states)?;
-                    self.print_exports(states.last().unwrap(), s)?;
+                    self.print_exports(states.last_mut().unwrap(), s)?;
                     self.update_custom_section_place(&mut states, "after export");
                 }
                 Payload::StartSection { func, range } => {
@@ -742,6 +853,7 @@ impl Printer<'_, '_> {
                     {
                         let state = states.pop().unwrap();
                         if let Some(parent) = states.last_mut() {
+                            self.scope = parent.scope;
                             match state.encoding {
                                 Encoding::Module => {
                                     parent.core.modules += 1;
@@ -1396,20 +1508,12 @@ impl Printer<'_, '_> {
             .print_core_functype_idx(state, ty, Some(func_idx))?
             .unwrap_or(0);
 
-        // Hints are stored on `self` in reverse order of function index so
-        // check the last one and see if it matches this function.
-        let hints = match self.code_section_hints.last() {
-            Some((f, _)) if *f == func_idx => {
-                let (_, hints) = self.code_section_hints.pop().unwrap();
-                hints
-            }
-            _ => Vec::new(),
-        };
+        let metadata = self.code_metadata.remove(&func_idx).unwrap_or_default();
 
-        if self.config.print_skeleton {
+        if self.skeleton(state, FilterKind::Func, func_idx) {
             self.result.write_str(" ...")?;
         } else {
-            self.print_func_body(state, func_idx, params, &body, &hints, validator)?;
+            self.print_func_body(state, func_idx, params, &body, &metadata, validator)?;
         }
 
         self.end_group()?;
@@ -1423,7 +1527,7 @@ impl Printer<'_, '_> {
         func_idx: u32,
         params: u32,
         body: &FunctionBody<'_>,
-        branch_hints: &[(usize, BranchHint)],
+        metadata: &[CodeAnnotation],
         mut validator: Option<operand_stack::FuncValidator>,
     ) -> Result<()> {
         let mut first = true;
@@ -1465,6 +1569,7 @@ impl Printer<'_, '_> {
 
         let nesting_start = self.nesting;
         let fold_instructions = self.config.fold_instructions;
+        let source_lines = self.config.source_lines.as_deref();
         let mut operator_state = OperatorState::new(self, OperatorSeparator::Newline);
 
         if fold_instructions {
@@ -1473,19 +1578,21 @@ impl Printer<'_, '_> {
             folded_printer.begin_function(func_idx)?;
             Self::print_operators(
                 &mut reader,
-                branch_hints,
+                metadata,
                 func_start,
                 &mut folded_printer,
                 validator,
+                source_lines,
             )?;
         } else {
             let mut flat_printer = PrintOperator::new(self, state, &mut operator_state);
             Self::print_operators(
                 &mut reader,
-                branch_hints,
+                metadata,
                 func_start,
                 &mut flat_printer,
                 validator,
+                source_lines,
             )?;
         }
 
@@ -1504,12 +1611,14 @@ impl Printer<'_, '_> {
 
     fn print_operators<'a, O: OpPrinter>(
         body: &mut BinaryReader<'a>,
-        mut branch_hints: &[(usize, BranchHint)],
+        mut metadata: &[CodeAnnotation],
         func_start: usize,
         op_printer: &mut O,
         mut validator: Option<operand_stack::FuncValidator>,
+        source_lines: Option<&(dyn SourceLines + Send + Sync)>,
     ) -> Result<()> {
         let mut ops = OperatorsReader::new(body.clone());
+        let mut prev_source = None;
         while !ops.eof() {
             if ops.is_end_then_eof() {
                 let mut annotation = None;
@@ -1528,13 +1637,15 @@ impl Printer<'_, '_> {
                 return Ok(());
             }
 
-            // Branch hints are stored in increasing order of their body offset
-            // so print them whenever their instruction comes up.
-            if let Some(((hint_offset, hint), rest)) = branch_hints.split_first() {
-                if hint.func_offset == (ops.original_position() - func_start) as u32 {
-                    branch_hints = rest;
-                    op_printer.branch_hint(*hint_offset, hint.taken)?;
+            // Code metadata is sorted in increasing order of body offset so
+            // print it whenever its instruction comes up.
+            let func_offset = (ops.original_position() - func_start) as u32;
+            while let Some((item, rest)) = metadata.split_first() {
+                if item.func_offset != func_offset {
+                    break;
                 }
+                metadata = rest;
+                op_printer.code_metadata(item.offset, &item.name, &item.data)?;
             }
             let mut annotation = None;
             if let Some(f) = &mut validator {
@@ -1550,8 +1661,18 @@ impl Printer<'_, '_> {
                     }
                 }
             }
+            // Only print the source location of an instruction if it's
+            // changed since the previous instruction.
+            let mut source = None;
+            if let Some(lines) = source_lines {
+                let cur = source_lines::describe(lines.frames(ops.original_position()));
+                if cur.is_some() && cur != prev_source {
+                    source = cur.clone();
+                }
+                prev_source = cur;
+            }
             op_printer.set_offset(ops.original_position());
-            op_printer.visit_operator(&mut ops, annotation.as_deref())?;
+            op_printer.visit_operator(&mut ops, source.as_deref(), annotation.as_deref())?;
         }
         ops.finish()?; // for the error message
         bail!("unexpected end of operators");
@@ -1586,11 +1707,48 @@ impl Printer<'_, '_> {
         Ok(())
     }
 
-    fn print_exports(&mut self, state: &State, data: ExportSectionReader) -> Result<()> {
+    /// Returns whether the contents of the item `index` of kind `kind` should
+    /// be elided, either due to [`Config::print_skeleton`] or because it
+    /// doesn't match [`Config::filter`].
+    fn skeleton(&self, state: &State, kind: FilterKind, index: u32) -> bool {
+        if self.config.print_skeleton {
+            return true;
+        }
+        let filter = match &self.config.filter {
+            Some(filter) => filter,
+            None => return false,
+        };
+        let name = match kind {
+            FilterKind::Func => state.core.func_names.name(index),
+            FilterKind::Data => state.core.data_names.name(index),
+            FilterKind::Elem => state.core.element_names.name(index),
+        };
+        let exports = match kind {
+            FilterKind::Func => state.core.func_exports.get(&index),
+            FilterKind::Data | FilterKind::Elem => None,
+        };
+        !(filter.0)(&FilterItem {
+            kind,
+            index,
+            name,
+            exports: exports.map(|e| &e[..]).unwrap_or(&[]),
+            scopes: &state.scopes,
+        })
+    }
+
+    fn print_exports(&mut self, state: &mut State, data: ExportSectionReader) -> Result<()> {
         for export in data.into_iter_with_offsets() {
             let (offset, export) = export?;
             self.newline(offset)?;
             self.print_export(state, &export)?;
+            if self.config.filter.is_some() && export.kind == ExternalKind::Func {
+                state
+                    .core
+                    .func_exports
+                    .entry(export.index)
+                    .or_default()
+                    .push(export.name.to_string());
+            }
         }
         Ok(())
     }
@@ -1646,11 +1804,18 @@ impl Printer<'_, '_> {
     where
         K: NamingNamespace,
     {
-        self._print_idx(&names.index_to_name, idx, K::desc())
+        self._print_idx(&names.index_to_name, idx, K::desc(), K::namespace())
     }
 
-    fn _print_idx(&mut self, names: &HashMap<u32, Naming>, idx: u32, desc: &str) -> Result<()> {
-        self.result.start_name()?;
+    fn _print_idx(
+        &mut self,
+        names: &HashMap<u32, Naming>,
+        idx: u32,
+        desc: &str,
+        namespace: &'static str,
+    ) -> Result<()> {
+        self.result
+            .start_reference(&self.item_id(namespace, None, idx))?;
         match names.get(&idx) {
             Some(name) => name.write_identifier(self)?,
             None if self.config.name_unnamed => write!(self.result, "$#{desc}{idx}")?,
@@ -1660,8 +1825,18 @@ impl Printer<'_, '_> {
         Ok(())
     }
 
+    fn item_id(&self, namespace: &'static str, parent: Option<u32>, index: u32) -> ItemId {
+        ItemId {
+            scope: self.scope,
+            namespace,
+            parent,
+            index,
+        }
+    }
+
     fn print_local_idx(&mut self, state: &State, func: u32, idx: u32) -> Result<()> {
-        self.result.start_name()?;
+        self.result
+            .start_reference(&self.item_id("local", Some(func), idx))?;
         match state.core.local_names.index_to_name.get(&(func, idx)) {
             Some(name) => name.write_identifier(self)?,
             None if self.config.name_unnamed => write!(self.result, "$#local{idx}")?,
@@ -1672,7 +1847,8 @@ impl Printer<'_, '_> {
     }
 
     fn print_field_idx(&mut self, state: &State, ty: u32, idx: u32) -> Result<()> {
-        self.result.start_name()?;
+        self.result
+            .start_reference(&self.item_id("field", Some(ty), idx))?;
         match state.core.field_names.index_to_name.get(&(ty, idx)) {
             Some(name) => name.write_identifier(self)?,
             None if self.config.name_unnamed => write!(self.result, "$#field{idx}")?,
@@ -1686,7 +1862,7 @@ impl Printer<'_, '_> {
     where
         K: NamingNamespace,
     {
-        self._print_name(&names.index_to_name, cur_idx, K::desc())
+        self._print_name(&names.index_to_name, cur_idx, K::desc(), K::namespace())
     }
 
     fn _print_name(
@@ -1694,8 +1870,10 @@ impl Printer<'_, '_> {
         names: &HashMap<u32, Naming>,
         cur_idx: u32,
         desc: &str,
+        namespace: &'static str,
     ) -> Result<()> {
-        self.result.start_name()?;
+        self.result
+            .start_definition(&self.item_id(namespace, None, cur_idx))?;
         match names.get(&cur_idx) {
             Some(name) => {
                 name.write(self)?;
@@ -1736,7 +1914,7 @@ impl Printer<'_, '_> {
             }
             self.result.write_str(" ")?;
 
-            if self.config.print_skeleton {
+            if self.skeleton(state, FilterKind::Elem, i as u32) {
                 self.result.write_str("...")?;
             } else {
                 match elem.items {
@@ -1784,7 +1962,7 @@ impl Printer<'_, '_> {
                     self.result.write_str(" ")?;
                 }
             }
-            if self.config.print_skeleton {
+            if self.skeleton(state, FilterKind::Data, i as u32) {
                 self.result.write_str("...")?;
             } else {
                 self.print_bytes(data.data)?;
@@ -1825,10 +2003,10 @@ impl Printer<'_, '_> {
         if fold {
             let mut folded_printer = PrintOperatorFolded::new(self, state, &mut operator_state);
             folded_printer.begin_const_expr();
-            Self::print_operators(&mut reader, &[], 0, &mut folded_printer, None)?;
+            Self::print_operators(&mut reader, &[], 0, &mut folded_printer, None, None)?;
         } else {
             let mut op_printer = PrintOperator::new(self, state, &mut operator_state);
-            Self::print_operators(&mut reader, &[], 0, &mut op_printer, None)?;
+            Self::print_operators(&mut reader, &[], 0, &mut op_printer, None, None)?;
         }
 
         Ok(())
@@ -1891,7 +2069,9 @@ impl Printer<'_, '_> {
 
             // These are parsed during `read_names` and are part of
             // printing elsewhere, so don't print them.
-            KnownCustom::Name(_) | KnownCustom::BranchHints(_) => Ok(()),
+            KnownCustom::Name(_) | KnownCustom::BranchHints(_) | KnownCustom::CodeMetadata(_) => {
+                Ok(())
+            }
             #[cfg(feature = "component-model")]
             KnownCustom::ComponentName(_) => Ok(()),
 
@@ -1912,7 +2092,7 @@ impl Printer<'_, '_> {
             write!(self.result, " ({place})")?;
         }
         self.result.write_str(" ")?;
-        if self.config.print_skeleton {
+        if self.config.print_skeleton || self.config.filter.is_some() {
             self.result.write_str("...")?;
         } else {
             self.print_bytes(section.data())?;
@@ -2035,24 +2215,75 @@ impl Printer<'_, '_> {
     }
 
     fn register_branch_hint_section(&mut self, section: BranchHintSectionReader<'_>) -> Result<()> {
-        self.code_section_hints.clear();
+        let mut items = Vec::new();
         for func in section {
             let func = func?;
-            if self.code_section_hints.len() >= MAX_WASM_FUNCTIONS as usize {
+            if items.len() >= MAX_WASM_FUNCTIONS as usize {
                 bail!("found too many hints");
             }
             if func.hints.count() >= MAX_WASM_FUNCTION_SIZE {
                 bail!("found too many hints");
             }
-            let hints = func
-                .hints
-                .into_iter_with_offsets()
-                .collect::<wasmparser::Result<Vec<_>>>()?;
-            self.code_section_hints.push((func.func, hints));
+            let mut hints = Vec::new();
+            for hint in func.hints.into_iter_with_offsets() {
+                let (offset, hint) = hint?;
+                hints.push(CodeAnnotation {
+                    offset,
+                    func_offset: hint.func_offset,
+                    name: String::from("branch_hint"),
+                    data: vec![u8::from(hint.taken)],
+                });
+            }
+            items.push((func.func, hints));
+        }
+        self.add_code_metadata(items);
+        Ok(())
+    }
+
+    fn register_code_metadata_section(
+        &mut self,
+        name: &str,
+        section: CodeMetadataSectionReader<'_>,
+    ) -> Result<()> {
+        let mut items = Vec::new();
+        for func in section {
+            let func = func?;
+            if items.len() >= MAX_WASM_FUNCTIONS as usize {
+                bail!("found too many code metadata functions");
+            }
+            if func.metadata.count() >= MAX_WASM_FUNCTION_SIZE {
+                bail!("found too many code metadata items");
+            }
+            let mut metadata = Vec::new();
+            for item in func.metadata.into_iter_with_offsets() {
+                let (offset, item) = item?;
+                metadata.push(CodeAnnotation {
+                    offset,
+                    func_offset: item.func_offset,
+                    name: name.to_string(),
+                    data: item.data.to_vec(),
+                });
+            }
+            items.push((func.func, metadata));
         }
-        self.code_section_hints.reverse();
+        self.add_code_metadata(items);
         Ok(())
     }
+
+    /// Merges the fully-parsed contents of a code metadata section with
+    /// those of previous sections.
+    ///
+    /// Sections are only added if they're entirely valid so that malformed
+    /// sections are ignored as a whole.
+    fn add_code_metadata(&mut self, items: Vec<(u32, Vec<CodeAnnotation>)>) {
+        for (func, metadata) in items {
+            let list = self.code_metadata.entry(func).or_default();
+            list.extend(metadata);
+            // Note that this is a stable sort so items for the same
+            // instruction are printed in the order of their sections.
+            list.sort_by_key(|item| item.func_offset);
+        }
+    }
 }
 
 struct NamedLocalPrinter {
@@ -2109,12 +2340,23 @@ impl NamedLocalPrinter {
         // Print the optional name if given...
         match name {
             Some(name) => {
+                if let Some(func) = func {
+                    dst.result
+                        .start_definition(&dst.item_id("local", Some(func), local))?;
+                }
                 name.write(dst)?;
+                if func.is_some() {
+                    dst.result.reset_color()?;
+                }
                 dst.result.write_str(" ")?;
                 self.end_group_after_local = true;
             }
             None if dst.config.name_unnamed && func.is_some() => {
-                write!(dst.result, "$#local{local} ")?;
+                dst.result
+                    .start_definition(&dst.item_id("local", func, local))?;
+                write!(dst.result, "$#local{local}")?;
+                dst.result.reset_color()?;
+                dst.result.write_str(" ")?;
                 self.end_group_after_local = true;
             }
             None => {
@@ -2242,6 +2484,39 @@ impl Printer<'_, '_> {
     print_float!(print_f64 f64 u64 i64 11);
 }
 
+// See https://webassembly.github.io/spec/core/text/values.html#text-id
+pub(crate) fn is_idchar(c: char) -> bool {
+    matches!(
+        c,
+        '0'..='9'
+        | 'a'..='z'
+        | 'A'..='Z'
+        | '!'
+        | '#'
+        | '$'
+        | '%'
+        | '&'
+        | '\''
+        | '*'
+        | '+'
+        | '-'
+        | '.'
+        | '/'
+        | ':'
+        | '<'
+        | '='
+        | '>'
+        | '?'
+        | '@'
+        | '\\'
+        | '^'
+        | '_'
+        | '`'
+        | '|'
+        | '~'
+    )
+}
+
 impl Naming {
     fn new<'a>(
         name: &'a str,
@@ -2278,42 +2553,9 @@ impl Naming {
         {
             kind = NamingKind::SyntheticPrefix(format!("#{group}{index}"));
         }
-        return Naming {
+        Naming {
             kind,
             name: name.to_string(),
-        };
-
-        // See https://webassembly.github.io/spec/core/text/values.html#text-id
-        fn is_idchar(c: char) -> bool {
-            matches!(
-                c,
-                '0'..='9'
-                | 'a'..='z'
-                | 'A'..='Z'
-                | '!'
-                | '#'
-                | '$'
-                | '%'
-                | '&'
-                | '\''
-                | '*'
-                | '+'
-                | '-'
-                | '.'
-                | '/'
-                | ':'
-                | '<'
-                | '='
-                | '>'
-                | '?'
-                | '@'
-                | '\\'
-                | '^'
-                | '_'
-                | '`'
-                | '|'
-                | '~'
-            )
         }
     }
 
@@ -2359,14 +2601,21 @@ impl Naming {
 /// Helper trait for the `NamingMap` type's `K` type parameter.
 trait NamingNamespace {
     fn desc() -> &'static str;
+
+    /// The name of this index space for [`ItemId::namespace`], which unlike
+    /// `desc` is unique amongst the index spaces of a component.
+    fn namespace() -> &'static str {
+        Self::desc()
+    }
 }
 
 macro_rules! naming_namespaces {
-    ($(struct $name:ident => $desc:tt)*) => ($(
+    ($(struct $name:ident => $desc:tt $(as $namespace:tt)?)*) => ($(
         struct $name;
 
         impl NamingNamespace for $name {
             fn desc() -> &'static str { $desc }
+            $(fn namespace() -> &'static str { $namespace })?
         }
     )*)
 }
@@ -2391,6 +2640,9 @@ naming_namespaces! {
     struct NameInstance => "instance"
     struct NameValue => "value"
     struct NameComponent => "component"
+    struct NameComponentType => "type" as "component-type"
+    struct NameComponentFunc => "func" as "component-func"
+    struct NameComponentInstance => "instance" as "component-instance"
 }
 
 fn name_map<K>(into: &mut NamingMap<u32, K>, names: NameMap<'_>, name: &str) -> Result<()> {
@@ -76,7 +76,8 @@ impl FuncValidator {
             nesting: 0,
             line: 0,
             group_lines: Vec::new(),
-            code_section_hints: Vec::new(),
+            code_metadata: Default::default(),
+            scope: 0,
         };
 
         if let Some(&Frame { height, .. }) = self.validator.get_control_frame(0) {
@@ -1,5 +1,6 @@
 use super::{Config, Print, PrintTermcolor, Printer, State};
 use anyhow::{Result, anyhow, bail};
+use std::mem;
 use termcolor::{Ansi, NoColor};
 use wasmparser::VisitSimdOperator;
 use wasmparser::{
@@ -48,6 +49,9 @@ struct Block {
     folded: Vec<FoldedInstruction>,
     predicate: Option<Vec<FoldedInstruction>>,
     consequent: Option<(Vec<FoldedInstruction>, usize)>,
+    /// The `do` and `catch` clauses of a legacy `try` block seen so far,
+    /// where the last one is still being filled in if `kind` is a catch.
+    clauses: Vec<FoldedInstruction>,
     offset: usize,
 }
 
@@ -56,7 +60,7 @@ pub struct PrintOperatorFolded<'printer, 'state, 'a, 'b> {
     state: &'state mut State,
     operator_state: &'printer mut OperatorState,
     control: Vec<Block>,
-    branch_hint: Option<FoldedInstruction>,
+    code_metadata: Vec<FoldedInstruction>,
     original_separator: OperatorSeparator,
 }
 
@@ -238,7 +242,7 @@ impl<'printer, 'state, 'a, 'b> PrintOperator<'printer, 'state, 'a, 'b> {
                 match name {
                     // Only print the name if one is found and there's also no
                     // name conflict.
-                    Some(name) if !name_conflict => name.write(self.printer)?,
+                    Some(name) if !name_conflict => name.write_identifier(self.printer)?,
 
                     // If there's no name conflict, and we're synthesizing
                     // names, and this isn't targeting the function itself then
@@ -533,7 +537,7 @@ macro_rules! define_visit {
     (before_op $self:ident $op:ident) => ($self.separator()?;);
 
     // After some opcodes the label stack is popped.
-    // (after_op $self:ident Delegate) => ($self.label_indices.pop(););
+    (after_op $self:ident Delegate) => ($self.operator_state.label_indices.pop(););
     (after_op $self:ident End) => ($self.operator_state.label_indices.pop(););
     (after_op $self:ident $op:ident) => ();
 
@@ -1405,11 +1409,12 @@ impl<'a> VisitSimdOperator<'a> for PrintOperator<'_, '_, '_, '_> {
 }
 
 pub trait OpPrinter {
-    fn branch_hint(&mut self, offset: usize, taken: bool) -> Result<()>;
+    fn code_metadata(&mut self, offset: usize, name: &str, data: &[u8]) -> Result<()>;
     fn set_offset(&mut self, offset: usize);
     fn visit_operator(
         &mut self,
         reader: &mut OperatorsReader<'_>,
+        source: Option<&str>,
         annotation: Option<&str>,
     ) -> Result<()>;
     fn finalize(&mut self, annotation: Option<&str>) -> Result<()>;
@@ -1417,11 +1422,14 @@ pub trait OpPrinter {
 }
 
 impl OpPrinter for PrintOperator<'_, '_, '_, '_> {
-    fn branch_hint(&mut self, offset: usize, taken: bool) -> Result<()> {
+    fn code_metadata(&mut self, offset: usize, name: &str, data: &[u8]) -> Result<()> {
         self.printer.newline(offset)?;
-        let desc = if taken { "\"\\01\"" } else { "\"\\00\"" };
         self.printer.result.start_comment()?;
-        write!(self.printer.result, "(@metadata.code.branch_hint {desc})")?;
+        write!(
+            self.printer.result,
+            "({})",
+            code_metadata_annotation(name, data)
+        )?;
         self.printer.result.reset_color()?;
         Ok(())
     }
@@ -1433,9 +1441,15 @@ impl OpPrinter for PrintOperator<'_, '_, '_, '_> {
     fn visit_operator(
         &mut self,
         reader: &mut OperatorsReader<'_>,
+        source: Option<&str>,
         annotation: Option<&str>,
     ) -> Result<()> {
         reader.visit_operator(self)??;
+        if let Some(s) = source {
+            self.result().start_comment()?;
+            write!(self.result(), " ;; {s}")?;
+            self.result().reset_color()?;
+        }
         if let Some(s) = annotation {
             self.printer.newline_unknown_pos()?;
             self.result().start_comment()?;
@@ -1461,12 +1475,9 @@ impl OpPrinter for PrintOperator<'_, '_, '_, '_> {
 }
 
 impl OpPrinter for PrintOperatorFolded<'_, '_, '_, '_> {
-    fn branch_hint(&mut self, offset: usize, taken: bool) -> Result<()> {
-        let mut hint = String::new();
-        hint.push_str("@metadata.code.branch_hint ");
-        hint.push_str(if taken { "\"\\01\"" } else { "\"\\00\"" });
-        self.branch_hint = Some(FoldedInstruction {
-            plain: hint,
+    fn code_metadata(&mut self, offset: usize, name: &str, data: &[u8]) -> Result<()> {
+        self.code_metadata.push(FoldedInstruction {
+            plain: code_metadata_annotation(name, data),
             folded: Vec::new(),
             results: 0,
             offset,
@@ -1481,6 +1492,7 @@ impl OpPrinter for PrintOperatorFolded<'_, '_, '_, '_> {
     fn visit_operator(
         &mut self,
         reader: &mut OperatorsReader<'_>,
+        source: Option<&str>,
         annotation: Option<&str>,
     ) -> Result<()> {
         let operator = reader.clone().read()?;
@@ -1501,13 +1513,14 @@ impl OpPrinter for PrintOperatorFolded<'_, '_, '_, '_> {
             nesting: self.printer.nesting,
             line: self.printer.line,
             group_lines: Vec::new(),
-            code_section_hints: Vec::new(),
+            code_metadata: Default::default(),
+            scope: self.printer.scope,
         };
 
         let mut op_printer =
             PrintOperator::new(&mut internal_printer, self.state, self.operator_state);
         reader.visit_operator(&mut op_printer)??;
-        if let Some(s) = annotation {
+        for s in source.into_iter().chain(annotation) {
             internal_printer.result.start_comment()?;
             write!(internal_printer.result, " (; {s}")?;
             internal_printer.result.start_comment()?;
@@ -1534,13 +1547,10 @@ impl OpPrinter for PrintOperatorFolded<'_, '_, '_, '_> {
             Operator::If { blockty } => self.push_if(blockty, inst),
             Operator::Else => self.handle_else(),
             Operator::End => self.handle_end(results),
-
-            Operator::Try { .. }
-            | Operator::Catch { .. }
-            | Operator::CatchAll { .. }
-            | Operator::Delegate { .. } => {
-                bail!("legacy-exceptions not supported")
-            }
+            Operator::Try { blockty } => self.push_block(blockty, FrameKind::LegacyTry, inst),
+            Operator::Catch { .. } => self.handle_catch(FrameKind::LegacyCatch, inst),
+            Operator::CatchAll => self.handle_catch(FrameKind::LegacyCatchAll, inst),
+            Operator::Delegate { .. } => self.handle_delegate(inst, results),
             _ => self.handle_plain(inst, params, results),
         }
     }
@@ -1632,7 +1642,7 @@ impl<'printer, 'state, 'a, 'b> PrintOperatorFolded<'printer, 'state, 'a, 'b> {
             state,
             operator_state,
             control: Vec::new(),
-            branch_hint: None,
+            code_metadata: Vec::new(),
             original_separator,
         }
     }
@@ -1647,6 +1657,7 @@ impl<'printer, 'state, 'a, 'b> PrintOperatorFolded<'printer, 'state, 'a, 'b> {
                 folded: Vec::new(),
                 predicate: None,
                 consequent: None,
+                clauses: Vec::new(),
                 offset: self.operator_state.op_offset,
             }),
             _ => bail!("invalid func_idx"),
@@ -1664,6 +1675,7 @@ impl<'printer, 'state, 'a, 'b> PrintOperatorFolded<'printer, 'state, 'a, 'b> {
             folded: Vec::new(),
             predicate: None,
             consequent: None,
+            clauses: Vec::new(),
             offset: 0,
         });
     }
@@ -1701,9 +1713,7 @@ impl<'printer, 'state, 'a, 'b> PrintOperatorFolded<'printer, 'state, 'a, 'b> {
             results,
             offset: self.operator_state.op_offset,
         };
-        if let Some(hint) = self.branch_hint.take() {
-            inst.folded.push(hint);
-        }
+        inst.folded.append(&mut self.code_metadata);
         stack.folded.push(inst);
 
         Ok(())
@@ -1739,16 +1749,23 @@ impl<'printer, 'state, 'a, 'b> PrintOperatorFolded<'printer, 'state, 'a, 'b> {
         Ok(())
     }
 
-    // The folding printer doesn't try to handle branch hints attached to blocks other than `if`.
-    fn reject_branch_hint(&mut self) -> Result<()> {
-        if self.branch_hint.is_some() {
-            bail!("branch hints are only supported on an `if` or a plain instructions");
+    // Code metadata can't be attached to instructions such as `else` or `end`
+    // which don't have their own s-expression in the folded form.
+    fn reject_code_metadata(&mut self) -> Result<()> {
+        if !self.code_metadata.is_empty() {
+            bail!("code metadata is only supported on blocks and plain instructions");
         }
         Ok(())
     }
 
     fn push_block(&mut self, ty: BlockType, kind: FrameKind, plain: String) -> Result<()> {
-        self.reject_branch_hint()?;
+        // Code metadata for a block is printed just before it since the
+        // block's own s-expression contains its body instead.
+        self.control
+            .last_mut()
+            .ok_or_else(|| anyhow!("no enclosing block"))?
+            .folded
+            .append(&mut self.code_metadata);
         self.control.push(Block {
             ty,
             kind,
@@ -1756,6 +1773,7 @@ impl<'printer, 'state, 'a, 'b> PrintOperatorFolded<'printer, 'state, 'a, 'b> {
             folded: Vec::new(),
             predicate: None,
             consequent: None,
+            clauses: Vec::new(),
             offset: self.operator_state.op_offset,
         });
         Ok(())
@@ -1772,9 +1790,7 @@ impl<'printer, 'state, 'a, 'b> PrintOperatorFolded<'printer, 'state, 'a, 'b> {
         {
             predicate.push(phrase)
         }
-        if let Some(hint) = self.branch_hint.take() {
-            predicate.push(hint);
-        }
+        predicate.append(&mut self.code_metadata);
         self.control.push(Block {
             ty,
             kind: FrameKind::If,
@@ -1782,13 +1798,14 @@ impl<'printer, 'state, 'a, 'b> PrintOperatorFolded<'printer, 'state, 'a, 'b> {
             folded: Vec::new(),
             predicate: Some(predicate),
             consequent: None,
+            clauses: Vec::new(),
             offset: self.operator_state.op_offset,
         });
         Ok(())
     }
 
     fn handle_else(&mut self) -> Result<()> {
-        self.reject_branch_hint()?;
+        self.reject_code_metadata()?;
         match self.control.pop() {
             Some(Block {
                 ty,
@@ -1805,6 +1822,7 @@ impl<'printer, 'state, 'a, 'b> PrintOperatorFolded<'printer, 'state, 'a, 'b> {
                 folded: Vec::new(),
                 predicate,
                 consequent: Some((folded, offset)),
+                clauses: Vec::new(),
                 offset: self.operator_state.op_offset,
             }),
             _ => bail!("no enclosing if block"),
@@ -1818,7 +1836,7 @@ impl<'printer, 'state, 'a, 'b> PrintOperatorFolded<'printer, 'state, 'a, 'b> {
     // the "plain" nomenclature to also represent the opening delimiters
     // of block instructions and other block-like clauses (e.g. "then", "else").
     fn handle_end(&mut self, results: u32) -> Result<()> {
-        self.reject_branch_hint()?;
+        self.reject_code_metadata()?;
         let frame = self.control.pop();
         let inst = match frame {
             Some(Block {
@@ -1887,6 +1905,12 @@ impl<'printer, 'state, 'a, 'b> PrintOperatorFolded<'printer, 'state, 'a, 'b> {
                     offset: if_offset,
                 }
             }
+            Some(
+                block @ Block {
+                    kind: FrameKind::LegacyTry | FrameKind::LegacyCatch | FrameKind::LegacyCatchAll,
+                    ..
+                },
+            ) => Self::finish_try(block, None, results),
             _ => bail!("unhandled frame kind"),
         };
 
@@ -1897,4 +1921,123 @@ impl<'printer, 'state, 'a, 'b> PrintOperatorFolded<'printer, 'state, 'a, 'b> {
             .push(inst);
         Ok(())
     }
+
+    // A legacy `catch` or `catch_all` finishes the previous clause of the
+    // enclosing `try` block, and its instructions form a new clause that's
+    // printed as `(catch $tag ...)` or `(catch_all ...)`.
+    fn handle_catch(&mut self, kind: FrameKind, plain: String) -> Result<()> {
+        self.reject_code_metadata()?;
+        let mut block = match self.control.pop() {
+            Some(
+                block @ Block {
+                    kind: FrameKind::LegacyTry | FrameKind::LegacyCatch,
+                    ..
+                },
+            ) => block,
+            _ => bail!("no enclosing try block"),
+        };
+        Self::finish_clause(&mut block, 0);
+        block.clauses.push(FoldedInstruction {
+            plain,
+            folded: Vec::new(),
+            results: 0,
+            offset: self.operator_state.op_offset,
+        });
+        block.kind = kind;
+        self.control.push(block);
+        Ok(())
+    }
+
+    // A `delegate` terminates a legacy `try` block in place of `end`, and is
+    // printed as a final `(delegate $label)` clause after the `do` clause.
+    fn handle_delegate(&mut self, plain: String, results: u32) -> Result<()> {
+        self.reject_code_metadata()?;
+        let block = match self.control.pop() {
+            Some(
+                block @ Block {
+                    kind: FrameKind::LegacyTry,
+                    ..
+                },
+            ) => block,
+            _ => bail!("no enclosing try block"),
+        };
+        let delegate = FoldedInstruction {
+            plain,
+            folded: Vec::new(),
+            results: 0,
+            offset: self.operator_state.op_offset,
+        };
+        let inst = Self::finish_try(block, Some(delegate), results);
+        self.control
+            .last_mut()
+            .ok_or_else(|| anyhow!("delegate without outer block"))?
+            .folded
+            .push(inst);
+        Ok(())
+    }
+
+    // Moves the instructions of the current clause of a legacy `try` block
+    // into its list of clauses, creating the `do` clause if no `catch` has
+    // been seen yet.
+    fn finish_clause(block: &mut Block, results: u32) {
+        let folded = mem::take(&mut block.folded);
+        match block.kind {
+            FrameKind::LegacyTry => block.clauses.push(FoldedInstruction {
+                plain: String::from("do"),
+                folded,
+                results,
+                offset: block.offset,
+            }),
+            _ => {
+                let clause = block.clauses.last_mut().unwrap();
+                clause.folded = folded;
+                clause.results = results;
+            }
+        }
+    }
+
+    fn finish_try(
+        mut block: Block,
+        delegate: Option<FoldedInstruction>,
+        results: u32,
+    ) -> FoldedInstruction {
+        Self::finish_clause(&mut block, results);
+        block.clauses.extend(delegate);
+        FoldedInstruction {
+            plain: block.plain,
+            folded: block.clauses,
+            results,
+            offset: block.offset,
+        }
+    }
+}
+
+/// Returns the text of the annotation for the code metadata `data` from the
+/// `metadata.code.{name}` section, without the surrounding parentheses.
+fn code_metadata_annotation(name: &str, data: &[u8]) -> String {
+    // Names which aren't valid identifiers need the quoted form of annotation
+    // ids, `@"..."`.
+    let mut ret = if name.chars().all(crate::is_idchar) {
+        format!("@metadata.code.{name} \"")
+    } else {
+        let mut ret = String::from("@\"metadata.code.");
+        for c in name.chars() {
+            if (0x20..0x7f).contains(&(c as u32)) && c != '"' && c != '\\' {
+                ret.push(c);
+            } else {
+                ret.push_str(&format!("\\u{{{:x}}}", c as u32));
+            }
+        }
+        ret.push_str("\" \"");
+        ret
+    };
+    for byte in data {
+        if *byte >= 0x20 && *byte < 0x7f && *byte != b'"' && *byte != b'\\' {
+            ret.push(*byte as char);
+        } else {
+            ret.push_str(&format!("\\{byte:02x}"));
+        }
+    }
+    ret.push('"');
+    ret
 }
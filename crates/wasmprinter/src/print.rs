@@ -91,6 +91,26 @@ pub trait Print {
         Ok(())
     }
 
+    /// Indicates that the name of `item` is about to be printed where the item
+    /// is defined, for example `$foo (;0;)` in `(func $foo (;0;) ...)`.
+    ///
+    /// As with [`Print::start_name`] the name is followed by a call to
+    /// [`Print::reset_color`]. By default this calls [`Print::start_name`].
+    fn start_definition(&mut self, item: &ItemId) -> io::Result<()> {
+        let _ = item;
+        self.start_name()
+    }
+
+    /// Indicates that the name or index of `item` is about to be printed
+    /// where the item is referenced, for example `$foo` in `call $foo`.
+    ///
+    /// As with [`Print::start_name`] the name is followed by a call to
+    /// [`Print::reset_color`]. By default this calls [`Print::start_name`].
+    fn start_reference(&mut self, item: &ItemId) -> io::Result<()> {
+        let _ = item;
+        self.start_name()
+    }
+
     /// Sets the colors settings for a keyword (`(module ...)`) to be printed.
     fn start_keyword(&mut self) -> io::Result<()> {
         Ok(())
@@ -117,6 +137,28 @@ pub trait Print {
     }
 }
 
+/// Identifies an item in a WebAssembly binary for
+/// [`Print::start_definition`] and [`Print::start_reference`].
+///
+/// Definitions and references to the same item have equal identifiers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct ItemId {
+    /// A number identifying the module or component that this item belongs
+    /// to, assigned in the order that modules and components are printed.
+    pub scope: u32,
+    /// The index space of this item, such as `"func"`, `"type"`, `"global"`,
+    /// or `"local"`. Index spaces of components are prefixed with
+    /// `component-` where they would otherwise clash with core index spaces,
+    /// for example `"component-func"`.
+    pub namespace: &'static str,
+    /// For locals the index of their function and for fields the index of
+    /// their type, otherwise `None`.
+    pub parent: Option<u32>,
+    /// The index of this item within its index space.
+    pub index: u32,
+}
+
 /// An adapter between the [`std::io::Write`] trait and [`Print`].
 pub struct PrintIoWrite<T>(pub T);
 
@@ -0,0 +1,61 @@
+use std::fmt;
+
+/// A mapping from the instructions of a WebAssembly binary to locations in
+/// the original source code that it was compiled from.
+///
+/// This is used with [`Config::source_lines`](crate::Config::source_lines)
+/// to annotate printed instructions with their source location. This crate
+/// doesn't itself parse any debugging information, so implementations of
+/// this trait are typically backed by DWARF sections in the binary.
+pub trait SourceLines: fmt::Debug {
+    /// Returns the source frames for the instruction at `offset`.
+    ///
+    /// The `offset` is the byte offset of the instruction within the whole
+    /// binary being printed. Frames are listed from innermost to outermost,
+    /// so if the instruction was part of an inlined function then the first
+    /// frame is the location within the inlined function and the last frame
+    /// is the location within the function that it was inlined into. An
+    /// empty list means that the location of the instruction isn't known.
+    fn frames(&self, offset: usize) -> &[SourceFrame];
+}
+
+/// A single frame of a source location returned from [`SourceLines`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceFrame {
+    /// The name of the function that this frame is within, if known.
+    pub function: Option<String>,
+    /// The source file of this frame, if known.
+    pub file: Option<String>,
+    /// The line within `file`, if known.
+    pub line: Option<u32>,
+}
+
+impl fmt::Display for SourceFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.file.as_deref().unwrap_or("<unknown>"))?;
+        if let Some(line) = self.line {
+            write!(f, ":{line}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Renders the location of `frames` as printed in the text format.
+///
+/// Inlined frames are collapsed so only the innermost location, which is
+/// what the instruction actually implements, and the outermost location,
+/// the call site within the function being printed, are shown.
+pub(crate) fn describe(frames: &[SourceFrame]) -> Option<String> {
+    let (inner, outer) = match frames {
+        [] => return None,
+        [frame] => return Some(frame.to_string()),
+        [inner, .., outer] => (inner, outer),
+    };
+    let mut ret = inner.to_string();
+    ret.push_str(" (");
+    if let Some(name) = &inner.function {
+        ret.push_str(&format!("`{name}` "));
+    }
+    ret.push_str(&format!("inlined at {outer})"));
+    Some(ret)
+}
@@ -46,3 +46,74 @@ fn offsets_and_lines_smoke_test() {
 
     assert_eq!(actual, expected);
 }
+
+#[test]
+fn source_lines_collapse_inlined_frames() {
+    use wasmprinter::{SourceFrame, SourceLines};
+
+    #[derive(Debug)]
+    struct Lines(Vec<(usize, Vec<SourceFrame>)>);
+
+    impl SourceLines for Lines {
+        fn frames(&self, offset: usize) -> &[SourceFrame] {
+            self.0
+                .iter()
+                .find(|(o, _)| *o == offset)
+                .map(|(_, f)| &f[..])
+                .unwrap_or(&[])
+        }
+    }
+
+    fn frame(function: &str, file: &str, line: u32) -> SourceFrame {
+        SourceFrame {
+            function: Some(function.to_string()),
+            file: Some(file.to_string()),
+            line: Some(line),
+        }
+    }
+
+    let bytes = wat::parse_str("(module (func i32.const 1 i32.const 2 drop drop))").unwrap();
+    let mut offsets = Vec::new();
+    for payload in wasmparser::Parser::new(0).parse_all(&bytes) {
+        if let wasmparser::Payload::CodeSectionEntry(body) = payload.unwrap() {
+            let mut ops = body.get_operators_reader().unwrap();
+            while !ops.eof() {
+                offsets.push(ops.original_position());
+                ops.read().unwrap();
+            }
+        }
+    }
+    let lines = Lines(vec![
+        (offsets[0], vec![frame("f", "a.c", 1)]),
+        (offsets[1], vec![frame("f", "a.c", 1)]),
+        (
+            offsets[2],
+            vec![
+                frame("inner", "b.h", 3),
+                frame("middle", "b.h", 7),
+                frame("f", "a.c", 2),
+            ],
+        ),
+    ]);
+
+    let mut config = wasmprinter::Config::new();
+    config.source_lines(lines);
+    let mut wat = String::new();
+    config
+        .print(&bytes, &mut wasmprinter::PrintFmtWrite(&mut wat))
+        .unwrap();
+    assert_eq!(
+        wat,
+        "\
+(module
+  (type (;0;) (func))
+  (func (;0;) (type 0)
+    i32.const 1 ;; a.c:1
+    i32.const 2
+    drop ;; b.h:3 (`inner` inlined at a.c:2)
+    drop
+  )
+)
+"
+    );
+}
@@ -136,7 +136,7 @@ impl<'a> Parse<'a> for Alias<'a> {
 }
 
 /// Represents the kind of instance export alias.
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum ComponentExportAliasKind {
     /// The alias is to a core module export.
     CoreModule,
@@ -186,7 +186,7 @@ impl<'a> Parse<'a> for ComponentExportAliasKind {
 }
 
 /// Represents the kind of outer alias.
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum ComponentOuterAliasKind {
     /// The alias is to an outer core module.
     CoreModule,
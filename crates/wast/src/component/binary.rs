@@ -20,7 +20,6 @@ pub fn encode(component: &Component<'_>, options: &EncodeOptions) -> Vec<u8> {
 }
 
 fn encode_fields(
-    // TODO: use the id and name for a future names section
     component_id: &Option<Id<'_>>,
     component_name: &Option<NameAnnotation<'_>>,
     fields: &[ComponentField<'_>],
@@ -151,7 +151,7 @@ impl<'a> ComponentField<'a> {
     fn parse_remaining(parser: Parser<'a>) -> Result<Vec<ComponentField<'a>>> {
         let mut fields = Vec::new();
         while !parser.is_empty() {
-            fields.push(parser.parens(ComponentField::parse)?);
+            fields.extend(parser.parens_recover(ComponentField::parse)?);
         }
         Ok(fields)
     }
@@ -3,7 +3,7 @@ use crate::core;
 use crate::gensym;
 use crate::kw;
 use crate::token::Id;
-use crate::token::{Index, Span};
+use crate::token::{Index, NameAnnotation, Span};
 use std::collections::HashMap;
 use std::mem;
 
@@ -64,6 +64,13 @@ struct Expander<'a> {
     /// Fields that are appended to the end of the module once everything has
     /// finished.
     component_fields_to_append: Vec<ComponentField<'a>>,
+
+    /// Previously defined types within this scope, keyed by their structure,
+    /// used to deduplicate inline type definitions.
+    defined_type_to_idx: HashMap<DefinedTypeKey<'a>, Index<'a>>,
+    func_type_to_idx: HashMap<FuncTypeKey<'a>, Index<'a>>,
+    component_type_to_idx: HashMap<ComponentTypeKey<'a>, Index<'a>>,
+    instance_type_to_idx: HashMap<InstanceTypeKey<'a>, Index<'a>>,
 }
 
 impl<'a> Expander<'a> {
@@ -159,7 +166,7 @@ impl<'a> Expander<'a> {
             // inline modules are expanded later during resolution
             CoreModuleKind::Inline { .. } => None,
             CoreModuleKind::Import { import, ty } => {
-                let idx = self.expand_core_type_use(ty);
+                let idx = self.expand_core_type_use(ty, module.span);
                 Some(ComponentField::Import(ComponentImport {
                     span: module.span,
                     name: import.name,
@@ -207,7 +214,7 @@ impl<'a> Expander<'a> {
                 None
             }
             NestedComponentKind::Import { import, ty } => {
-                let idx = self.expand_component_type_use(ty);
+                let idx = self.expand_component_type_use(ty, component.span);
                 Some(ComponentField::Import(ComponentImport {
                     span: component.span,
                     name: import.name,
@@ -237,7 +244,7 @@ impl<'a> Expander<'a> {
         }
         match &mut instance.kind {
             InstanceKind::Import { import, ty } => {
-                let idx = self.expand_component_type_use(ty);
+                let idx = self.expand_component_type_use(ty, instance.span);
                 Some(ComponentField::Import(ComponentImport {
                     span: instance.span,
                     name: import.name,
@@ -262,16 +269,16 @@ impl<'a> Expander<'a> {
     fn expand_canonical_func(&mut self, func: &mut CanonicalFunc<'a>) {
         match &mut func.kind {
             CanonicalFuncKind::Lift { ty, .. } => {
-                self.expand_component_type_use(ty);
+                self.expand_component_type_use(ty, func.span);
             }
-            CanonicalFuncKind::Core(func) => {
-                self.expand_core_func_kind(func);
+            CanonicalFuncKind::Core(kind) => {
+                self.expand_core_func_kind(kind, func.span);
             }
         }
     }
 
     fn expand_core_func(&mut self, mut func: CoreFunc<'a>) -> ComponentField<'a> {
-        self.expand_core_func_kind(&mut func.kind);
+        self.expand_core_func_kind(&mut func.kind, func.span);
         match func.kind {
             CoreFuncKind::Alias(a) => ComponentField::Alias(Alias {
                 span: func.span,
@@ -292,11 +299,11 @@ impl<'a> Expander<'a> {
         }
     }
 
-    fn expand_core_func_kind(&mut self, func: &mut CoreFuncKind<'a>) {
+    fn expand_core_func_kind(&mut self, func: &mut CoreFuncKind<'a>, span: Span) {
         match func {
             CoreFuncKind::TaskReturn(f) => {
                 if let Some(ty) = &mut f.result {
-                    self.expand_component_val_ty(ty);
+                    self.expand_component_val_ty(ty, span);
                 }
             }
 
@@ -320,7 +327,7 @@ impl<'a> Expander<'a> {
         }
         match &mut func.kind {
             FuncKind::Import { import, ty } => {
-                let idx = self.expand_component_type_use(ty);
+                let idx = self.expand_component_type_use(ty, func.span);
                 Some(ComponentField::Import(ComponentImport {
                     span: func.span,
                     name: import.name,
@@ -333,7 +340,7 @@ impl<'a> Expander<'a> {
                 }))
             }
             FuncKind::Lift { ty, info } => {
-                let idx = self.expand_component_type_use(ty);
+                let idx = self.expand_component_type_use(ty, func.span);
                 Some(ComponentField::CanonicalFunc(CanonicalFunc {
                     span: func.span,
                     id: func.id,
@@ -373,8 +380,8 @@ impl<'a> Expander<'a> {
 
     fn expand_type(&mut self, field: &mut Type<'a>) {
         match &mut field.def {
-            TypeDef::Defined(d) => self.expand_defined_ty(d),
-            TypeDef::Func(f) => self.expand_func_ty(f),
+            TypeDef::Defined(d) => self.expand_defined_ty(d, field.span),
+            TypeDef::Func(f) => self.expand_func_ty(f, field.span),
             TypeDef::Component(c) => self.expand_component_ty(c),
             TypeDef::Instance(i) => self.expand_instance_ty(i),
             TypeDef::Resource(_) => {}
@@ -402,13 +409,13 @@ impl<'a> Expander<'a> {
         }
     }
 
-    fn expand_func_ty(&mut self, ty: &mut ComponentFunctionType<'a>) {
+    fn expand_func_ty(&mut self, ty: &mut ComponentFunctionType<'a>, span: Span) {
         for param in ty.params.iter_mut() {
-            self.expand_component_val_ty(&mut param.ty);
+            self.expand_component_val_ty(&mut param.ty, span);
         }
 
         if let Some(result) = &mut ty.result {
-            self.expand_component_val_ty(result);
+            self.expand_component_val_ty(result, span);
         }
     }
 
@@ -511,40 +518,41 @@ impl<'a> Expander<'a> {
     }
 
     fn expand_item_sig(&mut self, ext: &mut ItemSig<'a>) {
+        let span = ext.span;
         match &mut ext.kind {
             ItemSigKind::CoreModule(t) => {
-                self.expand_core_type_use(t);
+                self.expand_core_type_use(t, span);
             }
             ItemSigKind::Func(t) => {
-                self.expand_component_type_use(t);
+                self.expand_component_type_use(t, span);
             }
             ItemSigKind::Component(t) => {
-                self.expand_component_type_use(t);
+                self.expand_component_type_use(t, span);
             }
             ItemSigKind::Instance(t) => {
-                self.expand_component_type_use(t);
+                self.expand_component_type_use(t, span);
             }
             ItemSigKind::Value(t) => {
-                self.expand_component_val_ty(&mut t.0);
+                self.expand_component_val_ty(&mut t.0, span);
             }
             ItemSigKind::Type(_) => {}
         }
     }
 
-    fn expand_defined_ty(&mut self, ty: &mut ComponentDefinedType<'a>) {
+    fn expand_defined_ty(&mut self, ty: &mut ComponentDefinedType<'a>, span: Span) {
         match ty {
             ComponentDefinedType::Primitive(_)
             | ComponentDefinedType::Flags(_)
             | ComponentDefinedType::Enum(_) => {}
             ComponentDefinedType::Record(r) => {
                 for field in r.fields.iter_mut() {
-                    self.expand_component_val_ty(&mut field.ty);
+                    self.expand_component_val_ty(&mut field.ty, span);
                 }
             }
             ComponentDefinedType::Variant(v) => {
                 for case in v.cases.iter_mut() {
                     if let Some(ty) = &mut case.ty {
-                        self.expand_component_val_ty(ty);
+                        self.expand_component_val_ty(ty, case.span);
                     }
                 }
             }
@@ -553,75 +561,76 @@ impl<'a> Expander<'a> {
                 element: t,
                 elements: _,
             }) => {
-                self.expand_component_val_ty(t);
+                self.expand_component_val_ty(t, span);
             }
             ComponentDefinedType::Tuple(t) => {
                 for field in t.fields.iter_mut() {
-                    self.expand_component_val_ty(field);
+                    self.expand_component_val_ty(field, span);
                 }
             }
             ComponentDefinedType::Option(t) => {
-                self.expand_component_val_ty(&mut t.element);
+                self.expand_component_val_ty(&mut t.element, span);
             }
             ComponentDefinedType::Result(r) => {
                 if let Some(ty) = &mut r.ok {
-                    self.expand_component_val_ty(ty);
+                    self.expand_component_val_ty(ty, span);
                 }
 
                 if let Some(ty) = &mut r.err {
-                    self.expand_component_val_ty(ty);
+                    self.expand_component_val_ty(ty, span);
                 }
             }
             ComponentDefinedType::Own(_) | ComponentDefinedType::Borrow(_) => {}
             ComponentDefinedType::Stream(t) => {
                 if let Some(ty) = &mut t.element {
-                    self.expand_component_val_ty(ty);
+                    self.expand_component_val_ty(ty, span);
                 }
             }
             ComponentDefinedType::Future(t) => {
                 if let Some(ty) = &mut t.element {
-                    self.expand_component_val_ty(ty);
+                    self.expand_component_val_ty(ty, span);
                 }
             }
         }
     }
 
-    fn expand_component_val_ty(&mut self, ty: &mut ComponentValType<'a>) {
+    fn expand_component_val_ty(&mut self, ty: &mut ComponentValType<'a>, span: Span) {
         let inline = match ty {
             ComponentValType::Inline(ComponentDefinedType::Primitive(_))
             | ComponentValType::Ref(_) => return,
             ComponentValType::Inline(inline) => {
-                self.expand_defined_ty(inline);
+                self.expand_defined_ty(inline, span);
                 mem::take(inline)
             }
         };
         // If this inline type has already been defined within this context
         // then reuse the previously defined type to avoid injecting too many
         // types into the type index space.
-        if let Some(idx) = inline.key().lookup(self) {
+        let key = inline.key();
+        if let Some(idx) = key.lookup(self) {
             *ty = ComponentValType::Ref(idx);
             return;
         }
 
         // And if this type isn't already defined we append it to the index
         // space with a fresh and unique name.
-        let span = Span::from_offset(0); // FIXME(#613): don't manufacture
         let id = gensym::generate(span);
 
         self.types_to_prepend.push(inline.into_any_type(span, id));
 
         let idx = Index::Id(id);
+        key.insert(self, idx);
         *ty = ComponentValType::Ref(idx);
     }
 
     fn expand_core_type_use<T>(
         &mut self,
         item: &mut CoreTypeUse<'a, T>,
+        span: Span,
     ) -> CoreItemRef<'a, kw::r#type>
     where
         T: TypeReference<'a>,
     {
-        let span = Span::from_offset(0); // FIXME(#613): don't manufacture
         let mut inline = match mem::take(item) {
             // If this type-use was already a reference to an existing type
             // then we put it back the way it was and return the corresponding
@@ -635,12 +644,13 @@ impl<'a> Expander<'a> {
             // processing below.
             CoreTypeUse::Inline(inline) => inline,
         };
-        inline.expand(self);
+        inline.expand(self, span);
 
         // If this inline type has already been defined within this context
         // then reuse the previously defined type to avoid injecting too many
         // types into the type index space.
-        if let Some(idx) = inline.key().lookup(self) {
+        let key = inline.key();
+        if let Some(idx) = key.lookup(self) {
             let ret = CoreItemRef {
                 idx,
                 kind: kw::r#type(span),
@@ -657,6 +667,7 @@ impl<'a> Expander<'a> {
         self.types_to_prepend.push(inline.into_any_type(span, id));
 
         let idx = Index::Id(id);
+        key.insert(self, idx);
         let ret = CoreItemRef {
             idx,
             kind: kw::r#type(span),
@@ -670,11 +681,11 @@ impl<'a> Expander<'a> {
     fn expand_component_type_use<T>(
         &mut self,
         item: &mut ComponentTypeUse<'a, T>,
+        span: Span,
     ) -> ItemRef<'a, kw::r#type>
     where
         T: TypeReference<'a>,
     {
-        let span = Span::from_offset(0); // FIXME(#613): don't manufacture
         let mut inline = match mem::take(item) {
             // If this type-use was already a reference to an existing type
             // then we put it back the way it was and return the corresponding
@@ -688,12 +699,13 @@ impl<'a> Expander<'a> {
             // processing below.
             ComponentTypeUse::Inline(inline) => inline,
         };
-        inline.expand(self);
+        inline.expand(self, span);
 
         // If this inline type has already been defined within this context
         // then reuse the previously defined type to avoid injecting too many
         // types into the type index space.
-        if let Some(idx) = inline.key().lookup(self) {
+        let key = inline.key();
+        if let Some(idx) = key.lookup(self) {
             let ret = ItemRef {
                 idx,
                 kind: kw::r#type(span),
@@ -710,6 +722,7 @@ impl<'a> Expander<'a> {
         self.types_to_prepend.push(inline.into_any_type(span, id));
 
         let idx = Index::Id(id);
+        key.insert(self, idx);
         let ret = ItemRef {
             idx,
             kind: kw::r#type(span),
@@ -761,19 +774,19 @@ impl<'a> Expander<'a> {
 trait TypeReference<'a> {
     type Key: TypeKey<'a>;
     fn key(&self) -> Self::Key;
-    fn expand(&mut self, cx: &mut Expander<'a>);
+    fn expand(&mut self, cx: &mut Expander<'a>, span: Span);
     fn into_any_type(self, span: Span, id: Id<'a>) -> AnyType<'a>;
 }
 
 impl<'a> TypeReference<'a> for ComponentDefinedType<'a> {
-    type Key = Todo; // FIXME(#598): should implement this
+    type Key = DefinedTypeKey<'a>;
 
     fn key(&self) -> Self::Key {
-        Todo
+        DefinedTypeKey::new(self, &LocalScopes::default())
     }
 
-    fn expand(&mut self, cx: &mut Expander<'a>) {
-        cx.expand_defined_ty(self)
+    fn expand(&mut self, cx: &mut Expander<'a>, span: Span) {
+        cx.expand_defined_ty(self, span)
     }
 
     fn into_any_type(self, span: Span, id: Id<'a>) -> AnyType<'a> {
@@ -788,13 +801,20 @@ impl<'a> TypeReference<'a> for ComponentDefinedType<'a> {
 }
 
 impl<'a> TypeReference<'a> for ComponentType<'a> {
-    type Key = Todo; // FIXME(#598): should implement this
+    type Key = Option<ComponentTypeKey<'a>>;
 
     fn key(&self) -> Self::Key {
-        Todo
+        let decls = self.decls.iter().map(|decl| match decl {
+            ComponentTypeDecl::CoreType(_) => Decl::CoreType,
+            ComponentTypeDecl::Type(t) => Decl::Type(t),
+            ComponentTypeDecl::Alias(a) => Decl::Alias(a),
+            ComponentTypeDecl::Import(i) => Decl::Import(i.name.0, &i.item),
+            ComponentTypeDecl::Export(e) => Decl::Export(e.name.0, &e.item),
+        });
+        DeclKey::list(decls, &mut LocalScopes::default()).map(ComponentTypeKey)
     }
 
-    fn expand(&mut self, cx: &mut Expander<'a>) {
+    fn expand(&mut self, cx: &mut Expander<'a>, _span: Span) {
         cx.expand_component_ty(self)
     }
 
@@ -816,7 +836,7 @@ impl<'a> TypeReference<'a> for ModuleType<'a> {
         Todo
     }
 
-    fn expand(&mut self, cx: &mut Expander<'a>) {
+    fn expand(&mut self, cx: &mut Expander<'a>, _span: Span) {
         cx.expand_module_ty(self)
     }
 
@@ -831,13 +851,19 @@ impl<'a> TypeReference<'a> for ModuleType<'a> {
 }
 
 impl<'a> TypeReference<'a> for InstanceType<'a> {
-    type Key = Todo; // FIXME(#598): should implement this
+    type Key = Option<InstanceTypeKey<'a>>;
 
     fn key(&self) -> Self::Key {
-        Todo
+        let decls = self.decls.iter().map(|decl| match decl {
+            InstanceTypeDecl::CoreType(_) => Decl::CoreType,
+            InstanceTypeDecl::Type(t) => Decl::Type(t),
+            InstanceTypeDecl::Alias(a) => Decl::Alias(a),
+            InstanceTypeDecl::Export(e) => Decl::Export(e.name.0, &e.item),
+        });
+        DeclKey::list(decls, &mut LocalScopes::default()).map(InstanceTypeKey)
     }
 
-    fn expand(&mut self, cx: &mut Expander<'a>) {
+    fn expand(&mut self, cx: &mut Expander<'a>, _span: Span) {
         cx.expand_instance_ty(self)
     }
 
@@ -853,14 +879,14 @@ impl<'a> TypeReference<'a> for InstanceType<'a> {
 }
 
 impl<'a> TypeReference<'a> for ComponentFunctionType<'a> {
-    type Key = Todo; // FIXME(#598): should implement this
+    type Key = FuncTypeKey<'a>;
 
     fn key(&self) -> Self::Key {
-        Todo
+        func_type_key(self, &LocalScopes::default())
     }
 
-    fn expand(&mut self, cx: &mut Expander<'a>) {
-        cx.expand_func_ty(self)
+    fn expand(&mut self, cx: &mut Expander<'a>, span: Span) {
+        cx.expand_func_ty(self, span)
     }
 
     fn into_any_type(self, span: Span, id: Id<'a>) -> AnyType<'a> {
@@ -876,7 +902,384 @@ impl<'a> TypeReference<'a> for ComponentFunctionType<'a> {
 
 trait TypeKey<'a> {
     fn lookup(&self, cx: &Expander<'a>) -> Option<Index<'a>>;
-    fn insert(&self, cx: &mut Expander<'a>, index: Index<'a>);
+    fn insert(self, cx: &mut Expander<'a>, index: Index<'a>);
+}
+
+/// The identifiers defined by the declarations of the component and instance
+/// types enclosing a type which is being keyed, innermost last.
+///
+/// Each identifier maps to the position of the declaration defining it.
+/// Expansion generates fresh identifiers for each copy of an inline type, so
+/// references to declarations within the type being keyed use these
+/// positions instead of the identifiers themselves.
+#[derive(Default)]
+struct LocalScopes<'a>(Vec<HashMap<Id<'a>, u32>>);
+
+impl<'a> LocalScopes<'a> {
+    fn index(&self, idx: &Index<'a>) -> IndexKey<'a> {
+        if let Index::Id(id) = idx {
+            for (depth, scope) in self.0.iter().rev().enumerate() {
+                if let Some(pos) = scope.get(id) {
+                    return IndexKey::Local(depth as u32, *pos);
+                }
+            }
+        }
+        IndexKey::Index(*idx)
+    }
+}
+
+/// Structural key for a reference to another item.
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+enum IndexKey<'a> {
+    /// A reference which is the same wherever the type is defined in the
+    /// current scope.
+    Index(Index<'a>),
+    /// A reference to the declaration at a position within an enclosing type,
+    /// `depth` types out from the innermost one.
+    Local(u32, u32),
+}
+
+/// Structural key for a component value type which has already been expanded,
+/// meaning that it's either a primitive or a reference to another type.
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+enum ValTypeKey<'a> {
+    Primitive(PrimitiveValType),
+    Ref(IndexKey<'a>),
+}
+
+impl<'a> ValTypeKey<'a> {
+    fn new(ty: &ComponentValType<'a>, scopes: &LocalScopes<'a>) -> ValTypeKey<'a> {
+        match ty {
+            ComponentValType::Inline(ComponentDefinedType::Primitive(p)) => {
+                ValTypeKey::Primitive(*p)
+            }
+            ComponentValType::Ref(idx) => ValTypeKey::Ref(scopes.index(idx)),
+            ComponentValType::Inline(_) => unreachable!("should be expanded already"),
+        }
+    }
+}
+
+/// Structural key for a [`ComponentDefinedType`] used to deduplicate inline
+/// definitions of the same type.
+#[derive(PartialEq, Eq, Hash)]
+enum DefinedTypeKey<'a> {
+    Primitive(PrimitiveValType),
+    Record(Box<[(&'a str, ValTypeKey<'a>)]>),
+    Variant(Box<[(&'a str, Option<ValTypeKey<'a>>, Option<Option<u32>>)]>),
+    List(ValTypeKey<'a>),
+    FixedSizeList(ValTypeKey<'a>, u32),
+    Tuple(Box<[ValTypeKey<'a>]>),
+    Flags(Box<[&'a str]>),
+    Enum(Box<[&'a str]>),
+    Option(ValTypeKey<'a>),
+    Result(Option<ValTypeKey<'a>>, Option<ValTypeKey<'a>>),
+    Own(IndexKey<'a>),
+    Borrow(IndexKey<'a>),
+    Stream(Option<ValTypeKey<'a>>),
+    Future(Option<ValTypeKey<'a>>),
+}
+
+impl<'a> DefinedTypeKey<'a> {
+    fn new(ty: &ComponentDefinedType<'a>, scopes: &LocalScopes<'a>) -> DefinedTypeKey<'a> {
+        let val = |ty: &ComponentValType<'a>| ValTypeKey::new(ty, scopes);
+        match ty {
+            ComponentDefinedType::Primitive(p) => DefinedTypeKey::Primitive(*p),
+            ComponentDefinedType::Record(r) => DefinedTypeKey::Record(
+                r.fields.iter().map(|f| (f.name, val(&f.ty))).collect(),
+            ),
+            ComponentDefinedType::Variant(v) => DefinedTypeKey::Variant(
+                v.cases
+                    .iter()
+                    .map(|c| {
+                        // Refinements are keyed by the position of the case
+                        // they refer to since case identifiers are local to
+                        // this variant.
+                        let refines = c.refines.as_ref().map(|r| match r {
+                            Refinement::Index(_, Index::Num(n, _)) => Some(*n),
+                            Refinement::Index(_, Index::Id(id)) => v
+                                .cases
+                                .iter()
+                                .position(|c| c.id == Some(*id))
+                                .map(|i| i as u32),
+                            Refinement::Resolved(n) => Some(*n),
+                        });
+                        (c.name, c.ty.as_ref().map(val), refines)
+                    })
+                    .collect(),
+            ),
+            ComponentDefinedType::List(l) => DefinedTypeKey::List(val(&l.element)),
+            ComponentDefinedType::FixedSizeList(l) => {
+                DefinedTypeKey::FixedSizeList(val(&l.element), l.elements)
+            }
+            ComponentDefinedType::Tuple(t) => {
+                DefinedTypeKey::Tuple(t.fields.iter().map(val).collect())
+            }
+            ComponentDefinedType::Flags(f) => DefinedTypeKey::Flags(f.names.clone().into()),
+            ComponentDefinedType::Enum(e) => DefinedTypeKey::Enum(e.names.clone().into()),
+            ComponentDefinedType::Option(o) => DefinedTypeKey::Option(val(&o.element)),
+            ComponentDefinedType::Result(r) => {
+                DefinedTypeKey::Result(r.ok.as_deref().map(val), r.err.as_deref().map(val))
+            }
+            ComponentDefinedType::Own(i) => DefinedTypeKey::Own(scopes.index(i)),
+            ComponentDefinedType::Borrow(i) => DefinedTypeKey::Borrow(scopes.index(i)),
+            ComponentDefinedType::Stream(s) => {
+                DefinedTypeKey::Stream(s.element.as_deref().map(val))
+            }
+            ComponentDefinedType::Future(f) => {
+                DefinedTypeKey::Future(f.element.as_deref().map(val))
+            }
+        }
+    }
+}
+
+impl<'a> TypeKey<'a> for DefinedTypeKey<'a> {
+    fn lookup(&self, cx: &Expander<'a>) -> Option<Index<'a>> {
+        cx.defined_type_to_idx.get(self).cloned()
+    }
+
+    fn insert(self, cx: &mut Expander<'a>, index: Index<'a>) {
+        cx.defined_type_to_idx.entry(self).or_insert(index);
+    }
+}
+
+/// Structural key for a [`ComponentFunctionType`]: the name and type of each
+/// parameter plus the optional result.
+type FuncTypeKey<'a> = (Box<[(&'a str, ValTypeKey<'a>)]>, Option<ValTypeKey<'a>>);
+
+fn func_type_key<'a>(ty: &ComponentFunctionType<'a>, scopes: &LocalScopes<'a>) -> FuncTypeKey<'a> {
+    let params = ty
+        .params
+        .iter()
+        .map(|p| (p.name, ValTypeKey::new(&p.ty, scopes)))
+        .collect();
+    (params, ty.result.as_ref().map(|r| ValTypeKey::new(r, scopes)))
+}
+
+impl<'a> TypeKey<'a> for FuncTypeKey<'a> {
+    fn lookup(&self, cx: &Expander<'a>) -> Option<Index<'a>> {
+        cx.func_type_to_idx.get(self).cloned()
+    }
+
+    fn insert(self, cx: &mut Expander<'a>, index: Index<'a>) {
+        cx.func_type_to_idx.entry(self).or_insert(index);
+    }
+}
+
+/// A declaration within a component or instance type.
+enum Decl<'b, 'a> {
+    CoreType,
+    Type(&'b Type<'a>),
+    Alias(&'b Alias<'a>),
+    Import(&'a str, &'b ItemSig<'a>),
+    Export(&'a str, &'b ItemSig<'a>),
+}
+
+impl<'a> Decl<'_, 'a> {
+    fn id(&self) -> Option<Id<'a>> {
+        match self {
+            Decl::CoreType => None,
+            Decl::Type(t) => t.id,
+            Decl::Alias(a) => a.id,
+            Decl::Import(_, item) | Decl::Export(_, item) => item.id,
+        }
+    }
+}
+
+/// The names given to a declaration in the text format, which are part of a
+/// key so deduplicated types don't lose any names in the `name` section.
+type DeclNames<'a> = (Option<&'a str>, Option<&'a str>);
+
+fn decl_names<'a>(id: Option<Id<'a>>, name: Option<NameAnnotation<'a>>) -> DeclNames<'a> {
+    (
+        id.filter(|id| !id.is_gensym()).map(|id| id.name()),
+        name.map(|n| n.name),
+    )
+}
+
+/// Structural key for a declaration within a component or instance type.
+#[derive(PartialEq, Eq, Hash)]
+enum DeclKey<'a> {
+    Type(DeclNames<'a>, TypeDefKey<'a>),
+    Alias(DeclNames<'a>, AliasKey<'a>),
+    Import(&'a str, DeclNames<'a>, ItemSigKey<'a>),
+    Export(&'a str, DeclNames<'a>, ItemSigKey<'a>),
+}
+
+impl<'a> DeclKey<'a> {
+    /// Returns the keys of all of `decls`, or `None` if the type they're
+    /// declared in isn't deduplicated.
+    ///
+    /// Types which declare core types aren't deduplicated, nor are types
+    /// which import or export types. Each use of such an instance or
+    /// component type would share the identities of those types, which WIT
+    /// decoding doesn't support as it expects each exported type to belong
+    /// to only one interface.
+    fn list<'b>(
+        decls: impl Iterator<Item = Decl<'b, 'a>> + Clone,
+        scopes: &mut LocalScopes<'a>,
+    ) -> Option<Box<[DeclKey<'a>]>>
+    where
+        'a: 'b,
+    {
+        let ids = decls
+            .clone()
+            .enumerate()
+            .filter_map(|(i, decl)| Some((decl.id()?, i as u32)))
+            .collect();
+        scopes.0.push(ids);
+        let ret = decls.map(|decl| DeclKey::new(decl, scopes)).collect();
+        scopes.0.pop();
+        ret
+    }
+
+    fn new(decl: Decl<'_, 'a>, scopes: &mut LocalScopes<'a>) -> Option<DeclKey<'a>> {
+        Some(match decl {
+            Decl::CoreType => return None,
+            Decl::Type(t) => {
+                if !t.exports.names.is_empty() {
+                    return None;
+                }
+                let def = match &t.def {
+                    TypeDef::Defined(d) => TypeDefKey::Defined(DefinedTypeKey::new(d, scopes)),
+                    TypeDef::Func(f) => TypeDefKey::Func(func_type_key(f, scopes)),
+                    TypeDef::Component(c) => {
+                        let decls = c.decls.iter().map(|decl| match decl {
+                            ComponentTypeDecl::CoreType(_) => Decl::CoreType,
+                            ComponentTypeDecl::Type(t) => Decl::Type(t),
+                            ComponentTypeDecl::Alias(a) => Decl::Alias(a),
+                            ComponentTypeDecl::Import(i) => Decl::Import(i.name.0, &i.item),
+                            ComponentTypeDecl::Export(e) => Decl::Export(e.name.0, &e.item),
+                        });
+                        TypeDefKey::Component(DeclKey::list(decls, scopes)?)
+                    }
+                    TypeDef::Instance(i) => {
+                        let decls = i.decls.iter().map(|decl| match decl {
+                            InstanceTypeDecl::CoreType(_) => Decl::CoreType,
+                            InstanceTypeDecl::Type(t) => Decl::Type(t),
+                            InstanceTypeDecl::Alias(a) => Decl::Alias(a),
+                            InstanceTypeDecl::Export(e) => Decl::Export(e.name.0, &e.item),
+                        });
+                        TypeDefKey::Instance(DeclKey::list(decls, scopes)?)
+                    }
+                    TypeDef::Resource(_) => return None,
+                };
+                DeclKey::Type(decl_names(t.id, t.name), def)
+            }
+            Decl::Alias(a) => {
+                let target = match &a.target {
+                    AliasTarget::Export {
+                        instance,
+                        name,
+                        kind,
+                    } => AliasKey::Export(scopes.index(instance), name, *kind),
+                    AliasTarget::CoreExport {
+                        instance,
+                        name,
+                        kind,
+                    } => AliasKey::CoreExport(scopes.index(instance), name, *kind),
+                    AliasTarget::Outer { outer, index, kind } => {
+                        AliasKey::Outer(*outer, *index, *kind)
+                    }
+                };
+                DeclKey::Alias(decl_names(a.id, a.name), target)
+            }
+            Decl::Import(name, item) => {
+                DeclKey::Import(name, decl_names(item.id, item.name), ItemSigKey::new(item, scopes)?)
+            }
+            Decl::Export(name, item) => {
+                DeclKey::Export(name, decl_names(item.id, item.name), ItemSigKey::new(item, scopes)?)
+            }
+        })
+    }
+}
+
+#[derive(PartialEq, Eq, Hash)]
+enum TypeDefKey<'a> {
+    Defined(DefinedTypeKey<'a>),
+    Func(FuncTypeKey<'a>),
+    Component(Box<[DeclKey<'a>]>),
+    Instance(Box<[DeclKey<'a>]>),
+}
+
+#[derive(PartialEq, Eq, Hash)]
+enum AliasKey<'a> {
+    Export(IndexKey<'a>, &'a str, ComponentExportAliasKind),
+    CoreExport(IndexKey<'a>, &'a str, core::ExportKind),
+    Outer(Index<'a>, Index<'a>, ComponentOuterAliasKind),
+}
+
+/// Structural key for an import or export within a component or instance
+/// type, whose inline types have already been expanded.
+#[derive(PartialEq, Eq, Hash)]
+enum ItemSigKey<'a> {
+    CoreModule(IndexKey<'a>, Option<&'a str>),
+    Func(IndexKey<'a>, Box<[&'a str]>),
+    Component(IndexKey<'a>, Box<[&'a str]>),
+    Instance(IndexKey<'a>, Box<[&'a str]>),
+    Value(ValTypeKey<'a>),
+}
+
+impl<'a> ItemSigKey<'a> {
+    fn new(item: &ItemSig<'a>, scopes: &LocalScopes<'a>) -> Option<ItemSigKey<'a>> {
+        let item_ref = |r: &ItemRef<'a, kw::r#type>| {
+            (scopes.index(&r.idx), r.export_names.clone().into())
+        };
+        Some(match &item.kind {
+            ItemSigKind::CoreModule(CoreTypeUse::Ref(r)) => {
+                ItemSigKey::CoreModule(scopes.index(&r.idx), r.export_name)
+            }
+            ItemSigKind::Func(ComponentTypeUse::Ref(r)) => {
+                let (idx, names) = item_ref(r);
+                ItemSigKey::Func(idx, names)
+            }
+            ItemSigKind::Component(ComponentTypeUse::Ref(r)) => {
+                let (idx, names) = item_ref(r);
+                ItemSigKey::Component(idx, names)
+            }
+            ItemSigKind::Instance(ComponentTypeUse::Ref(r)) => {
+                let (idx, names) = item_ref(r);
+                ItemSigKey::Instance(idx, names)
+            }
+            ItemSigKind::Value(ty) => ItemSigKey::Value(ValTypeKey::new(&ty.0, scopes)),
+            ItemSigKind::CoreModule(CoreTypeUse::Inline(_))
+            | ItemSigKind::Func(ComponentTypeUse::Inline(_))
+            | ItemSigKind::Component(ComponentTypeUse::Inline(_))
+            | ItemSigKind::Instance(ComponentTypeUse::Inline(_))
+            | ItemSigKind::Type(_) => return None,
+        })
+    }
+}
+
+/// Structural key for a [`ComponentType`].
+#[derive(PartialEq, Eq, Hash)]
+struct ComponentTypeKey<'a>(Box<[DeclKey<'a>]>);
+
+impl<'a> TypeKey<'a> for Option<ComponentTypeKey<'a>> {
+    fn lookup(&self, cx: &Expander<'a>) -> Option<Index<'a>> {
+        cx.component_type_to_idx.get(self.as_ref()?).cloned()
+    }
+
+    fn insert(self, cx: &mut Expander<'a>, index: Index<'a>) {
+        if let Some(key) = self {
+            cx.component_type_to_idx.entry(key).or_insert(index);
+        }
+    }
+}
+
+/// Structural key for an [`InstanceType`].
+#[derive(PartialEq, Eq, Hash)]
+struct InstanceTypeKey<'a>(Box<[DeclKey<'a>]>);
+
+impl<'a> TypeKey<'a> for Option<InstanceTypeKey<'a>> {
+    fn lookup(&self, cx: &Expander<'a>) -> Option<Index<'a>> {
+        cx.instance_type_to_idx.get(self.as_ref()?).cloned()
+    }
+
+    fn insert(self, cx: &mut Expander<'a>, index: Index<'a>) {
+        if let Some(key) = self {
+            cx.instance_type_to_idx.entry(key).or_insert(index);
+        }
+    }
 }
 
 struct Todo;
@@ -886,5 +1289,5 @@ impl<'a> TypeKey<'a> for Todo {
         None
     }
 
-    fn insert(&self, _cx: &mut Expander<'a>, _index: Index<'a>) {}
+    fn insert(self, _cx: &mut Expander<'a>, _index: Index<'a>) {}
 }
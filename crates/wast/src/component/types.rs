@@ -223,7 +223,7 @@ impl<'a> Parse<'a> for TypeDef<'a> {
 
 /// A primitive value type.
 #[allow(missing_docs)]
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum PrimitiveValType {
     Bool,
     S8,
@@ -6,7 +6,6 @@ use crate::encode::Encode;
 use crate::token::*;
 use std::borrow::Cow;
 use std::marker;
-#[cfg(feature = "dwarf")]
 use std::path::Path;
 
 /// Options that can be specified when encoding a component or a module to
@@ -18,6 +17,8 @@ use std::path::Path;
 pub struct EncodeOptions<'a> {
     #[cfg(feature = "dwarf")]
     dwarf_info: Option<(&'a Path, &'a str, GenerateDwarf)>,
+    source_map_info: Option<(&'a Path, &'a str)>,
+    source_mapping_url: Option<&'a str>,
 
     _marker: marker::PhantomData<&'a str>,
 }
@@ -30,6 +31,8 @@ mod dwarf_disabled;
 #[cfg(not(feature = "dwarf"))]
 use self::dwarf_disabled as dwarf;
 
+mod source_map;
+
 /// Configuration of how DWARF debugging information may be generated.
 #[derive(Copy, Clone, Debug)]
 #[non_exhaustive]
@@ -61,6 +64,26 @@ impl<'a> EncodeOptions<'a> {
         self
     }
 
+    /// Enables generation of a Source Map v3 file which maps the binary
+    /// offsets of instructions back to their location in the original text.
+    ///
+    /// The `file` and `contents` are the same as for [`EncodeOptions::dwarf`].
+    /// The generated source map is returned from
+    /// [`EncodeOptions::encode_wat_with_source_map`] and requires that
+    /// instruction spans were tracked during parsing with
+    /// [`ParseBuffer::track_instr_spans`](crate::parser::ParseBuffer::track_instr_spans).
+    pub fn source_map(&mut self, file: &'a Path, contents: &'a str) -> &mut Self {
+        self.source_map_info = Some((file, contents));
+        self
+    }
+
+    /// Appends a `sourceMappingURL` custom section to encoded modules which
+    /// points to `url` as the location of the module's source map.
+    pub fn source_mapping_url(&mut self, url: &'a str) -> &mut Self {
+        self.source_mapping_url = Some(url);
+        self
+    }
+
     /// Encodes the given [`Module`] with these options.
     ///
     /// For more information see [`Module::encode`].
@@ -68,10 +91,36 @@ impl<'a> EncodeOptions<'a> {
         &self,
         module: &mut Module<'_>,
     ) -> std::result::Result<Vec<u8>, crate::Error> {
+        self.encode_module_with_source_map(module)
+            .map(|(wasm, _)| wasm)
+    }
+
+    /// Encodes the given [`Module`] with these options, additionally returning
+    /// the JSON of its source map.
+    ///
+    /// The source map is `None` if [`EncodeOptions::source_map`] wasn't
+    /// configured or if the module is in the `(module binary ...)` form.
+    pub fn encode_module_with_source_map(
+        &self,
+        module: &mut Module<'_>,
+    ) -> std::result::Result<(Vec<u8>, Option<String>), crate::Error> {
         module.resolve()?;
         Ok(match &module.kind {
-            ModuleKind::Text(fields) => encode(&module.id, &module.name, fields, self),
-            ModuleKind::Binary(blobs) => blobs.iter().flat_map(|b| b.iter().cloned()).collect(),
+            ModuleKind::Text(fields) => {
+                let mut source_map = source_map::SourceMap::new(self);
+                let wasm = encode_with_source_map(
+                    &module.id,
+                    &module.name,
+                    fields,
+                    self,
+                    source_map.as_mut(),
+                );
+                (wasm, source_map.map(|s| s.finish()))
+            }
+            ModuleKind::Binary(blobs) => {
+                let wasm = blobs.iter().flat_map(|b| b.iter().cloned()).collect();
+                (wasm, None)
+            }
         })
     }
 
@@ -99,13 +148,43 @@ impl<'a> EncodeOptions<'a> {
             Wat::Component(_) => unreachable!(),
         }
     }
+
+    /// Encodes the given [`Wat`] with these options, additionally returning
+    /// the JSON of its source map.
+    ///
+    /// Source maps are only generated for core modules, so the source map is
+    /// always `None` for components. For more information see
+    /// [`EncodeOptions::encode_module_with_source_map`].
+    pub fn encode_wat_with_source_map(
+        &self,
+        wat: &mut Wat<'_>,
+    ) -> std::result::Result<(Vec<u8>, Option<String>), crate::Error> {
+        match wat {
+            Wat::Module(m) => self.encode_module_with_source_map(m),
+            #[cfg(feature = "component-model")]
+            Wat::Component(c) => Ok((self.encode_component(c)?, None)),
+            #[cfg(not(feature = "component-model"))]
+            Wat::Component(_) => unreachable!(),
+        }
+    }
 }
 
+#[cfg(feature = "component-model")]
 pub(crate) fn encode(
     module_id: &Option<Id<'_>>,
     module_name: &Option<NameAnnotation<'_>>,
     fields: &[ModuleField<'_>],
     opts: &EncodeOptions,
+) -> Vec<u8> {
+    encode_with_source_map(module_id, module_name, fields, opts, None)
+}
+
+fn encode_with_source_map<'a>(
+    module_id: &Option<Id<'a>>,
+    module_name: &Option<NameAnnotation<'a>>,
+    fields: &[ModuleField<'a>],
+    opts: &EncodeOptions<'a>,
+    source_map: Option<&mut source_map::SourceMap<'a>>,
 ) -> Vec<u8> {
     use CustomPlace::*;
     use CustomPlaceAnchor::*;
@@ -174,16 +253,17 @@ pub(crate) fn encode(
         });
     }
 
-    // Prepare to and emit the code section. This is where DWARF may optionally
-    // be emitted depending on configuration settings. Note that `code_section`
-    // will internally emit the branch hints section if necessary.
+    // Prepare to and emit the code section. This is where DWARF and source
+    // maps may optionally be emitted depending on configuration settings.
+    // Note that `code_section` will internally emit the branch hints section
+    // if necessary.
     let names = find_names(module_id, module_name, fields);
     let num_import_funcs = imports
         .iter()
         .filter(|i| matches!(i.item.kind, ItemKind::Func(..)))
         .count() as u32;
     let mut dwarf = dwarf::Dwarf::new(num_import_funcs, opts, &names, &types);
-    e.code_section(&funcs, num_import_funcs, dwarf.as_mut());
+    e.code_section(&funcs, num_import_funcs, dwarf.as_mut(), source_map);
 
     e.typed_section(&data);
 
@@ -194,6 +274,14 @@ pub(crate) fn encode(
     if let Some(dwarf) = &mut dwarf {
         dwarf.emit(&mut e);
     }
+    if let Some(url) = opts.source_mapping_url {
+        let mut data = Vec::new();
+        url.encode(&mut data);
+        e.wasm.section(&wasm_encoder::CustomSection {
+            name: "sourceMappingURL".into(),
+            data: data.into(),
+        });
+    }
 
     return e.wasm.finish();
 
@@ -249,33 +337,67 @@ impl Encoder<'_> {
     ///
     /// The `list` provided is the list of functions that are emitted into the
     /// code section. The `func_index` provided is the initial index of defined
-    /// functions, so it's the count of imported functions. The `dwarf` field is
-    /// optionally used to track debugging information.
+    /// functions, so it's the count of imported functions. The `dwarf` and
+    /// `source_map` fields are optionally used to track debugging information.
     fn code_section<'a>(
         &'a mut self,
         list: &[&'a Func<'_>],
         mut func_index: u32,
         mut dwarf: Option<&mut dwarf::Dwarf>,
+        mut source_map: Option<&mut source_map::SourceMap>,
     ) {
         self.custom_sections(CustomPlace::Before(CustomPlaceAnchor::Code));
 
         if !list.is_empty() {
             let mut branch_hints = wasm_encoder::BranchHints::new();
+            let mut code_metadata = Vec::<(&str, wasm_encoder::CodeMetadataSection)>::new();
             let mut code_section = wasm_encoder::CodeSection::new();
 
             for func in list.iter() {
-                let hints = func.encode(&mut code_section, dwarf.as_deref_mut());
-                if !hints.is_empty() {
-                    branch_hints.function_hints(func_index, hints.into_iter());
+                let metadata = func.encode(
+                    &mut code_section,
+                    dwarf.as_deref_mut(),
+                    source_map.as_deref_mut(),
+                );
+                if !metadata.branch_hints.is_empty() {
+                    branch_hints.function_hints(func_index, metadata.branch_hints.into_iter());
+                }
+
+                // Other code metadata is grouped by name into one section
+                // each, in the order that the names were first seen.
+                let mut items = metadata.code_metadata;
+                while let Some((name, ..)) = items.first() {
+                    let name = *name;
+                    let entries = items
+                        .iter()
+                        .filter(|(n, ..)| *n == name)
+                        .map(|(_, func_offset, data)| wasm_encoder::CodeMetadataEntry {
+                            func_offset: *func_offset,
+                            data,
+                        })
+                        .collect::<Vec<_>>();
+                    let section = match code_metadata.iter_mut().find(|(n, _)| *n == name) {
+                        Some((_, section)) => section,
+                        None => {
+                            let section = wasm_encoder::CodeMetadataSection::new(name);
+                            code_metadata.push((name, section));
+                            &mut code_metadata.last_mut().unwrap().1
+                        }
+                    };
+                    section.function_metadata(func_index, entries);
+                    items.retain(|(n, ..)| *n != name);
                 }
                 func_index += 1;
             }
 
-            // Branch hints section has to be inserted before the Code section
-            // Insert the section only if we have some hints
+            // Code metadata sections, such as branch hints, have to be
+            // inserted before the Code section, and only if they're non-empty.
             if !branch_hints.is_empty() {
                 self.wasm.section(&branch_hints);
             }
+            for (_, section) in code_metadata.iter() {
+                self.wasm.section(section);
+            }
 
             // Finally, insert the Code section from the tmp buffer
             self.wasm.section(&code_section);
@@ -283,6 +405,9 @@ impl Encoder<'_> {
             if let Some(dwarf) = &mut dwarf {
                 dwarf.set_code_section_size(code_section.byte_len());
             }
+            if let Some(source_map) = &mut source_map {
+                source_map.set_code_section_end(self.wasm.len(), code_section.byte_len());
+            }
         }
         self.custom_sections(CustomPlace::After(CustomPlaceAnchor::Code));
     }
@@ -716,17 +841,26 @@ impl SectionItem for Data<'_> {
     }
 }
 
-impl Func<'_> {
-    /// Encodes the function into `e` while returning all branch hints with
-    /// known relative offsets after encoding.
+/// Code metadata of a function with known relative offsets, returned from
+/// encoding the function.
+struct FuncMetadata<'a> {
+    branch_hints: Vec<wasm_encoder::BranchHint>,
+    /// The name, function offset, and data of all other code metadata.
+    code_metadata: Vec<(&'a str, u32, Vec<u8>)>,
+}
+
+impl<'a> Func<'a> {
+    /// Encodes the function into `e` while returning all branch hints and
+    /// other code metadata with known relative offsets after encoding.
     ///
-    /// The `dwarf` field is optional and used to track debugging information
-    /// for each instruction.
+    /// The `dwarf` and `source_map` fields are optional and used to track
+    /// debugging information for each instruction.
     fn encode(
         &self,
         section: &mut wasm_encoder::CodeSection,
         mut dwarf: Option<&mut dwarf::Dwarf>,
-    ) -> Vec<wasm_encoder::BranchHint> {
+        mut source_map: Option<&mut source_map::SourceMap>,
+    ) -> FuncMetadata<'a> {
         assert!(self.exports.names.is_empty());
         let (expr, locals) = match &self.kind {
             FuncKind::Inline { expression, locals } => (expression, locals),
@@ -746,49 +880,67 @@ impl Func<'_> {
         // encodes its length first then the body.
         let mut func =
             wasm_encoder::Function::new_with_locals_types(locals.iter().map(|t| t.ty.into()));
-        let branch_hints = expr.encode(&mut func, dwarf.as_deref_mut());
+        let metadata = expr.encode(&mut func, dwarf.as_deref_mut(), source_map.as_deref_mut());
         let func_size = func.byte_len();
         section.function(&func);
 
         if let Some(dwarf) = &mut dwarf {
             dwarf.end_func(func_size, section.byte_len());
         }
+        if let Some(source_map) = &mut source_map {
+            source_map.end_func(func_size, section.byte_len());
+        }
 
-        branch_hints
+        metadata
     }
 }
 
-impl Expression<'_> {
+impl<'a> Expression<'a> {
     /// Encodes this expression into `e` and optionally tracks debugging
-    /// information for each instruction in `dwarf`.
+    /// information for each instruction in `dwarf` and `source_map`.
     ///
-    /// Returns all branch hints, if any, found while parsing this function.
+    /// Returns all branch hints and other code metadata, if any, found while
+    /// parsing this function.
     fn encode(
         &self,
         func: &mut wasm_encoder::Function,
         mut dwarf: Option<&mut dwarf::Dwarf>,
-    ) -> Vec<wasm_encoder::BranchHint> {
+        mut source_map: Option<&mut source_map::SourceMap>,
+    ) -> FuncMetadata<'a> {
         let mut hints = Vec::with_capacity(self.branch_hints.len());
         let mut next_hint = self.branch_hints.iter().peekable();
+        let mut code_metadata = Vec::with_capacity(self.code_metadata.len());
+        let mut next_metadata = self.code_metadata.iter().peekable();
         let mut tmp = Vec::new();
 
         for (i, instr) in self.instrs.iter().enumerate() {
+            let func_offset = u32::try_from(func.byte_len() + tmp.len()).unwrap();
+
             // Branch hints are stored in order of increasing `instr_index` so
             // check to see if the next branch hint matches this instruction's
             // index.
             if let Some(hint) = next_hint.next_if(|h| h.instr_index == i) {
                 hints.push(wasm_encoder::BranchHint {
-                    branch_func_offset: u32::try_from(func.byte_len() + tmp.len()).unwrap(),
+                    branch_func_offset: func_offset,
                     branch_hint_value: hint.value,
                 });
             }
 
-            // If DWARF is enabled then track this instruction's binary offset
-            // and source location.
-            if let Some(dwarf) = &mut dwarf {
-                if let Some(span) = self.instr_spans.as_ref().map(|s| s[i]) {
+            // Other code metadata is similarly ordered, but there may be
+            // more than one item for each instruction.
+            while let Some(item) = next_metadata.next_if(|m| m.instr_index == i) {
+                code_metadata.push((item.name, func_offset, item.data.concat()));
+            }
+
+            // If DWARF or source maps are enabled then track this
+            // instruction's binary offset and source location.
+            if let Some(span) = self.instr_spans.as_ref().map(|s| s[i]) {
+                if let Some(dwarf) = &mut dwarf {
                     dwarf.instr(func.byte_len() + tmp.len(), span);
                 }
+                if let Some(source_map) = &mut source_map {
+                    source_map.instr(func.byte_len() + tmp.len(), span);
+                }
             }
 
             // Finally emit the instruction and move to the next.
@@ -797,7 +949,10 @@ impl Expression<'_> {
         func.raw(tmp.iter().copied());
         func.instructions().end();
 
-        hints
+        FuncMetadata {
+            branch_hints: hints,
+            code_metadata,
+        }
     }
 
     fn to_const_expr(&self) -> wasm_encoder::ConstExpr {
@@ -0,0 +1,173 @@
+//! Implementation of emitting a Source Map v3 file for `*.wat` files.
+//!
+//! Source maps are what browser developer tools consume to map locations in a
+//! compiled artifact back to original source. For WebAssembly there's only a
+//! single "line" in the generated output and the "column" of each mapping is
+//! the byte offset of an instruction from the start of the module. Modules
+//! refer to their source map with a `sourceMappingURL` custom section.
+//!
+//! Mappings are only generated for instructions in the code section of a
+//! top-level module, so this requires the `instr_spans` of each expression to
+//! have been tracked during parsing.
+
+use crate::core::binary::EncodeOptions;
+use crate::token::Span;
+use std::path::Path;
+
+pub struct SourceMap<'a> {
+    file: &'a Path,
+    contents: &'a str,
+
+    /// Byte offsets in `contents` at which each line starts.
+    line_starts: Vec<usize>,
+
+    /// Instructions within the function currently being encoded, as an offset
+    /// relative to the start of the function's body.
+    cur_func: Vec<(usize, Span)>,
+
+    /// Instructions encoded so far, as an offset relative to the start of the
+    /// code section's function bodies.
+    instrs: Vec<(usize, Span)>,
+
+    /// Offset within the module of the first function in the code section,
+    /// known once the code section has been emitted.
+    code_start: usize,
+}
+
+impl<'a> SourceMap<'a> {
+    pub fn new(opts: &EncodeOptions<'a>) -> Option<SourceMap<'a>> {
+        let (file, contents) = opts.source_map_info?;
+        let line_starts = std::iter::once(0)
+            .chain(contents.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Some(SourceMap {
+            file,
+            contents,
+            line_starts,
+            cur_func: Vec::new(),
+            instrs: Vec::new(),
+            code_start: 0,
+        })
+    }
+
+    pub fn instr(&mut self, offset: usize, span: Span) {
+        self.cur_func.push((offset, span));
+    }
+
+    pub fn end_func(&mut self, func_size: usize, code_section_end: usize) {
+        // Like with DWARF the function's body starts after its leb-encoded
+        // size, so it's the current end of the code section minus the size
+        // of the body.
+        let start = code_section_end - func_size;
+        self.instrs.extend(
+            self.cur_func
+                .drain(..)
+                .map(|(offset, span)| (start + offset, span)),
+        );
+    }
+
+    /// Configures the size of the module after the code section, containing
+    /// `code_section_size` bytes of function bodies, was appended.
+    pub fn set_code_section_end(&mut self, module_len: usize, code_section_size: usize) {
+        self.code_start = module_len - code_section_size;
+    }
+
+    /// Renders the JSON of this source map.
+    pub fn finish(&self) -> String {
+        let mut mappings = String::new();
+        let mut prev = (0, 0, 0);
+        for (i, (offset, span)) in self.instrs.iter().enumerate() {
+            let offset = self.code_start + offset;
+            let (line, col) = self.linecol(*span);
+            if i > 0 {
+                mappings.push(',');
+            }
+            vlq(&mut mappings, offset as i64 - prev.0 as i64);
+            vlq(&mut mappings, 0);
+            vlq(&mut mappings, line as i64 - prev.1 as i64);
+            vlq(&mut mappings, col as i64 - prev.2 as i64);
+            prev = (offset, line, col);
+        }
+
+        let mut json = String::from("{\"version\":3,\"sources\":[");
+        json_string(&mut json, &self.file.display().to_string());
+        json.push_str("],\"sourcesContent\":[");
+        json_string(&mut json, self.contents);
+        json.push_str("],\"names\":[],\"mappings\":");
+        json_string(&mut json, &mappings);
+        json.push('}');
+        json
+    }
+
+    /// Returns the 0-based line and column of `span`, where columns are
+    /// measured in UTF-16 code units as is conventional for source maps.
+    fn linecol(&self, span: Span) -> (usize, usize) {
+        let offset = span.offset();
+        let line = match self.line_starts.binary_search(&offset) {
+            Ok(i) => i,
+            Err(i) => i - 1,
+        };
+        let col = self.contents[self.line_starts[line]..offset]
+            .encode_utf16()
+            .count();
+        (line, col)
+    }
+}
+
+/// Appends the base64 VLQ encoding of `n` to `dst`.
+fn vlq(dst: &mut String, n: i64) {
+    const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut n = if n < 0 {
+        (n.unsigned_abs() << 1) | 1
+    } else {
+        (n as u64) << 1
+    };
+    loop {
+        let mut digit = n & 0x1f;
+        n >>= 5;
+        if n != 0 {
+            digit |= 0x20;
+        }
+        dst.push(BASE64[digit as usize] as char);
+        if n == 0 {
+            break;
+        }
+    }
+}
+
+fn json_string(dst: &mut String, s: &str) {
+    dst.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => dst.push_str("\\\""),
+            '\\' => dst.push_str("\\\\"),
+            '\n' => dst.push_str("\\n"),
+            '\r' => dst.push_str("\\r"),
+            '\t' => dst.push_str("\\t"),
+            c if (c as u32) < 0x20 => dst.push_str(&format!("\\u{:04x}", c as u32)),
+            c => dst.push(c),
+        }
+    }
+    dst.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::vlq;
+
+    #[test]
+    fn vlq_encoding() {
+        let encode = |n| {
+            let mut s = String::new();
+            vlq(&mut s, n);
+            s
+        };
+        assert_eq!(encode(0), "A");
+        assert_eq!(encode(1), "C");
+        assert_eq!(encode(-1), "D");
+        assert_eq!(encode(15), "e");
+        assert_eq!(encode(16), "gB");
+        assert_eq!(encode(-16), "hB");
+        assert_eq!(encode(1000), "w+B");
+    }
+}
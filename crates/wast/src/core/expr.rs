@@ -3,7 +3,7 @@ use crate::core::*;
 use crate::encode::Encode;
 use crate::kw;
 use crate::lexer::{Lexer, Token, TokenKind};
-use crate::parser::{Parse, Parser, Result};
+use crate::parser::{Cursor, Parse, Parser, Peek, Result};
 use crate::token::*;
 use std::mem;
 
@@ -21,6 +21,10 @@ pub struct Expression<'a> {
     /// Branch hints, if any, found while parsing instructions.
     pub branch_hints: Box<[BranchHint]>,
 
+    /// Other code metadata annotations, if any, found while parsing
+    /// instructions.
+    pub code_metadata: Box<[CodeMetadata<'a>]>,
+
     /// Optionally parsed spans of all instructions in `instrs`.
     ///
     /// This value is `None` as it's disabled by default. This can be enabled
@@ -46,6 +50,22 @@ pub struct BranchHint {
     pub value: u32,
 }
 
+/// A `@metadata.code.<name>` annotation in the code, other than a branch hint.
+/// Like [`BranchHint`] this doesn't produce an instruction itself but instead
+/// attaches its data to the following instruction in the
+/// `metadata.code.<name>` custom section.
+#[derive(Debug)]
+pub struct CodeMetadata<'a> {
+    /// The name of the metadata, without the `metadata.code.` prefix.
+    pub name: &'a str,
+    /// Index of instructions in `instrs` field of `Expression` that this
+    /// metadata applies to.
+    pub instr_index: usize,
+    /// The payload of this metadata, concatenated from all of the strings
+    /// in the annotation.
+    pub data: Vec<&'a [u8]>,
+}
+
 impl<'a> Parse<'a> for Expression<'a> {
     fn parse(parser: Parser<'a>) -> Result<Self> {
         let mut exprs = ExpressionParser::new(parser);
@@ -53,6 +73,7 @@ impl<'a> Parse<'a> for Expression<'a> {
         Ok(Expression {
             instrs: exprs.raw_instrs.into(),
             branch_hints: exprs.branch_hints.into(),
+            code_metadata: exprs.code_metadata.into(),
             instr_spans: exprs.spans.map(|s| s.into()),
         })
     }
@@ -64,6 +85,7 @@ impl<'a> Expression<'a> {
         Expression {
             instrs: [instr].into(),
             branch_hints: Box::new([]),
+            code_metadata: Box::new([]),
             instr_spans: None,
         }
     }
@@ -89,6 +111,7 @@ impl<'a> Expression<'a> {
         Ok(Expression {
             instrs: exprs.raw_instrs.into(),
             branch_hints: exprs.branch_hints.into(),
+            code_metadata: exprs.code_metadata.into(),
             instr_spans: exprs.spans.map(|s| s.into()),
         })
     }
@@ -117,6 +140,10 @@ struct ExpressionParser<'a> {
     /// <(index of branch instructions, BranchHintAnnotation)>
     branch_hints: Vec<BranchHint>,
 
+    /// Other code metadata annotations, collected similarly to
+    /// `branch_hints`.
+    code_metadata: Vec<CodeMetadata<'a>>,
+
     /// Storage for all span information in `raw_instrs`. Optionally disabled to
     /// reduce memory consumption of parsing expressions.
     spans: Option<Vec<Span>>,
@@ -144,8 +171,18 @@ enum Level<'a> {
     /// nested block.
     IfArm,
 
-    /// This means we are finishing the parsing of a branch hint annotation.
-    BranchHint,
+    /// This means we're parsing a folded legacy `try` block, and the state of
+    /// which clauses have been parsed is tracked in the payload.
+    Try(Try),
+
+    /// This means we're parsing inside of `(do ...)`, `(catch ...)`,
+    /// `(catch_all ...)`, or `(delegate ...)` which are all nested within a
+    /// `try` block.
+    TryArm,
+
+    /// This means we are finishing the parsing of a code metadata annotation,
+    /// such as a branch hint.
+    CodeMetadata,
 }
 
 /// Possible states of "what is currently being parsed?" in an `if` expression.
@@ -162,12 +199,32 @@ enum If<'a> {
     Else,
 }
 
+/// Possible states of "what is currently being parsed?" in a folded legacy
+/// `try` expression.
+enum Try {
+    /// Only the `try` instruction has been parsed, next thing to parse is the
+    /// `(do ...)` clause.
+    Do,
+    /// Parsing the `(do ...)` clause, and afterwards either a closing paren
+    /// or a `catch`, `catch_all`, or `delegate` clause is required.
+    AfterDo,
+    /// Parsing a `(catch ...)` clause, and afterwards further `catch` or
+    /// `catch_all` clauses may follow.
+    Catch,
+    /// Parsing a `(catch_all ...)` clause, nothing can come after.
+    CatchAll,
+    /// Parsing a `(delegate ...)` clause, which terminates the `try` block
+    /// in place of an `end` instruction so nothing can come after.
+    Delegate,
+}
+
 impl<'a> ExpressionParser<'a> {
     fn new(parser: Parser<'a>) -> ExpressionParser<'a> {
         ExpressionParser {
             raw_instrs: Vec::new(),
             stack: Vec::new(),
             branch_hints: Vec::new(),
+            code_metadata: Vec::new(),
             spans: if parser.track_instr_spans() {
                 Some(Vec::new())
             } else {
@@ -189,7 +246,7 @@ impl<'a> ExpressionParser<'a> {
             // As a small ease-of-life adjustment here, if we're parsing inside
             // of an `if block then we require that all sub-components are
             // s-expressions surrounded by `(` and `)`, so verify that here.
-            if let Some(Level::If(_)) = self.stack.last() {
+            if let Some(Level::If(_) | Level::Try(_)) = self.stack.last() {
                 if !parser.is_empty() && !parser.peek::<LParen>()? {
                     return Err(parser.error("expected `(`"));
                 }
@@ -219,10 +276,23 @@ impl<'a> ExpressionParser<'a> {
                         continue;
                     }
 
+                    // Folded legacy `try` blocks are similar to `if` in that
+                    // their clauses are nested s-expressions.
+                    if self.handle_try_lparen(parser)? {
+                        continue;
+                    }
+
                     // Handle the case of a branch hint annotation
                     if parser.peek::<annotation::metadata_code_branch_hint>()? {
                         self.parse_branch_hint(parser)?;
-                        self.stack.push(Level::BranchHint);
+                        self.stack.push(Level::CodeMetadata);
+                        continue;
+                    }
+
+                    // ... and any other code metadata annotation
+                    if parser.peek::<CodeMetadataAnnotation>()? {
+                        self.parse_code_metadata(parser)?;
+                        self.stack.push(Level::CodeMetadata);
                         continue;
                     }
 
@@ -246,6 +316,14 @@ impl<'a> ExpressionParser<'a> {
                             self.stack.push(Level::If(If::Clause(i, span)));
                         }
 
+                        // A folded legacy `try` has no condition like `if`
+                        // does, so the instruction is emitted immediately and
+                        // the clauses are parsed afterwards.
+                        i @ Instruction::Try(_) => {
+                            self.push_instr(i, span);
+                            self.stack.push(Level::Try(Try::Do));
+                        }
+
                         // Anything else means that we're parsing a nested form
                         // such as `(i32.add ...)` which means that the
                         // instruction we parsed will be coming at the end.
@@ -259,7 +337,8 @@ impl<'a> ExpressionParser<'a> {
                 Paren::Right(span) => match self.stack.pop().unwrap() {
                     Level::EndWith(i, s) => self.push_instr(i, s.unwrap_or(span)),
                     Level::IfArm => {}
-                    Level::BranchHint => {}
+                    Level::TryArm => {}
+                    Level::CodeMetadata => {}
 
                     // If an `if` statement hasn't parsed the clause or `then`
                     // block, then that's an error because there weren't enough
@@ -271,6 +350,17 @@ impl<'a> ExpressionParser<'a> {
                     Level::If(_) => {
                         self.push_instr(Instruction::End(None), span);
                     }
+
+                    // Similar to `if` a `try` must have a `do` clause, and
+                    // otherwise it's terminated with `end` unless a
+                    // `delegate` already terminated it.
+                    Level::Try(Try::Do) => {
+                        return Err(parser.error("previous `try` had no `do`"));
+                    }
+                    Level::Try(Try::Delegate) => {}
+                    Level::Try(_) => {
+                        self.push_instr(Instruction::End(None), span);
+                    }
                 },
             }
         }
@@ -372,6 +462,55 @@ impl<'a> ExpressionParser<'a> {
         }
     }
 
+    /// State transitions with parsing a folded legacy `try` statement.
+    ///
+    /// The syntactical form of a `try` statement looks like:
+    ///
+    /// ```wat
+    /// (try (do $do) (catch $tag $catch)* (catch_all $catch_all)?)
+    /// (try (do $do) (delegate $label))
+    /// ```
+    ///
+    /// This method is called after a `(` is parsed within the `(try ...`
+    /// block and returns `true` if it was handled here, or `false` if the
+    /// innermost block isn't a `try`.
+    fn handle_try_lparen(&mut self, parser: Parser<'a>) -> Result<bool> {
+        let t = match self.stack.last_mut() {
+            Some(Level::Try(t)) => t,
+            _ => return Ok(false),
+        };
+
+        let span = parser.cur_span();
+        let next = match t {
+            Try::Do => {
+                parser.parse::<kw::r#do>()?;
+                *t = Try::AfterDo;
+                self.stack.push(Level::TryArm);
+                return Ok(true);
+            }
+            Try::AfterDo | Try::Catch => {
+                let mut l = parser.lookahead1();
+                if l.peek::<kw::catch>()? {
+                    Try::Catch
+                } else if l.peek::<kw::catch_all>()? {
+                    Try::CatchAll
+                } else if matches!(t, Try::AfterDo) && l.peek::<kw::delegate>()? {
+                    Try::Delegate
+                } else {
+                    return Err(l.error());
+                }
+            }
+            Try::CatchAll | Try::Delegate => {
+                return Err(parser.error("unexpected token: too many payloads inside of `(try)`"));
+            }
+        };
+        *t = next;
+        let instr = parser.parse()?;
+        self.push_instr(instr, span);
+        self.stack.push(Level::TryArm);
+        Ok(true)
+    }
+
     fn parse_branch_hint(&mut self, parser: Parser<'a>) -> Result<()> {
         parser.parse::<annotation::metadata_code_branch_hint>()?;
 
@@ -390,6 +529,20 @@ impl<'a> ExpressionParser<'a> {
         Ok(())
     }
 
+    fn parse_code_metadata(&mut self, parser: Parser<'a>) -> Result<()> {
+        let name = parser.parse::<CodeMetadataAnnotation>()?.0;
+        let mut data = Vec::new();
+        while !parser.is_empty() {
+            data.push(parser.parse()?);
+        }
+        self.code_metadata.push(CodeMetadata {
+            name,
+            instr_index: self.raw_instrs.len(),
+            data,
+        });
+        Ok(())
+    }
+
     fn push_instr(&mut self, instr: Instruction<'a>, span: Span) {
         self.raw_instrs.push(instr);
         if let Some(spans) = &mut self.spans {
@@ -398,6 +551,35 @@ impl<'a> ExpressionParser<'a> {
     }
 }
 
+/// Any `@metadata.code.<name>` annotation, where the payload is the `name`.
+struct CodeMetadataAnnotation<'a>(&'a str);
+
+impl<'a> Parse<'a> for CodeMetadataAnnotation<'a> {
+    fn parse(parser: Parser<'a>) -> Result<Self> {
+        parser.step(|c| {
+            if let Some((a, rest)) = c.annotation()? {
+                if let Some(name) = a.strip_prefix("metadata.code.") {
+                    return Ok((CodeMetadataAnnotation(name), rest));
+                }
+            }
+            Err(c.error("expected a `@metadata.code` annotation"))
+        })
+    }
+}
+
+impl Peek for CodeMetadataAnnotation<'_> {
+    fn peek(cursor: Cursor<'_>) -> Result<bool> {
+        Ok(match cursor.annotation()? {
+            Some((a, _rest)) => a.starts_with("metadata.code."),
+            None => false,
+        })
+    }
+
+    fn display() -> &'static str {
+        "`@metadata.code.*`"
+    }
+}
+
 // TODO: document this obscenity
 macro_rules! instructions {
     (pub enum Instruction<'a> {
@@ -147,7 +147,7 @@ impl<'a> ModuleField<'a> {
     pub(crate) fn parse_remaining(parser: Parser<'a>) -> Result<Vec<ModuleField<'a>>> {
         let mut fields = Vec::new();
         while !parser.is_empty() {
-            fields.push(parser.parens(ModuleField::parse)?);
+            fields.extend(parser.parens_recover(ModuleField::parse)?);
         }
         Ok(fields)
     }
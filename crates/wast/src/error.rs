@@ -24,6 +24,7 @@ struct ErrorInner {
     file: Option<PathBuf>,
     span: Span,
     kind: ErrorKind,
+    others: Vec<Error>,
 }
 
 #[derive(Debug)]
@@ -47,6 +48,7 @@ impl Error {
                 file: None,
                 span,
                 kind: ErrorKind::Lex(kind),
+                others: Vec::new(),
             }),
         };
         ret.set_text(content);
@@ -60,6 +62,7 @@ impl Error {
                 file: None,
                 span,
                 kind: ErrorKind::Custom(message),
+                others: Vec::new(),
             }),
         };
         ret.set_text(content);
@@ -78,10 +81,21 @@ impl Error {
                 file: None,
                 span,
                 kind: ErrorKind::Custom(message),
+                others: Vec::new(),
             }),
         }
     }
 
+    /// Combines a list of errors, in the order they were found, into one.
+    ///
+    /// The first error is returned with the rest available via
+    /// [`Error::others`].
+    pub(crate) fn many(mut errors: Vec<Error>) -> Error {
+        let mut ret = errors.remove(0);
+        ret.inner.others.extend(errors);
+        ret
+    }
+
     /// Return the `Span` for this error.
     pub fn span(&self) -> Span {
         self.inner.span
@@ -94,6 +108,9 @@ impl Error {
     /// parsed, and this will extract a sub-slice as necessary to render in the
     /// `Display` implementation later on.
     pub fn set_text(&mut self, contents: &str) {
+        for other in self.inner.others.iter_mut() {
+            other.set_text(contents);
+        }
         if self.inner.text.is_some() {
             return;
         }
@@ -106,6 +123,9 @@ impl Error {
     /// The `path` here will be stored in this error and later rendered in the
     /// `Display` implementation.
     pub fn set_path(&mut self, path: &Path) {
+        for other in self.inner.others.iter_mut() {
+            other.set_path(path);
+        }
         if self.inner.file.is_some() {
             return;
         }
@@ -127,6 +147,25 @@ impl Error {
             ErrorKind::Custom(e) => e.clone(),
         }
     }
+
+    /// Returns any further errors which were found after this one.
+    ///
+    /// This is only non-empty when parsing with error recovery enabled via
+    /// [`ParseBuffer::recover`](crate::parser::ParseBuffer::recover), in
+    /// which case this error is the first one found in the input and the
+    /// returned errors are the remaining ones in the order they were found.
+    /// Each of these is also rendered by this error's `Display`
+    /// implementation.
+    pub fn others(&self) -> &[Error] {
+        &self.inner.others
+    }
+
+    fn fmt_others(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for other in self.inner.others.iter() {
+            write!(f, "\n\n{other}")?;
+        }
+        Ok(())
+    }
 }
 
 impl fmt::Display for Error {
@@ -138,7 +177,8 @@ impl fmt::Display for Error {
         let text = match &self.inner.text {
             Some(text) => text,
             None => {
-                return write!(f, "{} at byte offset {}", err, self.inner.span.offset);
+                write!(f, "{} at byte offset {}", err, self.inner.span.offset)?;
+                return self.fmt_others(f);
             }
         };
         let file = self
@@ -162,7 +202,8 @@ impl fmt::Display for Error {
             err = err,
             text = text.snippet,
             marker = "^",
-        )
+        )?;
+        self.fmt_others(f)
     }
 }
 
@@ -0,0 +1,1132 @@
+//! A source-preserving formatter for the WebAssembly text format.
+//!
+//! Unlike printing a binary back to text this works on the tokens of the
+//! original source, so comments, annotations and the author's choice of line
+//! breaks are all preserved. What's normalized is:
+//!
+//! * Indentation is two spaces per level of nesting, where both parentheses
+//!   and flat control instructions such as `block` ... `end` open a new level.
+//! * Whitespace within a line is collapsed to a single space and trailing
+//!   whitespace is removed.
+//! * Runs of blank lines are collapsed to a single blank line.
+//!
+//! Optionally the instructions of core wasm functions can be converted between
+//! the folded and flat forms with [`Formatter::instructions`].
+//!
+//! # Example
+//!
+//! ```
+//! let src = "(module\n(func $f (result i32)\n    ;; the answer\n  i32.const 42))";
+//! let formatted = wast::fmt::format(src)?;
+//! assert_eq!(
+//!     formatted,
+//!     "(module\n  (func $f (result i32)\n    ;; the answer\n    i32.const 42))\n",
+//! );
+//! # Ok::<(), wast::Error>(())
+//! ```
+
+use crate::Error;
+use crate::lexer::{Lexer, TokenKind};
+use crate::token::Span;
+use std::collections::VecDeque;
+use std::mem;
+
+/// How the instructions of functions are written by a [`Formatter`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InstrStyle {
+    /// Instructions are left in whichever form they were written in.
+    #[default]
+    Preserve,
+    /// Instructions are converted to the folded form, for example
+    /// `(i32.add (local.get 0) (i32.const 1))`.
+    ///
+    /// Instructions are only nested within one another when it's known from
+    /// the instruction alone how many operands it takes, so instructions such
+    /// as `call` are left unnested.
+    Folded,
+    /// Instructions are converted to the flat form, for example
+    /// `local.get 0 i32.const 1 i32.add`, each on its own line.
+    Flat,
+}
+
+/// Configuration for formatting WebAssembly text.
+#[derive(Debug, Clone, Default)]
+pub struct Formatter {
+    instrs: InstrStyle,
+}
+
+/// Formats `source` with the default configuration of [`Formatter`].
+pub fn format(source: &str) -> Result<String, Error> {
+    Formatter::new().format(source)
+}
+
+impl Formatter {
+    /// Creates a new formatter with the default configuration.
+    pub fn new() -> Formatter {
+        Formatter::default()
+    }
+
+    /// Configures how instructions within function bodies are written.
+    ///
+    /// Defaults to [`InstrStyle::Preserve`]. If a function's body can't be
+    /// understood, for example an `end` without a matching `block`, then
+    /// that function's instructions are left as they are.
+    pub fn instructions(&mut self, style: InstrStyle) -> &mut Self {
+        self.instrs = style;
+        self
+    }
+
+    /// Formats the `*.wat` or `*.wast` text in `source`.
+    ///
+    /// This only requires that `source` can be lexed and that its parentheses
+    /// are balanced, it does not otherwise need to be valid.
+    pub fn format(&self, source: &str) -> Result<String, Error> {
+        let mut nodes = parse(source)?;
+        if self.instrs != InstrStyle::Preserve {
+            convert_funcs(&mut nodes, self.instrs, true);
+        }
+        let mut printer = Printer::default();
+        printer.nodes(&nodes, 0, false);
+        if !printer.out.is_empty() {
+            printer.out.push('\n');
+        }
+        Ok(printer.out)
+    }
+}
+
+/// An item of source text along with the number of line breaks which
+/// precede it.
+#[derive(Clone, Debug)]
+struct Node<'a> {
+    newlines: usize,
+    item: Item<'a>,
+}
+
+#[derive(Clone, Debug)]
+enum Item<'a> {
+    /// Any token that isn't a comment or parenthesis.
+    Atom(&'a str),
+    /// A line or block comment.
+    Comment(&'a str),
+    /// A parenthesized list of nodes.
+    List(List<'a>),
+}
+
+#[derive(Clone, Debug)]
+struct List<'a> {
+    children: Vec<Node<'a>>,
+    /// Number of line breaks preceding the closing parenthesis.
+    close_newlines: usize,
+}
+
+impl<'a> Node<'a> {
+    fn new(newlines: usize, item: Item<'a>) -> Node<'a> {
+        Node { newlines, item }
+    }
+
+    fn atom(newlines: usize, s: &'a str) -> Node<'a> {
+        Node::new(newlines, Item::Atom(s))
+    }
+
+    fn list(newlines: usize, children: Vec<Node<'a>>) -> Node<'a> {
+        Node::new(
+            newlines,
+            Item::List(List {
+                children,
+                close_newlines: 0,
+            }),
+        )
+    }
+
+    fn keyword(&self) -> Option<&'a str> {
+        match self.item {
+            Item::Atom(s) if s.starts_with(|c: char| c.is_ascii_lowercase()) => Some(s),
+            _ => None,
+        }
+    }
+
+    fn is_id(&self) -> bool {
+        matches!(self.item, Item::Atom(s) if s.starts_with('$'))
+    }
+
+    /// Returns the keyword at the head of this node if it's a list.
+    fn head(&self) -> Option<&'a str> {
+        match &self.item {
+            Item::List(list) => match list.children.first()?.item {
+                Item::Atom(s) => Some(s),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Returns whether this is an instruction keyword or a folded
+    /// instruction.
+    fn is_instr(&self) -> bool {
+        match &self.item {
+            Item::Atom(_) => self.keyword().is_some_and(is_instr_keyword),
+            Item::List(_) => self.head().is_some_and(is_instr_keyword),
+            Item::Comment(_) => false,
+        }
+    }
+
+    /// Returns whether this node can be printed on a single line and how wide
+    /// that line is.
+    fn width(&self) -> Option<usize> {
+        match &self.item {
+            Item::Atom(s) => Some(s.len()),
+            Item::Comment(_) => None,
+            Item::List(list) => {
+                let mut width = 2 + list.children.len().saturating_sub(1);
+                for child in list.children.iter() {
+                    width += child.width()?;
+                }
+                Some(width)
+            }
+        }
+    }
+}
+
+/// Parses `source` into a tree of nodes.
+fn parse(source: &str) -> Result<Vec<Node<'_>>, Error> {
+    let mut stack = Vec::new();
+    let mut cur = Vec::new();
+    let mut newlines = 0;
+    for token in Lexer::new(source).iter(0) {
+        let token = token?;
+        let src = token.src(source);
+        let item = match token.kind {
+            TokenKind::Whitespace => {
+                newlines += src.matches('\n').count();
+                continue;
+            }
+            TokenKind::LineComment => Item::Comment(src.trim_end()),
+            TokenKind::BlockComment => Item::Comment(src),
+            TokenKind::LParen => {
+                stack.push((token.offset, newlines, mem::take(&mut cur)));
+                newlines = 0;
+                continue;
+            }
+            TokenKind::RParen => {
+                let (_, list_newlines, parent) = stack.pop().ok_or_else(|| {
+                    Error::parse(
+                        Span::from_offset(token.offset),
+                        source,
+                        "unexpected `)`".to_string(),
+                    )
+                })?;
+                let children = mem::replace(&mut cur, parent);
+                cur.push(Node::new(
+                    list_newlines,
+                    Item::List(List {
+                        children,
+                        close_newlines: newlines,
+                    }),
+                ));
+                newlines = 0;
+                continue;
+            }
+            _ => Item::Atom(src),
+        };
+        cur.push(Node::new(newlines, item));
+        newlines = 0;
+    }
+    if let Some((offset, ..)) = stack.pop() {
+        return Err(Error::parse(
+            Span::from_offset(offset),
+            source,
+            "unclosed `(`".to_string(),
+        ));
+    }
+    Ok(cur)
+}
+
+#[derive(Default)]
+struct Printer {
+    out: String,
+    /// Whether nothing has been printed yet on the current line.
+    line_start: bool,
+    /// Whether the next token directly follows an opening parenthesis.
+    after_paren: bool,
+    /// Whether the current line ends in a line comment, meaning that nothing
+    /// else can be printed on it.
+    line_comment: bool,
+}
+
+impl Printer {
+    fn nodes(&mut self, nodes: &[Node<'_>], level: usize, in_list: bool) {
+        // Number of flat control instructions, such as `block`, which are
+        // currently open.
+        let mut blocks = 0;
+        for (i, node) in nodes.iter().enumerate() {
+            self.separator(node.newlines, i == 0);
+            match &node.item {
+                Item::Atom(s) => {
+                    let mut indent = level + blocks;
+                    if !(in_list && i == 0) {
+                        match *s {
+                            "block" | "loop" | "if" | "try" | "try_table" => blocks += 1,
+                            "else" | "catch" | "catch_all" => {
+                                indent -= blocks.min(1);
+                            }
+                            "end" | "delegate" => {
+                                blocks = blocks.saturating_sub(1);
+                                indent = level + blocks;
+                            }
+                            _ => {}
+                        }
+                    }
+                    self.token(s, indent);
+                }
+                Item::Comment(s) => {
+                    self.token(s, level + blocks);
+                    self.line_comment = s.starts_with(";;");
+                }
+                Item::List(list) => {
+                    self.token("(", level + blocks);
+                    self.after_paren = true;
+                    self.nodes(&list.children, level + blocks + 1, true);
+                    self.separator(list.close_newlines.min(1), true);
+                    self.after_paren = true;
+                    self.token(")", level + blocks);
+                }
+            }
+        }
+    }
+
+    fn separator(&mut self, newlines: usize, first: bool) {
+        if self.out.is_empty() {
+            return;
+        }
+        if newlines > 0 || self.line_comment {
+            self.out.push('\n');
+            if newlines > 1 && !first {
+                self.out.push('\n');
+            }
+            self.line_start = true;
+            self.line_comment = false;
+        }
+    }
+
+    fn token(&mut self, s: &str, level: usize) {
+        if self.line_start {
+            for _ in 0..level {
+                self.out.push_str("  ");
+            }
+        } else if (!self.after_paren || s.starts_with(';')) && !self.out.is_empty() {
+            // Note that a comment directly after `(` would otherwise look like
+            // the start of a block comment.
+            self.out.push(' ');
+        }
+        self.out.push_str(s);
+        self.line_start = false;
+        self.after_paren = false;
+    }
+}
+
+/// Instructions which don't contain a `.` in their name.
+const DOTLESS_INSTRS: &[&str] = &[
+    "block",
+    "br",
+    "br_if",
+    "br_on_cast",
+    "br_on_cast_fail",
+    "br_on_non_null",
+    "br_on_null",
+    "br_table",
+    "call",
+    "call_indirect",
+    "call_ref",
+    "catch",
+    "catch_all",
+    "delegate",
+    "drop",
+    "else",
+    "end",
+    "if",
+    "loop",
+    "nop",
+    "resume",
+    "resume_throw",
+    "rethrow",
+    "return",
+    "return_call",
+    "return_call_indirect",
+    "return_call_ref",
+    "select",
+    "suspend",
+    "switch",
+    "throw",
+    "throw_ref",
+    "try",
+    "try_table",
+    "unreachable",
+];
+
+fn is_instr_keyword(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_ascii_lowercase())
+        && (s.contains('.') || DOTLESS_INSTRS.contains(&s))
+}
+
+fn is_block_keyword(s: &str) -> bool {
+    matches!(s, "block" | "loop" | "if" | "try" | "try_table")
+}
+
+/// Converts the instructions of all core wasm functions within `nodes`.
+///
+/// The `in_module` flag indicates whether `nodes` are the fields of a core
+/// module, as opposed to a component for example, where `func` has a
+/// different meaning.
+fn convert_funcs(nodes: &mut [Node<'_>], style: InstrStyle, in_module: bool) {
+    for node in nodes {
+        let head = node.head();
+        let Item::List(list) = &mut node.item else {
+            continue;
+        };
+        if in_module && head == Some("func") {
+            convert_func(list, style);
+            continue;
+        }
+        let is_module = match head {
+            Some("module") => true,
+            Some("core") => list.children.get(1).and_then(|n| n.keyword()) == Some("module"),
+            _ => false,
+        };
+        convert_funcs(&mut list.children, style, is_module);
+    }
+}
+
+fn convert_func(func: &mut List<'_>, style: InstrStyle) {
+    // Skip over everything before the body of the function, such as its name,
+    // type and locals.
+    let start = 1 + func.children[1..]
+        .iter()
+        .take_while(|n| {
+            n.is_id()
+                || matches!(n.item, Item::Comment(_))
+                || n.head().is_some_and(|h| {
+                    h.starts_with('@')
+                        || matches!(
+                            h,
+                            "export" | "import" | "type" | "param" | "result" | "local"
+                        )
+                })
+        })
+        .count();
+    let body = func.children.split_off(start);
+    let Some(instrs) = parse_instrs(body.clone()) else {
+        func.children.extend(body);
+        return;
+    };
+    match style {
+        InstrStyle::Preserve => unreachable!(),
+        InstrStyle::Folded => {
+            for instr in fold(instrs) {
+                instr.into_folded(&mut func.children);
+            }
+        }
+        InstrStyle::Flat => {
+            for instr in unfold(instrs) {
+                instr.into_flat(&mut func.children);
+            }
+        }
+    }
+}
+
+/// An instruction within a function body, parsed from either its flat or
+/// folded form.
+enum Instr<'a> {
+    /// A non-control instruction.
+    Plain {
+        op: &'a str,
+        imms: Vec<Node<'a>>,
+        /// Operands of this instruction if it was folded.
+        operands: Vec<Instr<'a>>,
+    },
+    /// A structured control instruction such as `block` or `if`.
+    Block(Block<'a>),
+    /// Anything else, such as comments and annotations, which is left as-is
+    /// and is never moved relative to the instructions around it.
+    Other(Node<'a>),
+}
+
+struct Block<'a> {
+    kind: &'a str,
+    /// The label, block type and, for `try_table`, catch clauses.
+    imms: Vec<Node<'a>>,
+    /// Folded operands of this instruction, such as the condition of an `if`.
+    conds: Vec<Instr<'a>>,
+    /// The bodies of this block. The first has no keyword and subsequent
+    /// bodies start with `else`, `catch`, `catch_all` or `delegate`.
+    arms: Vec<Arm<'a>>,
+    /// The label which can optionally follow `end`.
+    end: Vec<Node<'a>>,
+}
+
+struct Arm<'a> {
+    head: Vec<Node<'a>>,
+    body: Vec<Instr<'a>>,
+}
+
+impl Arm<'_> {
+    fn head_is(&self, keyword: &str) -> bool {
+        self.head.first().and_then(|n| n.keyword()) == Some(keyword)
+    }
+}
+
+/// Parses a sequence of instructions which may be in either flat or folded
+/// form, returning `None` if they're not well-structured.
+fn parse_instrs(nodes: Vec<Node<'_>>) -> Option<Vec<Instr<'_>>> {
+    match parse_seq(&mut nodes.into())? {
+        (instrs, None) => Some(instrs),
+        (_, Some(_)) => None,
+    }
+}
+
+type Nodes<'a> = VecDeque<Node<'a>>;
+
+/// Parses instructions until either the end of `nodes` or a keyword which
+/// ends or separates the bodies of a block, such as `end` or `else`. That
+/// keyword and its immediates are returned if found.
+fn parse_seq<'a>(nodes: &mut Nodes<'a>) -> Option<(Vec<Instr<'a>>, Option<Vec<Node<'a>>>)> {
+    let mut instrs = Vec::new();
+    while let Some(node) = nodes.pop_front() {
+        let keyword = match &node.item {
+            Item::Atom(_) if node.is_instr() => node.keyword().unwrap(),
+            Item::List(_) if node.is_instr() => {
+                instrs.push(parse_folded(node)?);
+                continue;
+            }
+            _ => {
+                instrs.push(Instr::Other(node));
+                continue;
+            }
+        };
+        match keyword {
+            "end" | "else" | "catch" | "catch_all" | "delegate" => {
+                let mut head = vec![node];
+                head.extend(immediates(nodes, |_| false));
+                return Some((instrs, Some(head)));
+            }
+            kind if is_block_keyword(kind) => {
+                let imms = block_immediates(nodes, kind);
+                instrs.push(Instr::Block(parse_flat_block(nodes, kind, imms)?));
+            }
+            op => {
+                let imms = immediates(nodes, is_list_immediate);
+                instrs.push(Instr::Plain {
+                    op,
+                    imms,
+                    operands: Vec::new(),
+                });
+            }
+        }
+    }
+    Some((instrs, None))
+}
+
+/// Takes the immediates of an instruction from `nodes`, which are atoms other
+/// than instructions and lists for which `lists` returns true.
+///
+/// Comments in between immediates, such as those annotating each label of a
+/// `br_table`, are taken as well.
+fn immediates<'a>(nodes: &mut Nodes<'a>, lists: impl Fn(&Node<'a>) -> bool) -> Vec<Node<'a>> {
+    let mut imms = Vec::new();
+    let mut comments = 0;
+    while let Some(node) = nodes.get(comments) {
+        match &node.item {
+            Item::Atom(_) if !node.is_instr() => {}
+            Item::List(_) if lists(node) => {}
+            Item::Comment(_) => {
+                comments += 1;
+                continue;
+            }
+            _ => break,
+        }
+        imms.extend(nodes.drain(..comments + 1));
+        comments = 0;
+    }
+    imms
+}
+
+/// Returns whether a list following a non-control instruction is one of its
+/// immediates, such as `(type 0)`, rather than another instruction.
+fn is_list_immediate(node: &Node<'_>) -> bool {
+    !node.is_instr() && !node.head().is_some_and(|h| h.starts_with('@'))
+}
+
+fn block_immediates<'a>(nodes: &mut Nodes<'a>, kind: &str) -> Vec<Node<'a>> {
+    immediates(nodes, |node| match node.head() {
+        Some("type" | "param" | "result") => true,
+        Some("catch" | "catch_ref" | "catch_all" | "catch_all_ref") => kind == "try_table",
+        _ => false,
+    })
+}
+
+fn parse_flat_block<'a>(
+    nodes: &mut Nodes<'a>,
+    kind: &'a str,
+    imms: Vec<Node<'a>>,
+) -> Option<Block<'a>> {
+    let mut block = Block {
+        kind,
+        imms,
+        conds: Vec::new(),
+        arms: Vec::new(),
+        end: Vec::new(),
+    };
+    let mut head = Vec::new();
+    loop {
+        let (body, terminator) = parse_seq(nodes)?;
+        block.arms.push(Arm { head, body });
+        let mut terminator = terminator?;
+        let allowed = match terminator[0].keyword()? {
+            "end" => {
+                terminator.remove(0);
+                block.end = terminator;
+                return Some(block);
+            }
+            "else" => kind == "if" && block.arms.len() == 1,
+            "catch" | "catch_all" => kind == "try" && !block.arms.last()?.head_is("catch_all"),
+            "delegate" => {
+                if kind != "try" || block.arms.len() != 1 {
+                    return None;
+                }
+                block.arms.push(Arm {
+                    head: terminator,
+                    body: Vec::new(),
+                });
+                return Some(block);
+            }
+            _ => false,
+        };
+        if !allowed {
+            return None;
+        }
+        head = terminator;
+    }
+}
+
+/// Parses a folded instruction from the list `node`.
+fn parse_folded(node: Node<'_>) -> Option<Instr<'_>> {
+    let Item::List(list) = node.item else {
+        unreachable!()
+    };
+    let mut nodes = Nodes::from(list.children);
+    let kind = nodes.pop_front()?.keyword()?;
+    if !is_block_keyword(kind) {
+        let imms = immediates(&mut nodes, is_list_immediate);
+        let operands = parse_instrs(nodes.into())?;
+        return Some(Instr::Plain {
+            op: kind,
+            imms,
+            operands,
+        });
+    }
+
+    let imms = block_immediates(&mut nodes, kind);
+    let mut block = Block {
+        kind,
+        imms,
+        conds: Vec::new(),
+        arms: Vec::new(),
+        end: Vec::new(),
+    };
+    match kind {
+        "try" => {
+            for (i, node) in nodes.into_iter().enumerate() {
+                let allowed = match node.head() {
+                    Some("do") => i == 0,
+                    Some("catch" | "catch_all") => {
+                        i > 0 && !block.arms.last()?.head_is("catch_all")
+                    }
+                    Some("delegate") => i == 1,
+                    _ => false,
+                };
+                if !allowed || block.arms.last().is_some_and(|a| a.head_is("delegate")) {
+                    return None;
+                }
+                let Item::List(list) = node.item else {
+                    unreachable!()
+                };
+                let mut children = Nodes::from(list.children);
+                let keyword = children.pop_front()?;
+                let head = if i == 0 {
+                    Vec::new()
+                } else {
+                    let mut head = vec![keyword];
+                    head.extend(immediates(&mut children, |_| false));
+                    head
+                };
+                block.arms.push(Arm {
+                    head,
+                    body: parse_instrs(children.into())?,
+                });
+            }
+            if block.arms.is_empty() {
+                return None;
+            }
+        }
+        "if" => {
+            while nodes.front()?.head() != Some("then") {
+                let node = nodes.pop_front()?;
+                block.conds.extend(parse_instrs(vec![node])?);
+            }
+            for (i, node) in nodes.into_iter().enumerate() {
+                let expected = if i == 0 { "then" } else { "else" };
+                if i > 1 || node.head() != Some(expected) {
+                    return None;
+                }
+                let Item::List(list) = node.item else {
+                    unreachable!()
+                };
+                let mut children = list.children;
+                let keyword = children.remove(0);
+                block.arms.push(Arm {
+                    head: if i == 0 { Vec::new() } else { vec![keyword] },
+                    body: parse_instrs(children)?,
+                });
+            }
+        }
+        _ => {
+            block.arms.push(Arm {
+                head: Vec::new(),
+                body: parse_instrs(nodes.into())?,
+            });
+        }
+    }
+    Some(Instr::Block(block))
+}
+
+/// Converts `instrs` to the flat form.
+fn unfold(instrs: Vec<Instr<'_>>) -> Vec<Instr<'_>> {
+    let mut ret = Vec::new();
+    for instr in instrs {
+        match instr {
+            Instr::Plain { op, imms, operands } => {
+                ret.extend(unfold(operands));
+                ret.push(Instr::Plain {
+                    op,
+                    imms,
+                    operands: Vec::new(),
+                });
+            }
+            Instr::Block(mut block) => {
+                ret.extend(unfold(mem::take(&mut block.conds)));
+                for arm in block.arms.iter_mut() {
+                    arm.body = unfold(mem::take(&mut arm.body));
+                }
+                ret.push(Instr::Block(block));
+            }
+            Instr::Other(node) => ret.push(Instr::Other(node)),
+        }
+    }
+    ret
+}
+
+/// Converts `instrs` to the folded form, nesting each instruction's operands
+/// within it when they're known.
+fn fold(instrs: Vec<Instr<'_>>) -> Vec<Instr<'_>> {
+    // Each instruction folded so far along with the number of values it
+    // pushes if it's a complete expression which doesn't consume any values
+    // from before it.
+    let mut ret: Vec<(Instr<'_>, Option<usize>)> = Vec::new();
+    for instr in instrs {
+        let (instr, results) = match instr {
+            Instr::Plain { op, imms, operands } => {
+                let mut operands = fold(operands);
+                let results = match plain_arity(op, &imms) {
+                    Some((params, results)) => {
+                        if operands.is_empty() && params > 0 {
+                            if let Some(popped) = pop_operands(&mut ret, params) {
+                                operands = popped;
+                            }
+                        }
+                        (operands.len() == params).then_some(results)
+                    }
+                    None => None,
+                };
+                (Instr::Plain { op, imms, operands }, results)
+            }
+            Instr::Block(mut block) => {
+                block.conds = fold(mem::take(&mut block.conds));
+                for arm in block.arms.iter_mut() {
+                    arm.body = fold(mem::take(&mut arm.body));
+                }
+                let results = match block_arity(&block.imms) {
+                    Some((0, results)) => {
+                        let params = usize::from(block.kind == "if");
+                        if block.conds.is_empty() && params > 0 {
+                            if let Some(popped) = pop_operands(&mut ret, params) {
+                                block.conds = popped;
+                            }
+                        }
+                        (block.conds.len() == params).then_some(results)
+                    }
+                    _ => None,
+                };
+                (Instr::Block(block), results)
+            }
+            Instr::Other(node) => (Instr::Other(node), None),
+        };
+        ret.push((instr, results));
+    }
+    ret.into_iter().map(|(instr, _)| instr).collect()
+}
+
+/// Removes the last `n` instructions from `instrs` if they each push a single
+/// value.
+fn pop_operands<'a>(
+    instrs: &mut Vec<(Instr<'a>, Option<usize>)>,
+    n: usize,
+) -> Option<Vec<Instr<'a>>> {
+    let start = instrs.len().checked_sub(n)?;
+    if !instrs[start..]
+        .iter()
+        .all(|(_, results)| *results == Some(1))
+    {
+        return None;
+    }
+    Some(instrs.drain(start..).map(|(instr, _)| instr).collect())
+}
+
+/// Returns the number of parameters and results of a non-control instruction
+/// if it can be determined from the instruction alone.
+fn plain_arity(op: &str, imms: &[Node<'_>]) -> Option<(usize, usize)> {
+    match op {
+        "nop" | "unreachable" => return Some((0, 0)),
+        "drop" => return Some((1, 0)),
+        "select" => {
+            // A typed `select` is only known to push one value if it has a
+            // single result type.
+            let results = imms.iter().try_fold(0, |n, imm| match imm.item {
+                Item::List(_) if imm.head() == Some("result") => Some(n + value_types(imm)?),
+                _ => None,
+            })?;
+            return (results <= 1).then_some((3, 1));
+        }
+        "local.get" | "global.get" | "ref.null" | "ref.func" | "memory.size" | "table.size" => {
+            return Some((0, 1));
+        }
+        "local.set" | "global.set" => return Some((1, 0)),
+        "local.tee" | "memory.grow" | "ref.is_null" | "table.get" => return Some((1, 1)),
+        "table.set" => return Some((2, 0)),
+        "table.grow" => return Some((2, 1)),
+        "memory.fill" | "memory.copy" | "memory.init" | "table.fill" | "table.copy"
+        | "table.init" => return Some((3, 0)),
+        _ => {}
+    }
+
+    let (ty, name) = op.split_once('.')?;
+    if !matches!(ty, "i32" | "i64" | "f32" | "f64") {
+        return None;
+    }
+    let arity = match name {
+        "const" => (0, 1),
+        _ if name.starts_with("load") => (1, 1),
+        _ if name.starts_with("store") => (2, 0),
+        "eqz" | "clz" | "ctz" | "popcnt" | "neg" | "abs" | "sqrt" | "ceil" | "floor" | "trunc"
+        | "nearest" | "extend8_s" | "extend16_s" | "extend32_s" => (1, 1),
+        "add" | "sub" | "mul" | "div" | "div_s" | "div_u" | "rem_s" | "rem_u" | "and" | "or"
+        | "xor" | "shl" | "shr_s" | "shr_u" | "rotl" | "rotr" | "min" | "max" | "copysign"
+        | "eq" | "ne" | "lt" | "lt_s" | "lt_u" | "gt" | "gt_s" | "gt_u" | "le" | "le_s"
+        | "le_u" | "ge" | "ge_s" | "ge_u" => (2, 1),
+        // Conversions, such as `i32.wrap_i64` or `f32.convert_i64_u`.
+        _ if name.contains('_') && !name.starts_with("atomic") => (1, 1),
+        _ => return None,
+    };
+    Some(arity)
+}
+
+/// Returns the number of parameters and results of a block with the
+/// immediates `imms`, if it can be determined without resolving a type
+/// index.
+fn block_arity(imms: &[Node<'_>]) -> Option<(usize, usize)> {
+    let mut params = 0;
+    let mut results = 0;
+    let mut explicit = false;
+    let mut type_use = false;
+    for imm in imms {
+        match imm.head() {
+            Some("param") => {
+                params += value_types(imm)?;
+                explicit = true;
+            }
+            Some("result") => {
+                results += value_types(imm)?;
+                explicit = true;
+            }
+            Some("type") => type_use = true,
+            _ => {}
+        }
+    }
+    if type_use && !explicit {
+        return None;
+    }
+    Some((params, results))
+}
+
+/// Returns the number of value types in a `param` or `result` list.
+fn value_types(node: &Node<'_>) -> Option<usize> {
+    let Item::List(list) = &node.item else {
+        return None;
+    };
+    let types = &list.children[1..];
+    if types.first().is_some_and(|n| n.is_id()) {
+        return Some(1);
+    }
+    Some(
+        types
+            .iter()
+            .filter(|n| !matches!(n.item, Item::Comment(_)))
+            .count(),
+    )
+}
+
+impl<'a> Instr<'a> {
+    /// Appends this instruction in flat form to `out`, with each instruction
+    /// on its own line.
+    fn into_flat(self, out: &mut Vec<Node<'a>>) {
+        match self {
+            Instr::Plain { op, imms, operands } => {
+                debug_assert!(operands.is_empty());
+                out.push(Node::atom(1, op));
+                out.extend(imms);
+            }
+            Instr::Block(block) => block.into_flat(out, Instr::into_flat),
+            Instr::Other(node) => out.push(node),
+        }
+    }
+
+    /// Appends this instruction in folded form to `out`.
+    fn into_folded(self, out: &mut Vec<Node<'a>>) {
+        let node = match self {
+            Instr::Plain { op, imms, operands } => {
+                let mut children = vec![Node::atom(0, op)];
+                children.extend(imms);
+                let mut operands_out = Vec::new();
+                for operand in operands {
+                    operand.into_folded(&mut operands_out);
+                }
+                let inline =
+                    operands_out.iter().all(|n| match &n.item {
+                        Item::List(list) => list.children[1..].iter().all(|c| !c.is_instr()),
+                        _ => false,
+                    }) && Node::list(0, children.iter().chain(&operands_out).cloned().collect())
+                        .width()
+                        .is_some_and(|w| w <= 60);
+                if inline {
+                    for operand in operands_out.iter_mut() {
+                        operand.newlines = 0;
+                    }
+                }
+                children.extend(operands_out);
+                Node::list(1, children)
+            }
+            Instr::Block(block) => {
+                let mut children = vec![Node::atom(0, block.kind)];
+                children.extend(block.imms);
+                for cond in block.conds {
+                    cond.into_folded(&mut children);
+                }
+                for (i, arm) in block.arms.into_iter().enumerate() {
+                    let mut arm_children = match block.kind {
+                        "if" if i == 0 => vec![Node::atom(0, "then")],
+                        "if" => vec![Node::atom(0, "else")],
+                        "try" if i == 0 => vec![Node::atom(0, "do")],
+                        // The `catch` and `delegate` clauses of a `try` keep
+                        // their keyword and immediates from the flat form.
+                        "try" => {
+                            let mut head = arm.head;
+                            head[0].newlines = 0;
+                            head
+                        }
+                        _ => {
+                            for instr in arm.body {
+                                instr.into_folded(&mut children);
+                            }
+                            continue;
+                        }
+                    };
+                    for instr in arm.body {
+                        instr.into_folded(&mut arm_children);
+                    }
+                    children.push(Node::list(1, arm_children));
+                }
+                Node::list(1, children)
+            }
+            Instr::Other(node) => node,
+        };
+        out.push(node);
+    }
+}
+
+impl<'a> Block<'a> {
+    /// Appends this block in flat form to `out`, using `body` to append the
+    /// instructions within it.
+    fn into_flat(self, out: &mut Vec<Node<'a>>, body: fn(Instr<'a>, &mut Vec<Node<'a>>)) {
+        for cond in self.conds {
+            body(cond, out);
+        }
+        out.push(Node::atom(1, self.kind));
+        out.extend(self.imms);
+        let mut delegate = false;
+        for arm in self.arms {
+            delegate = arm.head_is("delegate");
+            let mut head = arm.head;
+            if let Some(first) = head.first_mut() {
+                first.newlines = 1;
+            }
+            out.extend(head);
+            for instr in arm.body {
+                body(instr, out);
+            }
+        }
+        if !delegate {
+            out.push(Node::atom(1, "end"));
+            out.extend(self.end);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fmt(src: &str, style: InstrStyle) -> String {
+        Formatter::new().instructions(style).format(src).unwrap()
+    }
+
+    #[test]
+    fn reindent() {
+        let src = "
+;; leading comment
+
+
+(module   $m
+      (type (func))   ;; trailing
+(func (param i32)
+  (local i32)
+      block $l
+  local.get 0
+        br_if $l
+   end
+      (; block ;) nop
+  )
+  )
+";
+        let expected = "\
+;; leading comment
+
+(module $m
+  (type (func)) ;; trailing
+  (func (param i32)
+    (local i32)
+    block $l
+      local.get 0
+      br_if $l
+    end
+    (; block ;) nop
+  )
+)
+";
+        assert_eq!(fmt(src, InstrStyle::Preserve), expected);
+        assert_eq!(fmt(expected, InstrStyle::Preserve), expected);
+    }
+
+    #[test]
+    fn fold() {
+        let src = "\
+(module
+  (func (param i32) (result i32)
+    local.get 0
+    i32.const 1
+    i32.add
+    call 0
+    if (result i32)
+      i32.const 2
+    else
+      unreachable
+    end))
+";
+        let expected = "\
+(module
+  (func (param i32) (result i32)
+    (i32.add (local.get 0) (i32.const 1))
+    (call 0)
+    (if (result i32)
+      (then
+        (i32.const 2))
+      (else
+        (unreachable)))))
+";
+        assert_eq!(fmt(src, InstrStyle::Folded), expected);
+    }
+
+    #[test]
+    fn unfold() {
+        let src = "\
+(module
+  (func (param i32) (result i32)
+    (i32.add (local.get 0) (i32.const 1))
+    (if (result i32) (call 0)
+      (then (i32.const 2))
+      (else (unreachable)))))
+";
+        let expected = "\
+(module
+  (func (param i32) (result i32)
+    local.get 0
+    i32.const 1
+    i32.add
+    call 0
+    if (result i32)
+      i32.const 2
+    else
+      unreachable
+    end))
+";
+        assert_eq!(fmt(src, InstrStyle::Flat), expected);
+    }
+
+    #[test]
+    fn legacy_try() {
+        let flat = "\
+(module
+  (tag $e (param i32))
+  (func (result i32)
+    try (result i32)
+      call 0
+    catch $e
+    catch_all
+      i32.const 1
+    end
+    try
+      nop
+    delegate 0))
+";
+        let folded = "\
+(module
+  (tag $e (param i32))
+  (func (result i32)
+    (try (result i32)
+      (do
+        (call 0))
+      (catch $e)
+      (catch_all
+        (i32.const 1)))
+    (try
+      (do
+        (nop))
+      (delegate 0))))
+";
+        assert_eq!(fmt(flat, InstrStyle::Folded), folded);
+        assert_eq!(fmt(folded, InstrStyle::Flat), flat);
+    }
+
+    #[test]
+    fn errors() {
+        assert!(format("(module").is_err());
+        assert!(format("(module))").is_err());
+    }
+}
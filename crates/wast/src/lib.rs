@@ -11,6 +11,9 @@
 //!
 //! This crate provides a few major pieces of functionality
 //!
+//! * [`fmt`] - a formatter for the text format which works on the original
+//!   source and preserves comments and annotations.
+//!
 //! * [`lexer`] - this is a raw lexer for the wasm text format. This is not
 //!   customizable, but if you'd like to iterate over raw tokens this is the
 //!   module for you. You likely won't use this much.
@@ -348,6 +351,7 @@ macro_rules! annotation {
     };
 }
 
+pub mod fmt;
 pub mod lexer;
 pub mod parser;
 pub mod token;
@@ -118,13 +118,27 @@ pub(crate) const MAX_PARENS_DEPTH: usize = 100;
 /// # Ok(())
 /// # }
 /// ```
+///
+/// If error recovery was enabled with [`ParseBuffer::recover`] then the
+/// returned error contains every error found in the input, see
+/// [`Error::others`].
 pub fn parse<'a, T: Parse<'a>>(buf: &'a ParseBuffer<'a>) -> Result<T> {
     let parser = buf.parser();
-    let result = parser.parse()?;
-    if parser.cursor().token()?.is_none() {
-        Ok(result)
-    } else {
-        Err(parser.error("extra tokens remaining after parse"))
+    let result = parser.parse().and_then(|result| {
+        if parser.cursor().token()?.is_none() {
+            Ok(result)
+        } else {
+            Err(parser.error("extra tokens remaining after parse"))
+        }
+    });
+    let mut errors = buf.errors.take();
+    match result {
+        Ok(result) if errors.is_empty() => Ok(result),
+        Ok(_) => Err(Error::many(errors)),
+        Err(e) => {
+            errors.push(e);
+            Err(Error::many(errors))
+        }
     }
 }
 
@@ -305,6 +319,8 @@ pub struct ParseBuffer<'a> {
     cur: Cell<Position>,
     known_annotations: RefCell<HashMap<String, usize>>,
     track_instr_spans: bool,
+    recover: bool,
+    errors: RefCell<Vec<Error>>,
     depth: Cell<usize>,
     strings: Bump,
 }
@@ -387,6 +403,8 @@ impl ParseBuffer<'_> {
             known_annotations: Default::default(),
             strings: Default::default(),
             track_instr_spans: false,
+            recover: false,
+            errors: Default::default(),
         })
     }
 
@@ -403,6 +421,21 @@ impl ParseBuffer<'_> {
         self
     }
 
+    /// Configures whether parsing recovers from syntax errors to report more
+    /// than one error at a time.
+    ///
+    /// By default parsing stops at the first error found. When recovery is
+    /// enabled then a malformed module field, component field, or `*.wast`
+    /// directive is instead skipped up to its matching `)`, the error is
+    /// recorded, and parsing continues with the next item. All errors found
+    /// are then returned together from [`parse`].
+    ///
+    /// See [`Parser::parens_recover`] for the underlying mechanism.
+    pub fn recover(&mut self, recover: bool) -> &mut Self {
+        self.recover = recover;
+        self
+    }
+
     fn parser(&self) -> Parser<'_> {
         Parser { buf: self }
     }
@@ -442,12 +475,9 @@ impl ParseBuffer<'_> {
                 TokenKind::LParen => {
                     if let Some(annotation) = self.lexer.annotation(pos)? {
                         let text = annotation.annotation(self.lexer.input())?;
-                        match self.known_annotations.borrow().get(&text[..]) {
-                            Some(0) | None => {
-                                self.skip_annotation(&mut pos)?;
-                                continue;
-                            }
-                            Some(_) => {}
+                        if !self.is_known_annotation(&text) {
+                            self.skip_annotation(&mut pos)?;
+                            continue;
                         }
                     }
                     break token;
@@ -458,6 +488,21 @@ impl ParseBuffer<'_> {
         Ok(Some(token))
     }
 
+    /// Returns whether `annotation` is currently registered, either by its
+    /// name or by a registered prefix ending in `*`.
+    fn is_known_annotation(&self, annotation: &str) -> bool {
+        let known = self.known_annotations.borrow();
+        if known.get(annotation).is_some_and(|n| *n > 0) {
+            return true;
+        }
+        known.iter().any(|(name, n)| {
+            *n > 0
+                && name
+                    .strip_suffix('*')
+                    .is_some_and(|prefix| annotation.starts_with(prefix))
+        })
+    }
+
     fn skip_annotation(&self, pos: &mut usize) -> Result<()> {
         let mut depth = 1;
         let span = Span { offset: *pos };
@@ -768,6 +813,56 @@ impl<'a> Parser<'a> {
         res
     }
 
+    /// Same as [`Parser::parens`] except that errors are recovered from if
+    /// enabled with [`ParseBuffer::recover`].
+    ///
+    /// When recovery is enabled and `f` fails then the error is recorded in
+    /// the [`ParseBuffer`], all tokens up to and including the `)` matching
+    /// the leading `(` are skipped, and `Ok(None)` is returned. This is
+    /// intended to be used for lists of independent items, such as the fields
+    /// of a module, where the next item can be parsed regardless of whether
+    /// the previous one was malformed.
+    ///
+    /// An error is still returned if there is no leading `(` or if it has no
+    /// matching `)`, as there's no item boundary to resume parsing at.
+    pub fn parens_recover<T>(self, f: impl FnOnce(Parser<'a>) -> Result<T>) -> Result<Option<T>> {
+        if !self.buf.recover {
+            return self.parens(f).map(Some);
+        }
+        let err = match self.parens(f) {
+            Ok(result) => return Ok(Some(result)),
+            Err(e) => e,
+        };
+        let skipped = self.step(|cursor| {
+            let mut cursor = match cursor.lparen()? {
+                Some(rest) => rest,
+                None => return Ok((false, cursor)),
+            };
+            let mut depth = 1;
+            while let Some(token) = cursor.token()? {
+                cursor.advance_past(&token);
+                match token.kind {
+                    TokenKind::LParen => depth += 1,
+                    TokenKind::RParen => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return Ok((true, cursor));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Ok((false, cursor))
+        });
+        match skipped {
+            Ok(true) => {
+                self.buf.errors.borrow_mut().push(err);
+                Ok(None)
+            }
+            Ok(false) | Err(_) => Err(err),
+        }
+    }
+
     /// Return the depth of nested parens we've parsed so far.
     ///
     /// This is a low-level method that is only useful for implementing
@@ -879,6 +974,13 @@ impl<'a> Parser<'a> {
     /// returned value is still alive, and once dropped the parser will go back
     /// to skipping annotations with the name `annotation`.
     ///
+    /// # Prefixes
+    ///
+    /// If `annotation` ends with `*` then all annotations whose name starts
+    /// with the text before the `*` are registered. For example registering
+    /// `metadata.code.*` enables parsing both `@metadata.code.branch_hint` and
+    /// `@metadata.code.foo`.
+    ///
     /// # Example
     ///
     /// Let's see an example of how the `@name` annotation is parsed for modules
@@ -1003,7 +1105,7 @@ impl<'a> Parser<'a> {
         let _r = self.register_annotation("producers");
         let _r = self.register_annotation("name");
         let _r = self.register_annotation("dylink.0");
-        let _r = self.register_annotation("metadata.code.branch_hint");
+        let _r = self.register_annotation("metadata.code.*");
         f(self)
     }
 }
@@ -26,7 +26,7 @@ impl<'a> Parse<'a> for Wast<'a> {
             // bunch of directives, otherwise assume this is an inline module.
             if parser.peek2::<WastDirectiveToken>()? {
                 while !parser.is_empty() {
-                    directives.push(parser.parens(|p| p.parse())?);
+                    directives.extend(parser.parens_recover(|p| p.parse())?);
                 }
             } else {
                 let module = parser.parse::<Wat>()?;
@@ -571,7 +571,7 @@ impl<'a> Parse<'a> for WastThread<'a> {
         };
         let mut directives = Vec::new();
         while !parser.is_empty() {
-            directives.push(parser.parens(|p| p.parse())?);
+            directives.extend(parser.parens_recover(|p| p.parse())?);
         }
         Ok(WastThread {
             span,
@@ -199,6 +199,8 @@ pub fn parse_str(wat: impl AsRef<str>) -> Result<Vec<u8>> {
 pub struct Parser {
     #[cfg(feature = "dwarf")]
     generate_dwarf: Option<GenerateDwarf>,
+    recover: bool,
+    source_mapping_url: Option<String>,
     _private: (),
 }
 
@@ -222,18 +224,63 @@ impl Parser {
         self
     }
 
+    /// Configures whether syntax errors are recovered from to report all
+    /// errors in the input instead of just the first.
+    ///
+    /// When enabled a malformed module field, for example, is skipped and
+    /// parsing continues with the next field. The returned error then renders
+    /// all errors found. This is disabled by default.
+    ///
+    /// See [`ParseBuffer::recover`] for more information.
+    pub fn recover(&mut self, recover: bool) -> &mut Self {
+        self.recover = recover;
+        self
+    }
+
+    /// Configures a `sourceMappingURL` custom section to be appended to
+    /// textual modules pointing at `url` as the location of their source map.
+    ///
+    /// Source maps themselves are generated with methods such as
+    /// [`Parser::parse_str_with_source_map`]. Like with DWARF this has no
+    /// effect on WebAssembly binaries or `(module binary ...)` modules.
+    pub fn source_mapping_url(&mut self, url: impl Into<String>) -> &mut Self {
+        self.source_mapping_url = Some(url.into());
+        self
+    }
+
     /// Equivalent of [`parse_file`] but uses this parser's settings.
     pub fn parse_file(&self, path: impl AsRef<Path>) -> Result<Vec<u8>> {
         self._parse_file(path.as_ref())
     }
 
-    fn _parse_file(&self, file: &Path) -> Result<Vec<u8>> {
-        let contents = std::fs::read(file).map_err(|err| Error {
-            kind: Box::new(ErrorKind::Io {
-                err,
-                file: Some(file.to_owned()),
+    /// Equivalent of [`Parser::parse_file`] but additionally generates a
+    /// Source Map v3 file mapping the binary offsets of instructions back to
+    /// their line and column in `path`.
+    ///
+    /// The source map is returned as JSON, and is `None` if the input is
+    /// already a WebAssembly binary or isn't a textual core module.
+    pub fn parse_file_with_source_map(
+        &self,
+        path: impl AsRef<Path>,
+    ) -> Result<(Vec<u8>, Option<String>)> {
+        let file = path.as_ref();
+        let contents = read_file(file)?;
+        if contents.starts_with(b"\0asm") {
+            return Ok((contents, None));
+        }
+        match str::from_utf8(&contents) {
+            Ok(s) => self._parse_str(Some(file), s, true),
+            Err(_) => Err(Error {
+                kind: Box::new(ErrorKind::Custom {
+                    msg: "input bytes aren't valid utf-8".to_string(),
+                    file: Some(file.to_owned()),
+                }),
             }),
-        })?;
+        }
+    }
+
+    fn _parse_file(&self, file: &Path) -> Result<Vec<u8>> {
+        let contents = read_file(file)?;
         match self.parse_bytes(Some(file), &contents) {
             // If the result here is borrowed then that means that the input
             // `&contents` was itself already a wasm module. We've already got
@@ -263,7 +310,7 @@ impl Parser {
             return Ok(bytes.into());
         }
         match str::from_utf8(bytes) {
-            Ok(s) => self._parse_str(path, s).map(|s| s.into()),
+            Ok(s) => self._parse_str(path, s, false).map(|(s, _)| s.into()),
             Err(_) => Err(Error {
                 kind: Box::new(ErrorKind::Custom {
                     msg: "input bytes aren't valid utf-8".to_string(),
@@ -278,26 +325,64 @@ impl Parser {
     /// The `path` argument is an optional path to use when error messages are
     /// generated.
     pub fn parse_str(&self, path: Option<&Path>, wat: impl AsRef<str>) -> Result<Vec<u8>> {
-        self._parse_str(path, wat.as_ref())
+        self._parse_str(path, wat.as_ref(), false)
+            .map(|(wasm, _)| wasm)
+    }
+
+    /// Equivalent of [`Parser::parse_str`] but additionally generates a
+    /// Source Map v3 file mapping the binary offsets of instructions back to
+    /// their line and column in `wat`.
+    ///
+    /// The `path` argument, if provided, is used as the source file name in
+    /// the source map. The source map is returned as JSON, and is `None` if
+    /// `wat` isn't a textual core module.
+    pub fn parse_str_with_source_map(
+        &self,
+        path: Option<&Path>,
+        wat: impl AsRef<str>,
+    ) -> Result<(Vec<u8>, Option<String>)> {
+        self._parse_str(path, wat.as_ref(), true)
     }
 
-    fn _parse_str(&self, path: Option<&Path>, wat: &str) -> Result<Vec<u8>> {
-        let mut _buf = ParseBuffer::new(wat).map_err(|e| Error::cvt(e, wat, path))?;
+    fn _parse_str(
+        &self,
+        path: Option<&Path>,
+        wat: &str,
+        source_map: bool,
+    ) -> Result<(Vec<u8>, Option<String>)> {
+        let mut buf = ParseBuffer::new(wat).map_err(|e| Error::cvt(e, wat, path))?;
+        buf.recover(self.recover);
         #[cfg(feature = "dwarf")]
-        _buf.track_instr_spans(self.generate_dwarf.is_some());
-        let mut ast = parser::parse::<wast::Wat>(&_buf).map_err(|e| Error::cvt(e, wat, path))?;
+        buf.track_instr_spans(source_map || self.generate_dwarf.is_some());
+        #[cfg(not(feature = "dwarf"))]
+        buf.track_instr_spans(source_map);
+        let mut ast = parser::parse::<wast::Wat>(&buf).map_err(|e| Error::cvt(e, wat, path))?;
 
-        let mut _opts = EncodeOptions::default();
+        let mut opts = EncodeOptions::default();
         #[cfg(feature = "dwarf")]
         if let Some(style) = self.generate_dwarf {
-            _opts.dwarf(path.unwrap_or("<input>.wat".as_ref()), wat, style);
+            opts.dwarf(path.unwrap_or("<input>.wat".as_ref()), wat, style);
         }
-        _opts
-            .encode_wat(&mut ast)
+        if source_map {
+            opts.source_map(path.unwrap_or("<input>.wat".as_ref()), wat);
+        }
+        if let Some(url) = &self.source_mapping_url {
+            opts.source_mapping_url(url);
+        }
+        opts.encode_wat_with_source_map(&mut ast)
             .map_err(|e| Error::cvt(e, wat, path))
     }
 }
 
+fn read_file(file: &Path) -> Result<Vec<u8>> {
+    std::fs::read(file).map_err(|err| Error {
+        kind: Box::new(ErrorKind::Io {
+            err,
+            file: Some(file.to_owned()),
+        }),
+    })
+}
+
 /// Result of [`Detect::from_bytes`] to indicate what some input bytes look
 /// like.
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
@@ -478,4 +563,25 @@ mod test {
             "expected valid module field\n     --> foo:1:2\n      |\n    1 | ()\n      |  ^"
         );
     }
+
+    #[test]
+    fn test_source_map() {
+        let wat = "(module\n  (func\n    i32.const 1\n    drop))";
+        let mut parser = Parser::new();
+        parser.source_mapping_url("foo.wasm.map");
+        let (wasm, map) = parser
+            .parse_str_with_source_map(Some("foo.wat".as_ref()), wat)
+            .unwrap();
+        let map = map.unwrap();
+        assert!(map.starts_with(r#"{"version":3,"sources":["foo.wat"]"#));
+        // `i32.const` at offset 0x17 on line 2 column 4 and `drop` two bytes
+        // later on the next line.
+        assert!(map.ends_with(r#""mappings":"uBAEI,EACA"}"#), "{map}");
+        assert!(wasm.ends_with(b"\x10sourceMappingURL\x0cfoo.wasm.map"));
+
+        let (_, map) = parser
+            .parse_str_with_source_map(None, "(module binary)")
+            .unwrap();
+        assert!(map.is_none());
+    }
 }
@@ -91,4 +91,6 @@ run_fuzzers! {
     text_parser: string,
     reencode: unstructured,
     wit64: unstructured,
+    component_from_wit: unstructured,
+    differential: unstructured,
 }
@@ -0,0 +1,82 @@
+use arbitrary::{Result, Unstructured};
+use wit_component::{DecodedWasm, StringEncoding};
+use wit_parser::{LiftLowerAbi, ManglingAndAbi};
+
+/// Generates a component from a random WIT world.
+///
+/// A world is chosen from a package generated by `wit-smith`, and the
+/// `dummy_module` for that world is used as a template for `wasm-smith`: the
+/// core module it generates has the world's lowered imports available and
+/// must provide its lifted exports, but the function bodies are arbitrary.
+/// The result is then componentized with `ComponentEncoder`.
+pub fn run(u: &mut Unstructured<'_>) -> Result<()> {
+    let wit = u.arbitrary().and_then(|config| {
+        log::debug!("wit-smith config: {config:#?}");
+        wit_smith::smith(&config, u)
+    })?;
+    let (resolve, pkg) = match wit_component::decode(&wit).unwrap() {
+        DecodedWasm::WitPackage(resolve, pkg) => (resolve, pkg),
+        DecodedWasm::Component(..) => unreachable!(),
+    };
+
+    let worlds = &resolve.packages[pkg].worlds;
+    if worlds.is_empty() {
+        return Ok(());
+    }
+    let world = worlds[u.choose_index(worlds.len())?];
+    let mangling = match u.int_in_range(0..=3)? {
+        0 => ManglingAndAbi::Legacy(LiftLowerAbi::Sync),
+        1 => ManglingAndAbi::Legacy(LiftLowerAbi::AsyncCallback),
+        2 => ManglingAndAbi::Legacy(LiftLowerAbi::AsyncStackful),
+        3 => ManglingAndAbi::Standard32,
+        _ => unreachable!(),
+    };
+    log::debug!(
+        "generating a module for world {} with abi {mangling:?}",
+        resolve.worlds[world].name
+    );
+    let dummy = wit_component::dummy_module(&resolve, world, mangling);
+
+    let (mut module, _config) = crate::generate_valid_module(u, |config, _| {
+        config.available_imports = Some(dummy.clone());
+        config.exports = Some(dummy);
+
+        // `ComponentEncoder` validates the module with the default set of
+        // features, so don't enable anything beyond that.
+        config.custom_page_sizes_enabled = false;
+        config.wide_arithmetic_enabled = false;
+        config.shared_everything_threads_enabled = false;
+        config.stack_switching_enabled = false;
+        Ok(())
+    })?;
+
+    let encoding = match u.int_in_range(0..=2)? {
+        0 => StringEncoding::UTF8,
+        1 => StringEncoding::UTF16,
+        2 => StringEncoding::CompactUTF16,
+        _ => unreachable!(),
+    };
+    wit_component::embed_component_metadata(&mut module, &resolve, world, encoding).unwrap();
+
+    log::debug!("... componentizing the module");
+    let component = wit_component::ComponentEncoder::default()
+        .module(&module)
+        .unwrap()
+        .validate(true)
+        .encode()
+        .unwrap();
+    crate::log_wasm(&component, "component");
+
+    wasmparser::Validator::new_with_features(wasmparser::WasmFeatures::all())
+        .validate_all(&component)
+        .unwrap();
+
+    log::debug!("... decoding the component");
+    wit_component::decode(&component).unwrap();
+    Ok(())
+}
+
+#[test]
+fn smoke() {
+    super::test::test_n_times(100, run);
+}
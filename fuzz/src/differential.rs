@@ -0,0 +1,25 @@
+use arbitrary::{Result, Unstructured};
+use wasm_differential::{Differential, Interp};
+
+// Compare the results of running generated modules in `wasm-interp` against
+// Wasmtime, or against another `wasm-interp` when Wasmtime isn't enabled which
+// still exercises the harness itself.
+pub fn run(u: &mut Unstructured<'_>) -> Result<()> {
+    #[cfg(feature = "wasmtime")]
+    let mut differential = Differential::new(Interp::new(), wasm_differential::Wasmtime::new());
+    #[cfg(not(feature = "wasmtime"))]
+    let mut differential = Differential::new(Interp::new(), Interp::new());
+
+    let wasm = differential.generate(u)?;
+    crate::log_wasm(&wasm, "differential");
+    if let Some(mismatch) = differential.check(&wasm) {
+        crate::log_wasm(&mismatch.shrunk, "differential (shrunk)");
+        panic!("{mismatch}");
+    }
+    Ok(())
+}
+
+#[test]
+fn smoke() {
+    super::test::test_n_times(50, run);
+}
@@ -3,6 +3,8 @@ use std::fmt::Debug;
 use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
 use wasm_smith::{Component, Config, Module};
 
+pub mod component_from_wit;
+pub mod differential;
 pub mod incremental_parse;
 pub mod mutate;
 pub mod no_traps;
@@ -27,6 +29,9 @@ pub fn generate_valid_module(
     config.custom_page_sizes_enabled = u.arbitrary()?;
     config.wide_arithmetic_enabled = u.arbitrary()?;
     config.shared_everything_threads_enabled = u.arbitrary()?;
+    config.stack_switching_enabled = u.arbitrary()?;
+    config.generate_names = u.arbitrary()?;
+    config.generate_branch_hints = u.arbitrary()?;
 
     configure(&mut config, u)?;
 
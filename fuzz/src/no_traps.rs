@@ -15,7 +15,9 @@ pub fn run(u: &mut Unstructured<'_>) -> Result<()> {
         config.disallow_traps = true;
         config.threads_enabled = false;
         config.exceptions_enabled = false;
-        config.gc_enabled = false;
+        // Not implemented in wasmtime at this time.
+        config.stack_switching_enabled = false;
+        config.shared_everything_threads_enabled = false;
         config.max_memory32_bytes = config.max_memory32_bytes.min(1 << 18);
         config.max_memory64_bytes = config.max_memory64_bytes.min(1 << 18);
 
@@ -42,6 +44,8 @@ pub fn run(u: &mut Unstructured<'_>) -> Result<()> {
         eng_conf.wasm_memory64(true);
         eng_conf.wasm_multi_memory(true);
         eng_conf.wasm_tail_call(true);
+        eng_conf.wasm_function_references(true);
+        eng_conf.wasm_gc(true);
         eng_conf.consume_fuel(true);
         let engine = Engine::new(&eng_conf).unwrap();
         let module = match Module::from_binary(&engine, &wasm_bytes) {
@@ -53,10 +57,16 @@ pub fn run(u: &mut Unstructured<'_>) -> Result<()> {
             Err(e) => panic!("failed to compile module {e:?}"),
         };
 
+        // Non-nullable references can't be conjured up from the host, so
+        // skip modules which would need them to be instantiated.
+        if !module.imports().all(|i| can_dummy_import(&i.ty())) {
+            return Ok(());
+        }
+
         // Call all exported functions
         for export in module.exports() {
             match export.ty() {
-                ExternType::Func(func_ty) => {
+                ExternType::Func(func_ty) if func_ty.params().all(|p| can_dummy(&p)) => {
                     let mut store = Store::new(
                         &engine,
                         fuzz_stats::limits::StoreLimits {
@@ -75,8 +85,15 @@ pub fn run(u: &mut Unstructured<'_>) -> Result<()> {
                         Err(err) => return Ok(check_err(err)),
                     };
 
+                    // Wasmtime panics converting some `externref` results,
+                    // such as those from `extern.convert_any`, when the GC
+                    // heap hasn't been allocated yet, so force it to be.
+                    ExternRef::new(&mut store, ()).unwrap();
+
                     let args = fuzz_stats::dummy::dummy_values(func_ty.params());
-                    let mut results = fuzz_stats::dummy::dummy_values(func_ty.results());
+                    // The results are only overwritten, so their initial
+                    // values don't need to match their types.
+                    let mut results = vec![Val::I32(0); func_ty.results().len()];
                     let func = instance.get_func(&mut store, export.name()).unwrap();
                     match func.call(&mut store, &args, &mut results) {
                         Ok(_) => {}
@@ -87,6 +104,19 @@ pub fn run(u: &mut Unstructured<'_>) -> Result<()> {
             }
         }
 
+        fn can_dummy(ty: &ValType) -> bool {
+            !matches!(ty, ValType::Ref(r) if !r.is_nullable())
+        }
+
+        fn can_dummy_import(ty: &ExternType) -> bool {
+            match ty {
+                ExternType::Func(ty) => ty.results().all(|r| can_dummy(&r)),
+                ExternType::Global(ty) => can_dummy(ty.content()),
+                ExternType::Table(ty) => ty.element().is_nullable(),
+                ExternType::Memory(_) | ExternType::Tag(_) => true,
+            }
+        }
+
         fn check_err(err: anyhow::Error) {
             // Allow stack overflow since this generally can't be protected
             // against as it's an implementation detail of cranelift we could
@@ -100,6 +130,15 @@ pub fn run(u: &mut Unstructured<'_>) -> Result<()> {
                 return;
             }
 
+            // Allow GC allocations to fail, which is the GC equivalent of
+            // running out of memory.
+            if let Some(wasmtime::Trap::AllocationTooLarge) = err.downcast_ref::<wasmtime::Trap>() {
+                return;
+            }
+            if err.is::<wasmtime::GcHeapOutOfMemory<()>>() {
+                return;
+            }
+
             let s = err.to_string();
             // Allow "nominal" traps such as running out of fuel and the
             // module trying to allocate more resources than we'd like to
@@ -7,7 +7,13 @@ pub fn run(u: &mut Unstructured<'_>) -> Result<()> {
     cfg.print_offsets(u.arbitrary()?);
     cfg.name_unnamed(u.arbitrary()?);
 
-    let data = u.bytes(u.len())?;
+    // Half the time print a valid module from `wasm-smith`, which may
+    // contain names and branch hints, instead of raw input bytes.
+    let data = if u.arbitrary()? {
+        crate::generate_valid_module(u, |_, _| Ok(()))?.0
+    } else {
+        u.bytes(u.len())?.to_vec()
+    };
     crate::log_wasm(&data, &cfg);
     let mut dst = String::new();
     let _ = cfg.print(&data, &mut wasmprinter::PrintFmtWrite(&mut dst));
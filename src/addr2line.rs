@@ -1,4 +1,5 @@
-//! Shared support for `addr2line` and `validate` to parse DWARF sections.
+//! Shared support for `addr2line`, `coredump`, `print` and `validate` to parse
+//! DWARF sections.
 
 use addr2line::Context;
 use anyhow::{Context as _, Result, bail};
@@ -112,3 +113,76 @@ impl<'a> Addr2lineModules<'a> {
         Ok(Some((context, text_relative_addr)))
     }
 }
+
+/// The source location of each instruction in a binary, as described by its
+/// DWARF debugging information.
+///
+/// This is used to implement `wasm-tools print --print-source-lines`.
+#[cfg(feature = "print")]
+#[derive(Debug, Default)]
+pub struct SourceLineTable {
+    frames: HashMap<usize, Vec<wasmprinter::SourceFrame>>,
+}
+
+#[cfg(feature = "print")]
+impl SourceLineTable {
+    pub fn new(wasm: &[u8]) -> Result<SourceLineTable> {
+        let mut modules = Addr2lineModules::parse(wasm)?;
+        let mut ret = SourceLineTable::default();
+        for payload in Parser::new(0).parse_all(wasm) {
+            let body = match payload? {
+                Payload::CodeSectionEntry(body) => body,
+                _ => continue,
+            };
+            let mut ops = body.get_operators_reader()?;
+            while !ops.eof() {
+                let offset = ops.original_position();
+                ops.read()?;
+                let frames = modules.frames(offset as u64)?;
+                if !frames.is_empty() {
+                    ret.frames.insert(offset, frames);
+                }
+            }
+        }
+        Ok(ret)
+    }
+}
+
+#[cfg(feature = "print")]
+impl wasmprinter::SourceLines for SourceLineTable {
+    fn frames(&self, offset: usize) -> &[wasmprinter::SourceFrame] {
+        self.frames.get(&offset).map(|f| &f[..]).unwrap_or(&[])
+    }
+}
+
+#[cfg(feature = "print")]
+impl Addr2lineModules<'_> {
+    /// Returns the frames, innermost first, for the absolute address `addr`.
+    fn frames(&mut self, addr: u64) -> Result<Vec<wasmprinter::SourceFrame>> {
+        let mut ret = Vec::new();
+        let (cx, text_relative_addr) = match self.context(addr, false)? {
+            Some(pair) => pair,
+            None => return Ok(ret),
+        };
+        let mut frames = match cx.find_frames(text_relative_addr) {
+            addr2line::LookupResult::Output(result) => result?,
+            addr2line::LookupResult::Load { .. } => return Ok(ret),
+        };
+        while let Some(frame) = frames.next()? {
+            let function = match &frame.function {
+                Some(func) => Some(func.demangle()?.into_owned()),
+                None => None,
+            };
+            let (file, line) = match &frame.location {
+                Some(loc) => (loc.file.map(|f| f.to_string()), loc.line),
+                None => (None, None),
+            };
+            ret.push(wasmprinter::SourceFrame {
+                function,
+                file,
+                line,
+            });
+        }
+        Ok(ret)
+    }
+}
@@ -0,0 +1,483 @@
+use anyhow::Result;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::convert::Infallible;
+use wasm_encoder::reencode::{Error, Reencode, ReencodeComponent, component_utils, utils};
+use wasm_encoder::{ComponentSectionId, Encode, RawSection, SectionId};
+use wasmparser::{BinaryReader, Parser, Payload, Validator, WasmFeatures};
+
+/// Rewrites a WebAssembly binary into a smaller, canonical, encoding.
+///
+/// This command reencodes the input so that semantics are preserved but the
+/// binary is normalized:
+///
+/// * all LEB128 integers use their minimal encoding,
+///
+/// * adjacent `local` declarations of the same type are merged,
+///
+/// * structurally identical type rec groups are deduplicated and the remaining
+///   rec groups are sorted by how often they're referenced, subject to
+///   definitions preceding their uses,
+///
+/// * empty sections are removed.
+///
+/// Both core modules and components, including nested modules and components,
+/// are supported. Custom sections which contain code offsets, such as DWARF or
+/// `metadata.code.*` sections, are removed since the offsets they refer to
+/// change. Relocatable object files are not supported.
+#[derive(clap::Parser)]
+pub struct Opts {
+    #[clap(flatten)]
+    io: wasm_tools::InputOutput,
+
+    /// Output the text format of WebAssembly instead of the binary format.
+    #[clap(short = 't', long)]
+    wat: bool,
+}
+
+impl Opts {
+    pub fn general_opts(&self) -> &wasm_tools::GeneralOpts {
+        self.io.general_opts()
+    }
+
+    pub fn run(&self) -> Result<()> {
+        let input = self.io.get_input_wasm()?;
+        Validator::new_with_features(WasmFeatures::all()).validate_all(&input)?;
+        let output = if wasmparser::Parser::is_component(&input) {
+            canonicalize_component(&input)?
+        } else {
+            canonicalize_module(&input)?
+        };
+        self.io.output_wasm(&output, self.wat)?;
+        Ok(())
+    }
+}
+
+fn canonicalize_module(module: &[u8]) -> Result<Vec<u8>> {
+    let types = TypeLayout::new(module)?;
+    let mut canonicalizer = Canonicalizer {
+        types: Some(types),
+        error: None,
+    };
+    let mut encoded = wasm_encoder::Module::new();
+    let result = canonicalizer.parse_core_module(&mut encoded, Parser::new(0), module);
+    canonicalizer.finish(result)?;
+    drop_empty_sections(encoded.as_slice(), SectionId::Start as u8)
+}
+
+fn canonicalize_component(component: &[u8]) -> Result<Vec<u8>> {
+    let mut canonicalizer = Canonicalizer {
+        types: None,
+        error: None,
+    };
+    let mut encoded = wasm_encoder::Component::new();
+    let result = canonicalizer.parse_component(&mut encoded, Parser::new(0), component);
+    canonicalizer.finish(result)?;
+    drop_empty_sections(encoded.as_slice(), ComponentSectionId::Start as u8)
+}
+
+/// Removes all sections from the top-level of `wasm` whose contents are an
+/// empty vector.
+///
+/// Everything here is produced by `wasm-encoder` which means that a count of
+/// zero is always encoded as the single byte `0x00`. The only non-custom
+/// section which isn't a vector is the start section, identified by
+/// `start_id`, so that one is always preserved.
+fn drop_empty_sections(wasm: &[u8], start_id: u8) -> Result<Vec<u8>> {
+    let mut reader = BinaryReader::new(&wasm[8..], 8);
+    let mut ret = wasm[..8].to_vec();
+    while !reader.eof() {
+        let id = reader.read_u8()?;
+        let len = reader.read_var_u32()?;
+        let data = reader.read_bytes(len as usize)?;
+        if id != 0 && id != start_id && data == [0] {
+            continue;
+        }
+        ret.push(id);
+        data.encode(&mut ret);
+    }
+    Ok(ret)
+}
+
+/// The new layout of the type section of a core module.
+struct TypeLayout {
+    /// Rec groups of the original type section, in the order they should be
+    /// emitted. Deduplicated rec groups are not present.
+    order: Vec<usize>,
+    /// Map from an original type index to its new type index.
+    map: Vec<u32>,
+}
+
+impl TypeLayout {
+    fn new(module: &[u8]) -> Result<TypeLayout> {
+        let mut groups = Vec::new();
+        for payload in Parser::new(0).parse_all(module) {
+            if let Payload::TypeSection(s) = payload? {
+                for group in s {
+                    groups.push(group?);
+                }
+                break;
+            }
+        }
+
+        // Assign each rec group to a canonical group, where a canonical group
+        // is the first occurrence of a structurally identical group. Type
+        // references within the group are encoded relative to the group and
+        // references outside the group are encoded as the representative type
+        // they refer to, so identical keys mean identical types.
+        let mut representative = Vec::new();
+        let mut group_of_type = Vec::new();
+        let mut group_start = Vec::new();
+        let mut canonical_groups = Vec::new();
+        let mut group_deps = Vec::new();
+        let mut canonical_of_group = Vec::new();
+        let mut keys = HashMap::new();
+        for (index, group) in groups.iter().enumerate() {
+            let start = representative.len() as u32;
+            let len = group.types().len() as u32;
+            let mut key = KeyReencoder {
+                start,
+                end: start + len,
+                representative: &representative,
+                group_of_type: &group_of_type,
+                deps: Vec::new(),
+            };
+            let subtypes = group
+                .types()
+                .map(|t| key.sub_type(t.clone()))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(reencode_error)?;
+            let mut section = wasm_encoder::TypeSection::new();
+            section.ty().rec(subtypes);
+            let mut bytes = Vec::new();
+            section.encode(&mut bytes);
+            let deps = key.deps;
+
+            let canonical = *keys.entry(bytes).or_insert_with(|| {
+                canonical_groups.push(index);
+                group_deps.push(deps);
+                canonical_groups.len() - 1
+            });
+            canonical_of_group.push(canonical);
+            group_start.push(start);
+            let canonical_start = group_start[canonical_groups[canonical]];
+            for i in 0..len {
+                representative.push(canonical_start + i);
+                group_of_type.push(index);
+            }
+        }
+
+        // Count how often each canonical group is referenced from anywhere in
+        // the module.
+        let mut counter = UseCounter {
+            uses: vec![0; representative.len()],
+        };
+        let mut scratch = wasm_encoder::Module::new();
+        counter
+            .parse_core_module(&mut scratch, Parser::new(0), module)
+            .map_err(reencode_error)?;
+        let mut uses = vec![0u64; canonical_groups.len()];
+        for (ty, count) in counter.uses.iter().enumerate() {
+            uses[canonical_of_group[group_of_type[ty]]] += u64::from(*count);
+        }
+
+        // Topologically sort canonical groups, always picking the most used
+        // group next (ties broken by original position) amongst those whose
+        // dependencies have all been emitted.
+        let mut dependents = vec![Vec::new(); canonical_groups.len()];
+        let mut pending = vec![0; canonical_groups.len()];
+        for (group, deps) in group_deps.iter().enumerate() {
+            for dep in deps {
+                let dep = canonical_of_group[*dep];
+                dependents[dep].push(group);
+                pending[group] += 1;
+            }
+        }
+        let mut ready = BinaryHeap::new();
+        for (group, count) in pending.iter().enumerate() {
+            if *count == 0 {
+                ready.push((uses[group], Reverse(group)));
+            }
+        }
+        let mut new_start = vec![0; canonical_groups.len()];
+        let mut order = Vec::new();
+        let mut next = 0;
+        while let Some((_, Reverse(group))) = ready.pop() {
+            let original = canonical_groups[group];
+            new_start[group] = next;
+            next += groups[original].types().len() as u32;
+            order.push(original);
+            for dependent in dependents[group].iter() {
+                pending[*dependent] -= 1;
+                if pending[*dependent] == 0 {
+                    ready.push((uses[*dependent], Reverse(*dependent)));
+                }
+            }
+        }
+        assert_eq!(order.len(), canonical_groups.len());
+
+        // Duplicate rec groups have the same layout as their canonical group,
+        // so a type's offset within its own group is also its offset within
+        // the canonical group.
+        let map = group_of_type
+            .iter()
+            .enumerate()
+            .map(|(ty, group)| {
+                new_start[canonical_of_group[*group]] + (ty as u32 - group_start[*group])
+            })
+            .collect();
+        Ok(TypeLayout { order, map })
+    }
+}
+
+/// A reencoder used to produce a structural key for a rec group.
+struct KeyReencoder<'a> {
+    start: u32,
+    end: u32,
+    representative: &'a [u32],
+    group_of_type: &'a [usize],
+    deps: Vec<usize>,
+}
+
+impl Reencode for KeyReencoder<'_> {
+    type Error = Infallible;
+
+    fn type_index(&mut self, ty: u32) -> Result<u32, Error> {
+        if self.start <= ty && ty < self.end {
+            return Ok(ty - self.start);
+        }
+        let rep = self.representative[ty as usize];
+        self.deps.push(self.group_of_type[rep as usize]);
+        Ok((1 << 31) | rep)
+    }
+}
+
+/// A reencoder which only counts how many times each type is referenced.
+struct UseCounter {
+    uses: Vec<u32>,
+}
+
+impl Reencode for UseCounter {
+    type Error = Infallible;
+
+    fn type_index(&mut self, ty: u32) -> Result<u32, Error> {
+        self.uses[ty as usize] += 1;
+        Ok(ty)
+    }
+
+    fn parse_custom_section(
+        &mut self,
+        _module: &mut wasm_encoder::Module,
+        _section: wasmparser::CustomSectionReader<'_>,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+struct Canonicalizer {
+    /// The type layout of the core module being reencoded, or `None` if a
+    /// component is being reencoded in which case type indices are preserved.
+    types: Option<TypeLayout>,
+    /// Errors which happened during reencoding that aren't representable with
+    /// the reencoding error type.
+    error: Option<anyhow::Error>,
+}
+
+impl Canonicalizer {
+    fn finish(&mut self, result: Result<(), Error>) -> Result<()> {
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+        result.map_err(reencode_error)
+    }
+}
+
+fn reencode_error(err: Error) -> anyhow::Error {
+    match err {
+        Error::ParseError(e) => e.into(),
+        other => anyhow::anyhow!("{other}"),
+    }
+}
+
+impl Reencode for Canonicalizer {
+    type Error = Infallible;
+
+    fn type_index(&mut self, ty: u32) -> Result<u32, Error> {
+        Ok(match &self.types {
+            Some(types) => types.map[ty as usize],
+            None => ty,
+        })
+    }
+
+    fn parse_type_section(
+        &mut self,
+        types: &mut wasm_encoder::TypeSection,
+        section: wasmparser::TypeSectionReader<'_>,
+    ) -> Result<(), Error> {
+        let groups = section.into_iter().collect::<Result<Vec<_>, _>>()?;
+        let order = match &self.types {
+            Some(layout) => layout.order.clone(),
+            None => (0..groups.len()).collect(),
+        };
+        for i in order {
+            let group = &groups[i];
+            let subtypes = group
+                .types()
+                .map(|t| self.sub_type(t.clone()))
+                .collect::<Result<Vec<_>, _>>()?;
+            // A rec group of a single type is equivalent to the type on its
+            // own, which is the shorter encoding.
+            if subtypes.len() == 1 {
+                types.ty().subtype(&subtypes[0]);
+            } else {
+                types.ty().rec(subtypes);
+            }
+        }
+        Ok(())
+    }
+
+    fn new_function_with_parsed_locals(
+        &mut self,
+        func: &wasmparser::FunctionBody<'_>,
+    ) -> Result<wasm_encoder::Function, Error> {
+        let mut locals: Vec<(u32, wasm_encoder::ValType)> = Vec::new();
+        for pair in func.get_locals_reader()? {
+            let (count, ty) = pair?;
+            if count == 0 {
+                continue;
+            }
+            let ty = self.val_type(ty)?;
+            match locals.last_mut() {
+                Some((prev_count, prev_ty)) if *prev_ty == ty => *prev_count += count,
+                _ => locals.push((count, ty)),
+            }
+        }
+        Ok(wasm_encoder::Function::new(locals))
+    }
+
+    fn parse_custom_section(
+        &mut self,
+        module: &mut wasm_encoder::Module,
+        section: wasmparser::CustomSectionReader<'_>,
+    ) -> Result<(), Error> {
+        let name = section.name();
+        if name == "linking" || name.starts_with("reloc.") {
+            if self.error.is_none() {
+                self.error = Some(anyhow::anyhow!(
+                    "cannot canonicalize relocatable object file containing a `{name}` section"
+                ));
+            }
+            return Ok(());
+        }
+        if name.starts_with(".debug_") || name.starts_with("metadata.code.") {
+            log::warn!("removing custom section `{name}` which refers to code offsets");
+            return Ok(());
+        }
+        match section.as_known() {
+            wasmparser::KnownCustom::Name(reader) => match self.custom_name_section(reader) {
+                Ok(names) => {
+                    module.section(&names);
+                }
+                Err(e) => {
+                    log::warn!("removing malformed `name` section: {}", reencode_error(e));
+                }
+            },
+            _ => {
+                module.section(&self.custom_section(section)?);
+            }
+        }
+        Ok(())
+    }
+
+    fn parse_custom_name_subsection(
+        &mut self,
+        names: &mut wasm_encoder::NameSection,
+        section: wasmparser::Name<'_>,
+    ) -> Result<(), Error> {
+        // Type names need to be remapped, and entries for deduplicated types
+        // are dropped, so re-sort them here to keep the name map ordered.
+        match section {
+            wasmparser::Name::Type(map) => {
+                let mut entries = Vec::new();
+                for naming in map {
+                    let naming = naming?;
+                    entries.push((self.type_index(naming.index)?, naming.name));
+                }
+                entries.sort_by_key(|(index, _)| *index);
+                entries.dedup_by_key(|(index, _)| *index);
+                let mut map = wasm_encoder::NameMap::new();
+                for (index, name) in entries {
+                    map.append(index, name);
+                }
+                names.types(&map);
+                Ok(())
+            }
+            wasmparser::Name::Field(map) => {
+                let mut entries = Vec::new();
+                for naming in map {
+                    let naming = naming?;
+                    let fields = utils::name_map(naming.names, Ok)?;
+                    entries.push((self.type_index(naming.index)?, fields));
+                }
+                entries.sort_by_key(|(index, _)| *index);
+                entries.dedup_by_key(|(index, _)| *index);
+                let mut map = wasm_encoder::IndirectNameMap::new();
+                for (index, fields) in entries {
+                    map.append(index, &fields);
+                }
+                names.fields(&map);
+                Ok(())
+            }
+            other => utils::parse_custom_name_subsection(self, names, other),
+        }
+    }
+}
+
+impl ReencodeComponent for Canonicalizer {
+    fn parse_component_submodule(
+        &mut self,
+        component: &mut wasm_encoder::Component,
+        _parser: Parser,
+        module: &[u8],
+    ) -> Result<(), Error> {
+        match canonicalize_module(module) {
+            Ok(module) => {
+                component.section(&RawSection {
+                    id: ComponentSectionId::CoreModule as u8,
+                    data: &module,
+                });
+            }
+            Err(e) => {
+                if self.error.is_none() {
+                    self.error = Some(e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn parse_component_subcomponent(
+        &mut self,
+        component: &mut wasm_encoder::Component,
+        parser: Parser,
+        subcomponent: &[u8],
+        whole_component: &[u8],
+    ) -> Result<(), Error> {
+        let mut nested = wasm_encoder::Component::new();
+        component_utils::parse_component(self, &mut nested, parser, subcomponent, whole_component)?;
+        match drop_empty_sections(nested.as_slice(), ComponentSectionId::Start as u8) {
+            Ok(nested) => {
+                component.section(&RawSection {
+                    id: ComponentSectionId::Component as u8,
+                    data: &nested,
+                });
+            }
+            Err(e) => {
+                if self.error.is_none() {
+                    self.error = Some(e);
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,349 @@
+use addr2line::LookupResult;
+use anyhow::{Context as _, Result, bail};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use wasm_tools::addr2line::Addr2lineModules;
+use wasmparser::{
+    CoreDumpInstancesSection, CoreDumpModulesSection, CoreDumpSection, CoreDumpStackFrame,
+    CoreDumpStackSection, CoreDumpValue, KnownCustom, Name, Parser, Payload,
+};
+
+/// Inspect a WebAssembly coredump and print the stack of each of its threads.
+///
+/// Coredumps follow the format described in the `tool-conventions` repository
+/// where a coredump is itself a WebAssembly module with `core`, `coremodules`,
+/// `coreinstances` and `corestack` custom sections. Coredumps reference the
+/// modules that were executing by name, so the modules themselves must be
+/// provided with `--module` for function names, local names and source
+/// locations to be printed.
+///
+/// Function and local names are read from the `name` section of each module.
+/// If the module contains DWARF debugging information then each frame is
+/// additionally mapped to its original source file and line, including any
+/// inlined frames.
+#[derive(clap::Parser)]
+pub struct Opts {
+    #[clap(flatten)]
+    io: wasm_tools::InputOutput,
+
+    /// Module which was executing when the coredump was taken.
+    ///
+    /// This can be specified as either `NAME=PATH` where `NAME` is the name of
+    /// the module in the `coremodules` section of the coredump, or just `PATH`
+    /// in which case the module is matched by its file name. Modules which
+    /// aren't specified are looked up on the filesystem by their name in the
+    /// coredump, if possible.
+    #[clap(long = "module", short, value_name = "[NAME=]PATH")]
+    modules: Vec<String>,
+}
+
+impl Opts {
+    pub fn general_opts(&self) -> &wasm_tools::GeneralOpts {
+        self.io.general_opts()
+    }
+
+    pub fn run(&self) -> Result<()> {
+        let wasm = self.io.get_input_wasm()?;
+        let coredump = Coredump::parse(&wasm)?;
+
+        let mut names = HashMap::new();
+        let mut paths = Vec::new();
+        for module in self.modules.iter() {
+            match module.split_once('=') {
+                Some((name, path)) => {
+                    names.insert(name.to_string(), PathBuf::from(path));
+                }
+                None => paths.push(PathBuf::from(module)),
+            }
+        }
+
+        // Find and read each module referenced by the coredump, falling back
+        // to skipping modules which can't be found.
+        let mut binaries = Vec::new();
+        for name in coredump.modules.iter() {
+            let path = names.get(*name).cloned().or_else(|| {
+                paths
+                    .iter()
+                    .find(|p| p.file_name() == Path::new(name).file_name())
+                    .cloned()
+                    .or_else(|| Some(PathBuf::from(name)).filter(|p| p.is_file()))
+            });
+            let binary = match path {
+                Some(path) => Some(
+                    wat::parse_file(&path)
+                        .with_context(|| format!("failed to read module {path:?}"))?,
+                ),
+                None => {
+                    log::warn!("module `{name}` was not found, use `--module` to specify it");
+                    None
+                }
+            };
+            binaries.push(binary);
+        }
+        let mut modules = Vec::new();
+        for (name, binary) in coredump.modules.iter().zip(&binaries) {
+            let module = match binary {
+                Some(binary) => Some(
+                    Module::parse(binary)
+                        .with_context(|| format!("failed to parse module `{name}`"))?,
+                ),
+                None => None,
+            };
+            modules.push(module);
+        }
+
+        let mut output = self.io.output_writer()?;
+        coredump.print(&mut modules, &mut output)?;
+        Ok(())
+    }
+}
+
+/// The information contained within the custom sections of a coredump.
+struct Coredump<'a> {
+    process: Option<&'a str>,
+    modules: Vec<&'a str>,
+    instances: Vec<wasmparser::CoreDumpInstance>,
+    stacks: Vec<CoreDumpStackSection<'a>>,
+}
+
+impl<'a> Coredump<'a> {
+    fn parse(wasm: &'a [u8]) -> Result<Coredump<'a>> {
+        let mut ret = Coredump {
+            process: None,
+            modules: Vec::new(),
+            instances: Vec::new(),
+            stacks: Vec::new(),
+        };
+        let mut found = false;
+        for payload in Parser::new(0).parse_all(wasm) {
+            let section = match payload? {
+                Payload::CustomSection(s) => s,
+                _ => continue,
+            };
+            match section.as_known() {
+                KnownCustom::CoreDump(CoreDumpSection { name }) => {
+                    ret.process = Some(name);
+                    found = true;
+                }
+                KnownCustom::CoreDumpModules(CoreDumpModulesSection { modules }) => {
+                    ret.modules = modules;
+                }
+                KnownCustom::CoreDumpInstances(CoreDumpInstancesSection { instances }) => {
+                    ret.instances = instances;
+                }
+                KnownCustom::CoreDumpStack(stack) => ret.stacks.push(stack),
+                _ => {}
+            }
+        }
+        if !found {
+            bail!("input is not a coredump: no `core` custom section found");
+        }
+        Ok(ret)
+    }
+
+    fn print(&self, modules: &mut [Option<Module<'_>>], out: &mut dyn Write) -> Result<()> {
+        if let Some(process) = self.process {
+            writeln!(out, "coredump of `{process}`")?;
+        }
+        for stack in self.stacks.iter() {
+            writeln!(out, "thread `{}`:", stack.name)?;
+            for (i, frame) in stack.frames.iter().enumerate() {
+                self.print_frame(i, frame, modules, out)
+                    .with_context(|| format!("failed to print frame {i}"))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn print_frame(
+        &self,
+        i: usize,
+        frame: &CoreDumpStackFrame,
+        modules: &mut [Option<Module<'_>>],
+        out: &mut dyn Write,
+    ) -> Result<()> {
+        let module_index = match self.instances.get(frame.instanceidx as usize) {
+            Some(instance) => instance.module_index as usize,
+            None => bail!("frame references invalid instance {}", frame.instanceidx),
+        };
+        let module_name = self
+            .modules
+            .get(module_index)
+            .copied()
+            .unwrap_or("<unknown>");
+        let module = modules.get_mut(module_index).and_then(|m| m.as_mut());
+
+        write!(out, "  #{i} {module_name}!")?;
+        match module
+            .as_ref()
+            .and_then(|m| m.func_names.get(&frame.funcidx))
+        {
+            Some(name) => write!(out, "{name}")?,
+            None => write!(out, "<func {}>", frame.funcidx)?,
+        }
+        writeln!(out, " @ {:#x}", frame.codeoffset)?;
+
+        let mut local_names = None;
+        if let Some(module) = module {
+            local_names = module.local_names.get(&frame.funcidx).cloned();
+            module.print_source_lines(frame, out)?;
+        }
+
+        if !frame.locals.is_empty() {
+            writeln!(out, "      locals:")?;
+            for (i, value) in frame.locals.iter().enumerate() {
+                write!(out, "        {i}")?;
+                if let Some(name) = local_names.as_ref().and_then(|n| n.get(&(i as u32))) {
+                    write!(out, " ${name}")?;
+                }
+                writeln!(out, ": {}", DisplayValue(value))?;
+            }
+        }
+        if !frame.stack.is_empty() {
+            writeln!(out, "      stack:")?;
+            for (i, value) in frame.stack.iter().enumerate() {
+                writeln!(out, "        {i}: {}", DisplayValue(value))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+struct DisplayValue<'a>(&'a CoreDumpValue);
+
+impl std::fmt::Display for DisplayValue<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            CoreDumpValue::Missing => write!(f, "<missing>"),
+            CoreDumpValue::I32(v) => write!(f, "i32 {v}"),
+            CoreDumpValue::I64(v) => write!(f, "i64 {v}"),
+            CoreDumpValue::F32(v) => write!(f, "f32 {}", f32::from_bits(v.bits())),
+            CoreDumpValue::F64(v) => write!(f, "f64 {}", f64::from_bits(v.bits())),
+        }
+    }
+}
+
+/// A module referenced from a coredump.
+struct Module<'a> {
+    func_names: HashMap<u32, String>,
+    local_names: HashMap<u32, HashMap<u32, String>>,
+    /// Offset, within the module, of the start of each defined function's
+    /// body. Indexed by function index minus the number of imported
+    /// functions.
+    bodies: Vec<u64>,
+    imported_funcs: u32,
+    dwarf: Addr2lineModules<'a>,
+}
+
+impl<'a> Module<'a> {
+    fn parse(wasm: &'a [u8]) -> Result<Module<'a>> {
+        let mut ret = Module {
+            func_names: HashMap::new(),
+            local_names: HashMap::new(),
+            bodies: Vec::new(),
+            imported_funcs: 0,
+            dwarf: Addr2lineModules::parse(wasm)?,
+        };
+        for payload in Parser::new(0).parse_all(wasm) {
+            match payload? {
+                Payload::Version {
+                    encoding: wasmparser::Encoding::Component,
+                    ..
+                } => bail!("components are not supported in coredumps"),
+                Payload::ImportSection(s) => {
+                    for import in s {
+                        if let wasmparser::TypeRef::Func(_) = import?.ty {
+                            ret.imported_funcs += 1;
+                        }
+                    }
+                }
+                Payload::CodeSectionEntry(body) => {
+                    ret.bodies.push(body.range().start as u64);
+                }
+                Payload::CustomSection(s) => {
+                    let names = match s.as_known() {
+                        KnownCustom::Name(names) => names,
+                        _ => continue,
+                    };
+                    for name in names {
+                        match name? {
+                            Name::Function(map) => {
+                                for naming in map {
+                                    let naming = naming?;
+                                    ret.func_names.insert(naming.index, naming.name.to_string());
+                                }
+                            }
+                            Name::Local(map) => {
+                                for func in map {
+                                    let func = func?;
+                                    let mut locals = HashMap::new();
+                                    for naming in func.names {
+                                        let naming = naming?;
+                                        locals.insert(naming.index, naming.name.to_string());
+                                    }
+                                    ret.local_names.insert(func.index, locals);
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Prints the source file and line, plus any inlined frames, of `frame`
+    /// using DWARF debugging information, if present.
+    fn print_source_lines(
+        &mut self,
+        frame: &CoreDumpStackFrame,
+        out: &mut dyn Write,
+    ) -> Result<()> {
+        let body_start = match frame
+            .funcidx
+            .checked_sub(self.imported_funcs)
+            .and_then(|i| self.bodies.get(i as usize))
+        {
+            Some(start) => *start,
+            None => return Ok(()),
+        };
+        let addr = body_start + u64::from(frame.codeoffset);
+        let (cx, text_relative_addr) = match self.dwarf.context(addr, false)? {
+            Some(pair) => pair,
+            None => return Ok(()),
+        };
+        let mut frames = match cx.find_frames(text_relative_addr) {
+            LookupResult::Output(result) => result?,
+            LookupResult::Load { .. } => return Ok(()),
+        };
+        let mut first = true;
+        while let Some(frame) = frames.next()? {
+            write!(out, "      ")?;
+            if !first {
+                write!(out, "inlined into ")?;
+            }
+            first = false;
+            if let Some(func) = &frame.function {
+                write!(out, "{} ", func.demangle()?)?;
+            }
+            write!(out, "at ")?;
+            match &frame.location {
+                Some(loc) => {
+                    write!(out, "{}", loc.file.unwrap_or("<unknown>"))?;
+                    if let Some(line) = loc.line {
+                        write!(out, ":{line}")?;
+                    }
+                    if let Some(column) = loc.column {
+                        write!(out, ":{column}")?;
+                    }
+                }
+                None => write!(out, "<unknown>")?,
+            }
+            writeln!(out)?;
+        }
+        Ok(())
+    }
+}
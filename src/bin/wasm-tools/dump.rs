@@ -519,6 +519,17 @@ impl<'a> Dump<'a> {
                                 })
                             })?;
                         }
+                        KnownCustom::CodeMetadata(iter) => {
+                            self.print_iter(iter, |me, _pos, item| {
+                                write!(me.state, "func: {}", item.func)?;
+                                me.print(item.metadata.range().start)?;
+
+                                me.print_iter(item.metadata, |me, pos, item| {
+                                    write!(me.state, "{item:?}")?;
+                                    me.print(pos)
+                                })
+                            })?;
+                        }
                         KnownCustom::CoreDump(s) => {
                             write!(self.state, "name: {}", s.name)?;
                             self.print(c.range().end)?;
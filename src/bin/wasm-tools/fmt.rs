@@ -0,0 +1,107 @@
+use anyhow::{Context, Result, bail};
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use wast::fmt::{Formatter, InstrStyle};
+
+/// Format WebAssembly text files, preserving comments and layout.
+///
+/// This reformats `*.wat` and `*.wast` source text without round-tripping it
+/// through the binary format, so comments, annotations and the line breaks
+/// chosen by the author are all kept. Indentation and whitespace within lines
+/// are normalized.
+///
+/// Files given on the command line are reformatted in place, unless `--stdout`
+/// is passed. If no files are given then the text is read from stdin and the
+/// formatted text is printed to stdout.
+#[derive(clap::Parser)]
+pub struct Opts {
+    #[clap(flatten)]
+    general: wasm_tools::GeneralOpts,
+
+    /// Files to format in place.
+    ///
+    /// If not provided, or if this is `-`, then stdin is formatted to stdout.
+    files: Vec<PathBuf>,
+
+    /// Don't write any files, instead exit with an error if any input isn't
+    /// already formatted.
+    ///
+    /// The name of each file which would be changed is printed.
+    #[clap(long, conflicts_with = "stdout")]
+    check: bool,
+
+    /// Print the formatted text of files to stdout instead of modifying them.
+    #[clap(long)]
+    stdout: bool,
+
+    /// Convert the instructions of functions to the folded s-expression form.
+    #[clap(long, conflicts_with = "flat")]
+    fold: bool,
+
+    /// Convert the instructions of functions to the flat form.
+    #[clap(long)]
+    flat: bool,
+}
+
+impl Opts {
+    pub fn general_opts(&self) -> &wasm_tools::GeneralOpts {
+        &self.general
+    }
+
+    pub fn run(&self) -> Result<()> {
+        let mut formatter = Formatter::new();
+        if self.fold {
+            formatter.instructions(InstrStyle::Folded);
+        } else if self.flat {
+            formatter.instructions(InstrStyle::Flat);
+        }
+
+        let mut unformatted = 0;
+        if self.files.is_empty() || self.files.iter().any(|f| f.as_os_str() == "-") {
+            if self.files.len() > 1 {
+                bail!("stdin, `-`, cannot be formatted along with other files");
+            }
+            let mut input = String::new();
+            std::io::stdin()
+                .read_to_string(&mut input)
+                .context("failed to read stdin")?;
+            let output = formatter.format(&input)?;
+            if self.check {
+                if output != input {
+                    println!("<stdin>");
+                    unformatted += 1;
+                }
+            } else {
+                std::io::stdout().write_all(output.as_bytes())?;
+            }
+        }
+
+        for path in self.files.iter().filter(|f| f.as_os_str() != "-") {
+            let input = std::fs::read_to_string(path)
+                .with_context(|| format!("failed to read {path:?}"))?;
+            let output = formatter.format(&input).map_err(|mut e| {
+                e.set_path(path);
+                e
+            })?;
+            if self.stdout {
+                std::io::stdout().write_all(output.as_bytes())?;
+                continue;
+            }
+            if output == input {
+                continue;
+            }
+            if self.check {
+                println!("{}", path.display());
+                unformatted += 1;
+            } else {
+                std::fs::write(path, output)
+                    .with_context(|| format!("failed to write {path:?}"))?;
+            }
+        }
+
+        if unformatted > 0 {
+            bail!("{unformatted} input(s) are not formatted");
+        }
+        Ok(())
+    }
+}
@@ -57,6 +57,7 @@ subcommands! {
     (validate, "validate")
     #[command(alias = "wasm2wat")]
     (print, "print")
+    (fmt, "fmt")
     (smith, "smith")
     // The shrink subcommand relies on executing new processes to test a
     // predicate which isn't supported on wasm, so always omit this command on
@@ -66,6 +67,7 @@ subcommands! {
     (dump, "dump")
     (objdump, "objdump")
     (strip, "strip")
+    (canonicalize, "canonicalize")
     (compose, "compose")
     (demangle, "demangle")
     #[command(subcommand)]
@@ -74,6 +76,7 @@ subcommands! {
     (metadata, "metadata")
     (wit_smith, "wit-smith")
     (addr2line, "addr2line")
+    (coredump, "coredump")
     (completion, "completion")
     #[command(alias = "wast2json")]
     (json_from_wast, "json-from-wast")
@@ -1,5 +1,6 @@
-use anyhow::Result;
+use anyhow::{Context, Result, bail};
 use clap::Parser;
+use std::path::PathBuf;
 
 /// Parse the WebAssembly text format.
 ///
@@ -13,6 +14,21 @@ pub struct Opts {
     /// Output the text format of WebAssembly instead of the binary format.
     #[clap(short = 't', long)]
     wat: bool,
+
+    /// Generate a Source Map v3 file at this path which maps instructions in
+    /// the output binary back to their location in the input text.
+    ///
+    /// Source maps can only be generated when the input is a textual core
+    /// WebAssembly module.
+    #[clap(long, value_name = "PATH")]
+    source_map: Option<PathBuf>,
+
+    /// Append a `sourceMappingURL` custom section to the output pointing to
+    /// this URL as the location of the module's source map.
+    ///
+    /// This has no effect if the input is already a WebAssembly binary.
+    #[clap(long, value_name = "URL")]
+    source_map_url: Option<String>,
 }
 
 impl Opts {
@@ -21,7 +37,17 @@ impl Opts {
     }
 
     pub fn run(&self) -> Result<()> {
-        let binary = self.io.parse_input_wasm()?;
+        let (binary, source_map) = self.io.parse_input_wasm_with_source_map(
+            self.source_map.is_some(),
+            self.source_map_url.as_deref(),
+        )?;
+        if let Some(path) = &self.source_map {
+            let Some(source_map) = source_map else {
+                bail!("source maps can only be generated for textual core wasm modules");
+            };
+            std::fs::write(path, source_map)
+                .with_context(|| format!("failed to write `{}`", path.display()))?;
+        }
         self.io.output_wasm(&binary, self.wat)?;
         Ok(())
     }
@@ -1,5 +1,7 @@
-use anyhow::Result;
+use anyhow::{Result, bail};
 use clap::Parser;
+use regex::Regex;
+use wasmprinter::{FilterItem, FilterKind, FilterScopeKind};
 
 /// Print the textual form of a WebAssembly binary.
 #[derive(Parser)]
@@ -19,6 +21,38 @@ pub struct Opts {
     #[clap(long)]
     skeleton: bool,
 
+    /// Only print the bodies of functions matching `PATTERN`, printing
+    /// everything else as a skeleton.
+    ///
+    /// The `PATTERN` is either a function index or a regular expression which
+    /// is matched against function names from the `name` section. Note that
+    /// regular expressions match anywhere within the name unless anchored with
+    /// `^` and `$`. This is shorthand for `--item func:PATTERN` and may be
+    /// specified multiple times.
+    #[clap(long = "func", value_name = "PATTERN", value_parser = ItemPattern::func)]
+    funcs: Vec<ItemPattern>,
+
+    /// Only print the contents of items matching `SPEC`, printing everything
+    /// else as a skeleton.
+    ///
+    /// The `SPEC` has the form `KIND:PATTERN` where `KIND` is one of:
+    ///
+    /// * `func` - functions by index or name
+    ///
+    /// * `export` - functions by the name they're exported under
+    ///
+    /// * `data` - data segments by index or name
+    ///
+    /// * `elem` - element segments by index or name
+    ///
+    /// and `PATTERN` is an index or a regular expression, as with `--func`.
+    /// Items within nested modules and components can be selected by
+    /// prefixing `SPEC` with their path such as
+    /// `component:0/module:foo/func:bar`. Without a path items are matched at
+    /// any nesting depth. This may be specified multiple times.
+    #[clap(long = "item", value_name = "SPEC", value_parser = ItemPattern::item)]
+    items: Vec<ItemPattern>,
+
     /// Ensure all wasm items have `$`-based names, even if they don't have an
     /// entry in the `name` section.
     ///
@@ -35,6 +69,23 @@ pub struct Opts {
     #[clap(long)]
     print_operand_stack: bool,
 
+    /// Annotate instructions with their original source file and line.
+    ///
+    /// This uses the DWARF debugging information in the input, if any, and
+    /// prints locations as comments next to instructions whenever the
+    /// location changes. Instructions from inlined functions additionally
+    /// mention the call site they were inlined at.
+    #[clap(long)]
+    print_source_lines: bool,
+
+    /// Print the text format as an HTML document.
+    ///
+    /// In the HTML output every reference to an item, such as a function,
+    /// type, global or local, links to the item's definition. Hovering over a
+    /// reference to a type shows the type's definition.
+    #[clap(long)]
+    html: bool,
+
     /// The string to use when indenting.
     #[clap(long)]
     indent_text: Option<String>,
@@ -54,9 +105,21 @@ impl Opts {
         let mut config = wasmprinter::Config::new();
         config.print_offsets(self.print_offsets);
         config.print_skeleton(self.skeleton);
+        if !self.funcs.is_empty() || !self.items.is_empty() {
+            let patterns = self
+                .funcs
+                .iter()
+                .chain(&self.items)
+                .cloned()
+                .collect::<Vec<_>>();
+            config.filter(move |item| patterns.iter().any(|p| p.matches(item)));
+        }
         config.name_unnamed(self.name_unnamed);
         config.fold_instructions(self.fold_instructions);
         config.print_operand_stack(self.print_operand_stack);
+        if self.print_source_lines {
+            config.source_lines(wasm_tools::addr2line::SourceLineTable::new(&wasm)?);
+        }
         match self.indent_text.as_ref() {
             Some(s) => {
                 config.indent_text(s);
@@ -67,9 +130,134 @@ impl Opts {
                 }
             }
         }
+        if self.html {
+            let mut html = wasmprinter::PrintHtml::new(self.io.output_writer()?);
+            config.print(&wasm, &mut html)?;
+            html.finish()?;
+            return Ok(());
+        }
         self.io.output(wasm_tools::Output::Wat {
             wasm: &wasm,
             config,
         })
     }
 }
+
+/// A pattern selecting items to print with `--func` or `--item`.
+#[derive(Clone)]
+struct ItemPattern {
+    scopes: Option<Vec<(FilterScopeKind, Pattern)>>,
+    kind: ItemKind,
+    pattern: Pattern,
+}
+
+#[derive(Copy, Clone)]
+enum ItemKind {
+    Func,
+    Export,
+    Data,
+    Elem,
+}
+
+#[derive(Clone)]
+enum Pattern {
+    Index(u32),
+    Regex(Regex),
+}
+
+impl ItemPattern {
+    fn func(s: &str) -> Result<ItemPattern> {
+        Ok(ItemPattern {
+            scopes: None,
+            kind: ItemKind::Func,
+            pattern: Pattern::new(s)?,
+        })
+    }
+
+    fn item(mut s: &str) -> Result<ItemPattern> {
+        let mut scopes = Vec::new();
+        loop {
+            let (kind, rest) = match s.split_once(':') {
+                Some(pair) => pair,
+                None => bail!("expected `KIND:PATTERN`, found `{s}`"),
+            };
+            let scope = match kind {
+                "module" => FilterScopeKind::Module,
+                "component" => FilterScopeKind::Component,
+                _ => {
+                    let kind = match kind {
+                        "func" => ItemKind::Func,
+                        "export" => ItemKind::Export,
+                        "data" => ItemKind::Data,
+                        "elem" => ItemKind::Elem,
+                        _ => bail!(
+                            "unknown item kind `{kind}`, expected one of `func`, `export`, \
+                             `data`, `elem`, `module`, or `component`"
+                        ),
+                    };
+                    return Ok(ItemPattern {
+                        scopes: if scopes.is_empty() {
+                            None
+                        } else {
+                            Some(scopes)
+                        },
+                        kind,
+                        pattern: Pattern::new(rest)?,
+                    });
+                }
+            };
+            let (pattern, rest) = match rest.split_once('/') {
+                Some(pair) => pair,
+                None => bail!("expected an item after `{kind}:{rest}`"),
+            };
+            scopes.push((scope, Pattern::new(pattern)?));
+            s = rest;
+        }
+    }
+
+    fn matches(&self, item: &FilterItem<'_>) -> bool {
+        if let Some(scopes) = &self.scopes {
+            if scopes.len() != item.scopes.len() {
+                return false;
+            }
+            for ((kind, pattern), scope) in scopes.iter().zip(item.scopes) {
+                if *kind != scope.kind || !pattern.matches(scope.index, scope.name.as_deref()) {
+                    return false;
+                }
+            }
+        }
+        match (self.kind, item.kind) {
+            (ItemKind::Func, FilterKind::Func)
+            | (ItemKind::Data, FilterKind::Data)
+            | (ItemKind::Elem, FilterKind::Elem) => self.pattern.matches(item.index, item.name),
+            (ItemKind::Export, FilterKind::Func) => item
+                .exports
+                .iter()
+                .any(|name| self.pattern.matches_name(name)),
+            _ => false,
+        }
+    }
+}
+
+impl Pattern {
+    fn new(s: &str) -> Result<Pattern> {
+        if let Ok(index) = s.parse() {
+            return Ok(Pattern::Index(index));
+        }
+        Ok(Pattern::Regex(Regex::new(s)?))
+    }
+
+    fn matches(&self, index: u32, name: Option<&str>) -> bool {
+        match self {
+            Pattern::Index(i) => *i == index,
+            Pattern::Regex(_) => name.is_some_and(|name| self.matches_name(name)),
+        }
+    }
+
+    fn matches_name(&self, name: &str) -> bool {
+        match self {
+            Pattern::Index(i) => name == i.to_string(),
+            Pattern::Regex(r) => r.is_match(name),
+        }
+    }
+}
@@ -65,6 +65,15 @@ pub struct Opts {
     #[clap(short, long)]
     fuel: Option<u32>,
 
+    /// Enable swarm testing.
+    ///
+    /// Before generating the module, randomly disable some of the proposals,
+    /// instruction kinds and section kinds that the configuration otherwise
+    /// allows. These choices are made using the input seed, so they are
+    /// still deterministic.
+    #[clap(long)]
+    swarm: bool,
+
     /// JSON configuration file with settings to control the wasm output.
     #[clap(short, long)]
     config: Option<PathBuf>,
@@ -106,7 +115,13 @@ impl Opts {
             None => wasm_smith::InternalOptionalConfig::default(),
         };
         let config = self.module_config.clone().or(json);
-        let config = wasm_smith::Config::try_from(config)?;
+        let mut config = wasm_smith::Config::try_from(config)?;
+        if self.swarm {
+            config = config.swarm(&mut u).unwrap_or_else(|e| {
+                eprintln!("error: failed to generate swarm config: {e}");
+                process::exit(2);
+            });
+        }
         let mut module = Module::new(config, &mut u).unwrap_or_else(|e| {
             eprintln!("error: failed to generate module: {e}");
             process::exit(2);
@@ -17,6 +17,8 @@ use wast::lexer::Lexer;
 use wast::parser::{self, ParseBuffer};
 use wast::{QuoteWat, Wast, WastDirective, Wat};
 
+mod execute;
+
 /// A subcommand to test `*.wast` files short of executing WebAssembly code.
 ///
 /// This subcommand can be used to validate the `*.wast` test format used by the
@@ -27,10 +29,12 @@ use wast::{QuoteWat, Wast, WastDirective, Wat};
 /// * Modules that are declared to be valid are indeed valid.
 /// * Modules that are declared to be invalid are indeed invalid.
 ///
-/// This subcommand does not actually execute any WebAssembly code, so the
-/// `*.wast` test is not guaranteed to pass in a real runtime if passed to a
-/// runtime. This can be used as a quick check to double-check that a file is
-/// almost all valid, however.
+/// By default this subcommand does not actually execute any WebAssembly code,
+/// so the `*.wast` test is not guaranteed to pass in a real runtime if passed
+/// to a runtime. This can be used as a quick check to double-check that a file
+/// is almost all valid, however. With `--execute` the test is additionally run
+/// in a small reference interpreter which evaluates directives such as
+/// `assert_return` and `assert_trap`.
 ///
 /// The `wasm-tools` project itself also heavily relies on this subcommand for
 /// internal testing, so many tests for `wasm-tools` use this subcommand as a
@@ -78,6 +82,16 @@ pub struct Opts {
     /// Directory to place snapshots in with `--assert snapshot-*` flags.
     #[clap(long)]
     snapshot: Option<PathBuf>,
+
+    /// Execute the directives of the test with a reference interpreter.
+    ///
+    /// This runs modules along with directives such as `invoke`,
+    /// `assert_return`, `assert_trap`, and `assert_unlinkable` in order,
+    /// reporting a failure for each directive whose assertion doesn't hold.
+    /// Only core wasm modules are supported and proposals such as GC,
+    /// exceptions, and threads are not.
+    #[clap(long)]
+    execute: bool,
 }
 
 impl Opts {
@@ -98,7 +112,8 @@ impl Opts {
         }
         let mut lexer = Lexer::new(contents);
         lexer.allow_confusing_unicode(self.allow_confusing_unicode);
-        let buf = ParseBuffer::new_with_lexer(lexer).map_err(|e| adjust!(e))?;
+        let mut buf = ParseBuffer::new_with_lexer(lexer).map_err(|e| adjust!(e))?;
+        buf.recover(true);
         let wast = parser::parse::<Wast>(&buf).map_err(|e| adjust!(e))?;
 
         if let Some(path) = &self.input {
@@ -106,26 +121,40 @@ impl Opts {
                 .context("failed to run `json-from-wast` cli subcommand")?;
         }
 
-        let errors = wast
-            .directives
-            .into_par_iter()
-            .enumerate()
-            .filter_map(|(index, directive)| {
-                let span = directive.span();
-
-                self.test_wast_directive(test, directive, index)
-                    .with_context(|| {
-                        let (line, col) = span.linecol_in(contents);
-                        format!(
-                            "failed directive on {}:{}:{}",
-                            test.display(),
-                            line + 1,
-                            col + 1
-                        )
-                    })
-                    .err()
-            })
-            .collect::<Vec<_>>();
+        let failed_directive = |span: wast::token::Span| {
+            let (line, col) = span.linecol_in(contents);
+            format!(
+                "failed directive on {}:{}:{}",
+                test.display(),
+                line + 1,
+                col + 1
+            )
+        };
+        let test_directive = |index, directive: WastDirective, executor: Option<&mut _>| {
+            let span = directive.span();
+
+            self.test_wast_directive(test, directive, index, executor)
+                .with_context(|| failed_directive(span))
+                .err()
+        };
+        let errors = if self.execute {
+            // Executed directives depend on the ones before them, so they're
+            // all validated and executed in order.
+            let mut executor = execute::Executor::new(self.features.features())?;
+            wast.directives
+                .into_iter()
+                .enumerate()
+                .filter_map(|(index, directive)| {
+                    test_directive(index, directive, Some(&mut executor))
+                })
+                .collect::<Vec<_>>()
+        } else {
+            wast.directives
+                .into_par_iter()
+                .enumerate()
+                .filter_map(|(index, directive)| test_directive(index, directive, None))
+                .collect::<Vec<_>>()
+        };
         if errors.is_empty() {
             return Ok(());
         }
@@ -156,19 +185,38 @@ impl Opts {
         Ok((Path::new("<stdin>"), stdin))
     }
 
-    fn test_wast_directive(&self, test: &Path, directive: WastDirective, idx: usize) -> Result<()> {
+    /// Tests `directive`, additionally executing it with `executor` if
+    /// provided once it's been validated.
+    fn test_wast_directive(
+        &self,
+        test: &Path,
+        directive: WastDirective,
+        idx: usize,
+        executor: Option<&mut execute::Executor>,
+    ) -> Result<()> {
+        let matches = |error: &str, message: &str| self.assert_error_matches(test, error, message);
         match directive {
-            WastDirective::Module(mut module) | WastDirective::ModuleDefinition(mut module) => {
-                let actual = module.encode()?;
-
-                let mut test_path = test.to_path_buf();
-                test_path.push(idx.to_string());
+            WastDirective::Module(mut module) => {
+                let name = module.name();
+                let actual = self.test_wast_module(test, &mut module, idx)?;
+                if let Some(executor) = executor {
+                    executor.module(name, &actual)?;
+                }
+            }
 
-                self.test_wasm(&test_path, &actual, expect_binary_roundtrip(&module))
-                    .context("failed testing wasm binary produced by `wast`")?;
+            WastDirective::ModuleDefinition(mut module) => {
+                let name = module.name();
+                let actual = self.test_wast_module(test, &mut module, idx)?;
+                if let Some(executor) = executor {
+                    executor.module_definition(name, &actual)?;
+                }
             }
 
-            WastDirective::AssertUnlinkable { mut module, .. } => {
+            WastDirective::AssertUnlinkable {
+                mut module,
+                message,
+                ..
+            } => {
                 let actual = module.encode()?;
 
                 let mut test_path = test.to_path_buf();
@@ -176,6 +224,10 @@ impl Opts {
 
                 self.test_wasm(&test_path, &actual, true)
                     .context("failed testing wasm binary produced by `wast`")?;
+
+                if let Some(executor) = executor {
+                    executor.assert_unlinkable(&actual, message, &matches)?;
+                }
             }
 
             WastDirective::AssertMalformed {
@@ -261,6 +313,7 @@ impl Opts {
                             message,
                         },
                         idx,
+                        executor,
                     );
                 }
 
@@ -300,13 +353,16 @@ impl Opts {
 
             WastDirective::Thread(thread) => {
                 for (i, directive) in thread.directives.into_iter().enumerate() {
-                    self.test_wast_directive(test, directive, idx * 1000 + i)?;
+                    self.test_wast_directive(test, directive, idx * 1000 + i, None)?;
+                }
+                if executor.is_some() {
+                    bail!("threads are not supported by the interpreter")
                 }
             }
 
-            // This command doesn't actually execute any wasm code, so ignore
-            // all of these assertions.
-            WastDirective::ModuleInstance { .. }
+            // Without `--execute` no wasm code is run, so ignore all of these
+            // assertions.
+            directive @ (WastDirective::ModuleInstance { .. }
             | WastDirective::Register { .. }
             | WastDirective::Invoke(_)
             | WastDirective::AssertTrap { .. }
@@ -314,11 +370,28 @@ impl Opts {
             | WastDirective::AssertExhaustion { .. }
             | WastDirective::AssertException { .. }
             | WastDirective::AssertSuspension { .. }
-            | WastDirective::Wait { .. } => {}
+            | WastDirective::Wait { .. }) => {
+                if let Some(executor) = executor {
+                    executor.run(directive, &matches)?;
+                }
+            }
         }
         Ok(())
     }
 
+    /// Tests the module of a `module` or `module definition` directive,
+    /// returning its binary encoding.
+    fn test_wast_module(&self, test: &Path, module: &mut QuoteWat, idx: usize) -> Result<Vec<u8>> {
+        let actual = module.encode()?;
+
+        let mut test_path = test.to_path_buf();
+        test_path.push(idx.to_string());
+
+        self.test_wasm(&test_path, &actual, expect_binary_roundtrip(module))
+            .context("failed testing wasm binary produced by `wast`")?;
+        Ok(actual)
+    }
+
     /// Tests whether `assert` is enabled.
     fn assert(&self, assert: Assert) -> bool {
         let mut enabled = false;
@@ -0,0 +1,430 @@
+//! Execution of `*.wast` directives with the `wasm-interp` reference
+//! interpreter, used by `wasm-tools wast --execute`.
+
+use anyhow::{Context, Result, bail};
+use std::collections::HashMap;
+use wasm_interp::{Extern, Instance, Module, Store, Trap, Val};
+use wasmparser::{FuncType, GlobalType, MemoryType, TableType, ValType, WasmFeatures};
+use wast::core::{AbstractHeapType, HeapType, NanPattern, V128Pattern, WastArgCore, WastRetCore};
+use wast::token::{F32, F64, Id};
+use wast::{WastArg, WastDirective, WastExecute, WastInvoke, WastRet};
+
+/// The state of a `*.wast` script being executed, which carries over from one
+/// directive to the next.
+pub struct Executor {
+    features: WasmFeatures,
+    store: Store,
+    /// The exports of the `spectest` module that scripts can import from.
+    spectest: HashMap<&'static str, Extern>,
+    /// Instances by the names they were registered with via `register`.
+    registered: HashMap<String, Instance>,
+    /// Instances by their `$id` in the script.
+    instances: HashMap<String, Instance>,
+    /// Module definitions by their `$id` in the script.
+    definitions: HashMap<String, Module>,
+    /// The most recently defined module, for `module instance` directives
+    /// which don't name a module.
+    last_definition: Option<Module>,
+    /// The most recently instantiated module, which directives without a
+    /// module name refer to.
+    current: Option<Instance>,
+}
+
+impl Executor {
+    pub fn new(features: WasmFeatures) -> Result<Executor> {
+        let mut store = Store::new();
+        let mut spectest = HashMap::new();
+
+        // The printing functions of the spec interpreter's `spectest` module
+        // are provided here but don't print anything.
+        let prints: [(&str, &[ValType]); 7] = [
+            ("print", &[]),
+            ("print_i32", &[ValType::I32]),
+            ("print_i64", &[ValType::I64]),
+            ("print_f32", &[ValType::F32]),
+            ("print_f64", &[ValType::F64]),
+            ("print_i32_f32", &[ValType::I32, ValType::F32]),
+            ("print_f64_f64", &[ValType::F64, ValType::F64]),
+        ];
+        for (name, params) in prints {
+            let ty = FuncType::new(params.iter().copied(), []);
+            let func = store.func_new(ty, |_| Ok(Vec::new()));
+            spectest.insert(name, Extern::Func(func));
+        }
+
+        let globals = [
+            ("global_i32", ValType::I32, Val::I32(666)),
+            ("global_i64", ValType::I64, Val::I64(666)),
+            ("global_f32", ValType::F32, Val::F32(666.6f32.to_bits())),
+            ("global_f64", ValType::F64, Val::F64(666.6f64.to_bits())),
+        ];
+        for (name, content_type, val) in globals {
+            let ty = GlobalType {
+                content_type,
+                mutable: false,
+                shared: false,
+            };
+            spectest.insert(name, Extern::Global(store.global_new(ty, val)));
+        }
+
+        let table = TableType {
+            element_type: wasmparser::RefType::FUNCREF,
+            table64: false,
+            initial: 10,
+            maximum: Some(20),
+            shared: false,
+        };
+        let table = store.table_new(table, Val::FuncRef(None))?;
+        spectest.insert("table", Extern::Table(table));
+
+        let memory = MemoryType {
+            memory64: false,
+            shared: false,
+            initial: 1,
+            maximum: Some(2),
+            page_size_log2: None,
+        };
+        spectest.insert("memory", Extern::Memory(store.memory_new(memory)?));
+
+        Ok(Executor {
+            features,
+            store,
+            spectest,
+            registered: HashMap::new(),
+            instances: HashMap::new(),
+            definitions: HashMap::new(),
+            last_definition: None,
+            current: None,
+        })
+    }
+
+    /// Instantiates the module encoded as `bytes`, making it the current
+    /// instance.
+    pub fn module(&mut self, name: Option<Id<'_>>, bytes: &[u8]) -> Result<()> {
+        let module = self.compile(bytes)?;
+        let instance = self.instantiate(&module)?;
+        self.current = Some(instance);
+        if let Some(name) = name {
+            self.instances.insert(name.name().to_string(), instance);
+        }
+        Ok(())
+    }
+
+    /// Defines the module encoded as `bytes` without instantiating it.
+    pub fn module_definition(&mut self, name: Option<Id<'_>>, bytes: &[u8]) -> Result<()> {
+        let module = self.compile(bytes)?;
+        if let Some(name) = name {
+            self.definitions
+                .insert(name.name().to_string(), module.clone());
+        }
+        self.last_definition = Some(module);
+        Ok(())
+    }
+
+    /// Asserts that instantiating the module encoded as `bytes` fails with
+    /// `message`.
+    pub fn assert_unlinkable(
+        &mut self,
+        bytes: &[u8],
+        message: &str,
+        matches: &dyn Fn(&str, &str) -> Result<()>,
+    ) -> Result<()> {
+        let module = self.compile(bytes)?;
+        match self.instantiate(&module) {
+            Ok(_) => bail!("instantiation succeeded but should have failed: {message:?}"),
+            Err(e) if e.is::<Trap>() => Err(e),
+            Err(e) => matches(&format!("{e:?}"), message),
+        }
+    }
+
+    /// Executes `directive`, returning an error if it fails or if its
+    /// assertion doesn't hold.
+    ///
+    /// Directives which carry a module to validate are instead executed with
+    /// [`Executor::module`], [`Executor::module_definition`], and
+    /// [`Executor::assert_unlinkable`] once the module has been encoded. The
+    /// `matches` callback is used to check whether an error message matches
+    /// the expected message of an assertion.
+    pub fn run(
+        &mut self,
+        directive: WastDirective<'_>,
+        matches: &dyn Fn(&str, &str) -> Result<()>,
+    ) -> Result<()> {
+        match directive {
+            WastDirective::ModuleInstance {
+                instance, module, ..
+            } => {
+                let module = match module {
+                    Some(name) => self.definitions.get(name.name()),
+                    None => self.last_definition.as_ref(),
+                };
+                let module = module.context("module definition not found")?.clone();
+                let result = self.instantiate(&module)?;
+                self.current = Some(result);
+                if let Some(name) = instance {
+                    self.instances.insert(name.name().to_string(), result);
+                }
+            }
+            WastDirective::Register { name, module, .. } => {
+                let instance = self.instance(module)?;
+                self.registered.insert(name.to_string(), instance);
+            }
+            WastDirective::Invoke(invoke) => {
+                self.invoke(&invoke)?;
+            }
+            WastDirective::AssertReturn { exec, results, .. } => {
+                let actual = self.execute(exec)?;
+                if actual.len() != results.len() {
+                    bail!(
+                        "expected {} results, found {}: {actual:?}",
+                        results.len(),
+                        actual.len()
+                    );
+                }
+                for (i, (actual, expected)) in actual.iter().zip(&results).enumerate() {
+                    let expected = match expected {
+                        WastRet::Core(ret) => ret,
+                        #[allow(unreachable_patterns)]
+                        _ => bail!("component values are not supported"),
+                    };
+                    if !val_matches(actual, expected)? {
+                        bail!("result {i} mismatch: expected {expected:?}, found {actual:?}");
+                    }
+                }
+            }
+            WastDirective::AssertTrap { exec, message, .. } => {
+                let err = match self.execute(exec) {
+                    Ok(results) => bail!("expected a trap, found results {results:?}"),
+                    Err(e) => e,
+                };
+                expect_trap(err, message, matches)?;
+            }
+            WastDirective::AssertExhaustion { call, message, .. } => {
+                let err = match self.invoke(&call) {
+                    Ok(results) => bail!("expected a trap, found results {results:?}"),
+                    Err(e) => e,
+                };
+                expect_trap(err, message, matches)?;
+            }
+
+            WastDirective::Module(_)
+            | WastDirective::ModuleDefinition(_)
+            | WastDirective::AssertUnlinkable { .. }
+            | WastDirective::AssertMalformed { .. }
+            | WastDirective::AssertInvalid { .. } => {
+                unreachable!("modules are executed once they've been validated")
+            }
+
+            WastDirective::AssertException { .. } | WastDirective::AssertSuspension { .. } => {
+                bail!("exceptions and stack switching are not supported by the interpreter")
+            }
+            WastDirective::Thread(_) | WastDirective::Wait { .. } => {
+                bail!("threads are not supported by the interpreter")
+            }
+        }
+        Ok(())
+    }
+
+    /// Validates the encoded module `bytes`.
+    fn compile(&self, bytes: &[u8]) -> Result<Module> {
+        Module::new(self.features, bytes)
+    }
+
+    /// Instantiates `module`, resolving its imports from the `spectest` module
+    /// and registered instances.
+    fn instantiate(&mut self, module: &Module) -> Result<Instance> {
+        let mut imports = Vec::new();
+        for (m, name) in module.imports() {
+            let item = match self.registered.get(m) {
+                Some(instance) => self.store.get_export(*instance, name),
+                None if m == "spectest" => self.spectest.get(name).copied(),
+                None => None,
+            };
+            match item {
+                Some(item) => imports.push(item),
+                None => bail!("unknown import `{m}::{name}`"),
+            }
+        }
+        self.store.instantiate(module, &imports)
+    }
+
+    /// Returns the instance named `name` or the current instance.
+    fn instance(&self, name: Option<Id<'_>>) -> Result<Instance> {
+        match name {
+            Some(name) => self
+                .instances
+                .get(name.name())
+                .copied()
+                .with_context(|| format!("no instance named `{}`", name.name())),
+            None => self.current.context("no module has been instantiated"),
+        }
+    }
+
+    fn invoke(&mut self, invoke: &WastInvoke<'_>) -> Result<Vec<Val>> {
+        let instance = self.instance(invoke.module)?;
+        let func = self
+            .store
+            .get_export(instance, invoke.name)
+            .and_then(|e| e.into_func())
+            .with_context(|| format!("no function export named `{}`", invoke.name))?;
+        let args = invoke.args.iter().map(arg).collect::<Result<Vec<_>>>()?;
+        self.store.invoke(func, &args)
+    }
+
+    fn execute(&mut self, exec: WastExecute<'_>) -> Result<Vec<Val>> {
+        match exec {
+            WastExecute::Invoke(invoke) => self.invoke(&invoke),
+            WastExecute::Wat(mut wat) => {
+                let module = self.compile(&wat.encode()?)?;
+                self.instantiate(&module)?;
+                Ok(Vec::new())
+            }
+            WastExecute::Get { module, global, .. } => {
+                let instance = self.instance(module)?;
+                let global = self
+                    .store
+                    .get_export(instance, global)
+                    .and_then(|e| e.into_global())
+                    .with_context(|| format!("no global export named `{global}`"))?;
+                Ok(vec![self.store.global_get(global)])
+            }
+        }
+    }
+}
+
+/// Checks that `err` is a trap whose message matches `message`.
+fn expect_trap(
+    err: anyhow::Error,
+    message: &str,
+    matches: &dyn Fn(&str, &str) -> Result<()>,
+) -> Result<()> {
+    match err.downcast_ref::<Trap>() {
+        Some(trap) => matches(trap.message(), message),
+        None => Err(err),
+    }
+}
+
+fn arg(arg: &WastArg<'_>) -> Result<Val> {
+    let arg = match arg {
+        WastArg::Core(arg) => arg,
+        #[allow(unreachable_patterns)]
+        _ => bail!("component values are not supported"),
+    };
+    Ok(match arg {
+        WastArgCore::I32(x) => Val::I32(*x),
+        WastArgCore::I64(x) => Val::I64(*x),
+        WastArgCore::F32(x) => Val::F32(x.bits),
+        WastArgCore::F64(x) => Val::F64(x.bits),
+        WastArgCore::V128(x) => Val::V128(u128::from_le_bytes(x.to_le_bytes())),
+        WastArgCore::RefNull(heap) => match heap_kind(heap)? {
+            RefKind::Func => Val::FuncRef(None),
+            RefKind::Extern => Val::ExternRef(None),
+        },
+        WastArgCore::RefExtern(x) => Val::ExternRef(Some(*x)),
+        WastArgCore::RefHost(_) => bail!("host references are not supported"),
+    })
+}
+
+enum RefKind {
+    Func,
+    Extern,
+}
+
+fn heap_kind(heap: &HeapType<'_>) -> Result<RefKind> {
+    match heap {
+        HeapType::Abstract {
+            shared: false,
+            ty: AbstractHeapType::Func | AbstractHeapType::NoFunc,
+        }
+        | HeapType::Concrete(_) => Ok(RefKind::Func),
+        HeapType::Abstract {
+            shared: false,
+            ty: AbstractHeapType::Extern | AbstractHeapType::NoExtern,
+        } => Ok(RefKind::Extern),
+        _ => bail!("unsupported reference type: {heap:?}"),
+    }
+}
+
+fn val_matches(actual: &Val, expected: &WastRetCore<'_>) -> Result<bool> {
+    Ok(match (actual, expected) {
+        (Val::I32(a), WastRetCore::I32(b)) => a == b,
+        (Val::I64(a), WastRetCore::I64(b)) => a == b,
+        (Val::F32(a), WastRetCore::F32(b)) => f32_matches(*a, b),
+        (Val::F64(a), WastRetCore::F64(b)) => f64_matches(*a, b),
+        (Val::V128(a), WastRetCore::V128(b)) => v128_matches(*a, b),
+        (Val::FuncRef(a), WastRetCore::RefNull(ty)) => {
+            a.is_none()
+                && matches!(
+                    ty.as_ref().map(heap_kind).transpose()?,
+                    None | Some(RefKind::Func)
+                )
+        }
+        (Val::ExternRef(a), WastRetCore::RefNull(ty)) => {
+            a.is_none()
+                && matches!(
+                    ty.as_ref().map(heap_kind).transpose()?,
+                    None | Some(RefKind::Extern)
+                )
+        }
+        (Val::ExternRef(a), WastRetCore::RefExtern(b)) => match b {
+            Some(_) => a == b,
+            None => a.is_some(),
+        },
+        (Val::FuncRef(a), WastRetCore::RefFunc(None)) => a.is_some(),
+        (_, WastRetCore::Either(options)) => {
+            for option in options {
+                if val_matches(actual, option)? {
+                    return Ok(true);
+                }
+            }
+            false
+        }
+        (
+            _,
+            WastRetCore::RefFunc(Some(_))
+            | WastRetCore::RefHost(_)
+            | WastRetCore::RefAny
+            | WastRetCore::RefEq
+            | WastRetCore::RefArray
+            | WastRetCore::RefStruct
+            | WastRetCore::RefI31
+            | WastRetCore::RefI31Shared,
+        ) => bail!("unsupported result pattern: {expected:?}"),
+        _ => false,
+    })
+}
+
+fn f32_matches(actual: u32, expected: &NanPattern<F32>) -> bool {
+    match expected {
+        NanPattern::CanonicalNan => actual & 0x7fff_ffff == 0x7fc0_0000,
+        NanPattern::ArithmeticNan => actual & 0x7fc0_0000 == 0x7fc0_0000,
+        NanPattern::Value(expected) => actual == expected.bits,
+    }
+}
+
+fn f64_matches(actual: u64, expected: &NanPattern<F64>) -> bool {
+    match expected {
+        NanPattern::CanonicalNan => actual & 0x7fff_ffff_ffff_ffff == 0x7ff8_0000_0000_0000,
+        NanPattern::ArithmeticNan => actual & 0x7ff8_0000_0000_0000 == 0x7ff8_0000_0000_0000,
+        NanPattern::Value(expected) => actual == expected.bits,
+    }
+}
+
+fn v128_matches(actual: u128, expected: &V128Pattern) -> bool {
+    let bytes = actual.to_le_bytes();
+    macro_rules! lanes {
+        ($ty:ident) => {
+            bytes
+                .chunks_exact(std::mem::size_of::<$ty>())
+                .map(|b| $ty::from_le_bytes(b.try_into().unwrap()))
+        };
+    }
+    match expected {
+        V128Pattern::I8x16(b) => lanes!(i8).eq(b.iter().copied()),
+        V128Pattern::I16x8(b) => lanes!(i16).eq(b.iter().copied()),
+        V128Pattern::I32x4(b) => lanes!(i32).eq(b.iter().copied()),
+        V128Pattern::I64x2(b) => lanes!(i64).eq(b.iter().copied()),
+        V128Pattern::F32x4(b) => lanes!(u32).zip(b).all(|(a, b)| f32_matches(a, b)),
+        V128Pattern::F64x2(b) => lanes!(u64).zip(b).all(|(a, b)| f64_matches(a, b)),
+    }
+}
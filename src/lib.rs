@@ -8,7 +8,12 @@ use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use termcolor::{Ansi, ColorChoice, NoColor, StandardStream, WriteColor};
 
-#[cfg(any(feature = "addr2line", feature = "validate"))]
+#[cfg(any(
+    feature = "addr2line",
+    feature = "coredump",
+    feature = "print",
+    feature = "validate"
+))]
 pub mod addr2line;
 
 #[derive(clap::Parser)]
@@ -110,7 +115,23 @@ impl FromStr for GenerateDwarf {
 
 impl InputArg {
     pub fn get_binary_wasm(&self) -> Result<Vec<u8>> {
+        let (bytes, _) = self.get_binary_wasm_with_source_map(false, None)?;
+        Ok(bytes)
+    }
+
+    /// Same as [`InputArg::get_binary_wasm`] except that a source map is
+    /// additionally generated if `source_map` is `true` and the input is a
+    /// textual module.
+    ///
+    /// If `source_mapping_url` is specified then textual modules will have a
+    /// `sourceMappingURL` custom section appended pointing to the URL.
+    pub fn get_binary_wasm_with_source_map(
+        &self,
+        source_map: bool,
+        source_mapping_url: Option<&str>,
+    ) -> Result<(Vec<u8>, Option<String>)> {
         let mut parser = wat::Parser::new();
+        parser.recover(true);
         match (self.generate_full_dwarf, self.generate_dwarf) {
             (false, Some(GenerateDwarf::Lines)) => {
                 parser.generate_dwarf(wat::GenerateDwarf::Lines);
@@ -120,18 +141,29 @@ impl InputArg {
             }
             (false, None) => {}
         }
+        if let Some(url) = source_mapping_url {
+            parser.source_mapping_url(url);
+        }
         if let Some(path) = &self.input {
             if path != Path::new("-") {
+                if source_map {
+                    return Ok(parser.parse_file_with_source_map(path)?);
+                }
                 let bytes = parser.parse_file(path)?;
-                return Ok(bytes);
+                return Ok((bytes, None));
             }
         }
         let mut stdin = Vec::new();
         std::io::stdin()
             .read_to_end(&mut stdin)
             .context("failed to read <stdin>")?;
-        let bytes = parser.parse_bytes(Some("<stdin>".as_ref()), &stdin)?;
-        Ok(bytes.into_owned())
+        let path = Path::new("<stdin>");
+        if source_map && !stdin.starts_with(b"\0asm") {
+            let wat = std::str::from_utf8(&stdin).context("input bytes aren't valid utf-8")?;
+            return Ok(parser.parse_str_with_source_map(Some(path), wat)?);
+        }
+        let bytes = parser.parse_bytes(Some(path), &stdin)?;
+        Ok((bytes.into_owned(), None))
     }
 }
 
@@ -169,6 +201,20 @@ impl InputOutput {
         self.input.get_binary_wasm()
     }
 
+    /// Same as [`InputOutput::parse_input_wasm`] but additionally returns a
+    /// source map, see [`InputArg::get_binary_wasm_with_source_map`].
+    pub fn parse_input_wasm_with_source_map(
+        &self,
+        source_map: bool,
+        source_mapping_url: Option<&str>,
+    ) -> Result<(Vec<u8>, Option<String>)> {
+        let ret = self
+            .input
+            .get_binary_wasm_with_source_map(source_map, source_mapping_url)?;
+        parse_binary_wasm(wasmparser::Parser::new(0), &ret.0)?;
+        Ok(ret)
+    }
+
     pub fn output_wasm(&self, wasm: &[u8], wat: bool) -> Result<()> {
         if wat {
             self.output(Output::Wat {
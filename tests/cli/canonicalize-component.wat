@@ -0,0 +1,19 @@
+;; RUN: canonicalize % -t
+
+(component
+  (core module $m
+    (type (func))
+    (type (func (param i32)))
+    (type (func))
+    (func (export "f") (type 2)
+      (local i32) (local i32))
+  )
+  (core instance (instantiate $m))
+  (component
+    (core module
+      (type (func))
+      (type (func))
+      (func (type 1))
+    )
+  )
+)
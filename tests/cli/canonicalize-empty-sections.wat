@@ -0,0 +1,13 @@
+;; RUN: canonicalize % | objdump
+
+(module binary
+  "\00asm" "\01\00\00\00"
+  "\01\04\01\60\00\00"          ;; type section with one type
+  "\02\01\00"                   ;; empty import section
+  "\03\05\01\80\80\80\00"       ;; function section with a non-minimal LEB
+  "\04\01\00"                   ;; empty table section
+  "\05\01\00"                   ;; empty memory section
+  "\07\01\00"                   ;; empty export section
+  "\0a\0b\01\09\02\01\7f\01\7f\41\00\1a\0b" ;; code section
+  "\0b\01\00"                   ;; empty data section
+)
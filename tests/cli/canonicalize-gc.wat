@@ -0,0 +1,22 @@
+;; RUN: canonicalize % -t
+
+(module
+  (rec
+    (type $list (struct (field i32) (field (ref null $list))))
+    (type $f (func (param (ref $list))))
+  )
+  (type $unused (struct))
+  (rec
+    (type $list2 (struct (field i32) (field (ref null $list2))))
+    (type $f2 (func (param (ref $list2))))
+  )
+  (rec (type $single (array i8)))
+
+  (func (type $f2)
+    (local (ref null $list))
+    local.get 0
+    local.set 1)
+  (func (param (ref $single)))
+  (func (param (ref $single)))
+  (func (param (ref $single)))
+)
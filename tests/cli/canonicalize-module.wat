@@ -0,0 +1,18 @@
+;; RUN: canonicalize % -t
+
+(module
+  (type $a (func (param i32)))
+  (type $b (func))
+  (type $c (func (param i32)))
+  (type $d (func))
+  (import "" "f" (func $f (type $c)))
+  (func $g (type $d)
+    (local i32) (local i32 i64) (local i64)
+    i32.const 1
+    call $f
+    call $g)
+  (func (type $d) (call_indirect (type $c) (i32.const 0) (i32.const 0)))
+  (table 1 funcref)
+  (elem funcref)
+  (data)
+)
@@ -0,0 +1,10 @@
+;; RUN: canonicalize % -t
+
+(module
+  (type $a (func))
+  (type $b (struct (field $x i32)))
+  (type $c (func))
+  (type $d (struct (field $y i32)))
+  (func (type $c))
+  (func (param (ref $d)))
+)
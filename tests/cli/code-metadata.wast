@@ -0,0 +1,39 @@
+;; RUN: wast --assert default,snapshot-folded --snapshot tests/snapshots %
+
+(module
+  (func $flat (param i32) (result i32)
+    (@metadata.code.trace "\00\01\02")
+    local.get 0
+    (@metadata.code.trace "entry")
+    (@metadata.code.cost "\10")
+    i32.eqz
+    (@metadata.code.branch_hint "\01")
+    (@metadata.code.cost "\20")
+    if (result i32)
+      i32.const 1
+    else
+      (@metadata.code.cost "\30")
+      block (result i32)
+        i32.const 2
+      end
+    end)
+
+  (func $folded (param i32)
+    (drop
+      (i32.add
+        (local.get 0)
+        (@metadata.code.cost "\01")
+        (i32.const 1)
+        (@metadata.code.trace "split " "across " "strings")))
+    (@metadata.code.cost "\02")
+    (loop))
+
+  ;; Names which aren't identifiers are written as quoted annotation ids.
+  (func $quoted
+    (@"metadata.code.has space (and \"quotes\")" "\03")
+    nop)
+)
+
+(assert_malformed
+  (module quote "(func (@metadata.code.cost 1) nop)")
+  "expected a string")
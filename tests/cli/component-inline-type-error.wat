@@ -0,0 +1,9 @@
+;; FAIL: parse %
+
+;; Errors inside inline types are reported at the offending token within the
+;; inline type, not at the item which encloses it.
+(component
+  (import "a" (instance
+    (export "f" (func (param "x" (list (record (field "a" $nope))))))
+  ))
+)
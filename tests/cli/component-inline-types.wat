@@ -0,0 +1,17 @@
+;; RUN: print %
+
+;; Identical inline component types are defined once and shared by every use
+;; within the same scope, including uses that match an explicitly defined type.
+(component
+  (type $bytes (list u8))
+  (import "a" (func (param "x" (list u8)) (result (list u8))))
+  (import "b" (func (param "x" (list u8)) (result (list u8))))
+  (import "c" (func (param "x" (tuple u32 (option string)))))
+  (import "d" (func (param "y" (tuple u32 (option string)))))
+  (import "e" (instance
+    (export "f" (func (param "x" (list u8)) (result (list u8))))
+    (export "g" (func (param "x" (list u8)) (result (list u8))))
+  ))
+  (import "h" (func (param "x" (variant (case $a "a") (case "b" (refines $a))))))
+  (import "i" (func (param "x" (variant (case "a") (case $b "b") (case "c" (refines $b))))))
+)
@@ -0,0 +1,17 @@
+;; RUN: parse -g % -o %tmpdir/module.wasm | \
+;;      coredump tests/cli/coredump.wat --module %tmpdir/module.wasm
+;;
+;; This is the module referenced from the coredump in `coredump.wat`. Note that
+;; the code offsets in the coredump depend on the layout of the functions here.
+
+(module $module
+  (import "env" "abort" (func $abort (param i32)))
+  (func $inner (param $x i32) (result i32)
+    local.get $x
+    call $abort
+    unreachable)
+  (func $outer (export "run") (local $tmp i64)
+    i32.const 42
+    call $inner
+    drop)
+)
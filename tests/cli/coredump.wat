@@ -0,0 +1,15 @@
+;; RUN: coredump % --module module.wasm=tests/cli/coredump-module.wat
+
+(module
+  (@custom "core" "\00\0ctest-process")
+  (@custom "coremodules" "\01\00\0bmodule.wasm")
+  (@custom "coreinstances" "\01\00\00\00\00")
+  (@custom "corestack"
+    "\00\04main"
+    "\02"
+    ;; frame 0: instance 0, func 1, offset 5, locals `[i32 42]`, empty stack
+    "\00\00\01\05" "\01\7f\2a" "\00"
+    ;; frame 1: instance 0, func 2, offset 5, locals `[i64 7]`, stack
+    ;; `[i32 42]`
+    "\00\00\02\05" "\01\7e\07" "\01\7f\2a")
+)
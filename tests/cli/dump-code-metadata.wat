@@ -0,0 +1,11 @@
+;; RUN: dump %
+
+(module
+  (func
+    (@metadata.code.cost "\01")
+    nop
+    (@metadata.code.trace "a")
+    (@metadata.code.cost "\02\03")
+    nop
+  )
+)
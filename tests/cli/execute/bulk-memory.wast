@@ -0,0 +1,30 @@
+;; RUN: wast --execute %
+
+(module
+  (memory 1)
+  (data $d "hello")
+  (func (export "init") (param i32 i32 i32)
+    (memory.init $d (local.get 0) (local.get 1) (local.get 2)))
+  (func (export "drop") (data.drop $d))
+  (func (export "copy") (param i32 i32 i32)
+    (memory.copy (local.get 0) (local.get 1) (local.get 2)))
+  (func (export "fill") (param i32 i32 i32)
+    (memory.fill (local.get 0) (local.get 1) (local.get 2)))
+  (func (export "load8") (param i32) (result i32)
+    (i32.load8_u (local.get 0)))
+)
+
+(invoke "init" (i32.const 10) (i32.const 0) (i32.const 5))
+(assert_return (invoke "load8" (i32.const 10)) (i32.const 104))
+(assert_return (invoke "load8" (i32.const 14)) (i32.const 111))
+(assert_trap (invoke "init" (i32.const 0) (i32.const 1) (i32.const 5)) "out of bounds memory access")
+(invoke "copy" (i32.const 11) (i32.const 10) (i32.const 5))
+(assert_return (invoke "load8" (i32.const 11)) (i32.const 104))
+(assert_return (invoke "load8" (i32.const 15)) (i32.const 111))
+(invoke "fill" (i32.const 65535) (i32.const 7) (i32.const 1))
+(assert_return (invoke "load8" (i32.const 65535)) (i32.const 7))
+(assert_trap (invoke "fill" (i32.const 65535) (i32.const 7) (i32.const 2)) "out of bounds memory access")
+(invoke "fill" (i32.const 65536) (i32.const 7) (i32.const 0))
+(invoke "drop")
+(invoke "init" (i32.const 0) (i32.const 0) (i32.const 0))
+(assert_trap (invoke "init" (i32.const 0) (i32.const 0) (i32.const 1)) "out of bounds memory access")
@@ -0,0 +1,41 @@
+;; RUN: wast --execute %
+
+(module
+  (func $swap (export "swap") (param i32 i64) (result i64 i32)
+    (local.get 1) (local.get 0))
+  (func (export "block") (param i32) (result i32 i32)
+    (local.get 0)
+    (block (param i32) (result i32 i32)
+      (i32.const 1)
+      (br 0))
+  )
+  (func (export "loop") (param i32) (result i32)
+    (i32.const 0) (local.get 0)
+    (loop $l (param i32 i32) (result i32)
+      (local.set 0)
+      (i32.add (local.get 0))
+      (local.set 0 (i32.sub (local.get 0) (i32.const 1)))
+      (local.get 0)
+      (br_if $l (local.get 0))
+      (drop))
+  )
+  (func (export "if") (param i32) (result i32 i64)
+    (i32.const 5)
+    (if (param i32) (result i32 i64) (local.get 0)
+      (then (i64.const 1))
+      (else (drop) (i32.const 6) (i64.const 2))))
+  (func (export "call") (result i32 i64)
+    (local i64 i32)
+    (call $swap (i32.const 1) (i64.const 2))
+    (local.set 1)
+    (local.set 0)
+    (local.get 1)
+    (local.get 0))
+)
+
+(assert_return (invoke "swap" (i32.const 1) (i64.const 2)) (i64.const 2) (i32.const 1))
+(assert_return (invoke "block" (i32.const 9)) (i32.const 9) (i32.const 1))
+(assert_return (invoke "loop" (i32.const 4)) (i32.const 10))
+(assert_return (invoke "if" (i32.const 1)) (i32.const 5) (i64.const 1))
+(assert_return (invoke "if" (i32.const 0)) (i32.const 6) (i64.const 2))
+(assert_return (invoke "call") (i32.const 1) (i64.const 2))
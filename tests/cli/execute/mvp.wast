@@ -0,0 +1,146 @@
+;; RUN: wast --execute %
+
+(module
+  (memory 1)
+  (data (i32.const 8) "\01\02\03\04")
+  (global $g (mut i32) (i32.const 10))
+  (table 2 funcref)
+  (elem (i32.const 0) $fac $fib)
+  (type $i32->i64 (func (param i32) (result i64)))
+
+  (func $fac (export "fac") (param i32) (result i64)
+    (if (result i64) (i32.eqz (local.get 0))
+      (then (i64.const 1))
+      (else
+        (i64.mul
+          (i64.extend_i32_u (local.get 0))
+          (call $fac (i32.sub (local.get 0) (i32.const 1)))))))
+
+  (func $fib (export "fib") (param i32) (result i64)
+    (local i64 i64)
+    (local.set 2 (i64.const 1))
+    (block $done
+      (loop $l
+        (br_if $done (i32.eqz (local.get 0)))
+        (local.set 2 (i64.add (local.get 1) (local.tee 1 (local.get 2))))
+        (local.set 0 (i32.sub (local.get 0) (i32.const 1)))
+        (br $l)))
+    (local.get 1))
+
+  (func (export "indirect") (param i32 i32) (result i64)
+    (call_indirect (type $i32->i64) (local.get 1) (local.get 0)))
+
+  (func (export "switch") (param i32) (result i32)
+    (block $c (block $b (block $a
+      (br_table $a $b $c (local.get 0)))
+      (return (i32.const 100)))
+      (return (i32.const 200)))
+    (i32.const 300))
+
+  (func (export "load") (param i32) (result i32)
+    (i32.load (local.get 0)))
+  (func (export "store") (param i32 i64)
+    (i64.store16 offset=2 (local.get 0) (local.get 1)))
+  (func (export "grow") (param i32) (result i32)
+    (memory.grow (local.get 0)))
+
+  (func (export "bump") (result i32)
+    (global.set $g (i32.add (global.get $g) (i32.const 1)))
+    (global.get $g))
+
+  (func (export "div_s") (param i32 i32) (result i32)
+    (i32.div_s (local.get 0) (local.get 1)))
+  (func (export "trunc") (param f32) (result i32)
+    (i32.trunc_f32_s (local.get 0)))
+  (func (export "nearest") (param f64) (result f64)
+    (f64.nearest (local.get 0)))
+  (func (export "div") (param f32 f32) (result f32)
+    (f32.div (local.get 0) (local.get 1)))
+  (func (export "copysign") (param f64 f64) (result f64)
+    (f64.copysign (local.get 0) (local.get 1)))
+  (func (export "rotl") (param i64 i64) (result i64)
+    (i64.rotl (local.get 0) (local.get 1)))
+  (func (export "extend8") (param i32) (result i32)
+    (i32.extend8_s (local.get 0)))
+  (func (export "sat") (param f64) (result i64)
+    (i64.trunc_sat_f64_u (local.get 0)))
+
+  (func $loop (export "loop") (call $loop))
+  (func (export "unreachable") unreachable)
+)
+
+(assert_return (invoke "fac" (i32.const 20)) (i64.const 2432902008176640000))
+(assert_return (invoke "fib" (i32.const 50)) (i64.const 12586269025))
+(assert_return (invoke "indirect" (i32.const 0) (i32.const 5)) (i64.const 120))
+(assert_return (invoke "indirect" (i32.const 1) (i32.const 5)) (i64.const 5))
+(assert_trap (invoke "indirect" (i32.const 2) (i32.const 5)) "undefined element")
+(assert_return (invoke "switch" (i32.const 0)) (i32.const 100))
+(assert_return (invoke "switch" (i32.const 1)) (i32.const 200))
+(assert_return (invoke "switch" (i32.const 7)) (i32.const 300))
+
+(assert_return (invoke "load" (i32.const 8)) (i32.const 0x04030201))
+(invoke "store" (i32.const 8) (i64.const 0xffff))
+(assert_return (invoke "load" (i32.const 8)) (i32.const 0xffff0201))
+(assert_trap (invoke "load" (i32.const 65533)) "out of bounds memory access")
+(assert_return (invoke "grow" (i32.const 1)) (i32.const 1))
+(assert_return (invoke "load" (i32.const 65533)) (i32.const 0))
+(assert_return (invoke "grow" (i32.const 0x10000)) (i32.const -1))
+
+(assert_return (invoke "bump") (i32.const 11))
+(assert_return (invoke "bump") (i32.const 12))
+
+(assert_trap (invoke "div_s" (i32.const 1) (i32.const 0)) "integer divide by zero")
+(assert_trap (invoke "div_s" (i32.const 0x80000000) (i32.const -1)) "integer overflow")
+(assert_return (invoke "trunc" (f32.const -1.9)) (i32.const -1))
+(assert_trap (invoke "trunc" (f32.const nan)) "invalid conversion to integer")
+(assert_trap (invoke "trunc" (f32.const 2147483648)) "integer overflow")
+(assert_return (invoke "nearest" (f64.const 2.5)) (f64.const 2))
+(assert_return (invoke "nearest" (f64.const -0.5)) (f64.const -0))
+(assert_return (invoke "div" (f32.const 0) (f32.const 0)) (f32.const nan:canonical))
+(assert_return (invoke "div" (f32.const 1) (f32.const 0)) (f32.const inf))
+(assert_return (invoke "copysign" (f64.const 1) (f64.const -nan)) (f64.const -1))
+(assert_return (invoke "rotl" (i64.const 1) (i64.const 65)) (i64.const 2))
+(assert_return (invoke "extend8" (i32.const 0x80)) (i32.const -128))
+(assert_return (invoke "sat" (f64.const -1)) (i64.const 0))
+(assert_return (invoke "sat" (f64.const 1e30)) (i64.const -1))
+
+(assert_exhaustion (invoke "loop") "call stack exhausted")
+(assert_trap (invoke "unreachable") "unreachable")
+
+;; imports and registration
+(module $a
+  (global (export "g") i32 (i32.const 42))
+  (func (export "f") (result i32) (i32.const 7)))
+(register "a" $a)
+(module
+  (import "a" "g" (global $g i32))
+  (import "a" "f" (func $f (result i32)))
+  (import "spectest" "print_i32" (func $print (param i32)))
+  (import "spectest" "global_i32" (global $sg i32))
+  (func (export "sum") (result i32)
+    (call $print (global.get $g))
+    (i32.add (i32.add (global.get $g) (call $f)) (global.get $sg))))
+(assert_return (invoke "sum") (i32.const 715))
+(assert_return (get $a "g") (i32.const 42))
+(assert_unlinkable
+  (module (import "a" "missing" (func)))
+  "unknown import")
+(assert_unlinkable
+  (module (import "a" "f" (func (param i32))))
+  "incompatible import type")
+
+;; traps during instantiation, which still run earlier segments
+(module $m
+  (memory (export "mem") 1)
+  (func (export "load") (param i32) (result i32) (i32.load8_u (local.get 0))))
+(register "m" $m)
+(assert_trap
+  (module
+    (import "m" "mem" (memory 1))
+    (data (i32.const 0) "a")
+    (data (i32.const 65536) "b"))
+  "out of bounds memory access")
+(assert_return (invoke $m "load" (i32.const 0)) (i32.const 97))
+(assert_trap
+  (module (func $start unreachable) (start $start))
+  "unreachable")
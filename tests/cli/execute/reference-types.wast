@@ -0,0 +1,42 @@
+;; RUN: wast --execute %
+
+(module
+  (table $t 2 externref)
+  (table $f 3 funcref)
+  (elem $e func $a $b)
+  (func $a (result i32) (i32.const 1))
+  (func $b (result i32) (i32.const 2))
+  (func (export "set") (param i32 externref)
+    (table.set $t (local.get 0) (local.get 1)))
+  (func (export "get") (param i32) (result externref)
+    (table.get $t (local.get 0)))
+  (func (export "is_null") (param externref) (result i32)
+    (ref.is_null (local.get 0)))
+  (func (export "grow") (param i32 externref) (result i32)
+    (table.grow $t (local.get 1) (local.get 0)))
+  (func (export "size") (result i32) (table.size $t))
+  (func (export "init") (param i32 i32 i32)
+    (table.init $f $e (local.get 0) (local.get 1) (local.get 2)))
+  (func (export "call") (param i32) (result i32)
+    (call_indirect $f (result i32) (local.get 0)))
+  (func (export "func") (result funcref) (ref.func $a))
+  (func (export "select") (param externref externref i32) (result externref)
+    (select (result externref) (local.get 0) (local.get 1) (local.get 2)))
+)
+
+(assert_return (invoke "get" (i32.const 0)) (ref.null extern))
+(invoke "set" (i32.const 1) (ref.extern 5))
+(assert_return (invoke "get" (i32.const 1)) (ref.extern 5))
+(assert_return (invoke "is_null" (ref.extern 1)) (i32.const 0))
+(assert_return (invoke "is_null" (ref.null extern)) (i32.const 1))
+(assert_trap (invoke "get" (i32.const 2)) "out of bounds table access")
+(assert_return (invoke "grow" (i32.const 3) (ref.extern 9)) (i32.const 2))
+(assert_return (invoke "size") (i32.const 5))
+(assert_return (invoke "get" (i32.const 4)) (ref.extern 9))
+(assert_trap (invoke "call" (i32.const 0)) "uninitialized element")
+(invoke "init" (i32.const 1) (i32.const 0) (i32.const 2))
+(assert_return (invoke "call" (i32.const 1)) (i32.const 1))
+(assert_return (invoke "call" (i32.const 2)) (i32.const 2))
+(assert_trap (invoke "init" (i32.const 2) (i32.const 0) (i32.const 2)) "out of bounds table access")
+(assert_return (invoke "func") (ref.func))
+(assert_return (invoke "select" (ref.extern 1) (ref.extern 2) (i32.const 0)) (ref.extern 2))
@@ -0,0 +1,94 @@
+;; RUN: wast --execute %
+
+(module
+  (memory 1)
+  (data (i32.const 0) "\00\01\02\03\04\05\06\07\08\09\0a\0b\0c\0d\0e\0f")
+  (func (export "load") (result v128) (v128.load (i32.const 0)))
+  (func (export "load8x8_s") (result v128) (v128.load8x8_s (i32.const 8)))
+  (func (export "load_lane") (param v128) (result v128)
+    (v128.load32_lane 3 (i32.const 4) (local.get 0)))
+  (func (export "i8x16.add_sat_u") (param v128 v128) (result v128)
+    (i8x16.add_sat_u (local.get 0) (local.get 1)))
+  (func (export "i16x8.mul") (param v128 v128) (result v128)
+    (i16x8.mul (local.get 0) (local.get 1)))
+  (func (export "i32x4.lt_s") (param v128 v128) (result v128)
+    (i32x4.lt_s (local.get 0) (local.get 1)))
+  (func (export "i64x2.shl") (param v128 i32) (result v128)
+    (i64x2.shl (local.get 0) (local.get 1)))
+  (func (export "f32x4.min") (param v128 v128) (result v128)
+    (f32x4.min (local.get 0) (local.get 1)))
+  (func (export "f64x2.sqrt") (param v128) (result v128)
+    (f64x2.sqrt (local.get 0)))
+  (func (export "shuffle") (param v128 v128) (result v128)
+    (i8x16.shuffle 0 16 1 17 2 18 3 19 4 20 5 21 6 22 7 23 (local.get 0) (local.get 1)))
+  (func (export "swizzle") (param v128 v128) (result v128)
+    (i8x16.swizzle (local.get 0) (local.get 1)))
+  (func (export "bitmask") (param v128) (result i32)
+    (i8x16.bitmask (local.get 0)))
+  (func (export "narrow") (param v128 v128) (result v128)
+    (i8x16.narrow_i16x8_u (local.get 0) (local.get 1)))
+  (func (export "dot") (param v128 v128) (result v128)
+    (i32x4.dot_i16x8_s (local.get 0) (local.get 1)))
+  (func (export "extract") (param v128) (result i32)
+    (i16x8.extract_lane_s 7 (local.get 0)))
+  (func (export "replace") (param v128 f64) (result v128)
+    (f64x2.replace_lane 1 (local.get 0) (local.get 1)))
+  (func (export "trunc_sat") (param v128) (result v128)
+    (i32x4.trunc_sat_f32x4_s (local.get 0)))
+  (func (export "q15") (param v128 v128) (result v128)
+    (i16x8.q15mulr_sat_s (local.get 0) (local.get 1)))
+)
+
+(assert_return (invoke "load")
+  (v128.const i8x16 0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15))
+(assert_return (invoke "load8x8_s")
+  (v128.const i16x8 8 9 10 11 12 13 14 15))
+(assert_return (invoke "load_lane" (v128.const i32x4 0 0 0 0))
+  (v128.const i32x4 0 0 0 0x07060504))
+(assert_return (invoke "i8x16.add_sat_u"
+    (v128.const i8x16 250 0 0 0 0 0 0 0 0 0 0 0 0 0 0 1)
+    (v128.const i8x16 10 0 0 0 0 0 0 0 0 0 0 0 0 0 0 1))
+  (v128.const i8x16 255 0 0 0 0 0 0 0 0 0 0 0 0 0 0 2))
+(assert_return (invoke "i16x8.mul"
+    (v128.const i16x8 -1 2 3 4 5 6 7 0x4000)
+    (v128.const i16x8 2 2 2 2 2 2 2 4))
+  (v128.const i16x8 -2 4 6 8 10 12 14 0))
+(assert_return (invoke "i32x4.lt_s"
+    (v128.const i32x4 -1 2 3 4)
+    (v128.const i32x4 0 2 1 5))
+  (v128.const i32x4 -1 0 0 -1))
+(assert_return (invoke "i64x2.shl" (v128.const i64x2 1 3) (i32.const 65))
+  (v128.const i64x2 2 6))
+(assert_return (invoke "f32x4.min"
+    (v128.const f32x4 0 -0 nan 1)
+    (v128.const f32x4 -0 0 1 2))
+  (v128.const f32x4 -0 -0 nan:canonical 1))
+(assert_return (invoke "f64x2.sqrt" (v128.const f64x2 4 -1))
+  (v128.const f64x2 2 nan:arithmetic))
+(assert_return (invoke "shuffle"
+    (v128.const i8x16 0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15)
+    (v128.const i8x16 16 17 18 19 20 21 22 23 24 25 26 27 28 29 30 31))
+  (v128.const i8x16 0 16 1 17 2 18 3 19 4 20 5 21 6 22 7 23))
+(assert_return (invoke "swizzle"
+    (v128.const i8x16 10 11 12 13 14 15 16 17 18 19 20 21 22 23 24 25)
+    (v128.const i8x16 15 0 1 16 255 2 3 4 5 6 7 8 9 10 11 12))
+  (v128.const i8x16 25 10 11 0 0 12 13 14 15 16 17 18 19 20 21 22))
+(assert_return (invoke "bitmask" (v128.const i8x16 -1 0 -1 0 0 0 0 0 0 0 0 0 0 0 0 -128))
+  (i32.const 0x8005))
+(assert_return (invoke "narrow"
+    (v128.const i16x8 -1 0 255 256 1 2 3 4)
+    (v128.const i16x8 5 6 7 8 9 10 11 300))
+  (v128.const i8x16 0 0 255 255 1 2 3 4 5 6 7 8 9 10 11 255))
+(assert_return (invoke "dot"
+    (v128.const i16x8 1 2 3 4 -32768 -32768 0 0)
+    (v128.const i16x8 5 6 7 8 -32768 -32768 0 0))
+  (v128.const i32x4 17 53 -2147483648 0))
+(assert_return (invoke "extract" (v128.const i16x8 0 0 0 0 0 0 0 -5)) (i32.const -5))
+(assert_return (invoke "replace" (v128.const f64x2 1 2) (f64.const 3.5))
+  (v128.const f64x2 1 3.5))
+(assert_return (invoke "trunc_sat" (v128.const f32x4 1.5 -1.5 nan 3e10))
+  (v128.const i32x4 1 -1 0 0x7fffffff))
+(assert_return (invoke "q15"
+    (v128.const i16x8 -32768 16384 0 0 0 0 0 0)
+    (v128.const i16x8 -32768 16384 0 0 0 0 0 0))
+  (v128.const i16x8 32767 8192 0 0 0 0 0 0))
@@ -0,0 +1,28 @@
+;; RUN: wast --execute %
+
+(module
+  (type $t (func (param i64 i64) (result i64)))
+  (table funcref (elem $count))
+  (func $count (export "count") (param i64 i64) (result i64)
+    (if (result i64) (i64.eqz (local.get 0))
+      (then (local.get 1))
+      (else
+        (return_call $count
+          (i64.sub (local.get 0) (i64.const 1))
+          (i64.add (local.get 1) (i64.const 1))))))
+  (func $even (export "even") (param i32) (result i32)
+    (if (result i32) (i32.eqz (local.get 0))
+      (then (i32.const 1))
+      (else (return_call $odd (i32.sub (local.get 0) (i32.const 1))))))
+  (func $odd (param i32) (result i32)
+    (if (result i32) (i32.eqz (local.get 0))
+      (then (i32.const 0))
+      (else (return_call $even (i32.sub (local.get 0) (i32.const 1))))))
+  (func (export "indirect") (param i64) (result i64)
+    (return_call_indirect (type $t) (local.get 0) (i64.const 0) (i32.const 0)))
+)
+
+(assert_return (invoke "count" (i64.const 1000000) (i64.const 0)) (i64.const 1000000))
+(assert_return (invoke "even" (i32.const 100001)) (i32.const 0))
+(assert_return (invoke "even" (i32.const 100000)) (i32.const 1))
+(assert_return (invoke "indirect" (i64.const 50000)) (i64.const 50000))
@@ -0,0 +1,13 @@
+;; RUN: fmt --check %
+
+(module
+  (func (param i32) (result i32)
+    block $b (result i32) ;; comment
+      local.get 0
+    end
+  )
+  (func (param i32)
+    (if (local.get 0)
+      (then
+        (drop (i32.const 1)))))
+)
@@ -0,0 +1,38 @@
+;; RUN[preserve]: fmt --stdout %
+;; RUN[fold]: fmt --stdout --fold %
+;; RUN[flat]: fmt --stdout --flat %
+;; FAIL[check]: fmt --check %
+
+
+
+(module   $m
+        (type $t (func (param i32) (result i32)))   ;; a type
+   (memory 1)
+(func $f (type $t) (param $x i32) (result i32)
+      (local $y i32)
+  ;; flat instructions
+      local.get $x
+   i32.const 1
+          i32.add
+  local.tee $y
+    if (result i32)
+        (; a block comment ;) local.get $y
+      call $f
+    else
+          i32.const 0
+     end
+  )
+
+
+  (func $g (param i32)
+    (block $done
+   (loop $l
+  (br_if $done (i32.eqz (local.get 0)))
+        (local.set 0
+      (i32.sub (local.get 0) (i32.const 1)))
+    (i32.store (i32.const 0)
+                   (i32.load (i32.const 0)))
+          (br $l))))
+
+  (@custom "c" "data")
+)
@@ -0,0 +1,60 @@
+;; RUN: wast --assert default,snapshot-folded --snapshot tests/snapshots % -f legacy-exceptions
+
+(module
+  (tag $e0 (param i32))
+  (tag $e1)
+
+  (func $catch (result i32)
+    try $outer (result i32)
+      i32.const 1
+      throw $e0
+    catch $e0
+      i32.const 2
+      i32.add
+    catch $e1
+      i32.const 3
+    catch_all
+      i32.const 4
+      br $outer
+    end
+    i32.const 5
+    i32.mul)
+
+  (func $delegate (param i32)
+    block $b
+      try $t
+        try
+          local.get 0
+          br_if $b
+          throw $e1
+        delegate $t
+      catch_all
+        rethrow $t
+      end
+    end
+    try
+      nop
+    delegate 0)
+
+  (func $folded (result i32)
+    (try (result i32)
+      (do (throw $e0 (i32.const 0)))
+      (catch $e0 (i32.eqz))
+      (catch_all (i32.const 1))))
+)
+
+(assert_malformed
+  (module quote "(func (try))")
+  "previous `try` had no `do`")
+(assert_malformed
+  (module quote "(func (try (catch_all)))")
+  "expected keyword `do`")
+(assert_malformed
+  (module quote "(func (try (do) (catch_all) (catch_all)))")
+  "too many payloads inside of `(try)`")
+(assert_malformed
+  (module quote "(func (try (do) (catch_all) (delegate 0)))")
+  "too many payloads inside of `(try)`")
+(assert_malformed
+  (module quote "(func (try (do) (catch 0) (delegate 0)))")
+  "unexpected token")
@@ -0,0 +1,9 @@
+;; FAIL: parse %
+
+(module
+  (func $a (result i32) (i32.const))
+  (memory 1)
+  (global i32 (i32.const 0) oops)
+  (func $b)
+  (table)
+)
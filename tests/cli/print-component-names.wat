@@ -0,0 +1,37 @@
+;; RUN: print %
+;;
+;; Identifiers in every component index space should be preserved through the
+;; `component-name` section and used when printing.
+
+(component $C
+  (import "v" (value $v string))
+  (import "i" (instance $imp (export "f" (func))))
+  (import "c" (component $imported-c))
+  (type $t (record (field "a" u32)))
+  (type $r (resource (rep i32)))
+  (core type $ct (func))
+  (core module $m
+    (func (export "f"))
+    (memory (export "mem") 1)
+    (table (export "t") 1 funcref)
+    (global (export "g") i32 (i32.const 0))
+    (tag (export "tag"))
+  )
+  (core instance $ci (instantiate $m))
+  (alias core export $ci "f" (core func $cf))
+  (alias core export $ci "mem" (core memory $mem))
+  (alias core export $ci "t" (core table $table))
+  (alias core export $ci "g" (core global $global))
+  (alias core export $ci "tag" (core tag $tag))
+  (core func $new (canon resource.new $r))
+  (func $lifted (canon lift (core func $cf)))
+  (core func $lowered (canon lower (func $lifted)))
+  (alias export $imp "f" (func $aliased))
+  (component $inner
+    (alias outer $C $t (type $outer-t))
+    (export $inner-t "t" (type $outer-t))
+  )
+  (instance $inst (instantiate $inner))
+  (alias export $inst "t" (type $inst-t))
+  (export $exported "e" (func $lifted))
+)
@@ -0,0 +1,30 @@
+;; RUN[func-name]: print --func ^add$ %
+;; RUN[func-index]: print --func 2 %
+;; RUN[export]: print --item export:^run$ --item data:^strings$ %
+;; RUN[nested]: print --item component:0/module:inner/func:0 %
+;; FAIL[bad-kind]: print --item table:0 %
+
+(component
+  (core module $outer
+    (func (result i32) i32.const 1)
+  )
+  (component
+    (core module $inner
+      (memory 1)
+      (func $add (param i32 i32) (result i32)
+        local.get 0
+        local.get 1
+        i32.add)
+      (func $add_one (param i32) (result i32)
+        local.get 0
+        i32.const 1
+        call $add)
+      (func $run (export "run")
+        i32.const 0
+        call $add_one
+        drop)
+      (data $strings (i32.const 0) "hello")
+      (data $other (i32.const 8) "world")
+    )
+  )
+)
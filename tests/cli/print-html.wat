@@ -0,0 +1,16 @@
+;; RUN: print --html %
+
+(module
+  (type $pair (func (param i32 i32) (result i32)))
+  (global $g (mut i32) (i32.const 0))
+  (func $add (type $pair) (param $a i32) (param $b i32) (result i32)
+    local.get $a
+    local.get $b
+    i32.add)
+  (func (export "run<>") (param i32) (result i32)
+    (local $tmp i32)
+    local.get 0
+    global.get $g
+    call $add
+    local.tee $tmp)
+)
@@ -0,0 +1,21 @@
+;; RUN: wast --assert default --snapshot tests/snapshots %
+
+;; Branch targets are printed with `write_identifier` rather than `write`, so
+;; references to labels whose names can't be printed as plain identifiers don't
+;; carry a `(@name ...)` annotation.
+(module
+  (func
+    block (@name "")
+      i32.const 0
+      br_if 0
+    end
+    block (@name "a b")
+      br 0
+    end
+    block (@name "")
+      block (@name "")
+        br 1
+      end
+    end
+  )
+)
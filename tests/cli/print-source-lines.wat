@@ -0,0 +1,13 @@
+;; RUN[flat]: parse -g % | print --print-source-lines
+;; RUN[folded]: parse -g % | print --print-source-lines -f
+
+(module
+  (func $add (param i32 i32) (result i32)
+    local.get 0
+    local.get 1 i32.add
+  )
+  (func (export "f") (param i32) (result i32)
+    (call $add
+      (local.get 0)
+      (i32.const 1)))
+)
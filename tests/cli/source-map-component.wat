@@ -0,0 +1,3 @@
+;; FAIL: parse % --source-map %tmpdir/source-map.wasm.map
+
+(component)
@@ -0,0 +1,9 @@
+;; RUN: parse % --source-map %tmpdir/source-map.wasm.map -o %tmpdir/source-map.wasm
+;; RUN[url]: parse % --source-map-url source-map.wasm.map -t
+
+(module
+  (func (export "f") (result i32)
+    i32.const 1
+    i32.const 2
+    i32.add)
+)
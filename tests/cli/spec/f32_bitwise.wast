@@ -3,4 +3,5 @@
 ;;      --snapshot tests/snapshots \
 ;;      --ignore-error-messages \
 ;;      --features=wasm2 \
+;;      --execute \
 ;;      tests/testsuite/f32_bitwise.wast
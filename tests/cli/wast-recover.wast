@@ -0,0 +1,15 @@
+;; FAIL: wast %
+
+(module
+  (func (export "f") (result i32) i32.const 1)
+  (func (param i32 i32))
+  (memory 1 2 3)
+)
+(assert_return (invoke "f") (i32.const 1))
+(assert_return (invoke "f" (i32.const)) (i32.const 1))
+(component
+  (core module $m)
+  (core instance (instantiate $m (with)))
+)
+(assert_trap (invoke "f") "unreachable")
+(unknown_directive)